@@ -1,5 +1,7 @@
 pub mod aabb2;
 pub mod aabb2i;
+pub mod aabb3;
 
 pub use aabb2::*;
-pub use aabb2i::*;
\ No newline at end of file
+pub use aabb2i::*;
+pub use aabb3::*;
\ No newline at end of file
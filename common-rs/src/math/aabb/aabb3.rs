@@ -0,0 +1,110 @@
+use crate::math::vector::Vec3;
+use crate::math::matrix::Mat3x4;
+
+/// A Closed 3D Range - that is min and max count as being inside the bounds of the Aabb3
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb3 {
+	pub min: Vec3,
+	pub max: Vec3,
+}
+
+impl Aabb3 {
+	pub fn new(a: Vec3, b: Vec3) -> Aabb3 {
+		let min = Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+		let max = Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+		Aabb3 { min, max }
+	}
+
+	pub fn new_empty() -> Aabb3 {
+		Aabb3 {
+			min: Vec3::splat(f32::INFINITY),
+			max: Vec3::splat(-f32::INFINITY)
+		}
+	}
+
+	pub fn around_point(center: Vec3, extents: Vec3) -> Aabb3 {
+		Aabb3::new(center - extents, center + extents)
+	}
+
+	pub fn from_points(points: impl IntoIterator<Item=Vec3>) -> Aabb3 {
+		points.into_iter()
+			.fold(Aabb3::new_empty(), |bounds, point| bounds.include_point(point))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.min.x >= self.max.x
+		|| self.min.y >= self.max.y
+		|| self.min.z >= self.max.z
+	}
+
+	/// Whether or not any points have been included, unlike `is_empty` which is also true for degenerate bounds.
+	pub fn is_valid(&self) -> bool {
+		self.min.x <= self.max.x
+		&& self.min.y <= self.max.y
+		&& self.min.z <= self.max.z
+	}
+
+	pub fn include_point(&self, point: Vec3) -> Aabb3 {
+		Aabb3 {
+			min: Vec3::new(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z)),
+			max: Vec3::new(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z)),
+		}
+	}
+
+	pub fn union(&self, o: &Aabb3) -> Aabb3 {
+		self.include_point(o.min).include_point(o.max)
+	}
+
+	pub fn contains_point(&self, point: Vec3) -> bool {
+		self.min.x <= point.x && point.x <= self.max.x
+		&& self.min.y <= point.y && point.y <= self.max.y
+		&& self.min.z <= point.z && point.z <= self.max.z
+	}
+
+	pub fn intersects(&self, o: &Aabb3) -> bool {
+		self.min.x <= o.max.x && o.min.x <= self.max.x
+		&& self.min.y <= o.max.y && o.min.y <= self.max.y
+		&& self.min.z <= o.max.z && o.min.z <= self.max.z
+	}
+
+	pub fn closest_point(&self, point: Vec3) -> Vec3 {
+		Vec3::new(
+			point.x.clamp(self.min.x, self.max.x),
+			point.y.clamp(self.min.y, self.max.y),
+			point.z.clamp(self.min.z, self.max.z),
+		)
+	}
+
+	pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+		(self.closest_point(center) - center).length() <= radius
+	}
+
+	pub fn corners(&self) -> [Vec3; 8] {
+		let Aabb3{min, max} = *self;
+
+		[
+			Vec3::new(min.x, min.y, min.z),
+			Vec3::new(max.x, min.y, min.z),
+			Vec3::new(min.x, max.y, min.z),
+			Vec3::new(max.x, max.y, min.z),
+			Vec3::new(min.x, min.y, max.z),
+			Vec3::new(max.x, min.y, max.z),
+			Vec3::new(min.x, max.y, max.z),
+			Vec3::new(max.x, max.y, max.z),
+		]
+	}
+
+	/// The bounds enclosing this Aabb3 after being transformed by `transform`.
+	pub fn transform(&self, transform: &Mat3x4) -> Aabb3 {
+		Aabb3::from_points(self.corners().map(|corner| *transform * corner))
+	}
+
+	pub fn size(&self) -> Vec3 {
+		self.max - self.min
+	}
+
+	pub fn center(&self) -> Vec3 {
+		(self.min + self.max) / 2.0
+	}
+}
@@ -37,6 +37,30 @@ impl Quat {
 		)
 	}
 
+	/// Constructs a rotation from a pure rotation matrix. Results are undefined if `m` contains scale or shear.
+	pub fn from_mat3(m: &Mat3) -> Quat {
+		let [r0, r1, r2] = m.rows;
+		let trace = r0.x + r1.y + r2.z;
+
+		// Pick the largest component to divide by for stability
+		if trace > 0.0 {
+			let s = (trace + 1.0).sqrt() * 2.0;
+			Quat::from_raw((r2.y - r1.z) / s, (r0.z - r2.x) / s, (r1.x - r0.y) / s, 0.25 * s)
+
+		} else if r0.x > r1.y && r0.x > r2.z {
+			let s = (1.0 + r0.x - r1.y - r2.z).sqrt() * 2.0;
+			Quat::from_raw(0.25 * s, (r0.y + r1.x) / s, (r0.z + r2.x) / s, (r2.y - r1.z) / s)
+
+		} else if r1.y > r2.z {
+			let s = (1.0 + r1.y - r0.x - r2.z).sqrt() * 2.0;
+			Quat::from_raw((r0.y + r1.x) / s, 0.25 * s, (r1.z + r2.y) / s, (r0.z - r2.x) / s)
+
+		} else {
+			let s = (1.0 + r2.z - r0.x - r1.y).sqrt() * 2.0;
+			Quat::from_raw((r0.z + r2.x) / s, (r1.z + r2.y) / s, 0.25 * s, (r1.x - r0.y) / s)
+		}
+	}
+
 	pub fn from_pitch(pitch: f32) -> Quat {
 		Quat::new(Vec3::from_x(1.0), pitch)
 	}
@@ -70,6 +94,36 @@ impl Quat {
 		Quat::from_raw(-self.x, -self.y, -self.z, self.w)
 	}
 
+	pub fn dot(&self, o: Quat) -> f32 {
+		self.x*o.x + self.y*o.y + self.z*o.z + self.w*o.w
+	}
+
+	/// Spherical linear interpolation between `self` and `end`, always taking the shortest path.
+	pub fn slerp(&self, end: Quat, t: f32) -> Quat {
+		let mut cos_theta = self.dot(end);
+
+		// q and -q represent the same rotation, so flip to avoid going the long way around
+		let end = if cos_theta < 0.0 {
+			cos_theta = -cos_theta;
+			end * -1.0
+		} else {
+			end
+		};
+
+		// Nearly parallel - sin(theta) approaches zero so fall back to nlerp
+		if cos_theta > 0.9995 {
+			return t.lerp(*self, end).normalize();
+		}
+
+		let theta = cos_theta.acos();
+		let inv_sin_theta = 1.0 / theta.sin();
+
+		let a = ((1.0 - t) * theta).sin() * inv_sin_theta;
+		let b = (t * theta).sin() * inv_sin_theta;
+
+		*self * a + end * b
+	}
+
 	pub fn scale(&self, f: f32) -> Quat {
 		// TODO: improve
 		(*self * f + Quat::identity() * (1.0 - f)).normalize()
@@ -155,7 +209,7 @@ impl Mul<Vec3> for Quat {
 
 
 
-// TODO(pat.m): slerp?
+/// Component-wise lerp. Result is not normalized - see [`Quat::slerp`] for interpolating rotations.
 impl Lerp<Quat> for f32 {
 	fn lerp(self, start: Quat, end: Quat) -> Quat {
 		Quat {
@@ -265,4 +319,43 @@ mod tests {
 		// assert_almost_eq!(r180.pitch(), 0.0);
 		// assert_almost_eq!(r180.roll(), PI);
 	}
+
+	#[test]
+	fn test_slerp() {
+		let a = Quat::from_yaw(0.0);
+		let b = Quat::from_yaw(PI/2.0);
+
+		assert_vec_eq!(a.slerp(b, 0.0).forward(), a.forward());
+		assert_vec_eq!(a.slerp(b, 1.0).forward(), b.forward());
+		assert_vec_eq!(a.slerp(b, 0.5).forward(), Quat::from_yaw(PI/4.0).forward());
+		assert_almost_eq!(a.slerp(b, 0.3).magnitude(), 1.0);
+
+		// Should take the shortest path even if the quats are in opposite hemispheres
+		let neg_b = b * -1.0;
+		assert_vec_eq!(a.slerp(neg_b, 0.5).forward(), Quat::from_yaw(PI/4.0).forward());
+
+		// Nearly parallel quats shouldn't produce NaNs
+		let c = Quat::from_yaw(0.0001);
+		assert_almost_eq!(a.slerp(c, 0.5).magnitude(), 1.0);
+	}
+
+	#[test]
+	fn test_from_mat3() {
+		let rotations = [
+			Quat::identity(),
+			Quat::from_yaw(PI/3.0),
+			Quat::from_pitch(PI),
+			Quat::from_roll(-PI/2.0),
+			Quat::from_yaw(2.0) * Quat::from_pitch(-1.0) * Quat::from_roll(0.5),
+		];
+
+		for q in rotations {
+			let m = Mat3::from_columns([q.right(), q.up(), -q.forward()]);
+			let q2 = Quat::from_mat3(&m);
+
+			assert_vec_eq!(q2.forward(), q.forward());
+			assert_vec_eq!(q2.right(), q.right());
+			assert_vec_eq!(q2.up(), q.up());
+		}
+	}
 }
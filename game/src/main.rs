@@ -10,7 +10,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 	let mut engine = toybox::Engine::new("dungeon-sprites")?;
 
 	let mut gfx = engine.gfx.resource_context(None);
-	let atlas = load_texture(&mut gfx, "assets/atlas.png")?;
+	let atlas = engine.vfs.load_texture(&mut gfx, "atlas.png")?;
+	// Load shaders from the source tree in debug builds so they can be hot reloaded,
+	// but embed them in release builds so the binary doesn't depend on where it was built
+	#[cfg(debug_assertions)]
+	let shader = gfx.load_simple_shader(
+		concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/tex_3d.vert.glsl"),
+		concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/textured.frag.glsl"),
+	)?;
+
+	#[cfg(not(debug_assertions))]
 	let shader = gfx.new_simple_shader(
 		include_str!("shaders/tex_3d.vert.glsl"),
 		include_str!("shaders/textured.frag.glsl"),
@@ -68,25 +77,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 
 
-use std::path::Path;
-
-pub fn load_texture(gfx: &mut gfx::ResourceContext<'_>, path: impl AsRef<Path>) -> Result<gfx::TextureKey, Box<dyn std::error::Error>> {
-	let image = image::open(path)?.flipv().into_rgba8().into_flat_samples();
-	let image_size = Vec2i::new(image.layout.width as i32, image.layout.height as i32);
-	let texture_format = gfx::TextureFormat::srgba();
-
-	let texture = gfx.new_texture(image_size, texture_format);
-
-	{
-		let mut texture = gfx.resources.textures.get_mut(texture);
-		texture.upload_rgba8_raw(&image.samples);
-	}
-
-	Ok(texture)
-}
-
-
-
 #[derive(Default)]
 pub struct SpriteEditorState {
 	selected_cells: Aabb2i,
@@ -14,11 +14,12 @@ if "bpy" in locals():
 	imp.reload(serializer)
 	imp.reload(entity)
 	imp.reload(mesh)
+	imp.reload(collision)
 	imp.reload(anim)
 	imp.reload(util)
 else:
 	import bpy
-	from . import exporter, serializer, entity, mesh, anim, util
+	from . import exporter, serializer, entity, mesh, collision, anim, util
 
 
 # Register and add to the file selector
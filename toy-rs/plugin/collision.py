@@ -0,0 +1,154 @@
+import collections
+import math
+import bmesh
+
+from mathutils import Vector
+from mathutils.bvhtree import BVHTree
+from .util import swap_coords
+
+BoxShape = collections.namedtuple("BoxShape", "center half_extents")
+SphereShape = collections.namedtuple("SphereShape", "center radius")
+CapsuleShape = collections.namedtuple("CapsuleShape", "center radius half_height")
+HullShape = collections.namedtuple("HullShape", "points")
+MeshShape = collections.namedtuple("MeshShape", "positions triangles")
+
+NavMesh = collections.namedtuple("NavMesh", "name vertices polygons")
+NavGrid = collections.namedtuple("NavGrid", "name origin cell_size width depth heights")
+
+COLLISION_PROPERTY = 'collision'
+NAVIGATION_PROPERTY = 'navigation'
+NAV_CELL_SIZE_PROPERTY = 'nav_cell_size'
+
+# Surfaces steeper than this aren't walkable
+NAV_GRID_MAX_SLOPE = math.radians(45.0)
+
+
+
+class CollisionError(Exception):
+	pass
+
+
+# Returns the collision shape tagged on an object in its local space, or None if it isn't tagged
+def collect_collision_shape(depsgraph, obj):
+	shape_type = obj.get(COLLISION_PROPERTY)
+	if shape_type is None:
+		return None
+
+	if shape_type in ('hull', 'mesh'):
+		if obj.type != 'MESH':
+			raise CollisionError(f"'{obj.name}' has '{shape_type}' collision but isn't a mesh")
+
+		bm = bmesh.new()
+		bm.from_object(obj, depsgraph)
+
+		if shape_type == 'hull':
+			result = bmesh.ops.convex_hull(bm, input=bm.verts)
+			hull_verts = {elem for elem in result['geom'] if isinstance(elem, bmesh.types.BMVert)}
+			points = [swap_coords(v.co) for v in hull_verts]
+			bm.free()
+			return HullShape(points)
+
+		bmesh.ops.triangulate(bm, faces=bm.faces)
+		bm.verts.index_update()
+		positions = [swap_coords(v.co) for v in bm.verts]
+		triangles = [[v.index for v in f.verts] for f in bm.faces]
+		bm.free()
+		return MeshShape(positions, triangles)
+
+	center, half_extents = local_bounds(depsgraph, obj)
+
+	if shape_type == 'box':
+		return BoxShape(center, half_extents)
+
+	if shape_type == 'sphere':
+		return SphereShape(center, max(half_extents))
+
+	if shape_type == 'capsule':
+		# Capsules are aligned with blender's Z axis, which becomes Y
+		radius = max(half_extents[0], half_extents[2])
+		half_height = max(half_extents[1] - radius, 0.0)
+		return CapsuleShape(center, radius, half_height)
+
+	raise CollisionError(f"'{obj.name}' has unknown collision shape '{shape_type}'")
+
+
+# Returns the center and half extents of an object's local bounding box, in toy coordinates
+def local_bounds(depsgraph, obj):
+	if obj.type == 'EMPTY':
+		size = obj.empty_display_size
+		return [0.0, 0.0, 0.0], [size, size, size]
+
+	corners = [Vector(corner) for corner in obj.evaluated_get(depsgraph).bound_box]
+	min_corner = Vector(min(c[i] for c in corners) for i in range(3))
+	max_corner = Vector(max(c[i] for c in corners) for i in range(3))
+
+	center = swap_coords((min_corner + max_corner) / 2.0)
+	half = (max_corner - min_corner) / 2.0
+	return center, [half.x, half.z, half.y]
+
+
+
+def collect_navigation(depsgraph, obj):
+	nav_type = obj.get(NAVIGATION_PROPERTY)
+	if nav_type is None:
+		return None
+
+	if obj.type != 'MESH':
+		raise CollisionError(f"'{obj.name}' has '{nav_type}' navigation but isn't a mesh")
+
+	# Navigation data is baked in world space
+	bm = bmesh.new()
+	bm.from_object(obj, depsgraph)
+	bm.transform(obj.matrix_world)
+	bm.verts.index_update()
+
+	try:
+		if nav_type == 'mesh':
+			vertices = [swap_coords(v.co) for v in bm.verts]
+			polygons = [[v.index for v in f.verts] for f in bm.faces]
+			return NavMesh(obj.name, vertices, polygons)
+
+		if nav_type == 'grid':
+			cell_size = float(obj.get(NAV_CELL_SIZE_PROPERTY, 1.0))
+			return bake_nav_grid(obj.name, bm, cell_size)
+
+	finally:
+		bm.free()
+
+	raise CollisionError(f"'{obj.name}' has unknown navigation type '{nav_type}'")
+
+
+def bake_nav_grid(name, bm, cell_size):
+	if cell_size <= 0.0:
+		raise CollisionError(f"'{name}' has invalid nav cell size {cell_size}")
+
+	tree = BVHTree.FromBMesh(bm)
+	min_slope_z = math.cos(NAV_GRID_MAX_SLOPE)
+
+	min_x = min(v.co.x for v in bm.verts)
+	max_x = max(v.co.x for v in bm.verts)
+	min_y = min(v.co.y for v in bm.verts)
+	max_y = max(v.co.y for v in bm.verts)
+	top_z = max(v.co.z for v in bm.verts) + 1.0
+
+	# Toy Z is blender -Y, so the grid starts at blender's max Y
+	origin = [min_x, 0.0, -max_y]
+	width = max(math.ceil((max_x - min_x) / cell_size), 1)
+	depth = max(math.ceil((max_y - min_y) / cell_size), 1)
+
+	heights = []
+	for z in range(depth):
+		for x in range(width):
+			ray_origin = Vector((
+				origin[0] + (x + 0.5) * cell_size,
+				-(origin[2] + (z + 0.5) * cell_size),
+				top_z
+			))
+
+			location, normal, _, _ = tree.ray_cast(ray_origin, Vector((0.0, 0.0, -1.0)))
+			if location is not None and normal.z >= min_slope_z:
+				heights.append(location.z)
+			else:
+				heights.append(math.nan)
+
+	return NavGrid(name, origin, cell_size, width, depth, heights)
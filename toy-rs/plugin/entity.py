@@ -1,8 +1,136 @@
 
 import collections
+import math
 
 Entity = collections.namedtuple(
 	"Entity",
-	"name entity_id mesh_id position rotation scale"
+	"name entity_id mesh_id position rotation scale kind collision properties"
 )
 
+Camera = collections.namedtuple(
+	"Camera",
+	"projection fov_y ortho_height aspect near far"
+)
+
+Light = collections.namedtuple(
+	"Light",
+	"type color energy radius spot_angle spot_blend area_size"
+)
+
+Marker = collections.namedtuple(
+	"Marker",
+	"display size"
+)
+
+
+LIGHT_TYPE_CODES = {
+	'POINT': 'P',
+	'SPOT': 'S',
+	'SUN': 'U',
+	'AREA': 'A',
+}
+
+MARKER_DISPLAY_CODES = {
+	'PLAIN_AXES': 'A',
+	'ARROWS': 'R',
+	'SINGLE_ARROW': 'S',
+	'CIRCLE': 'C',
+	'CUBE': 'U',
+	'SPHERE': 'P',
+	'CONE': 'N',
+	'IMAGE': 'I',
+}
+
+
+
+# The property count is written as a u8, and int properties as i32
+MAX_PROPERTIES = 255
+INT_PROPERTY_RANGE = range(-2**31, 2**31)
+
+
+class PropertyError(Exception):
+	pass
+
+
+def collect_properties(obj):
+	properties = {}
+
+	for key in obj.keys():
+		# Skip blender/addon internal properties
+		if key.startswith('_'):
+			continue
+
+		value = obj[key]
+		if not isinstance(value, (bool, int, float, str)):
+			continue
+
+		# bool must be checked first since it's a subclass of int
+		if not isinstance(value, bool) and isinstance(value, int) and value not in INT_PROPERTY_RANGE:
+			raise PropertyError(f"'{obj.name}' property '{key}' is out of range for a 32 bit int ({value})")
+
+		properties[key] = value
+
+	if len(properties) > MAX_PROPERTIES:
+		raise PropertyError(f"'{obj.name}' has {len(properties)} custom properties, but at most {MAX_PROPERTIES} can be exported")
+
+	return properties
+
+
+# Returns a Camera, Light or Marker for objects with extra data worth exporting, or None
+def collect_kind(scene, obj):
+	if obj.type == 'CAMERA':
+		return collect_camera(scene, obj.data)
+
+	if obj.type == 'LIGHT':
+		return collect_light(obj.data)
+
+	if obj.type == 'EMPTY':
+		return Marker(MARKER_DISPLAY_CODES[obj.empty_display_type], obj.empty_display_size)
+
+	return None
+
+
+def collect_camera(scene, cam):
+	render = scene.render
+	aspect = (render.resolution_x * render.pixel_aspect_x) / (render.resolution_y * render.pixel_aspect_y)
+
+	# The sensor fit determines which dimension the fov and ortho scale apply to.
+	# 'AUTO' fits the larger dimension
+	fit = cam.sensor_fit
+	if fit == 'AUTO':
+		fit = 'HORIZONTAL' if aspect >= 1.0 else 'VERTICAL'
+		angle_x = angle_y = cam.angle
+	else:
+		angle_x = cam.angle_x
+		angle_y = cam.angle_y
+
+	if fit == 'HORIZONTAL':
+		fov_y = 2.0 * math.atan(math.tan(angle_x / 2.0) / aspect)
+		ortho_height = cam.ortho_scale / aspect
+	else:
+		fov_y = angle_y
+		ortho_height = cam.ortho_scale
+
+	projection = 'O' if cam.type == 'ORTHO' else 'P'
+	return Camera(projection, fov_y, ortho_height, aspect, cam.clip_start, cam.clip_end)
+
+
+def collect_light(light):
+	spot_angle = getattr(light, 'spot_size', 0.0)
+	spot_blend = getattr(light, 'spot_blend', 0.0)
+	radius = getattr(light, 'shadow_soft_size', 0.0)
+
+	area_size = (0.0, 0.0)
+	if light.type == 'AREA':
+		size_y = light.size_y if light.shape in {'RECTANGLE', 'ELLIPSE'} else light.size
+		area_size = (light.size, size_y)
+
+	return Light(
+		LIGHT_TYPE_CODES[light.type],
+		light.color[:],
+		light.energy,
+		radius,
+		spot_angle,
+		spot_blend,
+		area_size,
+	)
@@ -3,7 +3,9 @@ import bpy
 
 from bpy_extras.io_utils import ExportHelper
 from bpy.props import StringProperty, BoolProperty
-from . import mesh, entity, serializer
+from . import mesh, entity, serializer, collision
+from .entity import Camera, Light, Marker
+from .collision import BoxShape, SphereShape, CapsuleShape, HullShape, MeshShape
 from .util import swap_coords, swap_coords_scale
 
 # bpy.ops.export.toy_scene(filepath="/home/patrick/Development/wasm-toys/src/bin/fish/main.toy")
@@ -19,7 +21,11 @@ from .util import swap_coords, swap_coords_scale
 
 # version 4: convert vertex colours from srgb to linear
 # version 4: export uvs
-VERSION = 5
+# version 6: 32b vertex/triangle counts, 32b indices for large meshes
+# version 7: collision shapes and navigation data
+# version 8: mesh material slots
+# version 9: vertex normals and tangents
+VERSION = 9
 
 
 
@@ -39,6 +45,18 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 		name="Debug Run"
 	)
 
+	export_normals: BoolProperty(
+		name="Export Normals",
+		description="Export vertex normals, including custom split normals",
+		default=True,
+	)
+
+	export_tangents: BoolProperty(
+		name="Export Tangents",
+		description="Export tangents for meshes with uvs. Requires normals",
+		default=True,
+	)
+
 	def execute(self, context):
 		bpy.context.evaluated_depsgraph_get()
 		debug_run = self.debug_run
@@ -57,12 +75,25 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 		self.entity_ids = {}
 		self.mesh_ids = {}
 		self.mesh_count = 0
+		self.navigation_objects = set()
 
 		for scene in bpy.data.scenes:
 			scene.view_layers[0].update() # to make sure they have a depsgraph
 
 		current_scene = bpy.context.window.scene
 
+		try:
+			self.write_file(fname, debug_run)
+		except (collision.CollisionError, entity.PropertyError) as e:
+			self.report({'ERROR'}, str(e))
+			return {'CANCELLED'}
+		finally:
+			bpy.context.window.scene = current_scene
+
+		return {'FINISHED'}
+
+
+	def write_file(self, fname, debug_run):
 		with open(fname, 'wb') as out:
 			ser = serializer.Serializer(out, debug_run)
 			ser.write_magic_number(VERSION)
@@ -76,8 +107,11 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 				for m in self.collect_meshes(s, depsgraph):
 					mesh.write_mesh(ser, m)
 
+				for nav in self.collect_navigation(s, depsgraph):
+					self.write_navigation(ser, nav)
+
 				entities = []
-				for e in self.collect_entities(s, entities):
+				for e in self.collect_entities(s, depsgraph, entities):
 					self.write_entity(ser, e)
 
 				ser.start_section("SCNE")
@@ -88,10 +122,6 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 					ser.write_u32(e)
 				ser.end_section()
 
-		bpy.context.window.scene = current_scene
-
-		return {'FINISHED'}
-
 
 	def write_entity(self, ser, entity):
 		ser.start_section("ENTY")
@@ -101,9 +131,146 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 		ser.write_v4(*entity.rotation)
 		ser.write_v3(*entity.scale)
 		ser.write_u16(entity.mesh_id)
+
+		ser.write_u8(len(entity.properties))
+		for name, value in entity.properties.items():
+			ser.write_string(name)
+
+			# bool must be checked first since it's a subclass of int
+			if isinstance(value, bool):
+				ser.write_u8(ord('B'))
+				ser.write_u8(int(value))
+			elif isinstance(value, int):
+				ser.write_u8(ord('I'))
+				ser.write_i32(value)
+			elif isinstance(value, float):
+				ser.write_u8(ord('F'))
+				ser.write_f32(value)
+			else:
+				ser.write_u8(ord('S'))
+				ser.write_string(value)
+
+		if isinstance(entity.kind, Camera):
+			self.write_camera(ser, entity.kind)
+		elif isinstance(entity.kind, Light):
+			self.write_light(ser, entity.kind)
+		elif isinstance(entity.kind, Marker):
+			self.write_marker(ser, entity.kind)
+
+		if entity.collision is not None:
+			self.write_collision(ser, entity.collision)
+
 		ser.end_section()
 
 
+	def write_camera(self, ser, camera):
+		ser.start_section("CAMR")
+		ser.write_u8(ord(camera.projection))
+		if camera.projection == 'O':
+			ser.write_f32(camera.ortho_height)
+		else:
+			ser.write_f32(camera.fov_y)
+
+		ser.write_f32(camera.aspect)
+		ser.write_f32(camera.near)
+		ser.write_f32(camera.far)
+		ser.end_section()
+
+
+	def write_light(self, ser, light):
+		ser.start_section("LGHT")
+		ser.write_u8(ord(light.type))
+		ser.write_v3(*light.color)
+		ser.write_f32(light.energy)
+		ser.write_f32(light.radius)
+
+		if light.type == 'S':
+			ser.write_f32(light.spot_angle)
+			ser.write_f32(light.spot_blend)
+		elif light.type == 'A':
+			ser.write_v2(*light.area_size)
+
+		ser.end_section()
+
+
+	def write_marker(self, ser, marker):
+		ser.start_section("MRKR")
+		ser.write_u8(ord(marker.display))
+		ser.write_f32(marker.size)
+		ser.end_section()
+
+
+	def write_collision(self, ser, shape):
+		ser.start_section("COLL")
+
+		if isinstance(shape, BoxShape):
+			ser.write_u8(ord('B'))
+			ser.write_v3(*shape.center)
+			ser.write_v3(*shape.half_extents)
+
+		elif isinstance(shape, SphereShape):
+			ser.write_u8(ord('S'))
+			ser.write_v3(*shape.center)
+			ser.write_f32(shape.radius)
+
+		elif isinstance(shape, CapsuleShape):
+			ser.write_u8(ord('C'))
+			ser.write_v3(*shape.center)
+			ser.write_f32(shape.radius)
+			ser.write_f32(shape.half_height)
+
+		elif isinstance(shape, HullShape):
+			ser.write_u8(ord('H'))
+			ser.write_u32(len(shape.points))
+			for p in shape.points:
+				ser.write_v3(*p)
+
+		elif isinstance(shape, MeshShape):
+			ser.write_u8(ord('M'))
+			ser.write_u32(len(shape.positions))
+			for p in shape.positions:
+				ser.write_v3(*p)
+
+			ser.write_u32(len(shape.triangles))
+			for t in shape.triangles:
+				for i in t:
+					ser.write_u32(i)
+
+		ser.end_section()
+
+
+	def write_navigation(self, ser, nav):
+		if isinstance(nav, collision.NavMesh):
+			ser.start_section("NAVM")
+			ser.write_string(nav.name)
+
+			ser.write_u32(len(nav.vertices))
+			for v in nav.vertices:
+				ser.write_v3(*v)
+
+			ser.write_u32(len(nav.polygons))
+			for p in nav.polygons:
+				ser.write_u8(len(p))
+				for i in p:
+					ser.write_u32(i)
+
+			ser.end_section()
+
+		else:
+			ser.start_section("NAVG")
+			ser.write_string(nav.name)
+			ser.write_v3(*nav.origin)
+			ser.write_f32(nav.cell_size)
+			ser.write_u32(nav.width)
+			ser.write_u32(nav.depth)
+
+			# Unwalkable cells are NaN
+			for h in nav.heights:
+				ser.write_f32(h)
+
+			ser.end_section()
+
+
 	def collect_meshes(self, scene, depsgraph):
 		for obj in scene.objects:
 			if obj.type != 'MESH':
@@ -116,10 +283,21 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 			self.mesh_count += 1
 			self.mesh_ids[odata] = self.mesh_count # ids start at 1
 
-			yield mesh.collect_mesh(scene, depsgraph, obj)
+			yield mesh.collect_mesh(scene, depsgraph, obj, self.export_normals, self.export_tangents)
 
 
-	def collect_entities(self, scene, ent_list):
+	def collect_navigation(self, scene, depsgraph):
+		for obj in scene.objects:
+			if obj in self.navigation_objects:
+				continue
+
+			nav = collision.collect_navigation(depsgraph, obj)
+			if nav is not None:
+				self.navigation_objects.add(obj)
+				yield nav
+
+
+	def collect_entities(self, scene, depsgraph, ent_list):
 		for obj in scene.objects:
 			# Armature encoded with mesh
 			if obj.type == 'ARMATURE':
@@ -140,9 +318,7 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 			if obj.type == 'MESH' and obj.data:
 				mesh_id = self.mesh_ids.get(obj.data, 0)
 
-			# TODO: object type
 			# TODO: collections
-			# TODO: tags
 			# TODO: handle parent transforms
 
 			# going through matrix decompose is lossy, but is better than
@@ -156,6 +332,10 @@ class ExportToyScene(bpy.types.Operator, ExportHelper):
 				swap_coords(position.xyz),
 				swap_coords(rotation), # This okay so long as handedness stays the same
 				swap_coords_scale(scale),
+
+				entity.collect_kind(scene, obj),
+				collision.collect_collision_shape(depsgraph, obj),
+				entity.collect_properties(obj),
 			)
 
 
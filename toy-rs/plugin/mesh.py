@@ -6,15 +6,20 @@ from . import anim
 
 Mesh = collections.namedtuple(
 	"Mesh",
-	"vertices triangles color_data uv_data animation_data"
+	"vertices triangles normals tangents color_data uv_data materials material_indices animation_data"
 )
 
-Vertex = collections.namedtuple("Vertex", "position color_layers uv_layers weights")
+Vertex = collections.namedtuple("Vertex", "position normal tangent color_layers uv_layers weights")
 Bone = collections.namedtuple("Bone", "name head tail")
+Material = collections.namedtuple("Material", "name base_color texture double_sided transparent")
 
+MATERIAL_DOUBLE_SIDED = 1 << 0
+MATERIAL_TRANSPARENT = 1 << 1
+MATERIAL_HAS_TEXTURE = 1 << 2
 
 
-def collect_mesh(scene, depsgraph, obj):
+
+def collect_mesh(scene, depsgraph, obj, export_normals=True, export_tangents=True):
 	# We need to set armature to the rest pose otherwise we get 
 	# mesh data for the current pose instead of the unposed mesh data
 	armature = None
@@ -27,22 +32,41 @@ def collect_mesh(scene, depsgraph, obj):
 			depsgraph.update()
 			break
 
-	# Triangulate and bake deformations
+	# Bake deformations, and collect normals and tangents before triangulating
+	# so custom split normals are kept and tangents match blender's own
+	eval_obj = obj.evaluated_get(depsgraph)
+	eval_mesh = eval_obj.to_mesh()
+	eval_mesh.calc_loop_triangles()
+
+	loop_normals = None
+	loop_tangents = None
+
+	if export_normals:
+		loop_normals = collect_loop_normals(eval_mesh)
+
+		if export_tangents and len(eval_mesh.uv_layers) > 0:
+			loop_tangents = collect_loop_tangents(obj, eval_mesh)
+
 	bm = bmesh.new()
-	bm.from_object(obj, depsgraph)
-	bmesh.ops.triangulate(bm, faces=bm.faces)
+	bm.from_mesh(eval_mesh)
 
 	bm.verts.ensure_lookup_table()
 	bm.faces.ensure_lookup_table()
 
-	# Once we have our triangulated mesh data, we no longer need the original mesh
+	# bmesh faces and loops are created in the same order as the mesh's polygons and loops,
+	# so loop triangles can be mapped back onto bmesh loops
+	bm_loops = {}
+	for polygon in eval_mesh.polygons:
+		for offset, loop in enumerate(bm.faces[polygon.index].loops):
+			bm_loops[polygon.loop_start + offset] = loop
+
+	loop_triangles = [(tri.material_index, list(tri.loops)) for tri in eval_mesh.loop_triangles]
+
+	# Once we have our mesh data, we no longer need the original mesh
 	# so can restore the armature pose
 	if armature is not None:
 		armature.data.pose_position = original_armature_pose_position
 
-	for f in bm.faces:
-		assert len(f.loops) == 3
-
 	layer_deform = bm.verts.layers.deform.active
 
 	color_layers = bm.loops.layers.color.items()
@@ -52,8 +76,8 @@ def collect_mesh(scene, depsgraph, obj):
 
 	# TODO: find a faster way
 
-	def vertex_equivalent(vertex, vpos, vcolorlayers, vuvlayers):
-		if vpos != vertex.position:
+	def vertex_equivalent(vertex, vpos, vnormal, vtangent, vcolorlayers, vuvlayers):
+		if vpos != vertex.position or vnormal != vertex.normal or vtangent != vertex.tangent:
 			return False
 
 		for la, lb in zip(vcolorlayers, vertex.color_layers):
@@ -67,9 +91,9 @@ def collect_mesh(scene, depsgraph, obj):
 		return True
 
 
-	def cache_vertex(vpos, vcolorlayers, vuvlayers, vweights):
+	def cache_vertex(vpos, vnormal, vtangent, vcolorlayers, vuvlayers, vweights):
 		for i, vb in enumerate(verts):
-			if vertex_equivalent(vb, vpos, vcolorlayers, vuvlayers):
+			if vertex_equivalent(vb, vpos, vnormal, vtangent, vcolorlayers, vuvlayers):
 				return i
 
 		# Order by weight so if weights need to be dropped, the most important ones stay
@@ -77,19 +101,39 @@ def collect_mesh(scene, depsgraph, obj):
 			vweights = sorted(vweights, key=lambda x: x[1], reverse=True)
 
 		# NOTE: not comparing weights
-		verts.append(Vertex(vpos, vcolorlayers, vuvlayers, vweights))
+		verts.append(Vertex(vpos, vnormal, vtangent, vcolorlayers, vuvlayers, vweights))
 		return len(verts)-1
 
 
+	# Sort triangles by material so each material can be drawn as one contiguous range of indices
+	materials = [collect_material(slot) for slot in obj.material_slots]
+	material_indices = []
+
+	if len(materials) > 0:
+		loop_triangles.sort(key=lambda tri: tri[0])
+		material_indices = [min(material_index, len(materials)-1) for material_index, _ in loop_triangles]
+
 	# Extract vertices and indices from bmesh
-	for face in bm.faces:
-		for loop in face.loops:
+	for _, tri_loops in loop_triangles:
+		for loop_index in tri_loops:
+			loop = bm_loops[loop_index]
+			vnormal = loop_normals and loop_normals[loop_index]
+			vtangent = loop_tangents and loop_tangents[loop_index]
 			vcolorlayers = [loop[layer_id] for _, layer_id in color_layers]
 			vuvlayers = [loop[layer_id].uv for _, layer_id in uv_layers]
 			vweights = layer_deform and loop.vert[layer_deform].items() or None
-			tris.append(cache_vertex(loop.vert.co, vcolorlayers, vuvlayers, vweights))
+			tris.append(cache_vertex(loop.vert.co, vnormal, vtangent, vcolorlayers, vuvlayers, vweights))
 
 	vert_positions = [swap_coords(v.position) for v in verts]
+	normals = []
+	tangents = []
+
+	if loop_normals is not None:
+		normals = [swap_coords(v.normal) for v in verts]
+
+	if loop_tangents is not None:
+		tangents = [swap_coords(v.tangent[0]) + [v.tangent[1]] for v in verts]
+
 	uv_data = [
 		(name, [v.uv_layers[i].copy() for v in verts])
 		for i, (name, _) in enumerate(uv_layers)
@@ -129,40 +173,94 @@ def collect_mesh(scene, depsgraph, obj):
 			'animations': anim.collect_animations(scene, armature, bones)
 		}
 
-	# TODO: normal data
-
 	bm.free()
+	eval_obj.to_mesh_clear()
+
+	return Mesh(vert_positions, tris, normals, tangents, color_data, uv_data, materials, material_indices, animation_data)
+
+
+def collect_loop_normals(eval_mesh):
+	# corner_normals replaced calc_normals_split in blender 4.1
+	if hasattr(eval_mesh, 'corner_normals'):
+		return [normal.vector.copy().freeze() for normal in eval_mesh.corner_normals]
+
+	eval_mesh.calc_normals_split()
+	return [loop.normal.copy().freeze() for loop in eval_mesh.loops]
+
+
+# Tangents are calculated from the first uv layer, with the bitangent sign stored alongside
+def collect_loop_tangents(obj, eval_mesh):
+	try:
+		eval_mesh.calc_tangents(uvmap=eval_mesh.uv_layers[0].name)
+	except RuntimeError:
+		print("couldn't calculate tangents for '%s' - tangents can only be calculated for tris and quads. skipping..." % (obj.name,))
+		return None
+
+	return [(loop.tangent.copy().freeze(), loop.bitangent_sign) for loop in eval_mesh.loops]
+
+
+def collect_material(slot):
+	material = slot.material
+	if material is None:
+		return Material(slot.name, [1.0, 1.0, 1.0, 1.0], None, False, False)
+
+	base_color = list(material.diffuse_color)
+	texture = None
 
-	return Mesh(vert_positions, tris, color_data, uv_data, animation_data)
+	# Prefer the principled bsdf inputs if there are any, since that's what's actually rendered
+	if material.use_nodes and material.node_tree is not None:
+		for node in material.node_tree.nodes:
+			if node.type != 'BSDF_PRINCIPLED':
+				continue
+
+			base_color_input = node.inputs['Base Color']
+			base_color = list(base_color_input.default_value)
+
+			for link in base_color_input.links:
+				if link.from_node.type == 'TEX_IMAGE' and link.from_node.image is not None:
+					texture = link.from_node.image.name
+
+			break
+
+	double_sided = not material.use_backface_culling
+
+	# blend_method was replaced by surface_render_method in blender 4.2
+	blend_method = getattr(material, 'blend_method', 'OPAQUE')
+	render_method = getattr(material, 'surface_render_method', 'DITHERED')
+	transparent = blend_method not in ('OPAQUE', 'CLIP') or render_method == 'BLENDED'
+
+	return Material(material.name, base_color, texture, double_sided, transparent)
 
 
 def write_mesh(ser, mesh):
 	num_vertices = len(mesh.vertices)
 	num_triangles = len(mesh.triangles) // 3
 
-	# WebGL 1 only supports 16b element arrays
-	assert num_vertices < 65536
 	assert len(mesh.triangles) % 3 == 0
 
 	ser.start_section("MESH")
 
-	ser.write_u16(num_vertices)
+	ser.write_u32(num_vertices)
 	for v in mesh.vertices:
 		ser.write_v3(*v)
 	
-	ser.write_u16(num_triangles)
+	# Use the smallest index encoding that can address every vertex
+	ser.write_u32(num_triangles)
 	if num_vertices < 256:
 		for t in mesh.triangles:
 			ser.write_u8(t)
-	else:
+	elif num_vertices < 65536:
 		for t in mesh.triangles:
 			ser.write_u16(t)
+	else:
+		for t in mesh.triangles:
+			ser.write_u32(t)
 
 	ser.write_u8(len(mesh.color_data))
 	for name, data in mesh.color_data:
 		ser.write_tag("MDTA")
 		ser.write_string(name)
-		ser.write_u16(len(data))
+		ser.write_u32(len(data))
 		for el in data:
 			# Blender seems to store vertex colors in srgb,
 			# but since we're storing floats we might as well store linear colours.
@@ -173,11 +271,25 @@ def write_mesh(ser, mesh):
 	for name, data in mesh.uv_data:
 		ser.write_tag("MDUV")
 		ser.write_string(name)
-		ser.write_u16(len(data))
+		ser.write_u32(len(data))
 		for el in data:
 			ser.write_uf16(el[0])
 			ser.write_uf16(el[1])
 
+	if len(mesh.normals) > 0:
+		ser.start_section("MNRM")
+		ser.write_u32(len(mesh.normals))
+		for normal in mesh.normals:
+			ser.write_v3(*normal)
+		ser.end_section()
+
+	if len(mesh.tangents) > 0:
+		ser.start_section("MTAN")
+		ser.write_u32(len(mesh.tangents))
+		for tangent in mesh.tangents:
+			ser.write_v4(*tangent)
+		ser.end_section()
+
 	if mesh.animation_data is not None:
 		bones = mesh.animation_data['bones']
 		vert_weights = mesh.animation_data['data']
@@ -189,7 +301,7 @@ def write_mesh(ser, mesh):
 			ser.write_v3(*bone.head)
 			ser.write_v3(*bone.tail)
 
-		ser.write_u16(num_vertices)
+		ser.write_u32(num_vertices)
 			
 		def chunks(lst, n):
 			for i in range(0, len(lst), n):
@@ -227,4 +339,38 @@ def write_mesh(ser, mesh):
 
 		ser.end_section()
 
+	if len(mesh.materials) > 0:
+		write_materials(ser, mesh)
+
+	ser.end_section()
+
+
+def write_materials(ser, mesh):
+	ser.start_section("MTLS")
+	ser.write_u16(len(mesh.materials))
+
+	for material in mesh.materials:
+		flags = 0
+		if material.double_sided:
+			flags |= MATERIAL_DOUBLE_SIDED
+		if material.transparent:
+			flags |= MATERIAL_TRANSPARENT
+		if material.texture is not None:
+			flags |= MATERIAL_HAS_TEXTURE
+
+		ser.write_string(material.name)
+		ser.write_v4(*material.base_color)
+		ser.write_u8(flags)
+
+		if material.texture is not None:
+			ser.write_string(material.texture)
+
+	# Like vertex indices, use the smallest encoding that can address every material
+	if len(mesh.materials) <= 256:
+		for index in mesh.material_indices:
+			ser.write_u8(index)
+	else:
+		for index in mesh.material_indices:
+			ser.write_u16(index)
+
 	ser.end_section()
\ No newline at end of file
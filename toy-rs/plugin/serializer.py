@@ -7,6 +7,7 @@ import math
 STRUCT_U8 = struct.Struct('=B')
 STRUCT_U16 = struct.Struct('=H')
 STRUCT_U32 = struct.Struct('=I')
+STRUCT_I32 = struct.Struct('=i')
 
 STRUCT_UF16 = struct.Struct('=H')
 STRUCT_F32 = struct.Struct('=f')
@@ -84,6 +85,9 @@ class Serializer:
 	def write_u32(self, v):
 		self.write_raw(STRUCT_U32, v)
 
+	def write_i32(self, v):
+		self.write_raw(STRUCT_I32, v)
+
 
 	def start_section(self, tag):
 		section_buff = Buffer(tag)
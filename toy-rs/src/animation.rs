@@ -0,0 +1,277 @@
+use crate::types::*;
+use common::*;
+
+
+/// How sample times outside of an animations range are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationMode {
+	/// Wrap time around so the animation repeats forever.
+	Loop,
+
+	/// Hold the first/last frame outside of the animations range.
+	Clamp,
+}
+
+
+/// The local transforms of each bone in a [`MeshAnimationData`] at some point in time, indexed by bone.
+/// Can be turned into matrices suitable for skinning with [`MeshAnimationData::pose_matrices`].
+#[derive(Debug, Clone)]
+pub struct MeshPose {
+	pub bones: Vec<MeshAnimationFrame>,
+}
+
+
+impl MeshAnimationFrame {
+	/// A frame describing a bone sitting in its rest pose.
+	pub fn rest(bone: &MeshBone) -> MeshAnimationFrame {
+		MeshAnimationFrame {
+			position: bone.head,
+			rotation: Quat::identity(),
+			scale: Vec3::splat(1.0),
+		}
+	}
+
+	/// Interpolates position and scale linearly, and rotation spherically.
+	pub fn blend(&self, other: &MeshAnimationFrame, t: f32) -> MeshAnimationFrame {
+		MeshAnimationFrame {
+			position: t.lerp(self.position, other.position),
+			rotation: self.rotation.slerp(other.rotation, t),
+			scale: t.lerp(self.scale, other.scale),
+		}
+	}
+
+	/// Calculates the matrix that takes vertices in the rest pose of `bone` into this frame.
+	pub fn to_mat3x4(&self, bone: &MeshBone) -> Mat3x4 {
+		Mat3x4::translate(self.position)
+			* self.rotation.to_mat3x4()
+			* Mat3x4::scale(self.scale)
+			* Mat3x4::translate(-bone.head)
+	}
+}
+
+
+impl MeshAnimation {
+	pub fn num_frames(&self) -> usize {
+		self.channels.first()
+			.map_or(0, |channel| channel.frames.len())
+	}
+
+	/// Length of the animation in seconds.
+	pub fn duration(&self) -> f32 {
+		self.num_frames().saturating_sub(1) as f32 / self.fps
+	}
+
+	/// Samples a single channel at `time` seconds.
+	/// Returns `None` if the animation has no frames, or `channel` has fewer frames than the rest of the animation.
+	pub fn sample_channel(&self, channel: &MeshAnimationChannel, time: f32, mode: AnimationMode) -> Option<MeshAnimationFrame> {
+		let num_frames = self.num_frames();
+		if num_frames == 0 || channel.frames.len() < num_frames {
+			return None
+		}
+
+		let (frame, next_frame, t) = self.frame_position(time, mode);
+		Some(channel.frames[frame].blend(&channel.frames[next_frame], t))
+	}
+
+	/// Samples all channels at `time` seconds, writing the result into the associated bones of `pose`.
+	/// Bones not affected by this animation, or whose channels are too short to sample, are left untouched.
+	pub fn sample_into(&self, pose: &mut MeshPose, time: f32, mode: AnimationMode) {
+		for channel in self.channels.iter() {
+			let Some(bone) = pose.bones.get_mut(channel.bone) else { continue };
+
+			if let Some(frame) = self.sample_channel(channel, time, mode) {
+				*bone = frame;
+			}
+		}
+	}
+
+	/// Calculates the pair of frames to interpolate between for a given time, and how far between them
+	/// `time` falls.
+	fn frame_position(&self, time: f32, mode: AnimationMode) -> (usize, usize, f32) {
+		let last_frame = self.num_frames().saturating_sub(1);
+		if last_frame == 0 {
+			return (0, 0, 0.0)
+		}
+
+		let frame_time = time * self.fps;
+		let frame_time = match mode {
+			AnimationMode::Loop => frame_time.rem_euclid(last_frame as f32),
+			AnimationMode::Clamp => frame_time.clamp(0.0, last_frame as f32),
+		};
+
+		let frame = (frame_time.floor() as usize).min(last_frame);
+		let next_frame = (frame + 1).min(last_frame);
+
+		(frame, next_frame, frame_time - frame as f32)
+	}
+}
+
+
+impl MeshAnimationData {
+	pub fn find_animation(&self, name: &str) -> Option<&MeshAnimation> {
+		self.animations.iter()
+			.find(|anim| anim.name == name)
+	}
+
+	pub fn find_bone(&self, name: &str) -> Option<usize> {
+		self.bones.iter()
+			.position(|bone| bone.name == name)
+	}
+
+	pub fn rest_pose(&self) -> MeshPose {
+		MeshPose {
+			bones: self.bones.iter().map(MeshAnimationFrame::rest).collect(),
+		}
+	}
+
+	/// Samples `animation` at `time` seconds. Bones not affected by `animation` will be in their rest pose.
+	pub fn sample(&self, animation: &MeshAnimation, time: f32, mode: AnimationMode) -> MeshPose {
+		let mut pose = self.rest_pose();
+		animation.sample_into(&mut pose, time, mode);
+		pose
+	}
+
+	/// Samples two animations independently and blends between them.
+	/// A `blend` of 0.0 results in the pose from `from`, and 1.0 results in the pose from `to`.
+	pub fn sample_crossfade(&self, from: (&MeshAnimation, f32), to: (&MeshAnimation, f32), mode: AnimationMode, blend: f32) -> MeshPose {
+		let (from_anim, from_time) = from;
+		let (to_anim, to_time) = to;
+
+		let from_pose = self.sample(from_anim, from_time, mode);
+		let to_pose = self.sample(to_anim, to_time, mode);
+		from_pose.blend(&to_pose, blend)
+	}
+
+	/// Calculates skinning matrices for each bone in `pose`, in the same order as [`MeshAnimationData::bones`].
+	pub fn pose_matrices(&self, pose: &MeshPose) -> Vec<Mat3x4> {
+		let mut matrices = Vec::with_capacity(self.bones.len());
+		self.pose_matrices_into(pose, &mut matrices);
+		matrices
+	}
+
+	/// Same as [`MeshAnimationData::pose_matrices`] but reuses an existing allocation.
+	pub fn pose_matrices_into(&self, pose: &MeshPose, matrices: &mut Vec<Mat3x4>) {
+		matrices.clear();
+		matrices.extend(self.bones.iter().zip(&pose.bones)
+			.map(|(bone, frame)| frame.to_mat3x4(bone)));
+	}
+}
+
+
+impl MeshPose {
+	pub fn blend(&self, other: &MeshPose, t: f32) -> MeshPose {
+		MeshPose {
+			bones: self.bones.iter().zip(&other.bones)
+				.map(|(a, b)| a.blend(b, t))
+				.collect(),
+		}
+	}
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_util::assert_vec3_eq;
+
+	fn translation_frame(x: f32) -> MeshAnimationFrame {
+		MeshAnimationFrame {
+			position: Vec3::new(x, 0.0, 0.0),
+			rotation: Quat::identity(),
+			scale: Vec3::splat(1.0),
+		}
+	}
+
+	/// Two bones, and an animation moving the first from x=0 to x=2 over two seconds.
+	fn animation_data() -> MeshAnimationData {
+		let bone = |name: &str, head| MeshBone { name: name.into(), head, tail: head + Vec3::new(0.0, 1.0, 0.0) };
+
+		MeshAnimationData {
+			bones: vec![bone("root", Vec3::zero()), bone("arm", Vec3::new(0.0, 1.0, 0.0))],
+			weights: Vec::new(),
+			animations: vec![
+				MeshAnimation {
+					name: "move".into(),
+					fps: 1.0,
+					channels: vec![MeshAnimationChannel { bone: 0, frames: (0..3).map(|x| translation_frame(x as f32)).collect() }],
+				},
+				MeshAnimation {
+					name: "hold".into(),
+					fps: 1.0,
+					channels: vec![MeshAnimationChannel { bone: 0, frames: vec![translation_frame(4.0)] }],
+				},
+			],
+		}
+	}
+
+	fn root_x(data: &MeshAnimationData, animation: &str, time: f32, mode: AnimationMode) -> f32 {
+		let animation = data.find_animation(animation).unwrap();
+		data.sample(animation, time, mode).bones[0].position.x
+	}
+
+	#[test]
+	fn sample_frames() {
+		let data = animation_data();
+		let animation = data.find_animation("move").unwrap();
+		assert_eq!(animation.num_frames(), 3);
+		assert_eq!(animation.duration(), 2.0);
+
+		assert_eq!(root_x(&data, "move", 0.0, AnimationMode::Clamp), 0.0);
+		assert_eq!(root_x(&data, "move", 0.5, AnimationMode::Clamp), 0.5);
+		assert_eq!(root_x(&data, "move", 1.75, AnimationMode::Clamp), 1.75);
+		assert_eq!(root_x(&data, "move", 2.0, AnimationMode::Clamp), 2.0);
+
+		// Single frame animations hold their only frame
+		assert_eq!(root_x(&data, "hold", 3.0, AnimationMode::Loop), 4.0);
+
+		// Bones without channels stay in their rest pose
+		let pose = data.sample(animation, 1.0, AnimationMode::Loop);
+		assert_vec3_eq(pose.bones[1].position, data.bones[1].head);
+	}
+
+	#[test]
+	fn loop_and_clamp() {
+		let data = animation_data();
+
+		assert_eq!(root_x(&data, "move", -1.0, AnimationMode::Clamp), 0.0);
+		assert_eq!(root_x(&data, "move", 5.0, AnimationMode::Clamp), 2.0);
+
+		assert_eq!(root_x(&data, "move", 2.5, AnimationMode::Loop), 0.5);
+		assert_eq!(root_x(&data, "move", 4.0, AnimationMode::Loop), 0.0);
+		assert_eq!(root_x(&data, "move", -0.5, AnimationMode::Loop), 1.5);
+	}
+
+	#[test]
+	fn blend_poses() {
+		let data = animation_data();
+		let moving = data.find_animation("move").unwrap();
+		let holding = data.find_animation("hold").unwrap();
+
+		let pose = data.sample_crossfade((moving, 2.0), (holding, 0.0), AnimationMode::Clamp, 0.25);
+		assert_eq!(pose.bones[0].position.x, 2.5);
+
+		let rotated = MeshAnimationFrame { rotation: Quat::from_yaw(std::f32::consts::PI / 2.0), ..translation_frame(0.0) };
+		let halfway = translation_frame(0.0).blend(&rotated, 0.5);
+		assert!((halfway.rotation.dot(Quat::from_yaw(std::f32::consts::PI / 4.0)).abs() - 1.0).abs() < 0.0001);
+	}
+
+	#[test]
+	fn empty_and_short_channels() {
+		let mut data = animation_data();
+		data.animations[0].channels.push(MeshAnimationChannel { bone: 1, frames: vec![translation_frame(9.0)] });
+		data.animations[1].channels.clear();
+
+		let animation = &data.animations[0];
+		assert!(animation.sample_channel(&animation.channels[1], 1.0, AnimationMode::Loop).is_none());
+
+		let pose = data.sample(animation, 1.0, AnimationMode::Loop);
+		assert_eq!(pose.bones[0].position.x, 1.0);
+		assert_vec3_eq(pose.bones[1].position, data.bones[1].head);
+
+		let empty = MeshAnimation { name: "empty".into(), fps: 30.0, channels: vec![MeshAnimationChannel { bone: 0, frames: Vec::new() }] };
+		assert_eq!(empty.duration(), 0.0);
+		assert!(empty.sample_channel(&empty.channels[0], 1.0, AnimationMode::Clamp).is_none());
+		assert_vec3_eq(data.sample(&empty, 1.0, AnimationMode::Clamp).bones[0].position, Vec3::zero());
+	}
+}
@@ -0,0 +1,393 @@
+//! Dumps, validates and compares toy files.
+//!
+//! ```text
+//! toy-inspect dump <file.toy>
+//! toy-inspect validate <file.toy>...
+//! toy-inspect diff <a.toy> <b.toy>
+//! toy-inspect optimize <file.toy>
+//! ```
+//! `validate` exits with a non-zero status if any file fails to load or has warnings, so it can be used
+//! in asset pipelines. `optimize` reports what each [`toy::optimize`] pass would achieve for each mesh.
+
+use toy::validate::validate;
+use toy::optimize::{optimize_project, OptimizeOptions};
+use toy::{Project, SceneRef, Entity, EntityRef, Mesh, SectionInfo};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Transform components closer than this are considered unchanged by `diff`.
+const DIFF_EPSILON: f32 = 0.0001;
+
+
+fn main() -> Result<(), Box<dyn Error>> {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+
+	match args.as_slice() {
+		[command, path] if command == "dump" => {
+			let data = std::fs::read(path)?;
+			let (result, sections) = toy::load_with_sections(&data);
+
+			println!("{} ({} bytes)", path, data.len());
+			println!();
+			dump_sections(&sections);
+
+			let project = result.map_err(|err| format!("Failed to load '{}': {}", path, err))?;
+			dump_project(&project);
+		}
+
+		[command, paths @ ..] if command == "validate" && !paths.is_empty() => {
+			let mut failed = false;
+
+			for path in paths {
+				let project = match load_file(path) {
+					Ok(project) => project,
+					Err(err) => {
+						println!("{}: {}", path, err);
+						failed = true;
+						continue
+					}
+				};
+
+				let warnings = validate(&project);
+				failed |= !warnings.is_empty();
+
+				for warning in warnings {
+					println!("{}: {}", path, warning);
+				}
+			}
+
+			if failed {
+				std::process::exit(2);
+			}
+		}
+
+		[command, path_a, path_b] if command == "diff" => {
+			let project_a = load_file(path_a)?;
+			let project_b = load_file(path_b)?;
+
+			let differences = diff_projects(&project_a, &project_b);
+			for difference in differences.iter() {
+				println!("{}", difference);
+			}
+
+			if !differences.is_empty() {
+				std::process::exit(1);
+			}
+		}
+
+		[command, path] if command == "optimize" => {
+			let mut project = load_file(path)?;
+			let reports = optimize_project(&mut project, &OptimizeOptions::default())
+				.map_err(|err| format!("Failed to optimize '{}': {}", path, err))?;
+
+			for (mesh_index, mesh_reports) in reports.iter().enumerate() {
+				println!("Mesh {}:", mesh_index);
+
+				for report in mesh_reports {
+					println!("  {}", report);
+				}
+			}
+		}
+
+		_ => {
+			eprintln!("usage: toy-inspect dump <file.toy>");
+			eprintln!("       toy-inspect validate <file.toy>...");
+			eprintln!("       toy-inspect diff <a.toy> <b.toy>");
+			eprintln!("       toy-inspect optimize <file.toy>");
+			std::process::exit(1);
+		}
+	}
+
+	Ok(())
+}
+
+
+fn load_file(path: &str) -> Result<Project, Box<dyn Error>> {
+	let data = std::fs::read(path)?;
+	let project = toy::load(&data)
+		.map_err(|err| format!("Failed to load '{}': {}", path, err))?;
+
+	Ok(project)
+}
+
+
+
+fn dump_sections(sections: &[SectionInfo]) {
+	println!("Sections:");
+
+	for section in sections {
+		let indent = "  ".repeat(section.depth + 1);
+		println!("{}{} @ {:#x} ({} bytes)", indent, section.tag, section.offset, section.size);
+	}
+
+	println!();
+}
+
+
+fn dump_project(project: &Project) {
+	println!("Scenes:");
+	for scene in project.scenes() {
+		println!("  '{}' ({} entities)", scene.name, scene.entities.len());
+	}
+
+	println!();
+	println!("Entities:");
+	for entity in project.entities.iter() {
+		let Entity { position: p, rotation: r, scale: s, .. } = *entity;
+
+		println!("  '{}' {}", entity.name, describe_kind(entity));
+		println!("    position ({:.3}, {:.3}, {:.3})  rotation ({:.3}, {:.3}, {:.3}, {:.3})  scale ({:.3}, {:.3}, {:.3})",
+			p.x, p.y, p.z, r.x, r.y, r.z, r.w, s.x, s.y, s.z);
+
+		if entity.mesh_id != 0 {
+			println!("    mesh {}", entity.mesh_id - 1);
+		}
+
+		if let Some(collision) = &entity.collision {
+			println!("    collision {:?}", collision);
+		}
+
+		let properties: BTreeMap<_, _> = entity.properties.iter().collect();
+		for (name, value) in properties {
+			println!("    property '{}' = {:?}", name, value);
+		}
+	}
+
+	println!();
+	println!("Meshes:");
+	for (mesh_index, mesh) in project.meshes.iter().enumerate() {
+		println!("  {}: {}", mesh_index, describe_mesh(mesh));
+
+		if !mesh.normals.is_empty() || !mesh.tangents.is_empty() {
+			println!("    {} normals, {} tangents", mesh.normals.len(), mesh.tangents.len());
+		}
+
+		for layer in mesh.color_layers.iter() {
+			println!("    color layer '{}'", layer.name);
+		}
+
+		for layer in mesh.uv_layers.iter() {
+			println!("    uv layer '{}'", layer.name);
+		}
+
+		for (material_index, material) in mesh.materials.iter().enumerate() {
+			let num_triangles = mesh.material_indices.iter().filter(|&&index| index as usize == material_index).count();
+			let c = material.base_color;
+
+			println!("    material '{}': {} triangles, color ({:.3}, {:.3}, {:.3}, {:.3}), texture {:?}{}{}",
+				material.name, num_triangles, c.x, c.y, c.z, c.w, material.texture,
+				if material.double_sided { ", double sided" } else { "" },
+				if material.transparent { ", transparent" } else { "" });
+		}
+
+		if let Some(animation_data) = &mesh.animation_data {
+			println!("    {} bones", animation_data.bones.len());
+
+			for animation in animation_data.animations.iter() {
+				println!("    animation '{}': {} channels, {} frames @ {} fps ({:.2}s)",
+					animation.name, animation.channels.len(), animation.num_frames(), animation.fps,
+					animation.duration());
+			}
+		}
+	}
+
+	for nav_mesh in project.nav_meshes.iter() {
+		println!();
+		println!("Nav mesh '{}': {} vertices, {} polygons", nav_mesh.name, nav_mesh.vertices.len(), nav_mesh.polygons.len());
+	}
+
+	for nav_grid in project.nav_grids.iter() {
+		let walkable = nav_grid.heights.iter().filter(|height| height.is_some()).count();
+
+		println!();
+		println!("Nav grid '{}': {}x{} cells of size {}, {} walkable",
+			nav_grid.name, nav_grid.width, nav_grid.depth, nav_grid.cell_size, walkable);
+	}
+
+	let warnings = validate(project);
+	if !warnings.is_empty() {
+		println!();
+		println!("Warnings:");
+
+		for warning in warnings {
+			println!("  {}", warning);
+		}
+	}
+}
+
+
+fn describe_kind(entity: &Entity) -> String {
+	use toy::EntityKind;
+
+	match &entity.kind {
+		EntityKind::Object => "object".into(),
+		EntityKind::Camera(camera) => format!("camera {:?}", camera),
+		EntityKind::Light(light) => format!("light {:?}", light),
+		EntityKind::Marker(marker) => format!("marker {:?}", marker),
+	}
+}
+
+fn describe_mesh(mesh: &Mesh) -> String {
+	format!("{} vertices, {} triangles, {} materials", mesh.positions.len(), mesh.num_triangles(), mesh.materials.len())
+}
+
+
+
+fn diff_projects(a: &Project, b: &Project) -> Vec<String> {
+	let mut differences = Vec::new();
+
+	let scenes_a: BTreeMap<_, _> = a.scenes.iter().map(|scene| (scene.name.as_str(), SceneRef::from(a, scene))).collect();
+	let scenes_b: BTreeMap<_, _> = b.scenes.iter().map(|scene| (scene.name.as_str(), SceneRef::from(b, scene))).collect();
+
+	for (&name, scene_a) in scenes_a.iter() {
+		let Some(scene_b) = scenes_b.get(name) else {
+			differences.push(format!("- scene '{}'", name));
+			continue
+		};
+
+		let entities_a: Vec<_> = scene_a.entities().map(|entity| entity.name.clone()).collect();
+		let entities_b: Vec<_> = scene_b.entities().map(|entity| entity.name.clone()).collect();
+
+		for entity in unmatched_names(&entities_a, &entities_b) {
+			differences.push(format!("  scene '{}': - entity '{}'", name, entity));
+		}
+
+		for entity in unmatched_names(&entities_b, &entities_a) {
+			differences.push(format!("  scene '{}': + entity '{}'", name, entity));
+		}
+	}
+
+	for &name in scenes_b.keys().filter(|name| !scenes_a.contains_key(*name)) {
+		differences.push(format!("+ scene '{}'", name));
+	}
+
+	// Entities sharing a name are paired up in the order they appear in each file
+	let entities_a = entities_by_name(a);
+	let entities_b = entities_by_name(b);
+
+	for (&name, group_a) in entities_a.iter() {
+		let group_b = entities_b.get(name).map_or(&[][..], Vec::as_slice);
+		let label = |index| entity_label(name, index, group_a.len().max(group_b.len()));
+
+		for (index, entity_a) in group_a.iter().enumerate() {
+			let Some(entity_b) = group_b.get(index) else {
+				differences.push(format!("- entity {}", label(index)));
+				continue
+			};
+
+			let changes = diff_entities(entity_a, entity_a.mesh(), entity_b, entity_b.mesh());
+			for change in changes {
+				differences.push(format!("~ entity {}: {}", label(index), change));
+			}
+		}
+	}
+
+	for (&name, group_b) in entities_b.iter() {
+		let num_in_a = entities_a.get(name).map_or(0, Vec::len);
+
+		for index in num_in_a..group_b.len() {
+			differences.push(format!("+ entity {}", entity_label(name, index, group_b.len())));
+		}
+	}
+
+	differences
+}
+
+fn entities_by_name(project: &Project) -> BTreeMap<&str, Vec<EntityRef<'_>>> {
+	let mut entities: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+	for entity in project.entities.iter() {
+		entities.entry(entity.name.as_str())
+			.or_default()
+			.push(EntityRef::from(project, entity));
+	}
+
+	entities
+}
+
+/// Quotes `name`, numbering it by its position among same named entities if there are several.
+fn entity_label(name: &str, index: usize, group_size: usize) -> String {
+	if group_size > 1 {
+		format!("'{}' #{}", name, index + 1)
+	} else {
+		format!("'{}'", name)
+	}
+}
+
+/// Names in `names` left over after pairing each with an equal name in `others`, so repeated names are counted.
+fn unmatched_names<'n>(names: &'n [String], others: &[String]) -> Vec<&'n String> {
+	let mut remaining: Vec<&String> = others.iter().collect();
+
+	names.iter()
+		.filter(|&name| match remaining.iter().position(|&other| other == name) {
+			Some(position) => { remaining.swap_remove(position); false }
+			None => true,
+		})
+		.collect()
+}
+
+
+fn diff_entities(a: &Entity, mesh_a: Option<&Mesh>, b: &Entity, mesh_b: Option<&Mesh>) -> Vec<String> {
+	let mut changes = Vec::new();
+
+	let differs = |values_a: &[f32], values_b: &[f32]| {
+		values_a.iter().zip(values_b)
+			.any(|(a, b)| (a - b).abs() > DIFF_EPSILON || a.is_nan() != b.is_nan())
+	};
+
+	if differs(&[a.position.x, a.position.y, a.position.z], &[b.position.x, b.position.y, b.position.z]) {
+		changes.push(format!("position ({:.3}, {:.3}, {:.3}) -> ({:.3}, {:.3}, {:.3})",
+			a.position.x, a.position.y, a.position.z, b.position.x, b.position.y, b.position.z));
+	}
+
+	if differs(&[a.rotation.x, a.rotation.y, a.rotation.z, a.rotation.w], &[b.rotation.x, b.rotation.y, b.rotation.z, b.rotation.w]) {
+		changes.push(format!("rotation ({:.3}, {:.3}, {:.3}, {:.3}) -> ({:.3}, {:.3}, {:.3}, {:.3})",
+			a.rotation.x, a.rotation.y, a.rotation.z, a.rotation.w, b.rotation.x, b.rotation.y, b.rotation.z, b.rotation.w));
+	}
+
+	if differs(&[a.scale.x, a.scale.y, a.scale.z], &[b.scale.x, b.scale.y, b.scale.z]) {
+		changes.push(format!("scale ({:.3}, {:.3}, {:.3}) -> ({:.3}, {:.3}, {:.3})",
+			a.scale.x, a.scale.y, a.scale.z, b.scale.x, b.scale.y, b.scale.z));
+	}
+
+	let kind_a = describe_kind(a);
+	let kind_b = describe_kind(b);
+	if kind_a != kind_b {
+		changes.push(format!("{} -> {}", kind_a, kind_b));
+	}
+
+	match (mesh_a, mesh_b) {
+		(Some(mesh_a), Some(mesh_b)) => {
+			let (stats_a, stats_b) = (describe_mesh(mesh_a), describe_mesh(mesh_b));
+			if stats_a != stats_b {
+				changes.push(format!("mesh {} -> {}", stats_a, stats_b));
+			}
+		}
+
+		(None, Some(_)) => changes.push("mesh added".into()),
+		(Some(_), None) => changes.push("mesh removed".into()),
+		(None, None) => {}
+	}
+
+	if format!("{:?}", a.collision) != format!("{:?}", b.collision) {
+		changes.push("collision shape changed".into());
+	}
+
+	let mut property_names: Vec<_> = a.properties.keys().chain(b.properties.keys()).collect();
+	property_names.sort();
+	property_names.dedup();
+
+	for name in property_names {
+		match (a.properties.get(name), b.properties.get(name)) {
+			(Some(value_a), Some(value_b)) if value_a != value_b => {
+				changes.push(format!("property '{}' {:?} -> {:?}", name, value_a, value_b));
+			}
+
+			(Some(_), None) => changes.push(format!("- property '{}'", name)),
+			(None, Some(value)) => changes.push(format!("+ property '{}' = {:?}", name, value)),
+			_ => {}
+		}
+	}
+
+	changes
+}
@@ -0,0 +1,203 @@
+use crate::types::*;
+use common::*;
+
+
+/// A collision shape in the local space of the entity it is attached to.
+/// Tagged in blender with a `collision` custom property of `box`, `sphere`, `capsule`, `hull` or `mesh`.
+#[derive(Debug, Clone)]
+pub enum CollisionShape {
+	Box { center: Vec3, half_extents: Vec3 },
+	Sphere { center: Vec3, radius: f32 },
+
+	/// A capsule aligned with the local Y axis. `half_height` is the distance from `center` to the center
+	/// of either cap.
+	Capsule { center: Vec3, radius: f32, half_height: f32 },
+
+	/// The vertices of a convex hull.
+	ConvexHull { points: Vec<Vec3> },
+
+	/// Triangle list geometry, separate from any render mesh attached to the same entity.
+	TriangleMesh { positions: Vec<Vec3>, indices: Vec<u32> },
+}
+
+
+/// A [`CollisionShape`] with its entity's transform applied.
+/// Entity scale is folded into sizes, so shapes may be distorted if an entity has non-uniform scale
+/// that the shape can't represent, e.g., a sphere scaled along one axis.
+#[derive(Debug, Clone)]
+pub enum WorldCollisionShape {
+	/// An oriented box.
+	Box { center: Vec3, rotation: Quat, half_extents: Vec3 },
+	Sphere { center: Vec3, radius: f32 },
+
+	/// A capsule between the centers of its two caps.
+	Capsule { start: Vec3, end: Vec3, radius: f32 },
+	ConvexHull { points: Vec<Vec3> },
+	TriangleMesh { positions: Vec<Vec3>, indices: Vec<u32> },
+}
+
+
+impl Project {
+	/// Iterates over every entity with a collision shape, along with its shape in world space.
+	pub fn collision_shapes(&self) -> impl Iterator<Item=(EntityRef<'_>, WorldCollisionShape)> {
+		self.entities()
+			.filter_map(|entity| entity.world_collision_shape().map(|shape| (entity, shape)))
+	}
+}
+
+impl<'t> SceneRef<'t> {
+	/// Same as [`Project::collision_shapes`] but only for entities in this scene.
+	pub fn collision_shapes(&self) -> impl Iterator<Item=(EntityRef<'t>, WorldCollisionShape)> {
+		self.entities()
+			.filter_map(|entity| entity.world_collision_shape().map(|shape| (entity, shape)))
+	}
+}
+
+
+impl Entity {
+	pub fn world_collision_shape(&self) -> Option<WorldCollisionShape> {
+		let shape = self.collision.as_ref()?;
+		let transform = self.transform();
+		let scale = Vec3::new(self.scale.x.abs(), self.scale.y.abs(), self.scale.z.abs());
+
+		let world_shape = match *shape {
+			CollisionShape::Box { center, half_extents } => WorldCollisionShape::Box {
+				center: transform * center,
+				rotation: self.rotation,
+				half_extents: Vec3::new(half_extents.x * scale.x, half_extents.y * scale.y, half_extents.z * scale.z),
+			},
+
+			CollisionShape::Sphere { center, radius } => WorldCollisionShape::Sphere {
+				center: transform * center,
+				radius: radius * scale.x.max(scale.y).max(scale.z),
+			},
+
+			CollisionShape::Capsule { center, radius, half_height } => WorldCollisionShape::Capsule {
+				start: transform * (center - Vec3::from_y(half_height)),
+				end: transform * (center + Vec3::from_y(half_height)),
+				radius: radius * scale.x.max(scale.z),
+			},
+
+			CollisionShape::ConvexHull { ref points } => WorldCollisionShape::ConvexHull {
+				points: points.iter().map(|&point| transform * point).collect(),
+			},
+
+			CollisionShape::TriangleMesh { ref positions, ref indices } => WorldCollisionShape::TriangleMesh {
+				positions: positions.iter().map(|&position| transform * position).collect(),
+				indices: indices.clone(),
+			},
+		};
+
+		Some(world_shape)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{load, ToyResult};
+	use crate::test_util::*;
+
+	fn load_single_collision_shape(build: impl FnOnce(&mut Writer)) -> ToyResult<CollisionShape> {
+		let data = Writer::file(|w| {
+			w.entity(Vec3::zero(), Quat::identity(), Vec3::splat(1.0), |w| {
+				w.section(b"COLL", build);
+			});
+		});
+
+		let project = load(&data)?;
+		Ok(project.entities[0].collision.clone().expect("Entity missing collision shape"))
+	}
+
+	#[test]
+	fn box_collision_world_transform() {
+		let rotation = Quat::from_yaw(PI / 2.0);
+
+		let data = Writer::file(|w| {
+			w.entity(Vec3::new(10.0, 0.0, 0.0), rotation, Vec3::new(2.0, 1.0, -3.0), |w| {
+				w.section(b"COLL", |w| {
+					w.u8(b'B').vec3(Vec3::new(1.0, 0.0, 0.0)).vec3(Vec3::new(1.0, 2.0, 3.0));
+				});
+			});
+		});
+
+		let project = load(&data).unwrap();
+		let (_, shape) = project.collision_shapes().next().unwrap();
+
+		match shape {
+			WorldCollisionShape::Box { center, rotation: box_rotation, half_extents } => {
+				// Local +X scaled by 2, then rotated a quarter turn about Y onto -Z
+				assert_vec3_eq(center, Vec3::new(10.0, 0.0, -2.0));
+				assert_vec3_eq(half_extents, Vec3::new(2.0, 2.0, 9.0));
+				assert!(box_rotation.dot(rotation).abs() > 0.9999);
+			}
+
+			_ => panic!("Expected box, got {:?}", shape),
+		}
+	}
+
+	#[test]
+	fn sphere_and_capsule_collision() {
+		let sphere = load_single_collision_shape(|w| { w.u8(b'S').vec3(Vec3::from_y(1.0)).f32(0.5); }).unwrap();
+		assert!(matches!(sphere, CollisionShape::Sphere { radius, .. } if radius == 0.5));
+
+		let data = Writer::file(|w| {
+			w.entity(Vec3::zero(), Quat::identity(), Vec3::new(1.0, 2.0, 3.0), |w| {
+				w.section(b"COLL", |w| { w.u8(b'C').vec3(Vec3::zero()).f32(0.5).f32(1.0); });
+			});
+		});
+
+		let project = load(&data).unwrap();
+		match project.entities[0].world_collision_shape().unwrap() {
+			WorldCollisionShape::Capsule { start, end, radius } => {
+				assert_vec3_eq(start, Vec3::new(0.0, -2.0, 0.0));
+				assert_vec3_eq(end, Vec3::new(0.0, 2.0, 0.0));
+				assert_eq!(radius, 1.5);
+			}
+
+			shape => panic!("Expected capsule, got {:?}", shape),
+		}
+	}
+
+	#[test]
+	fn hull_and_mesh_collision() {
+		let hull = load_single_collision_shape(|w| {
+			w.u8(b'H').u32(4);
+			for point in [Vec3::zero(), Vec3::from_x(1.0), Vec3::from_y(1.0), Vec3::from_z(1.0)] {
+				w.vec3(point);
+			}
+		}).unwrap();
+
+		assert!(matches!(hull, CollisionShape::ConvexHull { ref points } if points.len() == 4));
+
+		let mesh = load_single_collision_shape(|w| {
+			w.u8(b'M').u32(3).vec3(Vec3::zero()).vec3(Vec3::from_x(1.0)).vec3(Vec3::from_z(1.0));
+			w.u32(1).u32(0).u32(1).u32(2);
+		}).unwrap();
+
+		match mesh {
+			CollisionShape::TriangleMesh { positions, indices } => {
+				assert_eq!(positions.len(), 3);
+				assert_eq!(indices, [0, 1, 2]);
+			}
+
+			_ => panic!("Expected triangle mesh, got {:?}", mesh),
+		}
+	}
+
+	#[test]
+	fn invalid_collision_shapes() {
+		let out_of_range = load_single_collision_shape(|w| {
+			w.u8(b'M').u32(1).vec3(Vec3::zero());
+			w.u32(1).u32(0).u32(0).u32(3);
+		});
+		assert!(out_of_range.is_err());
+
+		let unknown = load_single_collision_shape(|w| { w.u8(b'X'); });
+		assert!(unknown.is_err());
+
+		let truncated = load_single_collision_shape(|w| { w.u8(b'H').u32(1000).vec3(Vec3::zero()); });
+		assert!(truncated.is_err());
+	}
+}
@@ -0,0 +1,1309 @@
+//! Import of glTF 2.0 files into a [`Project`], for assets that don't come from the blender plugin.
+//!
+//! Nodes are flattened into [`Entity`]s with world space transforms, cameras become [`EntityKind::Camera`]s, scenes become [`Scene`]s, the primitives of
+//! each glTF mesh are merged into a single [`Mesh`] with a material slot per glTF material, and skins are converted to [`MeshAnimationData`] with
+//! animations resampled at [`ANIMATION_SAMPLE_RATE`].
+//!
+//! glTF is Y-up with +Z forward, which is already the convention the blender plugin converts into
+//! (see `swap_coords` in `plugin/util.py`), so positions and rotations are imported unchanged.
+//! Only texture coordinates need converting, since glTF places the uv origin at the top left, which also flips
+//! the bitangent sign stored in tangents.
+//!
+//! Anything that can't be represented in a [`Project`] is reported in [`GltfImport::warnings`] rather than failing the import.
+
+use crate::types::*;
+use crate::material::MeshMaterial;
+use crate::{ToyResult, DEFAULT_COLOR_DATA_NAME};
+use common::*;
+use failure::ensure;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use ::gltf::Document;
+use ::gltf::buffer::Data as BufferData;
+use ::gltf::animation::{Interpolation, Property};
+use ::gltf::animation::util::ReadOutputs;
+use ::gltf::mesh::Mode;
+
+
+/// Frame rate glTF animations are resampled at, since toy animations are stored as fixed rate frames.
+pub const ANIMATION_SAMPLE_RATE: f32 = 30.0;
+
+/// Longest animation that will be imported, in seconds. Longer animations fail the import rather than
+/// resampling into an unreasonable number of frames.
+pub const MAX_ANIMATION_DURATION: f32 = 3600.0;
+
+/// Name of the uv layer created from `TEXCOORD_0`. Further sets are suffixed `.001`, `.002`, etc, like in blender.
+pub const DEFAULT_UV_DATA_NAME: &str = "UVMap";
+
+
+/// The result of importing a glTF file.
+#[derive(Debug, Clone)]
+pub struct GltfImport {
+	pub project: Project,
+
+	/// Everything in the source file that couldn't be represented in `project`.
+	pub warnings: Vec<GltfWarning>,
+}
+
+
+/// Something in a glTF file that wasn't imported, or was only partially imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GltfWarning {
+	/// The file uses an extension the importer doesn't understand. Data relying on it may be imported incorrectly.
+	UnsupportedExtension { name: String },
+
+	/// The file doesn't define any scenes, so a single scene containing all root nodes was created.
+	NoScenes,
+
+	/// A primitive uses a point or line mode, so was skipped.
+	UnsupportedPrimitiveMode { mesh: String, mode: &'static str },
+
+	/// A primitive has no `POSITION` attribute, so was skipped.
+	MissingPositions { mesh: String },
+
+	MorphTargetsIgnored { mesh: String },
+
+	/// Some vertices were influenced by more than the 3 joints supported by [`MeshWeightVertex`].
+	/// The least significant weights were dropped.
+	WeightsTruncated { mesh: String, num_vertices: usize },
+
+	/// A skin has more joints than can be indexed by [`MeshWeightVertex`], so was skipped.
+	TooManyJoints { skin: String, num_joints: usize },
+
+	/// An animation channel targets morph target weights, or a node that isn't part of any skeleton.
+	AnimationChannelIgnored { animation: String, node: String, property: &'static str },
+
+	/// An animation channel has malformed or unreadable sampler data.
+	InvalidAnimationChannel { animation: String, node: String },
+
+	/// Some triangles referred to vertices outside of their primitive, so were skipped.
+	InvalidIndices { mesh: String, num_triangles: usize },
+}
+
+
+/// Loads a .gltf or .glb file. External buffers are resolved relative to `path`.
+pub fn load(path: impl AsRef<Path>) -> ToyResult<GltfImport> {
+	let path = path.as_ref();
+	let ::gltf::Gltf { document, blob } = ::gltf::Gltf::open(path)?;
+	let buffers = ::gltf::import_buffers(&document, path.parent(), blob)?;
+
+	Importer::new(&document, &buffers).import()
+}
+
+/// Same as [`load`], but for data that's already in memory.
+/// External buffers are resolved relative to `base_path`, and will fail to load if it is `None`.
+pub fn load_from_slice(data: &[u8], base_path: Option<&Path>) -> ToyResult<GltfImport> {
+	let ::gltf::Gltf { document, blob } = ::gltf::Gltf::from_slice(data)?;
+	let buffers = ::gltf::import_buffers(&document, base_path, blob)?;
+
+	Importer::new(&document, &buffers).import()
+}
+
+
+
+#[derive(Debug, Clone, Copy)]
+struct NodeTransform {
+	translation: Vec3,
+	rotation: Quat,
+	scale: Vec3,
+}
+
+impl NodeTransform {
+	fn to_mat3x4(self) -> Mat3x4 {
+		Mat3x4::translate(self.translation)
+			* self.rotation.to_mat3x4()
+			* Mat3x4::scale(self.scale)
+	}
+}
+
+
+struct AnimationSampler {
+	node: usize,
+	property: Property,
+	interpolation: Interpolation,
+	inputs: Vec<f32>,
+
+	/// Translations and scales only use xyz. For cubic splines, this contains (in tangent, value, out tangent) triples.
+	outputs: Vec<Vec4>,
+}
+
+
+struct Importer<'d> {
+	document: &'d Document,
+	buffers: &'d [BufferData],
+	warnings: Vec<GltfWarning>,
+
+	parents: Vec<Option<usize>>,
+	rest_transforms: Vec<NodeTransform>,
+	joint_nodes: HashSet<usize>,
+
+	/// Samplers for each animation, along with the animations name.
+	animations: Vec<(String, Vec<AnimationSampler>)>,
+
+	meshes: Vec<Mesh>,
+	mesh_ids: HashMap<MeshKey, u16>,
+}
+
+/// Identifies an imported mesh by glTF mesh and skin. Skinned meshes bake in the transform of the node they're
+/// attached to, so also need the bits of that transform to be shared between nodes.
+type MeshKey = (usize, Option<usize>, Option<[u32; 12]>);
+
+
+impl<'d> Importer<'d> {
+	fn new(document: &'d Document, buffers: &'d [BufferData]) -> Importer<'d> {
+		let num_nodes = document.nodes().len();
+
+		let mut parents = vec![None; num_nodes];
+		for node in document.nodes() {
+			for child in node.children() {
+				parents[child.index()] = Some(node.index());
+			}
+		}
+
+		let rest_transforms = document.nodes()
+			.map(|node| {
+				let (translation, rotation, scale) = node.transform().decomposed();
+				let [x, y, z, w] = rotation;
+
+				NodeTransform {
+					translation: translation.into(),
+					rotation: Quat::from_raw(x, y, z, w),
+					scale: scale.into(),
+				}
+			})
+			.collect();
+
+		let joint_nodes = document.skins()
+			.flat_map(|skin| skin.joints())
+			.map(|node| node.index())
+			.collect();
+
+		Importer {
+			document,
+			buffers,
+			warnings: Vec::new(),
+
+			parents,
+			rest_transforms,
+			joint_nodes,
+
+			animations: Vec::new(),
+
+			meshes: Vec::new(),
+			mesh_ids: HashMap::new(),
+		}
+	}
+
+	fn import(mut self) -> ToyResult<GltfImport> {
+		for name in self.document.extensions_used() {
+			self.warnings.push(GltfWarning::UnsupportedExtension { name: name.into() });
+		}
+
+		self.check_animation_channels();
+
+		let document = self.document;
+		let nodes: Vec<_> = document.nodes().collect();
+
+		for animation in document.animations() {
+			let samplers = self.read_animation_samplers(&animation);
+			self.animations.push((animation_name(&animation), samplers));
+		}
+
+		let mut entities = Vec::new();
+		let mut entity_ids = HashMap::new();
+		let mut scenes = Vec::new();
+
+		let mut scene_roots: Vec<(String, Vec<usize>)> = document.scenes()
+			.map(|scene| {
+				let name = scene.name().map_or_else(|| format!("Scene{}", scene.index()), Into::into);
+				(name, scene.nodes().map(|node| node.index()).collect())
+			})
+			.collect();
+
+		if scene_roots.is_empty() {
+			self.warnings.push(GltfWarning::NoScenes);
+
+			let roots = (0..self.parents.len())
+				.filter(|&node| self.parents[node].is_none())
+				.collect();
+
+			scene_roots.push(("Scene".into(), roots));
+		}
+
+		for (name, roots) in scene_roots {
+			let mut scene_entities = Vec::new();
+			let mut visited = HashSet::new();
+			let mut to_visit = roots;
+			to_visit.reverse();
+
+			// Depth first so entities end up in the same order as the hierarchy
+			while let Some(node_index) = to_visit.pop() {
+				// Guard against malformed hierarchies
+				if !visited.insert(node_index) {
+					continue
+				}
+
+				let node = nodes[node_index].clone();
+				let children: Vec<usize> = node.children().map(|child| child.index()).collect();
+				to_visit.extend(children.into_iter().rev());
+
+				// Joints are encoded in the animation data of skinned meshes, like armatures are in the plugin
+				if self.joint_nodes.contains(&node_index) {
+					continue
+				}
+
+				let entity_id = match entity_ids.get(&node_index) {
+					Some(&entity_id) => entity_id,
+					None => {
+						entities.push(self.import_entity(node)?);
+
+						// Entity ids are 1-based
+						let entity_id = entities.len() as u32;
+						entity_ids.insert(node_index, entity_id);
+						entity_id
+					}
+				};
+
+				scene_entities.push(entity_id);
+			}
+
+			scenes.push(Scene {
+				name,
+				entities: scene_entities,
+			});
+		}
+
+		Ok(GltfImport {
+			project: Project {
+				scenes,
+				entities,
+				meshes: self.meshes,
+				nav_meshes: Vec::new(),
+				nav_grids: Vec::new(),
+			},
+
+			warnings: self.warnings,
+		})
+	}
+
+	fn import_entity(&mut self, node: ::gltf::Node<'d>) -> ToyResult<Entity> {
+		let name = node_name(&node);
+
+		let world_transform = self.world_transform(node.index(), &self.rest_transforms);
+		let (position, mut rotation, scale) = decompose(&world_transform);
+
+		let mut kind = EntityKind::Object;
+
+		if let Some(camera) = node.camera() {
+			kind = EntityKind::Camera(import_camera(camera));
+
+			// glTF cameras look down -Z, but toy cameras look down -Y like blender cameras after conversion.
+			// See Entity::forward.
+			rotation = rotation * Quat::from_pitch(PI / 2.0);
+		}
+
+		let mut mesh_id = 0;
+
+		if let Some(mesh) = node.mesh() {
+			let skin = node.skin();
+			let bind_transform = skin.as_ref().map(|_| transform_bits(&world_transform));
+
+			let key = (mesh.index(), skin.as_ref().map(|skin| skin.index()), bind_transform);
+
+			mesh_id = match self.mesh_ids.get(&key) {
+				Some(&mesh_id) => mesh_id,
+				None => {
+					ensure!(self.meshes.len() < u16::MAX as usize, "Too many meshes");
+
+					let mesh = self.import_mesh(mesh, skin, node.index())?;
+					self.meshes.push(mesh);
+
+					// Mesh ids are 1-based
+					let mesh_id = self.meshes.len() as u16;
+					self.mesh_ids.insert(key, mesh_id);
+					mesh_id
+				}
+			};
+		}
+
+		Ok(Entity {
+			name,
+			mesh_id,
+			position,
+			rotation,
+			scale,
+			kind,
+			collision: None,
+			properties: HashMap::new(),
+		})
+	}
+
+	fn import_mesh(&mut self, mesh: ::gltf::Mesh<'d>, skin: Option<::gltf::Skin<'d>>, node_index: usize) -> ToyResult<Mesh> {
+		let buffers = self.buffers;
+		let mesh_name = mesh.name().map_or_else(|| format!("Mesh{}", mesh.index()), Into::into);
+
+		// Only import weights if we can actually use them
+		let skin = skin.filter(|skin| {
+			let num_joints = skin.joints().len();
+			let fits = num_joints <= u8::MAX as usize + 1;
+
+			if !fits {
+				let skin_name = skin.name().map_or_else(|| format!("Skin{}", skin.index()), Into::into);
+				self.warnings.push(GltfWarning::TooManyJoints { skin: skin_name, num_joints });
+			}
+
+			fits
+		});
+
+		let mut positions = Vec::new();
+		let mut indices = Vec::new();
+		let mut color_layers: Vec<MeshColorLayer> = Vec::new();
+		let mut uv_layers: Vec<MeshUvLayer> = Vec::new();
+		let mut weights = Vec::new();
+
+		// Normals and tangents are only kept if every primitive has them
+		let mut normals = Vec::new();
+		let mut tangents = Vec::new();
+		let mut all_have_normals = true;
+		let mut all_have_tangents = true;
+
+		// The glTF material of each triangle, or `None` for the default material
+		let mut triangle_materials = Vec::new();
+
+		let mut has_morph_targets = false;
+		let mut num_truncated_weights = 0;
+		let mut num_invalid_triangles = 0;
+
+		for primitive in mesh.primitives() {
+			let mode = primitive.mode();
+			if !matches!(mode, Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan) {
+				self.warnings.push(GltfWarning::UnsupportedPrimitiveMode { mesh: mesh_name.clone(), mode: mode_name(mode) });
+				continue
+			}
+
+			has_morph_targets |= primitive.morph_targets().len() > 0;
+
+			let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| data.0.as_slice()));
+
+			let Some(primitive_positions) = reader.read_positions() else {
+				self.warnings.push(GltfWarning::MissingPositions { mesh: mesh_name.clone() });
+				continue
+			};
+
+			let index_start = positions.len();
+			positions.extend(primitive_positions.map(Vec3::from));
+			let num_vertices = positions.len() - index_start;
+
+			let primitive_indices: Vec<u32> = match reader.read_indices() {
+				Some(indices) => indices.into_u32().collect(),
+				None => (0..num_vertices as u32).collect(),
+			};
+
+			let index_count = indices.len();
+			let primitive_triangles = triangulate(mode, &primitive_indices);
+
+			// Skip whole triangles with invalid indices so the rest stay aligned
+			indices.extend(primitive_triangles.chunks_exact(3)
+				.filter(|triangle| triangle.iter().all(|&index| (index as usize) < num_vertices))
+				.flatten()
+				.map(|&index| index + index_start as u32));
+
+			let num_triangles = (indices.len() - index_count) / 3;
+			num_invalid_triangles += primitive_triangles.len() / 3 - num_triangles;
+			triangle_materials.resize(triangle_materials.len() + num_triangles, primitive.material().index());
+
+			for set in 0.. {
+				let Some(colors) = reader.read_colors(set) else { break };
+				let layer_name = layer_name(DEFAULT_COLOR_DATA_NAME, set);
+
+				let layer = match color_layers.iter_mut().position(|layer| layer.name == layer_name) {
+					Some(layer_index) => &mut color_layers[layer_index],
+					None => {
+						color_layers.push(MeshColorLayer { name: layer_name, data: vec![Vec4::splat(1.0); index_start] });
+						color_layers.last_mut().unwrap()
+					}
+				};
+
+				layer.data.extend(colors.into_rgba_f32().map(Vec4::from));
+			}
+
+			for set in 0.. {
+				let Some(uvs) = reader.read_tex_coords(set) else { break };
+				let layer_name = layer_name(DEFAULT_UV_DATA_NAME, set);
+
+				let layer = match uv_layers.iter_mut().position(|layer| layer.name == layer_name) {
+					Some(layer_index) => &mut uv_layers[layer_index],
+					None => {
+						uv_layers.push(MeshUvLayer { name: layer_name, data: vec![Vec2::zero(); index_start] });
+						uv_layers.last_mut().unwrap()
+					}
+				};
+
+				layer.data.extend(uvs.into_f32().map(|[u, v]| Vec2::new(u, 1.0 - v)));
+			}
+
+			match reader.read_normals() {
+				Some(primitive_normals) => normals.extend(primitive_normals.map(Vec3::from)),
+				None => all_have_normals = false,
+			}
+
+			// Tangent w is the bitangent sign, which flips along with the v axis
+			match reader.read_tangents() {
+				Some(primitive_tangents) => tangents.extend(primitive_tangents.map(|[x, y, z, w]| Vec4::new(x, y, z, -w))),
+				None => all_have_tangents = false,
+			}
+
+			normals.resize(positions.len(), Vec3::zero());
+			tangents.resize(positions.len(), Vec4::zero());
+
+			// Make sure layers missing from this primitive still line up with the vertex list
+			for layer in color_layers.iter_mut() {
+				layer.data.resize(positions.len(), Vec4::splat(1.0));
+			}
+
+			for layer in uv_layers.iter_mut() {
+				layer.data.resize(positions.len(), Vec2::zero());
+			}
+
+			if let Some(skin) = &skin {
+				let num_joints = skin.joints().len();
+				let mut influences = vec![Vec::new(); num_vertices];
+
+				for set in 0.. {
+					let (Some(joints), Some(set_weights)) = (reader.read_joints(set), reader.read_weights(set)) else { break };
+
+					for (vertex_influences, (joints, set_weights)) in influences.iter_mut().zip(joints.into_u16().zip(set_weights.into_f32())) {
+						vertex_influences.extend(joints.into_iter().zip(set_weights)
+							.filter(|&(_, weight)| weight > 0.0));
+					}
+				}
+
+				for mut vertex_influences in influences {
+					// Keep the most significant weights
+					vertex_influences.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+					if vertex_influences.len() > 3 {
+						num_truncated_weights += 1;
+					}
+
+					let mut vertex = MeshWeightVertex::default();
+					for ((index, weight), (joint, joint_weight)) in vertex.indices.iter_mut().zip(&mut vertex.weights).zip(vertex_influences) {
+						// Skins with more than 256 joints were already skipped, so this also fits in a u8
+						ensure!((joint as usize) < num_joints, "Mesh '{}' is weighted to joint {}, but its skin only has {} joints",
+							mesh_name, joint, num_joints);
+
+						*index = joint as u8;
+						*weight = joint_weight;
+					}
+
+					weights.push(vertex);
+				}
+			}
+		}
+
+		if has_morph_targets {
+			self.warnings.push(GltfWarning::MorphTargetsIgnored { mesh: mesh_name.clone() });
+		}
+
+		if num_invalid_triangles > 0 {
+			self.warnings.push(GltfWarning::InvalidIndices { mesh: mesh_name.clone(), num_triangles: num_invalid_triangles });
+		}
+
+		if num_truncated_weights > 0 {
+			self.warnings.push(GltfWarning::WeightsTruncated { mesh: mesh_name, num_vertices: num_truncated_weights });
+		}
+
+		let animation_data = skin.map(|skin| self.import_skin(&skin, weights, node_index)).transpose()?;
+		let (materials, material_indices) = self.import_materials(&triangle_materials);
+
+		if !all_have_normals || positions.is_empty() {
+			normals.clear();
+			all_have_tangents = false;
+		}
+
+		if !all_have_tangents {
+			tangents.clear();
+		}
+
+		Ok(Mesh {
+			positions,
+			indices,
+			normals,
+			tangents,
+			color_layers,
+			uv_layers,
+			materials,
+			material_indices,
+			animation_data,
+		})
+	}
+
+	/// Creates a material slot for each distinct glTF material used by a mesh, in order of first use.
+	/// Meshes only using the default material get no materials.
+	fn import_materials(&self, triangle_materials: &[Option<usize>]) -> (Vec<MeshMaterial>, Vec<u16>) {
+		if triangle_materials.iter().all(Option::is_none) {
+			return (Vec::new(), Vec::new())
+		}
+
+		let mut slots = Vec::new();
+		let material_indices = triangle_materials.iter()
+			.map(|&material| match slots.iter().position(|&slot| slot == material) {
+				Some(slot) => slot as u16,
+				None => {
+					slots.push(material);
+					(slots.len() - 1) as u16
+				}
+			})
+			.collect();
+
+		let materials = slots.into_iter()
+			.map(|material| match material.and_then(|index| self.document.materials().nth(index)) {
+				Some(material) => import_material(material),
+				None => MeshMaterial {
+					name: "Default".into(),
+					base_color: Vec4::splat(1.0),
+					texture: None,
+					double_sided: false,
+					transparent: false,
+				},
+			})
+			.collect();
+
+		(materials, material_indices)
+	}
+
+	/// Warns about any channels that won't be picked up while sampling skeletal animations.
+	fn check_animation_channels(&mut self) {
+		// Animating the parent of a joint still affects the skeleton
+		let mut skeleton_nodes = HashSet::new();
+		for &joint in self.joint_nodes.iter() {
+			let mut node = Some(joint);
+			while let Some(node_index) = node {
+				if !skeleton_nodes.insert(node_index) {
+					break
+				}
+
+				node = self.parents[node_index];
+			}
+		}
+
+		for animation in self.document.animations() {
+			for channel in animation.channels() {
+				let target = channel.target();
+				let property = target.property();
+				let node_index = target.node().index();
+
+				if property != Property::MorphTargetWeights && skeleton_nodes.contains(&node_index) {
+					continue
+				}
+
+				self.warnings.push(GltfWarning::AnimationChannelIgnored {
+					animation: animation_name(&animation),
+					node: node_name(&target.node()),
+					property: property_name(property),
+				});
+			}
+		}
+	}
+
+	/// Calculates the world space transform of a node with the given local transforms.
+	fn world_transform(&self, node_index: usize, local_transforms: &[NodeTransform]) -> Mat3x4 {
+		let mut transform = local_transforms[node_index].to_mat3x4();
+		let mut node = node_index;
+
+		// Bound the walk in case of malformed hierarchies
+		for _ in 0..self.parents.len() {
+			let Some(parent) = self.parents[node] else { break };
+			transform = local_transforms[parent].to_mat3x4() * transform;
+			node = parent;
+		}
+
+		transform
+	}
+
+	fn read_animation_samplers(&mut self, animation: &::gltf::Animation<'d>) -> Vec<AnimationSampler> {
+		let buffers = self.buffers;
+		let mut samplers = Vec::new();
+
+		for channel in animation.channels() {
+			let target = channel.target();
+			let property = target.property();
+
+			if property == Property::MorphTargetWeights {
+				continue
+			}
+
+			let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(|data| data.0.as_slice()));
+			let interpolation = channel.sampler().interpolation();
+
+			let inputs: Option<Vec<f32>> = reader.read_inputs().map(Iterator::collect);
+			let outputs: Option<Vec<Vec4>> = reader.read_outputs().and_then(|outputs| match outputs {
+				ReadOutputs::Translations(values) | ReadOutputs::Scales(values) => Some(values.map(|v| Vec3::from(v).extend(0.0)).collect()),
+				ReadOutputs::Rotations(values) => Some(values.into_f32().map(Vec4::from).collect()),
+				ReadOutputs::MorphTargetWeights(_) => None,
+			});
+
+			let outputs_per_input = match interpolation {
+				Interpolation::CubicSpline => 3,
+				_ => 1,
+			};
+
+			match (inputs, outputs) {
+				(Some(inputs), Some(outputs)) if !inputs.is_empty() && outputs.len() == inputs.len() * outputs_per_input => {
+					samplers.push(AnimationSampler {
+						node: target.node().index(),
+						property,
+						interpolation,
+						inputs,
+						outputs,
+					});
+				}
+
+				_ => self.warnings.push(GltfWarning::InvalidAnimationChannel {
+					animation: animation_name(animation),
+					node: node_name(&target.node()),
+				}),
+			}
+		}
+
+		samplers
+	}
+}
+
+
+impl Importer<'_> {
+	/// Bone matrices are calculated relative to the node the mesh is attached to, since the entity transform
+	/// is applied after skinning.
+	fn import_skin(&self, skin: &::gltf::Skin<'_>, weights: Vec<MeshWeightVertex>, mesh_node: usize) -> ToyResult<MeshAnimationData> {
+		let buffers = self.buffers;
+		let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+
+		let inverse_bind_matrices: Vec<Mat3x4> = skin.reader(|buffer| buffers.get(buffer.index()).map(|data| data.0.as_slice()))
+			.read_inverse_bind_matrices()
+			.map(|matrices| matrices.map(mat3x4_from_gltf).collect())
+			.unwrap_or_else(|| vec![Mat3x4::identity(); joints.len()]);
+
+		let bones: Vec<MeshBone> = skin.joints().zip(&inverse_bind_matrices)
+			.map(|(joint, inverse_bind_matrix)| {
+				let bind_matrix = inverse_bind_matrix.inverse();
+				let head = bind_matrix.column_w();
+
+				// Bones point along their local y axis, like in blender
+				let tail = head + bind_matrix.column_y();
+
+				MeshBone {
+					name: node_name(&joint),
+					head,
+					tail,
+				}
+			})
+			.collect();
+
+		let inverse_mesh_transform = self.world_transform(mesh_node, &self.rest_transforms).inverse();
+		let mut animations = Vec::new();
+
+		for (name, samplers) in self.animations.iter() {
+			let affects_skin = samplers.iter()
+				.any(|sampler| joints.iter().any(|&joint| self.is_ancestor_or_self(sampler.node, joint)));
+
+			if !affects_skin {
+				continue
+			}
+
+			let duration = samplers.iter()
+				.filter_map(|sampler| sampler.inputs.last().copied())
+				.fold(0.0f32, f32::max);
+
+			ensure!(duration.is_finite() && duration <= MAX_ANIMATION_DURATION,
+				"Animation '{}' is {} seconds long, over the limit of {} seconds", name, duration, MAX_ANIMATION_DURATION);
+
+			let num_frames = (duration * ANIMATION_SAMPLE_RATE).round() as usize + 1;
+			let mut channels: Vec<MeshAnimationChannel> = (0..joints.len())
+				.map(|bone| MeshAnimationChannel { bone, frames: Vec::with_capacity(num_frames) })
+				.collect();
+
+			let mut local_transforms = self.rest_transforms.clone();
+
+			for frame in 0..num_frames {
+				let time = frame as f32 / ANIMATION_SAMPLE_RATE;
+
+				for sampler in samplers.iter() {
+					let value = sampler.sample(time);
+					let transform = &mut local_transforms[sampler.node];
+
+					match sampler.property {
+						Property::Translation => transform.translation = value.to_vec3(),
+						Property::Rotation => transform.rotation = Quat::from_raw(value.x, value.y, value.z, value.w).normalize(),
+						Property::Scale => transform.scale = value.to_vec3(),
+						Property::MorphTargetWeights => {}
+					}
+				}
+
+				for (channel, bone) in channels.iter_mut().zip(&bones) {
+					let joint = joints[channel.bone];
+					let skinning_matrix = inverse_mesh_transform
+						* self.world_transform(joint, &local_transforms)
+						* inverse_bind_matrices[channel.bone];
+
+					// MeshAnimationFrame::to_mat3x4 rotates and scales around the bones head, so position is
+					// where the head ends up
+					let (_, rotation, scale) = decompose(&skinning_matrix);
+
+					channel.frames.push(MeshAnimationFrame {
+						position: skinning_matrix * bone.head,
+						rotation,
+						scale,
+					});
+				}
+			}
+
+			animations.push(MeshAnimation {
+				name: name.clone(),
+				fps: ANIMATION_SAMPLE_RATE,
+				channels,
+			});
+		}
+
+		Ok(MeshAnimationData {
+			bones,
+			weights,
+			animations,
+		})
+	}
+
+	fn is_ancestor_or_self(&self, ancestor: usize, node_index: usize) -> bool {
+		let mut node = Some(node_index);
+
+		for _ in 0..=self.parents.len() {
+			match node {
+				Some(node_index) if node_index == ancestor => return true,
+				Some(node_index) => node = self.parents[node_index],
+				None => break,
+			}
+		}
+
+		false
+	}
+}
+
+
+impl AnimationSampler {
+	fn value(&self, key: usize) -> Vec4 {
+		match self.interpolation {
+			Interpolation::CubicSpline => self.outputs[key * 3 + 1],
+			_ => self.outputs[key],
+		}
+	}
+
+	fn sample(&self, time: f32) -> Vec4 {
+		let last_key = self.inputs.len() - 1;
+
+		if time <= self.inputs[0] {
+			return self.value(0)
+		}
+
+		if time >= self.inputs[last_key] {
+			return self.value(last_key)
+		}
+
+		let next_key = self.inputs.partition_point(|&input| input <= time);
+		let key = next_key - 1;
+
+		let delta = self.inputs[next_key] - self.inputs[key];
+		let t = (time - self.inputs[key]) / delta;
+
+		let (from, to) = (self.value(key), self.value(next_key));
+
+		match self.interpolation {
+			Interpolation::Step => from,
+
+			Interpolation::Linear if self.property == Property::Rotation => {
+				let from = Quat::from_raw(from.x, from.y, from.z, from.w);
+				let to = Quat::from_raw(to.x, to.y, to.z, to.w);
+				let Quat{x, y, z, w} = from.slerp(to, t);
+				Vec4::new(x, y, z, w)
+			}
+
+			Interpolation::Linear => from + (to - from) * t,
+
+			// https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#interpolation-cubic
+			Interpolation::CubicSpline => {
+				let out_tangent = self.outputs[key * 3 + 2] * delta;
+				let in_tangent = self.outputs[next_key * 3] * delta;
+
+				let (t2, t3) = (t * t, t * t * t);
+
+				from * (2.0*t3 - 3.0*t2 + 1.0)
+					+ out_tangent * (t3 - 2.0*t2 + t)
+					+ to * (-2.0*t3 + 3.0*t2)
+					+ in_tangent * (t3 - t2)
+			}
+		}
+	}
+}
+
+
+
+impl fmt::Display for GltfWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			GltfWarning::UnsupportedExtension { name } => write!(f, "Unsupported extension '{}'", name),
+			GltfWarning::NoScenes => write!(f, "File contains no scenes - root nodes were added to a default scene"),
+			GltfWarning::UnsupportedPrimitiveMode { mesh, mode } => write!(f, "Skipped {} primitive in mesh '{}'", mode, mesh),
+			GltfWarning::MissingPositions { mesh } => write!(f, "Skipped primitive without positions in mesh '{}'", mesh),
+			GltfWarning::MorphTargetsIgnored { mesh } => write!(f, "Morph targets ignored in mesh '{}'", mesh),
+			GltfWarning::WeightsTruncated { mesh, num_vertices } => write!(f, "{} vertices in mesh '{}' had weights for more than 3 joints", num_vertices, mesh),
+			GltfWarning::TooManyJoints { skin, num_joints } => write!(f, "Skin '{}' has too many joints ({}/256)", skin, num_joints),
+			GltfWarning::AnimationChannelIgnored { animation, node, property } => write!(f, "Animation '{}' channel targeting {} of node '{}' ignored", animation, property, node),
+			GltfWarning::InvalidAnimationChannel { animation, node } => write!(f, "Animation '{}' has invalid channel targeting node '{}'", animation, node),
+			GltfWarning::InvalidIndices { mesh, num_triangles } => write!(f, "Skipped {} triangles with out of range indices in mesh '{}'", num_triangles, mesh),
+		}
+	}
+}
+
+
+
+fn node_name(node: &::gltf::Node<'_>) -> String {
+	node.name().map_or_else(|| format!("Node{}", node.index()), Into::into)
+}
+
+fn animation_name(animation: &::gltf::Animation<'_>) -> String {
+	animation.name().map_or_else(|| format!("Animation{}", animation.index()), Into::into)
+}
+
+fn import_camera(camera: ::gltf::Camera<'_>) -> Camera {
+	use ::gltf::camera::Projection;
+
+	match camera.projection() {
+		Projection::Perspective(perspective) => Camera {
+			projection: CameraProjection::Perspective { fov_y: perspective.yfov() },
+			aspect: perspective.aspect_ratio().unwrap_or(1.0),
+			near: perspective.znear(),
+			far: perspective.zfar().unwrap_or(f32::INFINITY),
+		},
+
+		Projection::Orthographic(orthographic) => Camera {
+			projection: CameraProjection::Orthographic { height: 2.0 * orthographic.ymag() },
+			aspect: orthographic.xmag() / orthographic.ymag(),
+			near: orthographic.znear(),
+			far: orthographic.zfar(),
+		},
+	}
+}
+
+fn import_material(material: ::gltf::Material<'_>) -> MeshMaterial {
+	use ::gltf::image::Source;
+	use ::gltf::material::AlphaMode;
+
+	let pbr = material.pbr_metallic_roughness();
+
+	let texture = pbr.base_color_texture().map(|info| {
+		let image = info.texture().source();
+		match (image.name(), image.source()) {
+			(Some(name), _) => name.into(),
+			(None, Source::Uri { uri, .. }) => uri.into(),
+			(None, Source::View { .. }) => format!("Image{}", image.index()),
+		}
+	});
+
+	MeshMaterial {
+		name: material.name().map_or_else(|| format!("Material{}", material.index().unwrap_or(0)), Into::into),
+		base_color: Vec4::from(pbr.base_color_factor()),
+		texture,
+		double_sided: material.double_sided(),
+		transparent: material.alpha_mode() == AlphaMode::Blend,
+	}
+}
+
+fn layer_name(base: &str, set: u32) -> String {
+	match set {
+		0 => base.into(),
+		_ => format!("{}.{:03}", base, set),
+	}
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+	match mode {
+		Mode::Points => "points",
+		Mode::Lines => "lines",
+		Mode::LineLoop => "line loop",
+		Mode::LineStrip => "line strip",
+		Mode::Triangles => "triangles",
+		Mode::TriangleStrip => "triangle strip",
+		Mode::TriangleFan => "triangle fan",
+	}
+}
+
+fn property_name(property: Property) -> &'static str {
+	match property {
+		Property::Translation => "translation",
+		Property::Rotation => "rotation",
+		Property::Scale => "scale",
+		Property::MorphTargetWeights => "weights",
+	}
+}
+
+/// Converts triangle strips and fans to triangle lists.
+fn triangulate(mode: Mode, indices: &[u32]) -> Vec<u32> {
+	let num_triangles = indices.len().saturating_sub(2);
+
+	match mode {
+		Mode::TriangleStrip => (0..num_triangles)
+			.flat_map(|i| match i % 2 {
+				// Flip every second triangle to maintain winding
+				0 => [indices[i], indices[i+1], indices[i+2]],
+				_ => [indices[i+1], indices[i], indices[i+2]],
+			})
+			.collect(),
+
+		Mode::TriangleFan => (0..num_triangles)
+			.flat_map(|i| [indices[0], indices[i+1], indices[i+2]])
+			.collect(),
+
+		_ => indices[..indices.len() / 3 * 3].to_vec(),
+	}
+}
+
+/// The exact bits of a transform, for use in hash keys.
+fn transform_bits(transform: &Mat3x4) -> [u32; 12] {
+	std::array::from_fn(|index| transform.rows[index / 4].to_array()[index % 4].to_bits())
+}
+
+/// glTF matrices are column major.
+fn mat3x4_from_gltf(matrix: [[f32; 4]; 4]) -> Mat3x4 {
+	let [x, y, z, w] = matrix.map(|[x, y, z, _]| Vec3::new(x, y, z));
+	Mat3x4::from_columns([x, y, z, w])
+}
+
+/// Splits a transform into translation, rotation and scale. Assumes no shear.
+fn decompose(transform: &Mat3x4) -> (Vec3, Quat, Vec3) {
+	let [x, y, z, translation] = transform.columns();
+	let mut scale = Vec3::new(x.length(), y.length(), z.length());
+
+	if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+		return (translation, Quat::identity(), scale)
+	}
+
+	// Mirroring transforms can't be represented by a rotation, so fold it into the scale
+	if transform.determinant() < 0.0 {
+		scale.z = -scale.z;
+	}
+
+	let rotation = Quat::from_mat3(&Mat3::from_columns([x / scale.x, y / scale.y, z / scale.z]));
+	(translation, rotation.normalize(), scale)
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_util::assert_vec3_eq;
+
+	/// A single mesh with three vertices, indexed by the first `num_indices` of `[0, 1, 2, 0, 1, 5]`.
+	fn triangle_file(num_indices: usize) -> String {
+		format!(r#"{{
+			"asset": {{ "version": "2.0" }},
+			"scenes": [{{ "nodes": [0] }}],
+			"nodes": [{{ "name": "triangle", "mesh": 0 }}],
+			"meshes": [{{ "name": "mesh", "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}] }}],
+			"buffers": [{{
+				"byteLength": 48,
+				"uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAABAAUA"
+			}}],
+			"bufferViews": [
+				{{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+				{{ "buffer": 0, "byteOffset": 36, "byteLength": 12 }}
+			],
+			"accessors": [
+				{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }},
+				{{ "bufferView": 1, "componentType": 5123, "count": {}, "type": "SCALAR" }}
+			]
+		}}"#, num_indices)
+	}
+
+	#[test]
+	fn import_triangle() {
+		let import = load_from_slice(triangle_file(3).as_bytes(), None).unwrap();
+		assert_eq!(import.warnings, []);
+
+		let mesh = &import.project.meshes[0];
+		assert_eq!(mesh.positions.len(), 3);
+		assert_eq!(mesh.indices, [0, 1, 2]);
+		assert!(import.project.find_entity("triangle").unwrap().mesh().is_some());
+	}
+
+	#[test]
+	fn out_of_range_indices_skip_whole_triangles() {
+		let import = load_from_slice(triangle_file(6).as_bytes(), None).unwrap();
+		assert_eq!(import.warnings, [GltfWarning::InvalidIndices { mesh: "mesh".into(), num_triangles: 1 }]);
+		assert_eq!(import.project.meshes[0].indices, [0, 1, 2]);
+	}
+
+	/// Packs accessors into the binary chunk of a `.glb` file.
+	#[derive(Default)]
+	struct GlbBuilder {
+		bin: Vec<u8>,
+		views: Vec<String>,
+		accessors: Vec<String>,
+	}
+
+	impl GlbBuilder {
+		fn floats(&mut self, ty: &str, num_components: usize, data: &[f32]) -> usize {
+			let mut min = vec![f32::MAX; num_components];
+			let mut max = vec![f32::MIN; num_components];
+
+			for element in data.chunks(num_components) {
+				for (component, &value) in element.iter().enumerate() {
+					min[component] = min[component].min(value);
+					max[component] = max[component].max(value);
+				}
+			}
+
+			let bytes: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+			let bounds = format!(r#", "min": {:?}, "max": {:?}"#, min, max);
+			self.accessor(&bytes, 5126, ty, data.len() / num_components, &bounds)
+		}
+
+		fn u16s(&mut self, data: &[u16]) -> usize {
+			let bytes: Vec<u8> = data.iter().flat_map(|value| value.to_le_bytes()).collect();
+			self.accessor(&bytes, 5123, "SCALAR", data.len(), "")
+		}
+
+		fn u8_vec4s(&mut self, data: &[[u8; 4]]) -> usize {
+			self.accessor(data.as_flattened(), 5121, "VEC4", data.len(), "")
+		}
+
+		fn accessor(&mut self, bytes: &[u8], component_type: u32, ty: &str, count: usize, extra: &str) -> usize {
+			self.views.push(format!(r#"{{ "buffer": 0, "byteOffset": {}, "byteLength": {} }}"#, self.bin.len(), bytes.len()));
+			self.bin.extend_from_slice(bytes);
+			self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+
+			self.accessors.push(format!(r#"{{ "bufferView": {}, "componentType": {}, "count": {}, "type": "{}"{} }}"#,
+				self.views.len() - 1, component_type, count, ty, extra));
+			self.accessors.len() - 1
+		}
+
+		/// `document` is the rest of the top level glTF object, without the buffers.
+		fn build(&self, document: &str) -> Vec<u8> {
+			let mut json = format!(r#"{{ "asset": {{ "version": "2.0" }}, {}, "buffers": [{{ "byteLength": {} }}], "bufferViews": [{}], "accessors": [{}] }}"#,
+				document, self.bin.len(), self.views.join(", "), self.accessors.join(", ")).into_bytes();
+			json.resize(json.len().next_multiple_of(4), b' ');
+
+			let mut glb = Vec::new();
+			glb.extend_from_slice(b"glTF");
+			glb.extend_from_slice(&2u32.to_le_bytes());
+			glb.extend_from_slice(&((12 + 8 + json.len() + 8 + self.bin.len()) as u32).to_le_bytes());
+
+			glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+			glb.extend_from_slice(b"JSON");
+			glb.extend_from_slice(&json);
+
+			glb.extend_from_slice(&(self.bin.len() as u32).to_le_bytes());
+			glb.extend_from_slice(b"BIN\0");
+			glb.extend_from_slice(&self.bin);
+			glb
+		}
+	}
+
+	const QUAD_POSITIONS: [f32; 12] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0];
+
+	#[test]
+	fn triangulate_strips_and_fans() {
+		let mut glb = GlbBuilder::default();
+		let positions = glb.floats("VEC3", 3, &QUAD_POSITIONS);
+
+		let file = glb.build(&format!(r#"
+			"scenes": [{{ "nodes": [0] }}],
+			"nodes": [{{ "mesh": 0 }}],
+			"meshes": [{{ "primitives": [
+				{{ "attributes": {{ "POSITION": {0} }}, "mode": 5 }},
+				{{ "attributes": {{ "POSITION": {0} }}, "mode": 6 }}
+			] }}]"#, positions));
+
+		let import = load_from_slice(&file, None).unwrap();
+		assert_eq!(import.warnings, []);
+
+		let mesh = &import.project.meshes[0];
+		assert_eq!(mesh.positions.len(), 8);
+		assert_eq!(mesh.indices, [0, 1, 2, 2, 1, 3, 4, 5, 6, 4, 6, 7]);
+	}
+
+	#[test]
+	fn flip_uvs_and_tangent_sign() {
+		let mut glb = GlbBuilder::default();
+		let positions = glb.floats("VEC3", 3, &QUAD_POSITIONS[..9]);
+		let normals = glb.floats("VEC3", 3, &[0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+		let tangents = glb.floats("VEC4", 4, &[1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, -1.0]);
+		let uvs = glb.floats("VEC2", 2, &[0.25, 0.0, 1.0, 0.5, 0.0, 1.0]);
+
+		let file = glb.build(&format!(r#"
+			"scenes": [{{ "nodes": [0] }}],
+			"nodes": [{{ "mesh": 0 }}],
+			"meshes": [{{ "primitives": [
+				{{ "attributes": {{ "POSITION": {}, "NORMAL": {}, "TANGENT": {}, "TEXCOORD_0": {} }} }}
+			] }}]"#, positions, normals, tangents, uvs));
+
+		let import = load_from_slice(&file, None).unwrap();
+		let mesh = &import.project.meshes[0];
+
+		let uvs = &mesh.uv_layer_by_name(DEFAULT_UV_DATA_NAME).unwrap().data;
+		assert_eq!(uvs.iter().map(|uv| uv.to_tuple()).collect::<Vec<_>>(), [(0.25, 1.0), (1.0, 0.5), (0.0, 0.0)]);
+
+		assert_vec3_eq(mesh.normals[0], Vec3::new(0.0, 0.0, 1.0));
+		assert_eq!(mesh.tangents.iter().map(|tangent| tangent.w).collect::<Vec<_>>(), [-1.0, -1.0, 1.0]);
+	}
+
+	#[test]
+	fn import_cameras() {
+		let file = r#"{
+			"asset": { "version": "2.0" },
+			"scenes": [{ "nodes": [0] }],
+			"nodes": [{ "name": "camera", "camera": 0, "translation": [0, 1, 2] }],
+			"cameras": [{ "type": "perspective", "perspective": { "yfov": 1.0, "znear": 0.1, "zfar": 100, "aspectRatio": 1.5 } }]
+		}"#;
+
+		let import = load_from_slice(file.as_bytes(), None).unwrap();
+		let camera = import.project.find_entity("camera").unwrap();
+
+		assert_eq!(camera.camera(), Some(&Camera {
+			projection: CameraProjection::Perspective { fov_y: 1.0 },
+			aspect: 1.5,
+			near: 0.1,
+			far: 100.0,
+		}));
+
+		// glTF cameras look down -Z when not rotated
+		assert_vec3_eq(camera.position, Vec3::new(0.0, 1.0, 2.0));
+		assert_vec3_eq(camera.forward(), Vec3::new(0.0, 0.0, -1.0));
+	}
+
+	#[test]
+	fn import_material_slots() {
+		let mut glb = GlbBuilder::default();
+		let positions = glb.floats("VEC3", 3, &QUAD_POSITIONS[..9]);
+
+		let file = glb.build(&format!(r#"
+			"scenes": [{{ "nodes": [0] }}],
+			"nodes": [{{ "mesh": 0 }}],
+			"meshes": [{{ "primitives": [
+				{{ "attributes": {{ "POSITION": {0} }}, "material": 1 }},
+				{{ "attributes": {{ "POSITION": {0} }} }},
+				{{ "attributes": {{ "POSITION": {0} }}, "material": 0 }},
+				{{ "attributes": {{ "POSITION": {0} }}, "material": 1 }}
+			] }}],
+			"materials": [
+				{{ "name": "Red", "pbrMetallicRoughness": {{ "baseColorFactor": [1, 0, 0, 1] }} }},
+				{{ "name": "Glass", "alphaMode": "BLEND", "doubleSided": true }}
+			]"#, positions));
+
+		let import = load_from_slice(&file, None).unwrap();
+		let mesh = &import.project.meshes[0];
+
+		let names: Vec<_> = mesh.materials.iter().map(|material| material.name.as_str()).collect();
+		assert_eq!(names, ["Glass", "Default", "Red"]);
+		assert_eq!(mesh.material_indices, [0, 1, 2, 0]);
+
+		let (glass, red) = (&mesh.materials[0], &mesh.materials[2]);
+		assert!(glass.transparent && glass.double_sided);
+		assert!(!red.transparent && !red.double_sided);
+		assert_eq!(red.base_color.to_array(), [1.0, 0.0, 0.0, 1.0]);
+	}
+
+	/// A triangle skinned to a two joint chain, with an animation moving the root joint along x over `duration`
+	/// seconds. The second and third vertices are weighted to `arm_joint`. A skinned node is added for each
+	/// position in `mesh_nodes`.
+	fn skinned_file(mesh_nodes: &[[f32; 3]], arm_joint: u8, duration: f32) -> Vec<u8> {
+		let mut glb = GlbBuilder::default();
+		let positions = glb.floats("VEC3", 3, &QUAD_POSITIONS[..9]);
+		let joints = glb.u8_vec4s(&[[0, 0, 0, 0], [arm_joint, 0, 0, 0], [arm_joint, 0, 0, 0]]);
+		let weights = glb.floats("VEC4", 4, &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+		let indices = glb.u16s(&[0, 1, 2]);
+
+		// Column major, the arm's bind pose is one unit up
+		let inverse_bind_matrices = glb.floats("MAT4", 16, &[
+			1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+			1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, -1.0, 0.0, 1.0,
+		]);
+
+		let times = glb.floats("SCALAR", 1, &[0.0, duration]);
+		let translations = glb.floats("VEC3", 3, &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+		let body_nodes: Vec<String> = mesh_nodes.iter().enumerate()
+			.map(|(index, position)| format!(r#"{{ "name": "body{}", "mesh": 0, "skin": 0, "translation": {:?} }}"#, index, position))
+			.collect();
+
+		let scene_nodes: Vec<usize> = std::iter::once(0).chain(2..2 + mesh_nodes.len()).collect();
+
+		glb.build(&format!(r#"
+			"scenes": [{{ "nodes": {:?} }}],
+			"nodes": [
+				{{ "name": "root", "children": [1] }},
+				{{ "name": "arm", "translation": [0, 1, 0] }},
+				{}
+			],
+			"meshes": [{{ "primitives": [
+				{{ "attributes": {{ "POSITION": {}, "JOINTS_0": {}, "WEIGHTS_0": {} }}, "indices": {} }}
+			] }}],
+			"skins": [{{ "joints": [0, 1], "inverseBindMatrices": {} }}],
+			"animations": [{{
+				"name": "slide",
+				"samplers": [{{ "input": {}, "output": {} }}],
+				"channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "translation" }} }}]
+			}}]"#,
+			scene_nodes, body_nodes.join(", "), positions, joints, weights, indices, inverse_bind_matrices, times, translations))
+	}
+
+	#[test]
+	fn import_skin_and_animation() {
+		let import = load_from_slice(&skinned_file(&[[0.0; 3]], 1, 1.0), None).unwrap();
+		assert_eq!(import.warnings, []);
+
+		// Joints are only imported as bones
+		let names: Vec<_> = import.project.entities.iter().map(|entity| entity.name.as_str()).collect();
+		assert_eq!(names, ["body0"]);
+
+		let animation_data = import.project.meshes[0].animation_data.as_ref().unwrap();
+		let bone_names: Vec<_> = animation_data.bones.iter().map(|bone| bone.name.as_str()).collect();
+		assert_eq!(bone_names, ["root", "arm"]);
+		assert_vec3_eq(animation_data.bones[1].head, Vec3::new(0.0, 1.0, 0.0));
+
+		assert_eq!(animation_data.weights.len(), 3);
+		assert_eq!(animation_data.weights[1].indices[0], 1);
+		assert_eq!(animation_data.weights[1].weights[0], 1.0);
+
+		let animation = &animation_data.animations[0];
+		assert_eq!(animation.name, "slide");
+		assert_eq!(animation.fps, ANIMATION_SAMPLE_RATE);
+		assert_eq!(animation.num_frames(), ANIMATION_SAMPLE_RATE as usize + 1);
+
+		let [root, arm] = &animation.channels[..] else { panic!("Expected two channels") };
+		assert_vec3_eq(root.frames[0].position, Vec3::zero());
+		assert_vec3_eq(root.frames.last().unwrap().position, Vec3::new(1.0, 0.0, 0.0));
+		assert_vec3_eq(arm.frames.last().unwrap().position, Vec3::new(1.0, 1.0, 0.0));
+	}
+
+	#[test]
+	fn skinned_meshes_are_shared_per_bind_transform() {
+		let import = load_from_slice(&skinned_file(&[[0.0; 3], [2.0, 0.0, 0.0], [0.0; 3]], 1, 1.0), None).unwrap();
+		let mesh_ids: Vec<_> = import.project.entities.iter().map(|entity| entity.mesh_id).collect();
+
+		assert_eq!(import.project.meshes.len(), 2);
+		assert_eq!(mesh_ids, [1, 2, 1]);
+
+		// Skinned vertices follow their joints regardless of the mesh node's transform, so the offset
+		// is baked into the second mesh's animation
+		let offset_mesh = &import.project.meshes[1];
+		let root = &offset_mesh.animation_data.as_ref().unwrap().animations[0].channels[0];
+		assert_vec3_eq(root.frames[0].position, Vec3::new(-2.0, 0.0, 0.0));
+		assert_vec3_eq(root.frames.last().unwrap().position, Vec3::new(-1.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn reject_invalid_joints_and_durations() {
+		assert!(load_from_slice(&skinned_file(&[[0.0; 3]], 2, 1.0), None).is_err());
+		assert!(load_from_slice(&skinned_file(&[[0.0; 3]], 1, 1.0e9), None).is_err());
+	}
+}
@@ -1,54 +1,81 @@
 use crate::types::*;
+use crate::collision::CollisionShape;
+use crate::navigation::{NavMesh, NavGrid};
+use crate::material::MeshMaterial;
+use crate::view::*;
 use crate::ToyResult;
 use std::convert::TryInto;
+use std::collections::HashMap;
+use std::cell::RefCell;
 
 use common::*;
 use failure::{ensure, bail, format_err};
 
 // version 4: convert vertex colours from srgb to linear
 // version 5: export uvs
-const SCENE_VERSION: u8 = 5;
+// version 6: 32b vertex/triangle counts, 32b indices for meshes with more than 65536 vertices
+// version 7: collision shapes and navigation data
+// version 8: mesh material slots
+// version 9: vertex normals and tangents
+pub(crate) const SCENE_VERSION: u8 = 9;
 
+/// Decodes an entire toy file. See [`ToyView`] for loading only parts of a file without copying.
 pub fn load(data: &[u8]) -> ToyResult<Project> {
-	let reader = ToyReader { buf: data };
-	reader.read_all()
+	ToyView::from_reader(ToyReader::new(data, None))?
+		.to_project()
 }
 
+/// Same as [`load`], but also records every section encountered while loading, ordered by offset.
+/// Sections read before a load error are still returned, which makes this useful for inspecting broken files.
+pub fn load_with_sections(data: &[u8]) -> (ToyResult<Project>, Vec<SectionInfo>) {
+	let section_log = RefCell::new(Vec::new());
+	let result = ToyView::from_reader(ToyReader::new(data, Some(&section_log)))
+		.and_then(|view| view.to_project());
 
-type Tag = [u8; 4];
+	let mut sections = section_log.into_inner();
+	sections.sort_by_key(|section| section.offset);
 
-struct ToyReader<'data> { buf: &'data [u8] }
+	(result, sections)
+}
 
-impl<'d> ToyReader<'d> {
-	fn read_all(mut self) -> ToyResult<Project> {
-		self.read_magic()?;
 
-		let mut meshes = Vec::new();
-		let mut entities = Vec::new();
-		let mut scenes = Vec::new();
+/// Describes the location of a section within a toy file. See [`load_with_sections`].
+#[derive(Debug, Clone)]
+pub struct SectionInfo {
+	pub tag: String,
 
-		while !self.buf.is_empty() {
-			let (tag, mut section) = self.read_section()?;
+	/// Offset of the section's contents from the start of the file, after its tag and size.
+	pub offset: usize,
+	pub size: usize,
 
-			let to_err = |e| format_err!("While parsing '{}' section: {}", tag_to_string(&tag), e);
+	/// How many sections this section is nested within.
+	pub depth: usize,
+}
 
-			match &tag {
-				b"SCNE" => scenes.push(section.read_scene().map_err(to_err)?),
-				b"MESH" => meshes.push(section.read_mesh().map_err(to_err)?),
-				b"ENTY" => entities.push(section.read_entity().map_err(to_err)?),
-				_ => { bail!("Unexpected tag '{}' encountered", tag_to_string(&tag)); }
-			}
-		}
 
-		Ok(Project {
-			scenes,
-			entities,
-			meshes,
-		})
+pub(crate) type Tag = [u8; 4];
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ToyReader<'data> {
+	buf: &'data [u8],
+
+	/// Address of the start of the file, for calculating section offsets.
+	origin: usize,
+	depth: usize,
+	section_log: Option<&'data RefCell<Vec<SectionInfo>>>,
+}
+
+impl<'d> ToyReader<'d> {
+	pub(crate) fn new(data: &'d [u8], section_log: Option<&'d RefCell<Vec<SectionInfo>>>) -> ToyReader<'d> {
+		ToyReader { buf: data, origin: data.as_ptr() as usize, depth: 0, section_log }
+	}
+
+	pub(crate) fn is_empty(&self) -> bool {
+		self.buf.is_empty()
 	}
 
-	fn read_magic(&mut self) -> ToyResult<()> {
-		ensure!(&self.buf[..3] == b"TOY", "Expected magic string");
+	pub(crate) fn read_magic(&mut self) -> ToyResult<()> {
+		ensure!(self.buf.starts_with(b"TOY"), "Expected magic string");
 		self.buf = &self.buf[3..];
 
 		let version = self.read_u8()?;
@@ -57,7 +84,7 @@ impl<'d> ToyReader<'d> {
 		Ok(())
 	}
 
-	fn read_section(&mut self) -> ToyResult<(Tag, ToyReader<'_>)> {
+	pub(crate) fn read_section(&mut self) -> ToyResult<(Tag, ToyReader<'d>)> {
 		let tag = self.read_tag()?;
 		let section_size = self.read_u32()? as usize;
 		ensure!(section_size <= self.buf.len(), "Invalid section size for '{}'", tag_to_string(&tag));
@@ -65,56 +92,58 @@ impl<'d> ToyReader<'d> {
 		let (section, rest) = self.buf.split_at(section_size);
 		self.buf = rest;
 
-		Ok((tag, ToyReader{ buf: section }))
+		if let Some(section_log) = self.section_log {
+			section_log.borrow_mut().push(SectionInfo {
+				tag: tag_to_string(&tag),
+				offset: section.as_ptr() as usize - self.origin,
+				size: section_size,
+				depth: self.depth,
+			});
+		}
+
+		let section_reader = ToyReader {
+			buf: section,
+			origin: self.origin,
+			depth: self.depth + 1,
+			section_log: self.section_log,
+		};
+
+		Ok((tag, section_reader))
 	}
 
-	fn expect_section(&mut self, expected: &Tag) -> ToyResult<ToyReader<'_>> {
+	fn expect_section(&mut self, expected: &Tag) -> ToyResult<ToyReader<'d>> {
 		let (tag, section) = self.read_section()?;
 		ensure!(&tag == expected, "Encountered unexpected tag '{}'", tag_to_string(&tag));
 		Ok(section)
 	}
 
-	fn read_mesh(&mut self) -> ToyResult<Mesh> {
-		let num_vertices = self.read_u16()? as usize;
-		let mut vertices = Vec::with_capacity(num_vertices);
-		for _ in 0..num_vertices {
-			vertices.push(self.read_vec3()?);
-		}
-
-		let wide_indices = num_vertices >= 256;
+	/// Reads the layout of a mesh, leaving vertex data in place. See [`MeshView::to_mesh`] for decoding it.
+	pub(crate) fn read_mesh_view(&mut self) -> ToyResult<MeshView<'d>> {
+		let num_vertices = self.read_u32()? as usize;
+		let positions = ArrayView::new(self.read_bytes(num_vertices, F32x3::SIZE)?);
 
-		let num_triangles = self.read_u16()? as usize;
-		let num_indices = num_triangles * 3;
-		let mut indices = Vec::with_capacity(num_indices);
+		// Indices use the smallest encoding that can address every vertex
+		let index_size = match num_vertices {
+			n if n < 256 => 1,
+			n if n < 65536 => 2,
+			_ => 4,
+		};
 
-		if wide_indices {
-			for _ in 0..num_indices {
-				indices.push(self.read_u16()?);
-			}
-
-		} else {
-			for _ in 0..num_indices {
-				indices.push(self.read_u8()? as u16);
-			}
-		}
+		let num_triangles = self.read_u32()? as usize;
+		let indices = IndexView::new(self.read_bytes(num_triangles * 3, index_size)?, index_size);
 
 		let num_color_layers = self.read_u8()? as usize;
 		let mut color_layers = Vec::with_capacity(num_color_layers);
 		for _ in 0..num_color_layers {
 			self.expect_tag(b"MDTA")?;
 
-			let layer_name = self.read_string()?;
-			let num_points = self.read_u16()? as usize;
-			ensure!(num_points == num_vertices, "Color layer '{}' different size to vertex list", layer_name);
-
-			let mut layer_data = Vec::with_capacity(num_points);
-			for _ in 0..num_points {
-				layer_data.push(self.read_vec4()?);
-			}
+			let name = self.read_str()?;
+			let num_points = self.read_u32()? as usize;
+			ensure!(num_points == num_vertices, "Color layer '{}' different size to vertex list", name);
 
-			color_layers.push(MeshColorLayer {
-				name: layer_name,
-				data: layer_data,
+			color_layers.push(ColorLayerView {
+				name,
+				data: ArrayView::new(self.read_bytes(num_points, F32x4::SIZE)?),
 			})
 		}
 
@@ -123,40 +152,99 @@ impl<'d> ToyReader<'d> {
 		for _ in 0..num_uv_layers {
 			self.expect_tag(b"MDUV")?;
 
-			let layer_name = self.read_string()?;
-			let num_points = self.read_u16()? as usize;
-
-			ensure!(num_points == num_vertices, "UV layer '{}' different size to vertex list", layer_name);
-
-			let mut layer_data = Vec::with_capacity(num_points);
-			for _ in 0..num_points {
-				let point = Vec2::new(self.read_uf16()?, self.read_uf16()?);
-				layer_data.push(point);
-			}
+			let name = self.read_str()?;
+			let num_points = self.read_u32()? as usize;
+			ensure!(num_points == num_vertices, "UV layer '{}' different size to vertex list", name);
 
-			uv_layers.push(MeshUvLayer {
-				name: layer_name,
-				data: layer_data,
+			uv_layers.push(UvLayerView {
+				name,
+				data: ArrayView::new(self.read_bytes(num_points, Unorm16x2::SIZE)?),
 			})
 		}
 
-		let mut animation_data = None;
+		// Normals, tangents, skinning and materials are stored in optional trailing sections
+		let mut normals = ArrayView::empty();
+		let mut tangents = ArrayView::empty();
+		let mut skin_section = None;
+		let mut materials_section = None;
 
-		if !self.buf.is_empty() {
-			let mut section = self.expect_section(b"SKIN")?;
-			animation_data = Some(section.read_animation_data()?);
+		while !self.buf.is_empty() {
+			let (tag, mut section) = self.read_section()?;
+			match &tag {
+				b"MNRM" => normals = section.read_array()?,
+				b"MTAN" => tangents = section.read_array()?,
+				b"SKIN" => skin_section = Some(section),
+				b"MTLS" => materials_section = Some(section),
+				_ => bail!("Unexpected tag '{}' encountered in mesh", tag_to_string(&tag)),
+			}
 		}
 
-		Ok(Mesh {
-			positions: vertices,
+		ensure!(normals.is_empty() || normals.len() == num_vertices, "Normal layer different size to vertex list");
+		ensure!(tangents.is_empty() || tangents.len() == num_vertices, "Tangent layer different size to vertex list");
+
+		Ok(MeshView {
+			positions,
 			indices,
+			normals,
+			tangents,
 			color_layers,
 			uv_layers,
-			animation_data,
+			skin_section,
+			materials_section,
 		})
 	}
 
-	fn read_animation_data(&mut self) -> ToyResult<MeshAnimationData> {
+	pub(crate) fn read_materials(&mut self, num_triangles: usize) -> ToyResult<(Vec<MeshMaterial>, Vec<u16>)> {
+		const DOUBLE_SIDED: u8 = 1 << 0;
+		const TRANSPARENT: u8 = 1 << 1;
+		const HAS_TEXTURE: u8 = 1 << 2;
+
+		let num_materials = self.read_u16()? as usize;
+		self.ensure_remaining(num_materials, 18)?;
+
+		let mut materials = Vec::with_capacity(num_materials);
+		for _ in 0..num_materials {
+			let name = self.read_string()?;
+			let base_color = self.read_vec4()?;
+			let flags = self.read_u8()?;
+
+			let texture = match flags & HAS_TEXTURE != 0 {
+				true => Some(self.read_string()?),
+				false => None,
+			};
+
+			materials.push(MeshMaterial {
+				name,
+				base_color,
+				texture,
+				double_sided: flags & DOUBLE_SIDED != 0,
+				transparent: flags & TRANSPARENT != 0,
+			});
+		}
+
+		// Like vertex indices, material indices use the smallest encoding that can address every material
+		let mut material_indices = Vec::with_capacity(num_triangles);
+		if num_materials <= 256 {
+			self.ensure_remaining(num_triangles, 1)?;
+			for _ in 0..num_triangles {
+				material_indices.push(self.read_u8()? as u16);
+			}
+
+		} else {
+			self.ensure_remaining(num_triangles, 2)?;
+			for _ in 0..num_triangles {
+				material_indices.push(self.read_u16()?);
+			}
+		}
+
+		if let Some(&index) = material_indices.iter().find(|&&index| index as usize >= num_materials) {
+			bail!("Material index {} out of range for mesh with {} materials", index, num_materials);
+		}
+
+		Ok((materials, material_indices))
+	}
+
+	pub(crate) fn read_animation_data(&mut self, num_mesh_vertices: usize) -> ToyResult<MeshAnimationData> {
 		let num_bones = self.read_u8()? as usize;
 		let mut bones = Vec::with_capacity(num_bones);
 		for _ in 0..num_bones {
@@ -167,7 +255,10 @@ impl<'d> ToyReader<'d> {
 			});
 		}
 
-		let num_vertices = self.read_u16()? as usize;
+		let num_vertices = self.read_u32()? as usize;
+		ensure!(num_vertices == num_mesh_vertices, "Skin weights different size to vertex list ({}/{})", num_vertices, num_mesh_vertices);
+		self.ensure_remaining(num_vertices / 4, 1)?;
+
 		let mut weights = Vec::with_capacity(num_vertices);
 
 		// I'm sorry
@@ -185,18 +276,17 @@ impl<'d> ToyReader<'d> {
 				for (index, weight) in vertex.indices.iter_mut().zip(&mut vertex.weights).take(count) {
 					*index = self.read_u8()?;
 					*weight = self.read_uf16()?;
+					ensure!((*index as usize) < bones.len(), "Skin weight refers to bone {} out of range for {} bones", index, bones.len());
 				}
 				weights.push(vertex);
 			}
 		}
 
-		// Remove extra unnecessary vertices
-		for _ in 0..(num_vertices%4) {
-			weights.pop();
-		}
+		// Remove the padding from the last group of four
+		weights.truncate(num_vertices);
 
 		let mut section = self.expect_section(b"ANMS")?;
-		let animations = section.read_animations()?;
+		let animations = section.read_animations(&bones)?;
 
 		Ok(MeshAnimationData {
 			bones,
@@ -205,7 +295,7 @@ impl<'d> ToyReader<'d> {
 		})
 	}
 
-	fn read_animations(&mut self) -> ToyResult<Vec<MeshAnimation>> {
+	fn read_animations(&mut self, bones: &[MeshBone]) -> ToyResult<Vec<MeshAnimation>> {
 		let mut animations = Vec::new();
 
 		while !self.buf.is_empty() {
@@ -213,21 +303,26 @@ impl<'d> ToyReader<'d> {
 			animations.push(MeshAnimation {
 				name: section.read_string()?,
 				fps: section.read_f32()?,
-				channels: section.read_animation_channels()?,
+				channels: section.read_animation_channels(bones)?,
 			});
 		}
 
 		Ok(animations)
 	}
 
-	fn read_animation_channels(&mut self) -> ToyResult<Vec<MeshAnimationChannel>> {
+	fn read_animation_channels(&mut self, bones: &[MeshBone]) -> ToyResult<Vec<MeshAnimationChannel>> {
 		let num_frames = self.read_u16()? as usize;
 		let num_channels = self.read_u8()? as usize;
 		let mut channels = Vec::with_capacity(num_channels);
 
 		for _ in 0..num_channels {
+			let bone_name = self.read_string()?;
+			let bone = bones.iter()
+				.position(|bone| bone.name == bone_name)
+				.ok_or_else(|| format_err!("Animation channel refers to unknown bone '{}'", bone_name))?;
+
 			channels.push(MeshAnimationChannel {
-				bone: self.read_string()?,
+				bone,
 				frames: self.read_animation_frames(num_frames)?,
 			})
 		}
@@ -236,6 +331,8 @@ impl<'d> ToyReader<'d> {
 	}
 
 	fn read_animation_frames(&mut self, num_frames: usize) -> ToyResult<Vec<MeshAnimationFrame>> {
+		self.ensure_remaining(num_frames, 40)?;
+
 		let mut frames = Vec::with_capacity(num_frames);
 
 		for _ in 0..num_frames {
@@ -249,19 +346,225 @@ impl<'d> ToyReader<'d> {
 		Ok(frames)
 	}
 
-	fn read_entity(&mut self) -> ToyResult<Entity> {
+	pub(crate) fn read_entity(&mut self) -> ToyResult<Entity> {
+		let name = self.read_string()?;
+		let position = self.read_vec3()?;
+		let rotation = self.read_quat()?;
+		let scale = self.read_vec3()?;
+		let mesh_id = self.read_u16()?;
+
+		let mut properties = HashMap::new();
+		let num_properties = self.read_u8()? as usize;
+		for _ in 0..num_properties {
+			let name = self.read_string()?;
+			let value = self.read_property()?;
+			properties.insert(name, value);
+		}
+
+		// Cameras, lights, markers and collision shapes are stored in optional trailing sections
+		let mut kind = EntityKind::Object;
+		let mut collision = None;
+
+		while !self.buf.is_empty() {
+			let (tag, mut section) = self.read_section()?;
+			match &tag {
+				b"CAMR" => kind = EntityKind::Camera(section.read_camera()?),
+				b"LGHT" => kind = EntityKind::Light(section.read_light()?),
+				b"MRKR" => kind = EntityKind::Marker(section.read_marker()?),
+				b"COLL" => collision = Some(section.read_collision_shape()?),
+				_ => bail!("Unexpected tag '{}' encountered in entity '{}'", tag_to_string(&tag), name),
+			}
+		}
+
 		Ok(Entity {
-			name: self.read_string()?,
-			position: self.read_vec3()?,
-			rotation: self.read_quat()?,
-			scale: self.read_vec3()?,
-			mesh_id: self.read_u16()?,
+			name,
+			mesh_id,
+			position,
+			rotation,
+			scale,
+			kind,
+			collision,
+			properties,
+		})
+	}
+
+	fn read_camera(&mut self) -> ToyResult<Camera> {
+		let projection = match self.read_u8()? {
+			b'P' => CameraProjection::Perspective { fov_y: self.read_f32()? },
+			b'O' => CameraProjection::Orthographic { height: self.read_f32()? },
+			ty => bail!("Unknown camera projection '{}'", ty as char),
+		};
+
+		Ok(Camera {
+			projection,
+			aspect: self.read_f32()?,
+			near: self.read_f32()?,
+			far: self.read_f32()?,
+		})
+	}
+
+	fn read_light(&mut self) -> ToyResult<Light> {
+		let ty = self.read_u8()?;
+		let color = self.read_vec3()?;
+		let energy = self.read_f32()?;
+		let radius = self.read_f32()?;
+
+		let kind = match ty {
+			b'P' => LightKind::Point,
+			b'S' => LightKind::Spot {
+				angle: self.read_f32()?,
+				blend: self.read_f32()?,
+			},
+			b'U' => LightKind::Sun,
+			b'A' => LightKind::Area { size: self.read_vec2()? },
+			_ => bail!("Unknown light type '{}'", ty as char),
+		};
+
+		Ok(Light {
+			kind,
+			color,
+			energy,
+			radius,
+		})
+	}
+
+	fn read_marker(&mut self) -> ToyResult<Marker> {
+		let display = match self.read_u8()? {
+			b'A' => MarkerDisplay::PlainAxes,
+			b'R' => MarkerDisplay::Arrows,
+			b'S' => MarkerDisplay::SingleArrow,
+			b'C' => MarkerDisplay::Circle,
+			b'U' => MarkerDisplay::Cube,
+			b'P' => MarkerDisplay::Sphere,
+			b'N' => MarkerDisplay::Cone,
+			b'I' => MarkerDisplay::Image,
+			ty => bail!("Unknown marker display type '{}'", ty as char),
+		};
+
+		Ok(Marker {
+			display,
+			size: self.read_f32()?,
+		})
+	}
+
+	fn read_property(&mut self) -> ToyResult<EntityProperty> {
+		let property = match self.read_u8()? {
+			b'B' => EntityProperty::Bool(self.read_u8()? != 0),
+			b'I' => EntityProperty::Int(self.read_u32()? as i32),
+			b'F' => EntityProperty::Float(self.read_f32()?),
+			b'S' => EntityProperty::String(self.read_string()?),
+			ty => bail!("Unknown property type '{}'", ty as char),
+		};
+
+		Ok(property)
+	}
+
+	fn read_collision_shape(&mut self) -> ToyResult<CollisionShape> {
+		let shape = match self.read_u8()? {
+			b'B' => CollisionShape::Box {
+				center: self.read_vec3()?,
+				half_extents: self.read_vec3()?,
+			},
+
+			b'S' => CollisionShape::Sphere {
+				center: self.read_vec3()?,
+				radius: self.read_f32()?,
+			},
+
+			b'C' => CollisionShape::Capsule {
+				center: self.read_vec3()?,
+				radius: self.read_f32()?,
+				half_height: self.read_f32()?,
+			},
+
+			b'H' => CollisionShape::ConvexHull {
+				points: self.read_vec3_list()?,
+			},
+
+			b'M' => {
+				let positions = self.read_vec3_list()?;
+				let num_triangles = self.read_u32()? as usize;
+				self.ensure_remaining(num_triangles, 12)?;
+
+				let num_indices = num_triangles * 3;
+				let mut indices = Vec::with_capacity(num_indices);
+				for _ in 0..num_indices {
+					let index = self.read_u32()?;
+					ensure!((index as usize) < positions.len(), "Collision mesh index {} out of range", index);
+					indices.push(index);
+				}
+
+				CollisionShape::TriangleMesh { positions, indices }
+			}
+
+			ty => bail!("Unknown collision shape '{}'", ty as char),
+		};
+
+		Ok(shape)
+	}
+
+	pub(crate) fn read_nav_mesh(&mut self) -> ToyResult<NavMesh> {
+		let name = self.read_string()?;
+		let vertices = self.read_vec3_list()?;
+
+		let num_polygons = self.read_u32()? as usize;
+		self.ensure_remaining(num_polygons, 13)?;
+
+		let mut polygons = Vec::with_capacity(num_polygons);
+		for _ in 0..num_polygons {
+			let num_indices = self.read_u8()? as usize;
+			ensure!(num_indices >= 3, "Nav mesh '{}' has a polygon with fewer than 3 vertices", name);
+
+			let mut polygon = Vec::with_capacity(num_indices);
+			for _ in 0..num_indices {
+				let index = self.read_u32()?;
+				ensure!((index as usize) < vertices.len(), "Nav mesh '{}' index {} out of range", name, index);
+				polygon.push(index);
+			}
+
+			polygons.push(polygon);
+		}
+
+		Ok(NavMesh {
+			name,
+			vertices,
+			polygons,
+		})
+	}
+
+	pub(crate) fn read_nav_grid(&mut self) -> ToyResult<NavGrid> {
+		let name = self.read_string()?;
+		let origin = self.read_vec3()?;
+		let cell_size = self.read_f32()?;
+		let width = self.read_u32()?;
+		let depth = self.read_u32()?;
+
+		let num_cells = (width as usize).checked_mul(depth as usize)
+			.filter(|&num_cells| self.buf.len() / 4 >= num_cells)
+			.ok_or_else(|| format_err!("Nav grid '{}' has too few cells", name))?;
+
+		let mut heights = Vec::with_capacity(num_cells);
+		for _ in 0..num_cells {
+			// Unwalkable cells are stored as NaN
+			let height = self.read_f32()?;
+			heights.push(Some(height).filter(|height| !height.is_nan()));
+		}
+
+		Ok(NavGrid {
+			name,
+			origin,
+			cell_size,
+			width,
+			depth,
+			heights,
 		})
 	}
 
-	fn read_scene(&mut self) -> ToyResult<Scene> {
+	pub(crate) fn read_scene(&mut self) -> ToyResult<Scene> {
 		let name = self.read_string()?;
 		let num_entities = self.read_u32()? as usize;
+		self.ensure_remaining(num_entities, 4)?;
+
 		let mut entities = Vec::with_capacity(num_entities);
 		for _ in 0..num_entities {
 			entities.push(self.read_u32()?);
@@ -280,6 +583,28 @@ impl<'d> ToyReader<'d> {
 		Ok(())
 	}
 
+	/// Checks that `count` elements of at least `element_size` bytes could fit in what's left of this section,
+	/// so that corrupt counts are caught before being used to allocate.
+	fn ensure_remaining(&self, count: usize, element_size: usize) -> ToyResult<()> {
+		ensure!(self.buf.len() / element_size >= count,
+			"Unexpected EOF while expecting {} elements of {} bytes", count, element_size);
+		Ok(())
+	}
+
+	/// Takes `count` elements of `element_size` bytes without decoding them.
+	fn read_bytes(&mut self, count: usize, element_size: usize) -> ToyResult<&'d [u8]> {
+		self.ensure_remaining(count, element_size)?;
+		let (bytes, rest) = self.buf.split_at(count * element_size);
+		self.buf = rest;
+		Ok(bytes)
+	}
+
+	/// Reads a u32 count followed by that many elements.
+	fn read_array<E: Encoding>(&mut self) -> ToyResult<ArrayView<'d, E>> {
+		let count = self.read_u32()? as usize;
+		Ok(ArrayView::new(self.read_bytes(count, E::SIZE)?))
+	}
+
 	fn read_tag(&mut self) -> ToyResult<Tag> {
 		ensure!(self.buf.len() >= 4, "Unexpected EOF while expecting tag");
 		let (tag, rest) = self.buf.split_at(4);
@@ -316,6 +641,13 @@ impl<'d> ToyReader<'d> {
 		Ok(self.read_u16()? as f32 / 65535.0)
 	}
 
+	fn read_vec2(&mut self) -> ToyResult<Vec2> {
+		Ok(Vec2::new(
+			self.read_f32()?,
+			self.read_f32()?
+		))
+	}
+
 	fn read_vec3(&mut self) -> ToyResult<Vec3> {
 		Ok(Vec3::new(
 			self.read_f32()?,
@@ -324,6 +656,18 @@ impl<'d> ToyReader<'d> {
 		))
 	}
 
+	fn read_vec3_list(&mut self) -> ToyResult<Vec<Vec3>> {
+		let count = self.read_u32()? as usize;
+		self.ensure_remaining(count, 12)?;
+
+		let mut list = Vec::with_capacity(count);
+		for _ in 0..count {
+			list.push(self.read_vec3()?);
+		}
+
+		Ok(list)
+	}
+
 	fn read_vec4(&mut self) -> ToyResult<Vec4> {
 		Ok(Vec4::new(
 			self.read_f32()?,
@@ -343,6 +687,10 @@ impl<'d> ToyReader<'d> {
 	}
 
 	fn read_string(&mut self) -> ToyResult<String> {
+		self.read_str().map(Into::into)
+	}
+
+	fn read_str(&mut self) -> ToyResult<&'d str> {
 		let length = self.read_u8()? as usize;
 
 		ensure!(self.buf.len() >= length, "Unexpected EOF while reading string");
@@ -350,14 +698,199 @@ impl<'d> ToyReader<'d> {
 		self.buf = tail;
 
 		std::str::from_utf8(utf8)
-			.map(Into::into)
 			.map_err(Into::into)
 	}
 }
 
 
-fn tag_to_string(tag: &Tag) -> String {
-	unsafe {
-		std::str::from_utf8_unchecked(tag).into()
+pub(crate) fn tag_to_string(tag: &Tag) -> String {
+	String::from_utf8_lossy(tag).into_owned()
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_util::*;
+
+	/// Exercises everything that reads loaded data, so that invalid references surface as panics.
+	fn use_project(project: &Project) {
+		for scene in project.scenes() {
+			for entity in scene.entities() {
+				let _ = entity.mesh();
+				let _ = entity.world_collision_shape();
+			}
+		}
+
+		for mesh in project.meshes.iter() {
+			for [a, b, c] in mesh.triangles() {
+				let _ = (mesh.positions[a as usize], mesh.positions[b as usize], mesh.positions[c as usize]);
+			}
+		}
+
+		for nav_mesh in project.nav_meshes.iter() {
+			let _ = nav_mesh.polygon_neighbors();
+			for polygon in 0..nav_mesh.polygons.len() {
+				let _ = nav_mesh.polygon_center(polygon);
+			}
+		}
+
+		let _ = crate::validate::validate(project);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn sample_file_loads() {
+		let project = load(&sample_file()).unwrap();
+
+		assert_eq!(project.meshes.len(), 1);
+		assert_eq!(project.find_scene("Main").unwrap().entities().count(), 2);
+		assert!(project.meshes[0].animation_data.is_some());
+		assert_eq!(project.cameras().count(), 1);
+		use_project(&project);
+	}
+
+	/// A mesh with `num_vertices` vertices, and `num_weights` skin weights each fully weighted to `bone_index`.
+	fn skinned_mesh_file(num_vertices: u32, num_weights: u32, bone_index: u8) -> Vec<u8> {
+		Writer::file(|w| {
+			w.section(b"MESH", |w| {
+				w.u32(num_vertices);
+				for i in 0..num_vertices { w.vec3(Vec3::from_x(i as f32)); }
+				w.u32(1).u8(0).u8(1).u8(2).u8(0).u8(0);
+
+				w.section(b"SKIN", |w| {
+					w.u8(1).string("Bone").vec3(Vec3::zero()).vec3(Vec3::from_y(1.0));
+					w.u32(num_weights);
+
+					for group_start in (0..num_weights).step_by(4) {
+						let group_size = (num_weights - group_start).min(4);
+						let counts_packed = (0..group_size).fold(0, |packed, slot| packed | 1 << (6 - 2*slot));
+
+						w.u8(counts_packed);
+						for _ in 0..group_size { w.u8(bone_index).u16(65535); }
+					}
+
+					w.section(b"ANMS", |_| {});
+				});
+			});
+		})
+	}
+
+	#[test]
+	fn skin_weights_match_vertices() {
+		let project = load(&sample_file()).unwrap();
+		let mesh = &project.meshes[0];
+		assert_eq!(mesh.animation_data.as_ref().unwrap().weights.len(), mesh.positions.len());
+
+		for num_vertices in 3..10 {
+			let project = load(&skinned_mesh_file(num_vertices, num_vertices, 0)).unwrap();
+			let mesh = &project.meshes[0];
+			let weights = &mesh.animation_data.as_ref().unwrap().weights;
+
+			assert_eq!(weights.len(), mesh.positions.len());
+			assert!(weights.iter().all(|vertex| vertex.weights[0] == 1.0));
+		}
+	}
+
+	#[test]
+	fn invalid_skin_weights() {
+		assert!(load(&skinned_mesh_file(5, 4, 0)).is_err());
+		assert!(load(&skinned_mesh_file(4, 5, 0)).is_err());
+		assert!(load(&skinned_mesh_file(4, 4, 1)).is_err());
+	}
+
+	#[test]
+	fn empty_and_truncated_files() {
+		assert!(load(&[]).is_err());
+		assert!(load(b"TO").is_err());
+		assert!(load(b"TOY").is_err());
+
+		// Files cut off between top level sections are still valid, as long as no references are broken
+		let data = sample_file();
+		let (_, sections) = load_with_sections(&data);
+		let section_ends: Vec<_> = sections.iter()
+			.filter(|section| section.depth == 0)
+			.map(|section| section.offset + section.size)
+			.collect();
+
+		for length in 0..data.len() {
+			if let Ok(project) = load(&data[..length]) {
+				assert!(length == 4 || section_ends.contains(&length), "Truncated file of length {} loaded", length);
+				use_project(&project);
+			}
+		}
+	}
+
+	#[test]
+	fn invalid_references() {
+		let mesh_index_out_of_range = Writer::file(|w| {
+			w.section(b"MESH", |w| {
+				w.u32(3).vec3(Vec3::zero()).vec3(Vec3::zero()).vec3(Vec3::zero());
+				w.u32(1).u8(0).u8(1).u8(3).u8(0).u8(0);
+			});
+		});
+		assert!(load(&mesh_index_out_of_range).is_err());
+
+		let missing_mesh = Writer::file(|w| {
+			w.section(b"ENTY", |w| {
+				w.string("Thing").vec3(Vec3::zero()).quat(Quat::identity()).vec3(Vec3::splat(1.0)).u16(1).u8(0);
+			});
+		});
+		assert!(load(&missing_mesh).is_err());
+
+		for entity_id in [0, 2] {
+			let missing_entity = Writer::file(|w| {
+				w.entity(Vec3::zero(), Quat::identity(), Vec3::splat(1.0), |_| {});
+				w.section(b"SCNE", |w| { w.string("Main").u32(1).u32(entity_id); });
+			});
+			assert!(load(&missing_entity).is_err());
+		}
+	}
+
+	#[test]
+	fn scene_ref_skips_invalid_entities() {
+		let mut project = load(&sample_file()).unwrap();
+		project.scenes[0].entities.extend([0, 100]);
+
+		assert_eq!(project.scenes().next().unwrap().entities().count(), 2);
+	}
+
+	#[test]
+	fn huge_counts_fail_without_allocating() {
+		let data = Writer::file(|w| {
+			w.section(b"MESH", |w| { w.u32(u32::MAX); });
+			w.section(b"SCNE", |w| { w.string("Main").u32(u32::MAX); });
+		});
+		assert!(load(&data).is_err());
+
+		let data = Writer::file(|w| {
+			w.section(b"NAVG", |w| { w.string("Grid").vec3(Vec3::zero()).f32(1.0).u32(u32::MAX).u32(u32::MAX); });
+		});
+		assert!(load(&data).is_err());
+	}
+
+	proptest::proptest! {
+		#[test]
+		fn arbitrary_data_never_panics(data in proptest::collection::vec(proptest::num::u8::ANY, 0..1024)) {
+			let mut file = b"TOY".to_vec();
+			file.push(SCENE_VERSION);
+			file.extend_from_slice(&data);
+
+			if let Ok(project) = load(&file) {
+				use_project(&project);
+			}
+		}
+
+		#[test]
+		fn corrupted_sample_never_panics(corruptions in proptest::collection::vec((0.0..1.0f64, proptest::num::u8::ANY), 1..8)) {
+			let mut data = sample_file();
+			for (position, byte) in corruptions {
+				let index = (position * data.len() as f64) as usize;
+				data[index] = byte;
+			}
+
+			if let Ok(project) = load(&data) {
+				use_project(&project);
+			}
+		}
+	}
+}
@@ -2,9 +2,30 @@
 
 pub mod types;
 pub mod importer;
+pub mod view;
+pub mod animation;
+pub mod skinning;
+pub mod query;
+pub mod collision;
+pub mod navigation;
+pub mod material;
+pub mod normals;
+pub mod gltf;
+pub mod validate;
+pub mod optimize;
+
+#[cfg(test)]
+pub(crate) mod test_util;
 
 pub use self::types::*;
 pub use self::importer::*;
+pub use self::view::*;
+pub use self::animation::*;
+pub use self::query::*;
+pub use self::collision::*;
+pub use self::navigation::*;
+pub use self::material::*;
+pub use self::normals::*;
 
 pub const DEFAULT_COLOR_DATA_NAME: &'static str = "Col";
 
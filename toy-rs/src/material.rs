@@ -0,0 +1,155 @@
+use crate::types::*;
+use common::*;
+
+
+/// A material slot of a [`Mesh`], with the properties of the blender material assigned to it.
+#[derive(Debug, Clone)]
+pub struct MeshMaterial {
+	pub name: String,
+
+	/// Linear RGBA. Taken from the base color of a Principled BSDF if there is one, otherwise the materials
+	/// viewport display color.
+	pub base_color: Vec4,
+
+	/// The name of the image connected to the base color of the material, if any.
+	pub texture: Option<String>,
+
+	/// Whether backface culling is disabled for this material.
+	pub double_sided: bool,
+
+	/// Whether this material uses any blend mode other than opaque.
+	pub transparent: bool,
+}
+
+
+/// A run of consecutive triangles in a [`Mesh`] that share a material. See [`Mesh::sub_meshes`].
+/// Offsets and counts are in indices rather than triangles, so they can be used to draw part of an index buffer
+/// built from [`Mesh::indices`] directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubMesh {
+	/// An index into [`Mesh::materials`], or `None` if the mesh has no materials.
+	pub material: Option<usize>,
+
+	pub index_offset: u32,
+	pub num_indices: u32,
+}
+
+
+impl Mesh {
+	/// Splits [`Mesh::indices`] into runs of triangles that share a material, so each can be drawn separately.
+	/// The exporter sorts triangles by material, so each material normally has exactly one sub-mesh.
+	/// Meshes without materials produce a single sub-mesh covering every triangle.
+	pub fn sub_meshes(&self) -> impl Iterator<Item=SubMesh> + '_ {
+		let num_triangles = self.num_triangles();
+		let mut triangle = 0;
+
+		std::iter::from_fn(move || {
+			if triangle >= num_triangles {
+				return None
+			}
+
+			let start = triangle;
+			let material = self.material_indices.get(start).copied();
+
+			triangle += 1;
+			while triangle < num_triangles && self.material_indices.get(triangle).copied() == material {
+				triangle += 1;
+			}
+
+			Some(SubMesh {
+				material: material.map(usize::from),
+				index_offset: start as u32 * 3,
+				num_indices: (triangle - start) as u32 * 3,
+			})
+		})
+	}
+
+	/// The material assigned to triangle `triangle`, if the mesh has materials.
+	pub fn triangle_material(&self, triangle: usize) -> Option<&MeshMaterial> {
+		let material_index = *self.material_indices.get(triangle)?;
+		self.materials.get(material_index as usize)
+	}
+
+	pub fn material_by_name(&self, name: &str) -> Option<&MeshMaterial> {
+		self.materials.iter()
+			.find(|material| material.name == name)
+	}
+}
+
+
+impl SubMesh {
+	pub fn num_triangles(&self) -> usize {
+		self.num_indices as usize / 3
+	}
+
+	/// The range of [`Mesh::indices`] covered by this sub-mesh.
+	pub fn index_range(&self) -> std::ops::Range<usize> {
+		let start = self.index_offset as usize;
+		start..start + self.num_indices as usize
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::load;
+	use crate::test_util::*;
+
+	#[test]
+	fn material_sub_meshes() {
+		let data = Writer::file(|w| {
+			w.section(b"MESH", |w| {
+				w.u32(3).vec3(Vec3::zero()).vec3(Vec3::from_x(1.0)).vec3(Vec3::from_z(1.0));
+				w.u32(4);
+				for _ in 0..4 { w.u8(0).u8(1).u8(2); }
+				w.u8(0).u8(0);
+
+				w.section(b"MTLS", |w| {
+					w.u16(2);
+					w.string("Stone").f32(1.0).f32(1.0).f32(1.0).f32(1.0).u8(0b100).string("stone.png");
+					w.string("Moss").f32(0.0).f32(1.0).f32(0.0).f32(0.5).u8(0b011);
+					w.u8(0).u8(0).u8(1).u8(0);
+				});
+			});
+		});
+
+		let project = load(&data).unwrap();
+		let mesh = &project.meshes[0];
+
+		let stone = mesh.material_by_name("Stone").unwrap();
+		assert_eq!(stone.texture.as_deref(), Some("stone.png"));
+		assert!(!stone.double_sided && !stone.transparent);
+
+		let moss = mesh.triangle_material(2).unwrap();
+		assert_eq!(moss.name, "Moss");
+		assert!(moss.texture.is_none() && moss.double_sided && moss.transparent);
+
+		let sub_meshes: Vec<_> = mesh.sub_meshes().collect();
+		assert_eq!(sub_meshes, [
+			SubMesh { material: Some(0), index_offset: 0, num_indices: 6 },
+			SubMesh { material: Some(1), index_offset: 6, num_indices: 3 },
+			SubMesh { material: Some(0), index_offset: 9, num_indices: 3 },
+		]);
+
+		let material_index_out_of_range = Writer::file(|w| {
+			w.section(b"MESH", |w| {
+				w.u32(3).vec3(Vec3::zero()).vec3(Vec3::zero()).vec3(Vec3::zero());
+				w.u32(1).u8(0).u8(1).u8(2).u8(0).u8(0);
+				w.section(b"MTLS", |w| { w.u16(1).string("A").f32(1.0).f32(1.0).f32(1.0).f32(1.0).u8(0).u8(1); });
+			});
+		});
+		assert!(load(&material_index_out_of_range).is_err());
+	}
+
+	#[test]
+	fn mesh_without_materials_has_one_sub_mesh() {
+		let mut project = load(&sample_file()).unwrap();
+		let mesh = &mut project.meshes[0];
+		mesh.materials.clear();
+		mesh.material_indices.clear();
+
+		assert_eq!(mesh.sub_meshes().collect::<Vec<_>>(), [SubMesh { material: None, index_offset: 0, num_indices: 3 }]);
+		assert!(mesh.triangle_material(0).is_none());
+	}
+}
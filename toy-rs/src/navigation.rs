@@ -0,0 +1,216 @@
+use crate::types::*;
+use common::*;
+use std::collections::HashMap;
+
+
+/// A navigation mesh baked from a mesh object tagged with a `navigation` custom property of `mesh` in blender.
+/// Vertices are in world space, and polygons are assumed to be convex.
+#[derive(Debug, Clone)]
+pub struct NavMesh {
+	pub name: String,
+	pub vertices: Vec<Vec3>,
+
+	/// Indices into [`NavMesh::vertices`], one list per polygon.
+	pub polygons: Vec<Vec<u32>>,
+}
+
+
+/// A grid of walkable cells on the XZ plane, baked by raycasting down onto a mesh object tagged with a
+/// `navigation` custom property of `grid` in blender.
+/// Cell size is controlled by an optional `nav_cell_size` property.
+#[derive(Debug, Clone)]
+pub struct NavGrid {
+	pub name: String,
+
+	/// The world space position of the minimum corner of the grid. The Y component is always zero.
+	pub origin: Vec3,
+	pub cell_size: f32,
+	pub width: u32,
+	pub depth: u32,
+
+	/// The world space height of the walkable surface in each cell, or `None` if the cell isn't walkable.
+	/// Stored in rows along X, such that the cell at `(x, z)` is at index `z * width + x`.
+	pub heights: Vec<Option<f32>>,
+}
+
+
+impl NavMesh {
+	/// Triangulates each polygon as a fan, producing a triangle list.
+	pub fn triangles(&self) -> impl Iterator<Item=[u32; 3]> + '_ {
+		self.polygons.iter()
+			.flat_map(|polygon| {
+				let first = polygon[0];
+				polygon[1..].windows(2)
+					.map(move |edge| [first, edge[0], edge[1]])
+			})
+	}
+
+	pub fn polygon_center(&self, polygon: usize) -> Vec3 {
+		let polygon = &self.polygons[polygon];
+		let sum = polygon.iter()
+			.fold(Vec3::zero(), |sum, &index| sum + self.vertices[index as usize]);
+
+		sum / polygon.len() as f32
+	}
+
+	/// Finds the polygons sharing an edge with each polygon, indexed the same as [`NavMesh::polygons`].
+	pub fn polygon_neighbors(&self) -> Vec<Vec<usize>> {
+		let mut edge_polygons: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+
+		for (polygon_index, polygon) in self.polygons.iter().enumerate() {
+			let next_indices = polygon.iter().cycle().skip(1);
+
+			for (&a, &b) in polygon.iter().zip(next_indices) {
+				edge_polygons.entry((a.min(b), a.max(b)))
+					.or_default()
+					.push(polygon_index);
+			}
+		}
+
+		let mut neighbors = vec![Vec::new(); self.polygons.len()];
+
+		for polygons in edge_polygons.values() {
+			for &polygon in polygons {
+				neighbors[polygon].extend(polygons.iter().filter(|&&other| other != polygon));
+			}
+		}
+
+		for polygon_neighbors in neighbors.iter_mut() {
+			polygon_neighbors.sort_unstable();
+			polygon_neighbors.dedup();
+		}
+
+		neighbors
+	}
+}
+
+
+impl NavGrid {
+	/// The height of the walkable surface at cell `(x, z)`, or `None` if it is out of bounds or not walkable.
+	pub fn height(&self, x: u32, z: u32) -> Option<f32> {
+		if x >= self.width || z >= self.depth {
+			return None
+		}
+
+		self.heights[(z * self.width + x) as usize]
+	}
+
+	pub fn is_walkable(&self, x: u32, z: u32) -> bool {
+		self.height(x, z).is_some()
+	}
+
+	/// The cell containing world space `position`, ignoring height.
+	pub fn cell_at(&self, position: Vec3) -> Option<(u32, u32)> {
+		let x = ((position.x - self.origin.x) / self.cell_size).floor();
+		let z = ((position.z - self.origin.z) / self.cell_size).floor();
+
+		if x < 0.0 || z < 0.0 || x >= self.width as f32 || z >= self.depth as f32 {
+			return None
+		}
+
+		Some((x as u32, z as u32))
+	}
+
+	/// The world space position of the walkable surface at the center of cell `(x, z)`, if it is walkable.
+	pub fn cell_center(&self, x: u32, z: u32) -> Option<Vec3> {
+		let height = self.height(x, z)?;
+
+		Some(Vec3::new(
+			self.origin.x + (x as f32 + 0.5) * self.cell_size,
+			height,
+			self.origin.z + (z as f32 + 0.5) * self.cell_size,
+		))
+	}
+}
+
+
+impl Project {
+	pub fn find_nav_mesh(&self, name: &str) -> Option<&NavMesh> {
+		self.nav_meshes.iter()
+			.find(|nav_mesh| nav_mesh.name == name)
+	}
+
+	pub fn find_nav_grid(&self, name: &str) -> Option<&NavGrid> {
+		self.nav_grids.iter()
+			.find(|nav_grid| nav_grid.name == name)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::load;
+	use crate::test_util::*;
+
+	#[test]
+	fn nav_mesh() {
+		let data = Writer::file(|w| {
+			w.section(b"NAVM", |w| {
+				w.string("Nav");
+				w.u32(6);
+				for x in 0..3 {
+					w.vec3(Vec3::new(x as f32, 0.0, 0.0));
+					w.vec3(Vec3::new(x as f32, 0.0, 1.0));
+				}
+
+				// Two quads sharing the edge between vertices 2 and 3
+				w.u32(2);
+				w.u8(4).u32(0).u32(2).u32(3).u32(1);
+				w.u8(4).u32(2).u32(4).u32(5).u32(3);
+			});
+		});
+
+		let project = load(&data).unwrap();
+		let nav_mesh = project.find_nav_mesh("Nav").unwrap();
+
+		assert_eq!(nav_mesh.polygons.len(), 2);
+		assert_eq!(nav_mesh.triangles().count(), 4);
+		assert_eq!(nav_mesh.polygon_neighbors(), [vec![1], vec![0]]);
+		assert_vec3_eq(nav_mesh.polygon_center(1), Vec3::new(1.5, 0.0, 0.5));
+	}
+
+	#[test]
+	fn nav_mesh_index_out_of_range() {
+		let data = Writer::file(|w| {
+			w.section(b"NAVM", |w| {
+				w.string("Nav").u32(1).vec3(Vec3::zero());
+				w.u32(1).u8(3).u32(0).u32(0).u32(1);
+			});
+		});
+
+		assert!(load(&data).is_err());
+	}
+
+	#[test]
+	fn nav_grid() {
+		let data = Writer::file(|w| {
+			w.section(b"NAVG", |w| {
+				w.string("Grid").vec3(Vec3::new(-1.0, 0.0, -1.0)).f32(1.0).u32(2).u32(2);
+				w.f32(0.0).f32(f32::NAN).f32(0.5).f32(1.0);
+			});
+		});
+
+		let project = load(&data).unwrap();
+		let grid = project.find_nav_grid("Grid").unwrap();
+
+		assert_eq!(grid.heights, [Some(0.0), None, Some(0.5), Some(1.0)]);
+		assert!(!grid.is_walkable(1, 0));
+		assert!(!grid.is_walkable(2, 0));
+		assert_eq!(grid.cell_at(Vec3::new(0.5, 10.0, -0.5)), Some((1, 0)));
+		assert_eq!(grid.cell_at(Vec3::new(1.5, 0.0, 0.0)), None);
+		assert_vec3_eq(grid.cell_center(0, 1).unwrap(), Vec3::new(-0.5, 0.5, 0.5));
+	}
+
+	#[test]
+	fn nav_grid_too_few_cells() {
+		let data = Writer::file(|w| {
+			w.section(b"NAVG", |w| {
+				w.string("Grid").vec3(Vec3::zero()).f32(1.0).u32(100).u32(100);
+				w.f32(0.0);
+			});
+		});
+
+		assert!(load(&data).is_err());
+	}
+}
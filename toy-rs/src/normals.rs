@@ -0,0 +1,315 @@
+use crate::types::*;
+use crate::ToyResult;
+use common::*;
+use std::collections::HashMap;
+
+
+/// How [`Mesh::compute_normals`] should generate normals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalMode {
+	/// The normals of triangles sharing a vertex position are averaged, weighted by triangle area.
+	Smooth,
+
+	/// Like [`NormalMode::Smooth`], but weighted by the angle of each triangle at the vertex instead, which
+	/// makes the result independent of how faces were triangulated.
+	AngleWeighted,
+
+	/// Each triangle gets its own vertices with the normal of the triangle.
+	/// This splits every vertex - see [`Mesh::unweld`].
+	Flat,
+}
+
+
+impl Mesh {
+	/// Replaces [`Mesh::normals`] with normals generated from triangle geometry.
+	/// Smooth normals are accumulated by vertex position rather than by index, since the exporter splits vertices
+	/// wherever colors or uvs differ, and averaging by index would leave visible seams.
+	/// Any existing [`Mesh::tangents`] are cleared, since they depend on the normals.
+	///
+	/// Fails without modifying the mesh if its layers are inconsistent - see [`Mesh::check_vertex_data`].
+	pub fn compute_normals(&mut self, mode: NormalMode) -> ToyResult<()> {
+		self.check_vertex_data()?;
+
+		if mode == NormalMode::Flat {
+			self.unweld()?;
+		}
+
+		let mut accumulated_normals: HashMap<[u32; 3], Vec3> = HashMap::new();
+		let mut flat_normals = vec![Vec3::zero(); self.positions.len()];
+
+		for triangle in self.triangles() {
+			let [a, b, c] = triangle.map(|index| self.positions[index as usize]);
+
+			// Not normalized so larger triangles contribute more
+			let face_normal = (b - a).cross(c - a);
+
+			match mode {
+				NormalMode::Smooth => for position in [a, b, c] {
+					*accumulated_normals.entry(position_key(position)).or_default() += face_normal;
+				}
+
+				NormalMode::AngleWeighted => {
+					let face_normal = normalize_or_zero(face_normal);
+
+					for (position, prev, next) in [(a, c, b), (b, a, c), (c, b, a)] {
+						let angle = corner_angle(position, prev, next);
+						*accumulated_normals.entry(position_key(position)).or_default() += face_normal * angle;
+					}
+				}
+
+				NormalMode::Flat => for index in triangle {
+					flat_normals[index as usize] = normalize_or_zero(face_normal);
+				}
+			}
+		}
+
+		self.normals = match mode {
+			NormalMode::Flat => flat_normals,
+			_ => self.positions.iter()
+				.map(|&position| normalize_or_zero(accumulated_normals.get(&position_key(position)).copied().unwrap_or_default()))
+				.collect(),
+		};
+
+		self.tangents.clear();
+		Ok(())
+	}
+
+	/// Replaces [`Mesh::tangents`] with tangents generated from the uv layer `uv_layer`, or the first uv layer
+	/// if `None`. Angle weighted normals are computed first if the mesh doesn't have any.
+	///
+	/// Per-triangle tangents are projected onto the normal plane and averaged weighted by corner angle, and `w`
+	/// holds the sign of the bitangent, such that `bitangent = w * normal.cross(tangent)` - the same layout as
+	/// exported tangents. The tangents themselves aren't MikkTSpace, so normal maps baked in blender may shade
+	/// slightly differently than with exported tangents. Vertices aren't split, so a vertex shared by triangles
+	/// with mirrored uvs gets a single averaged tangent - [`Mesh::unweld`] first if that matters.
+	///
+	/// Returns false and leaves the mesh unchanged if the uv layer doesn't exist, and fails if the mesh's layers are
+	/// inconsistent - see [`Mesh::check_vertex_data`].
+	pub fn compute_tangents(&mut self, uv_layer: Option<&str>) -> ToyResult<bool> {
+		self.check_vertex_data()?;
+
+		let uv_layer = match uv_layer {
+			Some(name) => self.uv_layer_by_name(name),
+			None => self.uv_layers.first(),
+		};
+
+		let Some(uvs) = uv_layer.map(|layer| layer.data.clone()) else {
+			return Ok(false)
+		};
+
+		if self.normals.len() != self.positions.len() {
+			self.compute_normals(NormalMode::AngleWeighted)?;
+		}
+
+		let num_vertices = self.positions.len();
+		let mut accumulated_tangents = vec![Vec3::zero(); num_vertices];
+		let mut accumulated_bitangents = vec![Vec3::zero(); num_vertices];
+
+		for triangle in self.triangles() {
+			let [a, b, c] = triangle.map(|index| self.positions[index as usize]);
+			let [uv_a, uv_b, uv_c] = triangle.map(|index| uvs[index as usize]);
+
+			let (edge_1, edge_2) = (b - a, c - a);
+			let (uv_edge_1, uv_edge_2) = (uv_b - uv_a, uv_c - uv_a);
+
+			// Directions of increasing u and v across the triangle, scaled by twice the uv area
+			let tangent = edge_1 * uv_edge_2.y - edge_2 * uv_edge_1.y;
+			let bitangent = edge_2 * uv_edge_1.x - edge_1 * uv_edge_2.x;
+
+			let corners = [(0, a, c, b), (1, b, a, c), (2, c, b, a)];
+			for (corner, position, prev, next) in corners {
+				let index = triangle[corner] as usize;
+				let normal = self.normals[index];
+				let angle = corner_angle(position, prev, next);
+
+				accumulated_tangents[index] += normalize_or_zero(tangent - normal * normal.dot(tangent)) * angle;
+				accumulated_bitangents[index] += normalize_or_zero(bitangent - normal * normal.dot(bitangent)) * angle;
+			}
+		}
+
+		self.tangents = accumulated_tangents.into_iter()
+			.zip(accumulated_bitangents)
+			.zip(&self.normals)
+			.map(|((tangent, bitangent), &normal)| {
+				let tangent = normalize_or_zero(tangent - normal * normal.dot(tangent));
+				let tangent = match tangent.length() > 0.0 {
+					true => tangent,
+					false => any_perpendicular(normal),
+				};
+
+				let sign = match normal.cross(tangent).dot(bitangent) < 0.0 {
+					true => -1.0,
+					false => 1.0,
+				};
+
+				tangent.extend(sign)
+			})
+			.collect();
+
+		Ok(true)
+	}
+
+	/// Splits vertices so that no two triangles share any, duplicating every per-vertex layer.
+	/// Needed for flat shading, since a vertex can only have one normal.
+	///
+	/// Fails without modifying the mesh if its layers are inconsistent - see [`Mesh::check_vertex_data`].
+	pub fn unweld(&mut self) -> ToyResult<()> {
+		fn split<T: Copy>(data: &mut Vec<T>, indices: &[u32]) {
+			if data.is_empty() {
+				return
+			}
+
+			*data = indices.iter().map(|&index| data[index as usize]).collect();
+		}
+
+		self.check_vertex_data()?;
+
+		let indices = std::mem::take(&mut self.indices);
+
+		split(&mut self.positions, &indices);
+		split(&mut self.normals, &indices);
+		split(&mut self.tangents, &indices);
+
+		for layer in self.color_layers.iter_mut() {
+			split(&mut layer.data, &indices);
+		}
+
+		for layer in self.uv_layers.iter_mut() {
+			split(&mut layer.data, &indices);
+		}
+
+		if let Some(animation_data) = &mut self.animation_data {
+			split(&mut animation_data.weights, &indices);
+		}
+
+		self.indices = (0..indices.len() as u32).collect();
+		Ok(())
+	}
+}
+
+
+
+fn position_key(Vec3{x, y, z}: Vec3) -> [u32; 3] {
+	[x.to_bits(), y.to_bits(), z.to_bits()]
+}
+
+/// The angle at `position` of the triangle formed with its neighbours.
+fn corner_angle(position: Vec3, prev: Vec3, next: Vec3) -> f32 {
+	let to_prev = normalize_or_zero(prev - position);
+	let to_next = normalize_or_zero(next - position);
+	to_prev.dot(to_next).clamp(-1.0, 1.0).acos()
+}
+
+pub(crate) fn normalize_or_zero(v: Vec3) -> Vec3 {
+	let length = v.length();
+	if length > 0.0 {
+		v / length
+	} else {
+		Vec3::zero()
+	}
+}
+
+fn any_perpendicular(v: Vec3) -> Vec3 {
+	let axis = match v.x.abs() < 0.9 {
+		true => Vec3::from_x(1.0),
+		false => Vec3::from_y(1.0),
+	};
+
+	normalize_or_zero(axis - v * v.dot(axis))
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::load;
+	use crate::test_util::*;
+
+	#[test]
+	fn normals_and_tangents() {
+		let project = load(&sample_file()).unwrap();
+		let mesh = &project.meshes[0];
+		assert_eq!(mesh.normals.len(), 3);
+		assert_eq!(mesh.tangents.len(), 3);
+
+		let mut quad = quad_mesh();
+		assert!(quad.compute_tangents(None).unwrap());
+
+		for (normal, tangent) in quad.normals.iter().zip(&quad.tangents) {
+			assert_vec3_eq(*normal, Vec3::from_y(1.0));
+			assert_vec3_eq(tangent.to_vec3(), Vec3::from_x(1.0));
+			assert_eq!(tangent.w, 1.0);
+		}
+
+		// Mirroring uvs flips the bitangent
+		for uv in quad.uv_layers[0].data.iter_mut() {
+			uv.x = 1.0 - uv.x;
+		}
+
+		assert!(quad.compute_tangents(Some("UVMap")).unwrap());
+		assert!(quad.tangents.iter().all(|tangent| tangent.w == -1.0));
+		assert!(!quad.compute_tangents(Some("Missing")).unwrap());
+	}
+
+	#[test]
+	fn computed_normal_modes() {
+		// Two triangles folded 90 degrees along the X axis
+		let mut mesh = quad_mesh();
+		mesh.positions[2] = Vec3::new(1.0, 1.0, 0.0);
+		mesh.positions[3] = Vec3::new(0.0, 1.0, 0.0);
+		mesh.indices = vec![0, 1, 2, 0, 2, 3, 0, 3, 1];
+		mesh.positions.push(Vec3::zero());
+		mesh.uv_layers[0].data.push(Vec2::zero());
+
+		mesh.compute_normals(NormalMode::Flat).unwrap();
+		assert_eq!(mesh.positions.len(), 9);
+		assert_eq!(mesh.uv_layers[0].data.len(), 9);
+		assert_eq!(mesh.indices, (0..9).collect::<Vec<_>>());
+		assert_vec3_eq(mesh.normals[0], Vec3::from_z(1.0));
+
+		let mut mesh = quad_mesh();
+		mesh.positions = vec![Vec3::zero(), Vec3::from_x(1.0), Vec3::from_y(1.0), Vec3::from_z(-1.0)];
+		mesh.indices = vec![0, 1, 2, 0, 1, 3];
+
+		// Triangles are perpendicular, and meet at vertex 0 with the same angle, so both modes agree there
+		for mode in [NormalMode::Smooth, NormalMode::AngleWeighted] {
+			mesh.compute_normals(mode).unwrap();
+			assert_vec3_eq(mesh.normals[0], Vec3::new(0.0, 1.0, 1.0).normalize());
+			assert_vec3_eq(mesh.normals[2], Vec3::from_z(1.0));
+		}
+	}
+
+	#[test]
+	fn flat_normals_split_every_layer() {
+		let mut project = load(&sample_file()).unwrap();
+		let mesh = &mut project.meshes[0];
+
+		// A back face sharing every vertex with the front face
+		mesh.indices.extend([0, 2, 1]);
+		mesh.material_indices.push(0);
+		mesh.color_layers[0].data[2] = Vec4::new(0.0, 0.0, 1.0, 1.0);
+		mesh.animation_data.as_mut().unwrap().weights[2].weights = [0.5, 0.5, 0.0];
+
+		mesh.compute_normals(NormalMode::Flat).unwrap();
+		assert_eq!(mesh.positions.len(), 6);
+		assert!(mesh.tangents.is_empty());
+		assert!(mesh.check_vertex_data().is_ok());
+
+		let colors = &mesh.color_layers[0].data;
+		let weights = &mesh.animation_data.as_ref().unwrap().weights;
+		assert_eq!((colors[2].z, colors[4].z), (1.0, 1.0));
+		assert_eq!((weights[2].weights, weights[4].weights), ([0.5, 0.5, 0.0], [0.5, 0.5, 0.0]));
+		assert_vec3_eq(mesh.normals[0], -mesh.normals[3]);
+
+		// Short layers are rejected rather than panicking
+		mesh.color_layers[0].data.pop();
+		assert!(mesh.compute_normals(NormalMode::Flat).is_err());
+		assert!(mesh.unweld().is_err());
+		assert_eq!(mesh.positions.len(), 6);
+
+		mesh.color_layers.clear();
+		mesh.animation_data.as_mut().unwrap().weights.truncate(1);
+		assert!(mesh.compute_normals(NormalMode::Smooth).is_err());
+		assert!(mesh.compute_tangents(None).is_err());
+	}
+}
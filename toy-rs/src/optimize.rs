@@ -0,0 +1,966 @@
+//! Post-processing passes that make imported meshes cheaper to draw.
+//!
+//! The exporter writes a vertex for every unique triangle corner and leaves triangles in whatever order blender
+//! stores them. The passes here weld vertices back together, drop degenerate triangles and reorder triangles and
+//! vertices for the GPU. Each pass returns an [`OptimizeReport`] comparing [`MeshStats`] from before and after,
+//! or an error if the mesh fails [`Mesh::check_vertex_data`].
+//!
+//! Triangles are only ever reordered within a [`SubMesh`](crate::SubMesh), so the triangles of each material
+//! stay contiguous.
+
+use crate::types::*;
+use crate::normals::normalize_or_zero;
+use crate::ToyResult;
+use common::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::fmt;
+
+/// Size of the FIFO post-transform cache simulated by [`MeshStats`] and by the overdraw pass.
+pub const STATS_CACHE_SIZE: usize = 16;
+
+/// The default for [`OptimizeOptions::weld_tolerance`].
+pub const DEFAULT_WELD_TOLERANCE: f32 = 0.00001;
+
+/// Size of the LRU cache modelled by Tom Forsyth's vertex cache optimisation. Deliberately larger than
+/// [`STATS_CACHE_SIZE`], since the scoring only uses it to prefer recently used vertices.
+const FORSYTH_CACHE_SIZE: usize = 32;
+
+const FETCH_CACHE_LINE_SIZE: usize = 64;
+const FETCH_CACHE_LINES: usize = 64;
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct OptimizeOptions {
+	/// Vertices whose attributes all differ by at most this much are merged. `None` skips welding.
+	pub weld_tolerance: Option<f32>,
+
+	/// Removes triangles with repeated indices or zero area, and triangles that repeat another with the same winding.
+	pub remove_degenerate_triangles: bool,
+
+	pub optimize_vertex_cache: bool,
+
+	/// How much the ACMR of the vertex cache ordering is allowed to grow in exchange for less overdraw,
+	/// e.g. 1.05 allows 5% more cache misses. `None` skips the pass.
+	pub overdraw_threshold: Option<f32>,
+
+	pub optimize_vertex_fetch: bool,
+}
+
+impl Default for OptimizeOptions {
+	fn default() -> OptimizeOptions {
+		OptimizeOptions {
+			weld_tolerance: Some(DEFAULT_WELD_TOLERANCE),
+			remove_degenerate_triangles: true,
+			optimize_vertex_cache: true,
+			overdraw_threshold: Some(1.05),
+			optimize_vertex_fetch: true,
+		}
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizePass {
+	Weld,
+	RemoveDegenerateTriangles,
+	VertexCache,
+	Overdraw,
+	VertexFetch,
+}
+
+
+/// Measurements of how efficiently a mesh can be drawn, used to compare meshes before and after each pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshStats {
+	pub num_vertices: usize,
+	pub num_triangles: usize,
+
+	/// Average cache miss ratio - vertices transformed per triangle with a [`STATS_CACHE_SIZE`] FIFO cache.
+	/// Between 3 in the worst case and around 0.5 for large regular grids.
+	pub acmr: f32,
+
+	/// Average transformed vertex ratio - vertices transformed per vertex referenced by the index buffer.
+	/// 1 is optimal.
+	pub atvr: f32,
+
+	/// Bytes read from the vertex buffer per byte of referenced vertex data, through a small cache of 64 byte lines.
+	/// The vertex size is estimated from the layers present in the mesh. 1 is optimal.
+	pub overfetch: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OptimizeReport {
+	pub pass: OptimizePass,
+	pub before: MeshStats,
+	pub after: MeshStats,
+}
+
+
+/// Runs every pass enabled in `options` on each mesh in `project`, returning the reports for each mesh
+/// in the same order as [`Project::meshes`].
+pub fn optimize_project(project: &mut Project, options: &OptimizeOptions) -> ToyResult<Vec<Vec<OptimizeReport>>> {
+	project.meshes.iter_mut()
+		.map(|mesh| optimize_mesh(mesh, options))
+		.collect()
+}
+
+/// Runs every pass enabled in `options` on `mesh`. Passes run in the order they're declared in [`OptimizePass`],
+/// since welding exposes degenerate triangles, and the overdraw pass works on clusters of the vertex cache order.
+///
+/// Fails without modifying `mesh` if its layers are inconsistent - see [`Mesh::check_vertex_data`].
+pub fn optimize_mesh(mesh: &mut Mesh, options: &OptimizeOptions) -> ToyResult<Vec<OptimizeReport>> {
+	mesh.check_vertex_data()?;
+
+	let mut reports = Vec::new();
+
+	if let Some(tolerance) = options.weld_tolerance {
+		reports.push(weld_vertices(mesh, tolerance)?);
+	}
+
+	if options.remove_degenerate_triangles {
+		reports.push(remove_degenerate_triangles(mesh)?);
+	}
+
+	if options.optimize_vertex_cache {
+		reports.push(optimize_vertex_cache(mesh)?);
+	}
+
+	if let Some(threshold) = options.overdraw_threshold {
+		reports.push(optimize_overdraw(mesh, threshold)?);
+	}
+
+	if options.optimize_vertex_fetch {
+		reports.push(optimize_vertex_fetch(mesh)?);
+	}
+
+	Ok(reports)
+}
+
+
+/// Merges vertices whose positions and other attributes all differ by at most `tolerance` per component.
+/// The first of each group of merged vertices is kept. A tolerance of zero only merges exact duplicates.
+pub fn weld_vertices(mesh: &mut Mesh, tolerance: f32) -> ToyResult<OptimizeReport> {
+	run_pass(mesh, OptimizePass::Weld, |mesh| {
+		let num_vertices = mesh.positions.len();
+		let attributes: Vec<Vec<f32>> = (0..num_vertices).map(|vertex| vertex_attributes(mesh, vertex)).collect();
+
+		let cell = |position: Vec3| match tolerance > 0.0 {
+			true => [position.x, position.y, position.z].map(|v| (v / tolerance).floor() as i64),
+			false => [position.x, position.y, position.z].map(|v| v.to_bits() as i64),
+		};
+
+		let neighbour_range = if tolerance > 0.0 { -1..=1 } else { 0..=0 };
+
+		// Vertices that were kept, by grid cell
+		let mut kept_vertices: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+		let mut remap = vec![None; num_vertices];
+		let mut num_welded = 0;
+
+		for vertex in 0..num_vertices {
+			let [x, y, z] = cell(mesh.positions[vertex]);
+			let mut existing = None;
+
+			'search: for dx in neighbour_range.clone() {
+				for dy in neighbour_range.clone() {
+					for dz in neighbour_range.clone() {
+						let Some(candidates) = kept_vertices.get(&[x + dx, y + dy, z + dz]) else {
+							continue
+						};
+
+						existing = candidates.iter().copied()
+							.find(|&candidate| attributes[candidate].iter().zip(&attributes[vertex])
+								.all(|(a, b)| (a - b).abs() <= tolerance));
+
+						if existing.is_some() {
+							break 'search
+						}
+					}
+				}
+			}
+
+			remap[vertex] = match existing {
+				Some(existing) => remap[existing],
+				None => {
+					kept_vertices.entry([x, y, z]).or_default().push(vertex);
+					num_welded += 1;
+					Some(num_welded as u32 - 1)
+				}
+			};
+		}
+
+		remap_vertices(mesh, &remap, num_welded);
+	})
+}
+
+
+/// Removes triangles that can't produce any pixels, and triangles that repeat an earlier triangle with the same
+/// winding. Triangles with opposite winding are kept, since they're usually intentional double sided geometry.
+pub fn remove_degenerate_triangles(mesh: &mut Mesh) -> ToyResult<OptimizeReport> {
+	run_pass(mesh, OptimizePass::RemoveDegenerateTriangles, |mesh| {
+		let mut seen = HashSet::new();
+		let mut indices = Vec::with_capacity(mesh.indices.len());
+		let mut material_indices = Vec::with_capacity(mesh.material_indices.len());
+
+		for (triangle_index, triangle) in mesh.triangles().enumerate() {
+			if is_degenerate_triangle(mesh, triangle) {
+				continue
+			}
+
+			// Rotate so the lowest index is first, so that the same triangle starting from another corner matches
+			let first = (0..3).min_by_key(|&corner| triangle[corner]).unwrap();
+			let key = [triangle[first], triangle[(first + 1) % 3], triangle[(first + 2) % 3]];
+
+			if !seen.insert(key) {
+				continue
+			}
+
+			indices.extend_from_slice(&triangle);
+			material_indices.extend(mesh.material_indices.get(triangle_index));
+		}
+
+		mesh.indices = indices;
+		mesh.material_indices = material_indices;
+	})
+}
+
+
+/// Reorders triangles to reduce vertex shader invocations, using Tom Forsyth's
+/// [linear-speed vertex cache optimisation](https://tomforsyth1000.github.io/papers/fast_vert_cache_opt.html).
+pub fn optimize_vertex_cache(mesh: &mut Mesh) -> ToyResult<OptimizeReport> {
+	run_pass(mesh, OptimizePass::VertexCache, |mesh| {
+		for range in sub_mesh_ranges(mesh) {
+			let indices = &mut mesh.indices[range];
+			let order = vertex_cache_order(indices);
+			reorder_triangles(indices, &order);
+		}
+	})
+}
+
+
+/// Reorders clusters of triangles so that triangles facing outwards from the center of the mesh are drawn first,
+/// so that more of the triangles behind them fail the depth test.
+/// Should run after [`optimize_vertex_cache`] - the vertex cache order is split into clusters at points where the
+/// cache would be mostly empty anyway, allowing ACMR to grow by at most a factor of `threshold`.
+///
+/// Based on Sander et al. "Fast Triangle Reordering for Vertex Locality and Reduced Overdraw".
+pub fn optimize_overdraw(mesh: &mut Mesh, threshold: f32) -> ToyResult<OptimizeReport> {
+	run_pass(mesh, OptimizePass::Overdraw, |mesh| {
+		for range in sub_mesh_ranges(mesh) {
+			let indices = &mut mesh.indices[range];
+			let order = overdraw_order(indices, &mesh.positions, threshold);
+			reorder_triangles(indices, &order);
+		}
+	})
+}
+
+
+/// Renumbers vertices in the order they're first used by the index buffer, so vertex fetches move linearly
+/// through memory. Vertices that aren't used by any triangle are removed.
+pub fn optimize_vertex_fetch(mesh: &mut Mesh) -> ToyResult<OptimizeReport> {
+	run_pass(mesh, OptimizePass::VertexFetch, |mesh| {
+		let mut remap = vec![None; mesh.positions.len()];
+		let mut num_used = 0;
+
+		for &index in mesh.indices.iter() {
+			let new_index = &mut remap[index as usize];
+			if new_index.is_none() {
+				*new_index = Some(num_used as u32);
+				num_used += 1;
+			}
+		}
+
+		remap_vertices(mesh, &remap, num_used);
+	})
+}
+
+
+
+impl MeshStats {
+	pub fn from_mesh(mesh: &Mesh) -> MeshStats {
+		let vertex_size = approximate_vertex_size(mesh);
+		let mut cache = FifoCache::new(STATS_CACHE_SIZE);
+		let mut fetch_cache: VecDeque<usize> = VecDeque::with_capacity(FETCH_CACHE_LINES);
+		let mut referenced = HashSet::new();
+
+		let mut num_transformed = 0;
+		let mut bytes_fetched = 0;
+
+		for &index in mesh.indices.iter() {
+			referenced.insert(index);
+
+			if !cache.access(index) {
+				continue
+			}
+
+			num_transformed += 1;
+
+			let start = index as usize * vertex_size;
+			let end = start + vertex_size;
+
+			for line in start / FETCH_CACHE_LINE_SIZE ..= (end - 1) / FETCH_CACHE_LINE_SIZE {
+				if let Some(position) = fetch_cache.iter().position(|&cached| cached == line) {
+					fetch_cache.remove(position);
+				} else {
+					bytes_fetched += FETCH_CACHE_LINE_SIZE;
+					fetch_cache.truncate(FETCH_CACHE_LINES - 1);
+				}
+
+				fetch_cache.push_front(line);
+			}
+		}
+
+		let ratio = |a: usize, b: usize| if b > 0 { a as f32 / b as f32 } else { 0.0 };
+
+		MeshStats {
+			num_vertices: mesh.positions.len(),
+			num_triangles: mesh.num_triangles(),
+			acmr: ratio(num_transformed, mesh.num_triangles()),
+			atvr: ratio(num_transformed, referenced.len()),
+			overfetch: ratio(bytes_fetched, referenced.len() * vertex_size),
+		}
+	}
+}
+
+
+/// Triangles with repeated or out of range indices, or zero area.
+/// Area is compared relative to the size of the triangle, so that tiny triangles in small scale meshes are kept but
+/// collinear triangles are removed from large meshes, where rounding leaves them with a small nonzero area.
+pub fn is_degenerate_triangle(mesh: &Mesh, [a, b, c]: [u32; 3]) -> bool {
+	if a == b || b == c || c == a {
+		return true
+	}
+
+	let vertex = |index: u32| mesh.positions.get(index as usize).copied();
+	let (Some(a), Some(b), Some(c)) = (vertex(a), vertex(b), vertex(c)) else {
+		return true
+	};
+
+	let length_sq = |v: Vec3| v.dot(v);
+	let cross = (b - a).cross(c - a);
+	let max_edge_length_sq = length_sq(b - a).max(length_sq(c - b)).max(length_sq(a - c));
+
+	// The cross product is at most max_edge_length_sq long, for a right angle between the two longest edges
+	let limit = f32::EPSILON * max_edge_length_sq;
+	length_sq(cross) <= limit * limit
+}
+
+
+
+/// Every pass checks the mesh first, since remapping vertices would otherwise panic on short layers.
+fn run_pass(mesh: &mut Mesh, pass: OptimizePass, apply: impl FnOnce(&mut Mesh)) -> ToyResult<OptimizeReport> {
+	mesh.check_vertex_data()?;
+
+	let before = MeshStats::from_mesh(mesh);
+	apply(mesh);
+	let after = MeshStats::from_mesh(mesh);
+
+	Ok(OptimizeReport { pass, before, after })
+}
+
+
+/// Every attribute of `vertex` that must match for it to be welded to another vertex.
+fn vertex_attributes(mesh: &Mesh, vertex: usize) -> Vec<f32> {
+	let Vec3 { x, y, z } = mesh.positions[vertex];
+	let mut attributes = vec![x, y, z];
+
+	if let Some(&Vec3 { x, y, z }) = mesh.normals.get(vertex) {
+		attributes.extend([x, y, z]);
+	}
+
+	if let Some(&Vec4 { x, y, z, w }) = mesh.tangents.get(vertex) {
+		attributes.extend([x, y, z, w]);
+	}
+
+	for layer in mesh.color_layers.iter() {
+		if let Some(&Vec4 { x, y, z, w }) = layer.data.get(vertex) {
+			attributes.extend([x, y, z, w]);
+		}
+	}
+
+	for layer in mesh.uv_layers.iter() {
+		if let Some(&Vec2 { x, y }) = layer.data.get(vertex) {
+			attributes.extend([x, y]);
+		}
+	}
+
+	if let Some(weights) = mesh.animation_data.as_ref().and_then(|data| data.weights.get(vertex)) {
+		attributes.extend(weights.indices.map(f32::from));
+		attributes.extend(weights.weights);
+	}
+
+	attributes
+}
+
+
+/// Moves vertex `i` of every per-vertex layer to `remap[i]`, dropping vertices mapped to `None`, and updates
+/// indices to match. Where several vertices map to the same place, the first is kept.
+fn remap_vertices(mesh: &mut Mesh, remap: &[Option<u32>], num_vertices: usize) {
+	fn remap_layer<T: Copy>(data: &mut Vec<T>, remap: &[Option<u32>], num_vertices: usize) {
+		if data.is_empty() {
+			return
+		}
+
+		let mut remapped = vec![None; num_vertices];
+		for (&vertex, &new_index) in data.iter().zip(remap) {
+			if let Some(new_index) = new_index {
+				remapped[new_index as usize].get_or_insert(vertex);
+			}
+		}
+
+		*data = remapped.into_iter()
+			.map(|vertex| vertex.expect("Every remapped vertex must have a source"))
+			.collect();
+	}
+
+	remap_layer(&mut mesh.positions, remap, num_vertices);
+	remap_layer(&mut mesh.normals, remap, num_vertices);
+	remap_layer(&mut mesh.tangents, remap, num_vertices);
+
+	for layer in mesh.color_layers.iter_mut() {
+		remap_layer(&mut layer.data, remap, num_vertices);
+	}
+
+	for layer in mesh.uv_layers.iter_mut() {
+		remap_layer(&mut layer.data, remap, num_vertices);
+	}
+
+	if let Some(animation_data) = &mut mesh.animation_data {
+		remap_layer(&mut animation_data.weights, remap, num_vertices);
+	}
+
+	for index in mesh.indices.iter_mut() {
+		*index = remap[*index as usize].expect("Vertices used by triangles can't be removed");
+	}
+}
+
+
+fn sub_mesh_ranges(mesh: &Mesh) -> Vec<Range<usize>> {
+	mesh.sub_meshes()
+		.map(|sub_mesh| sub_mesh.index_range())
+		.collect()
+}
+
+/// Rearranges the triangles in `indices` so that triangle `i` is what was triangle `order[i]`.
+fn reorder_triangles(indices: &mut [u32], order: &[usize]) {
+	let original = indices.to_vec();
+
+	for (new_triangle, &old_triangle) in order.iter().enumerate() {
+		indices[new_triangle*3..new_triangle*3 + 3].copy_from_slice(&original[old_triangle*3..old_triangle*3 + 3]);
+	}
+}
+
+
+
+fn vertex_cache_order(indices: &[u32]) -> Vec<usize> {
+	let num_triangles = indices.len() / 3;
+
+	// Renumber vertices so per-vertex state only needs to cover this sub-mesh
+	let mut local_ids = HashMap::new();
+	let corners: Vec<usize> = indices.iter()
+		.map(|&index| {
+			let next_id = local_ids.len();
+			*local_ids.entry(index).or_insert(next_id)
+		})
+		.collect();
+
+	let num_vertices = local_ids.len();
+
+	// Triangles using each vertex. The first `remaining[vertex]` entries are triangles that haven't been emitted yet
+	let mut remaining = vec![0; num_vertices];
+	for &vertex in corners.iter() {
+		remaining[vertex] += 1;
+	}
+
+	let mut adjacency_offsets = Vec::with_capacity(num_vertices + 1);
+	adjacency_offsets.push(0);
+	for &count in remaining.iter() {
+		adjacency_offsets.push(adjacency_offsets.last().unwrap() + count);
+	}
+
+	let mut adjacency = vec![0; corners.len()];
+	let mut fill_offsets = adjacency_offsets.clone();
+	for (corner, &vertex) in corners.iter().enumerate() {
+		adjacency[fill_offsets[vertex]] = corner / 3;
+		fill_offsets[vertex] += 1;
+	}
+
+	let mut vertex_scores: Vec<f32> = remaining.iter()
+		.map(|&remaining| forsyth_vertex_score(None, remaining))
+		.collect();
+
+	let mut emitted = vec![false; num_triangles];
+	let mut order = Vec::with_capacity(num_triangles);
+	let mut cache: Vec<usize> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+	let mut best_triangle = None;
+	let mut next_unemitted = 0;
+
+	while order.len() < num_triangles {
+		// If nothing in the cache has triangles left, continue from the next triangle in the original order
+		let triangle = match best_triangle.take() {
+			Some(triangle) => triangle,
+			None => {
+				while emitted[next_unemitted] {
+					next_unemitted += 1;
+				}
+
+				next_unemitted
+			}
+		};
+
+		emitted[triangle] = true;
+		order.push(triangle);
+
+		let triangle_vertices = &corners[triangle*3..triangle*3 + 3];
+
+		for &vertex in triangle_vertices {
+			let live = &mut adjacency[adjacency_offsets[vertex]..adjacency_offsets[vertex] + remaining[vertex]];
+			let position = live.iter().position(|&other| other == triangle).unwrap();
+			live.swap(position, live.len() - 1);
+			remaining[vertex] -= 1;
+		}
+
+		let mut new_cache = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+		for &vertex in triangle_vertices.iter().chain(cache.iter()) {
+			if !new_cache.contains(&vertex) {
+				new_cache.push(vertex);
+			}
+		}
+
+		for &vertex in new_cache.iter().skip(FORSYTH_CACHE_SIZE) {
+			vertex_scores[vertex] = forsyth_vertex_score(None, remaining[vertex]);
+		}
+
+		new_cache.truncate(FORSYTH_CACHE_SIZE);
+
+		for (position, &vertex) in new_cache.iter().enumerate() {
+			vertex_scores[vertex] = forsyth_vertex_score(Some(position), remaining[vertex]);
+		}
+
+		// Only triangles touching the cache can have improved, so the best is picked from those
+		let mut best_score = f32::NEG_INFINITY;
+		for &vertex in new_cache.iter() {
+			let live = &adjacency[adjacency_offsets[vertex]..adjacency_offsets[vertex] + remaining[vertex]];
+
+			for &other in live {
+				let score: f32 = corners[other*3..other*3 + 3].iter()
+					.map(|&vertex| vertex_scores[vertex])
+					.sum();
+
+				if score > best_score {
+					best_score = score;
+					best_triangle = Some(other);
+				}
+			}
+		}
+
+		cache = new_cache;
+	}
+
+	order
+}
+
+
+fn forsyth_vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+	const CACHE_DECAY_POWER: f32 = 1.5;
+	const LAST_TRIANGLE_SCORE: f32 = 0.75;
+	const VALENCE_BOOST_SCALE: f32 = 2.0;
+	const VALENCE_BOOST_POWER: f32 = 0.5;
+
+	if remaining_triangles == 0 {
+		return -1.0
+	}
+
+	let cache_score = match cache_position {
+		// Vertices of the last triangle get a fixed score, so that the order doesn't depend on which was used first
+		Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+		Some(position) => {
+			let scale = 1.0 / (FORSYTH_CACHE_SIZE - 3) as f32;
+			(1.0 - (position - 3) as f32 * scale).powf(CACHE_DECAY_POWER)
+		}
+		None => 0.0,
+	};
+
+	// Favour vertices with few triangles left, so they can leave the cache sooner
+	let valence_boost = VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-VALENCE_BOOST_POWER);
+
+	cache_score + valence_boost
+}
+
+
+
+fn overdraw_order(indices: &[u32], positions: &[Vec3], threshold: f32) -> Vec<usize> {
+	let num_triangles = indices.len() / 3;
+	let triangle_vertices = |triangle: usize| [0, 1, 2].map(|corner| positions[indices[triangle*3 + corner] as usize]);
+
+	let mut mesh_centroid = Vec3::zero();
+	let mut mesh_area = 0.0;
+
+	for triangle in 0..num_triangles {
+		let [a, b, c] = triangle_vertices(triangle);
+		let area = (b - a).cross(c - a).length();
+		mesh_centroid += (a + b + c) * (area / 3.0);
+		mesh_area += area;
+	}
+
+	if mesh_area > 0.0 {
+		mesh_centroid /= mesh_area;
+	}
+
+	let cluster_starts = overdraw_clusters(indices, threshold);
+	let cluster_ends = cluster_starts.iter().skip(1).copied().chain([num_triangles]);
+
+	let mut clusters: Vec<(f32, Range<usize>)> = cluster_starts.iter().copied().zip(cluster_ends)
+		.map(|(start, end)| {
+			let mut centroid = Vec3::zero();
+			let mut area_weighted_normal = Vec3::zero();
+			let mut area = 0.0;
+
+			for triangle in start..end {
+				let [a, b, c] = triangle_vertices(triangle);
+				let normal = (b - a).cross(c - a);
+				let triangle_area = normal.length();
+
+				centroid += (a + b + c) * (triangle_area / 3.0);
+				area_weighted_normal += normal;
+				area += triangle_area;
+			}
+
+			if area > 0.0 {
+				centroid /= area;
+			}
+
+			// Clusters further out along their own normal are more likely to occlude the rest of the mesh
+			let occlusion_potential = (centroid - mesh_centroid).dot(normalize_or_zero(area_weighted_normal));
+			(occlusion_potential, start..end)
+		})
+		.collect();
+
+	clusters.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+	clusters.into_iter()
+		.flat_map(|(_, triangles)| triangles)
+		.collect()
+}
+
+
+/// Splits `indices` into runs of triangles that can be reordered while keeping the ACMR of each run within
+/// `threshold` of the run of the vertex cache order it came from. Returns the first triangle of each run.
+fn overdraw_clusters(indices: &[u32], threshold: f32) -> Vec<usize> {
+	let num_triangles = indices.len() / 3;
+
+	let triangle_misses = |cache: &mut FifoCache, triangle: usize| {
+		indices[triangle*3..triangle*3 + 3].iter()
+			.filter(|&&index| cache.access(index))
+			.count()
+	};
+
+	// Triangles that miss the cache entirely are where the vertex cache order jumped to an unconnected part of the
+	// mesh, so splitting there costs nothing
+	let mut cache = FifoCache::new(STATS_CACHE_SIZE);
+	let mut hard_boundaries: Vec<usize> = (0..num_triangles)
+		.filter(|&triangle| triangle_misses(&mut cache, triangle) == 3)
+		.collect();
+
+	if hard_boundaries.first() != Some(&0) {
+		hard_boundaries.insert(0, 0);
+	}
+
+	let mut clusters = Vec::new();
+
+	for (boundary_index, &start) in hard_boundaries.iter().enumerate() {
+		let end = hard_boundaries.get(boundary_index + 1).copied().unwrap_or(num_triangles);
+
+		let mut cache = FifoCache::new(STATS_CACHE_SIZE);
+		let misses: usize = (start..end).map(|triangle| triangle_misses(&mut cache, triangle)).sum();
+		let max_acmr = threshold * misses as f32 / (end - start) as f32;
+
+		// Split again wherever restarting the cache keeps the ACMR of the part so far under the threshold
+		let mut cache = FifoCache::new(STATS_CACHE_SIZE);
+		let mut cluster_start = start;
+		let mut cluster_misses = 0;
+		clusters.push(start);
+
+		for triangle in start..end {
+			cluster_misses += triangle_misses(&mut cache, triangle);
+			let acmr = cluster_misses as f32 / (triangle + 1 - cluster_start) as f32;
+
+			if triangle + 1 < end && acmr <= max_acmr {
+				clusters.push(triangle + 1);
+				cluster_start = triangle + 1;
+				cluster_misses = 0;
+				cache = FifoCache::new(STATS_CACHE_SIZE);
+			}
+		}
+	}
+
+	clusters
+}
+
+
+
+/// Simulates a FIFO post-transform vertex cache.
+struct FifoCache {
+	entries: VecDeque<u32>,
+	size: usize,
+}
+
+impl FifoCache {
+	fn new(size: usize) -> FifoCache {
+		FifoCache { entries: VecDeque::with_capacity(size), size }
+	}
+
+	/// Returns true if `vertex` wasn't in the cache, and so would have been transformed.
+	fn access(&mut self, vertex: u32) -> bool {
+		if self.entries.contains(&vertex) {
+			return false
+		}
+
+		if self.entries.len() == self.size {
+			self.entries.pop_back();
+		}
+
+		self.entries.push_front(vertex);
+		true
+	}
+}
+
+
+/// Roughly the size of a vertex with every layer of `mesh`, as it would be laid out in a vertex buffer.
+fn approximate_vertex_size(mesh: &Mesh) -> usize {
+	let if_present = |present: bool, size: usize| if present { size } else { 0 };
+
+	std::mem::size_of::<Vec3>()
+		+ if_present(!mesh.normals.is_empty(), std::mem::size_of::<Vec3>())
+		+ if_present(!mesh.tangents.is_empty(), std::mem::size_of::<Vec4>())
+		+ mesh.color_layers.len() * std::mem::size_of::<Vec4>()
+		+ mesh.uv_layers.len() * std::mem::size_of::<Vec2>()
+		+ if_present(mesh.animation_data.is_some(), std::mem::size_of::<MeshWeightVertex>())
+}
+
+
+
+impl fmt::Display for OptimizePass {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			OptimizePass::Weld => "weld",
+			OptimizePass::RemoveDegenerateTriangles => "remove degenerate triangles",
+			OptimizePass::VertexCache => "vertex cache",
+			OptimizePass::Overdraw => "overdraw",
+			OptimizePass::VertexFetch => "vertex fetch",
+		};
+
+		f.write_str(name)
+	}
+}
+
+impl fmt::Display for OptimizeReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let OptimizeReport { pass, before, after } = self;
+
+		write!(f, "{}: {} -> {} vertices, {} -> {} triangles, ACMR {:.3} -> {:.3}, ATVR {:.3} -> {:.3}, overfetch {:.3} -> {:.3}",
+			pass, before.num_vertices, after.num_vertices, before.num_triangles, after.num_triangles,
+			before.acmr, after.acmr, before.atvr, after.atvr, before.overfetch, after.overfetch)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::material::{MeshMaterial, SubMesh};
+	use crate::load;
+	use crate::test_util::*;
+
+	/// A `size` by `size` grid of quads with a vertex per triangle corner like the exporter produces, triangles in a
+	/// scrambled order, and the second half of the triangles using a second material.
+	fn grid_mesh(size: u32) -> Mesh {
+		let mut mesh = quad_mesh();
+		mesh.positions.clear();
+		mesh.indices.clear();
+		mesh.uv_layers[0].data.clear();
+		mesh.materials = ["A", "B"].iter()
+			.map(|&name| MeshMaterial { name: name.into(), base_color: Vec4::splat(1.0), texture: None, double_sided: false, transparent: false })
+			.collect();
+
+		let num_triangles = size * size * 2;
+		let triangles_per_material = num_triangles / 2;
+
+		for material in 0..2 {
+			for i in 0..triangles_per_material {
+				let triangle = material * triangles_per_material + (i * 37) % triangles_per_material;
+				let (quad, upper) = (triangle / 2, triangle % 2 == 1);
+				let (x, z) = ((quad % size) as f32, (quad / size) as f32);
+
+				let corners = match upper {
+					false => [(x, z), (x + 1.0, z), (x + 1.0, z + 1.0)],
+					true => [(x, z), (x + 1.0, z + 1.0), (x, z + 1.0)],
+				};
+
+				for (x, z) in corners {
+					mesh.indices.push(mesh.positions.len() as u32);
+					mesh.positions.push(Vec3::new(x, 0.0, -z));
+					mesh.uv_layers[0].data.push(Vec2::new(x, z));
+				}
+
+				mesh.material_indices.push(material as u16);
+			}
+		}
+
+		mesh
+	}
+
+	fn triangle_positions(mesh: &Mesh, sub_mesh: &SubMesh) -> Vec<[[u32; 3]; 3]> {
+		let mut triangles: Vec<_> = mesh.indices[sub_mesh.index_range()].chunks_exact(3)
+			.map(|triangle| {
+				let mut corners = [0, 1, 2].map(|corner| {
+					let Vec3 { x, y, z } = mesh.positions[triangle[corner] as usize];
+					[x, y, z].map(f32::to_bits)
+				});
+				let first = (0..3).min_by_key(|&corner| corners[corner]).unwrap();
+				corners.rotate_left(first);
+				corners
+			})
+			.collect();
+
+		triangles.sort();
+		triangles
+	}
+
+	#[test]
+	fn weld_and_remove_degenerate_triangles() {
+		let mut mesh = grid_mesh(10);
+
+		let report = weld_vertices(&mut mesh, DEFAULT_WELD_TOLERANCE).unwrap();
+		assert_eq!((report.before.num_vertices, report.after.num_vertices), (600, 121));
+		assert_eq!(mesh.uv_layers[0].data.len(), 121);
+		assert_eq!(mesh.num_triangles(), 200);
+
+		// A vertex in the same place with a different uv stays separate
+		let mut quad = quad_mesh();
+		quad.positions.push(quad.positions[2]);
+		quad.uv_layers[0].data.push(Vec2::new(0.5, 0.5));
+		quad.indices = vec![0, 1, 2, 0, 4, 3];
+		weld_vertices(&mut quad, DEFAULT_WELD_TOLERANCE).unwrap();
+		assert_eq!(quad.positions.len(), 5);
+
+		// Two copies of an existing triangle, one starting from another corner, a collapsed triangle and a zero area triangle
+		let [a, b, c] = mesh.triangles().nth(1).unwrap();
+		mesh.indices.extend_from_slice(&[a, b, c, b, c, a, a, a, b]);
+		mesh.positions.push(mesh.positions[a as usize]);
+		mesh.uv_layers[0].data.push(Vec2::zero());
+		mesh.indices.extend_from_slice(&[a, 121, b]);
+		mesh.material_indices.resize(204, 1);
+
+		let report = remove_degenerate_triangles(&mut mesh).unwrap();
+		assert_eq!((report.before.num_triangles, report.after.num_triangles), (204, 200));
+		assert_eq!(mesh.material_indices.len(), 200);
+	}
+
+	#[test]
+	fn degenerate_triangles_are_scale_relative() {
+		let mut mesh = quad_mesh();
+
+		// A valid triangle a hundred micrometers across
+		mesh.positions = vec![Vec3::zero(), Vec3::new(1.0e-4, 0.0, 0.0), Vec3::new(0.0, 1.0e-4, 0.0)];
+		assert!(!is_degenerate_triangle(&mesh, [0, 1, 2]));
+
+		// Collinear points a kilometer apart, with the middle one nudged off the line by rounding
+		mesh.positions = vec![Vec3::zero(), Vec3::new(1000.0, 1000.0, 0.0), Vec3::new(300.0, 300.00003, 0.0)];
+		assert!(is_degenerate_triangle(&mesh, [0, 1, 2]));
+
+		// A thin but real sliver
+		mesh.positions = vec![Vec3::zero(), Vec3::new(1000.0, 0.0, 0.0), Vec3::new(500.0, 0.01, 0.0)];
+		assert!(!is_degenerate_triangle(&mesh, [0, 1, 2]));
+
+		mesh.positions = vec![Vec3::splat(5.0); 3];
+		assert!(is_degenerate_triangle(&mesh, [0, 1, 2]));
+		assert!(is_degenerate_triangle(&mesh, [0, 1, 3]));
+	}
+
+	#[test]
+	fn optimize_mesh_keeps_triangles_and_materials() {
+		let original = grid_mesh(10);
+		let mut mesh = original.clone();
+
+		let reports = optimize_mesh(&mut mesh, &OptimizeOptions::default()).unwrap();
+		let passes: Vec<_> = reports.iter().map(|report| report.pass).collect();
+		assert_eq!(passes, [OptimizePass::Weld, OptimizePass::RemoveDegenerateTriangles, OptimizePass::VertexCache,
+			OptimizePass::Overdraw, OptimizePass::VertexFetch]);
+
+		let welded = reports[0].after;
+		let optimized = reports.last().unwrap().after;
+		assert!(optimized.acmr < welded.acmr, "{}", reports[2]);
+		assert!(optimized.acmr <= 1.0, "{}", reports[2]);
+		assert!(optimized.overfetch <= welded.overfetch, "{}", reports[4]);
+
+		let original_sub_meshes: Vec<_> = original.sub_meshes().collect();
+		let sub_meshes: Vec<_> = mesh.sub_meshes().collect();
+		assert_eq!(sub_meshes, original_sub_meshes);
+
+		for sub_mesh in sub_meshes.iter() {
+			assert_eq!(triangle_positions(&mesh, sub_mesh), triangle_positions(&original, sub_mesh));
+		}
+
+		// Vertices are numbered in order of first use
+		let mut next_vertex = 0;
+		for &index in mesh.indices.iter() {
+			assert!(index <= next_vertex);
+			next_vertex = next_vertex.max(index + 1);
+		}
+
+		assert_eq!(next_vertex as usize, mesh.positions.len());
+	}
+
+	#[test]
+	fn optimize_keeps_every_layer_in_sync() {
+		// Colors and weights derived from position, so they should still match each vertex after welding and reordering
+		let mut mesh = grid_mesh(4);
+		let colors = mesh.positions.iter().map(|position| Vec4::new(position.x, position.z, 0.0, 1.0)).collect();
+		let weights = mesh.positions.iter()
+			.map(|position| MeshWeightVertex { indices: [0; 3], weights: [position.x / 4.0, 0.0, 0.0] })
+			.collect();
+
+		mesh.color_layers.push(MeshColorLayer { name: "Col".into(), data: colors });
+		mesh.animation_data = Some(MeshAnimationData { bones: Vec::new(), weights, animations: Vec::new() });
+
+		optimize_mesh(&mut mesh, &OptimizeOptions::default()).unwrap();
+		assert_eq!(mesh.positions.len(), 25);
+		assert_eq!(mesh.uv_layers[0].data.len(), 25);
+		assert!(mesh.check_vertex_data().is_ok());
+
+		let weights = &mesh.animation_data.as_ref().unwrap().weights;
+		for ((position, color), weight) in mesh.positions.iter().zip(&mesh.color_layers[0].data).zip(weights) {
+			assert_eq!((color.x, color.y), (position.x, position.z));
+			assert_eq!(weight.weights[0], position.x / 4.0);
+		}
+
+		// The sample file has every kind of vertex layer
+		let mut project = load(&sample_file()).unwrap();
+		let mesh = &mut project.meshes[0];
+		optimize_mesh(mesh, &OptimizeOptions::default()).unwrap();
+
+		assert_eq!(mesh.num_triangles(), 1);
+		assert!(mesh.check_vertex_data().is_ok());
+		assert_eq!(mesh.animation_data.as_ref().unwrap().weights.len(), mesh.positions.len());
+	}
+
+	#[test]
+	fn short_layers_are_rejected() {
+		let mut project = load(&sample_file()).unwrap();
+		let mesh = &mut project.meshes[0];
+		mesh.animation_data.as_mut().unwrap().weights.pop();
+
+		assert!(optimize_mesh(mesh, &OptimizeOptions::default()).is_err());
+		assert!(weld_vertices(mesh, DEFAULT_WELD_TOLERANCE).is_err());
+		assert_eq!(mesh.positions.len(), 3);
+
+		let mut quad = quad_mesh();
+		quad.uv_layers[0].data.truncate(2);
+		assert!(optimize_vertex_fetch(&mut quad).is_err());
+
+		let mut quad = quad_mesh();
+		quad.indices.push(4);
+		assert!(optimize_vertex_cache(&mut quad).is_err());
+	}
+}
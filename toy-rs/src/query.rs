@@ -0,0 +1,595 @@
+use crate::types::*;
+use crate::ToyResult;
+use common::*;
+use std::collections::{HashMap, HashSet};
+
+
+/// Precalculated lookup data for querying the entities of a [`Project`].
+/// Building an index calculates world space bounds for every entity and a bounding volume hierarchy over them, so
+/// that bounds and radius queries only visit nearby entities. It is best built once and reused for all queries
+/// made while loading a level.
+///
+/// ```ignore
+/// let index = project.build_entity_index();
+///
+/// for torch in index.query().name_glob("torch.*").has_property("lit") {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EntityIndex<'toy> {
+	project: &'toy Project,
+	world_bounds: Vec<Aabb3>,
+	bounds_tree: BoundsTree,
+	name_lookup: HashMap<&'toy str, usize>,
+}
+
+
+impl Project {
+	pub fn build_entity_index(&self) -> EntityIndex<'_> {
+		EntityIndex::new(self)
+	}
+}
+
+
+impl<'toy> EntityIndex<'toy> {
+	pub fn new(project: &'toy Project) -> EntityIndex<'toy> {
+		// Calculate local bounds once per mesh, since they're often shared between entities
+		let mesh_bounds: Vec<Aabb3> = project.meshes.iter()
+			.map(|mesh| Aabb3::from_points(mesh.positions.iter().copied()))
+			.collect();
+
+		let world_bounds: Vec<Aabb3> = project.entities.iter()
+			.map(|entity| {
+				let local_bounds = match entity.mesh_id {
+					0 => None,
+					mesh_id => mesh_bounds.get(mesh_id as usize - 1)
+						.filter(|bounds| bounds.is_valid()),
+				};
+
+				match local_bounds {
+					Some(bounds) => bounds.transform(&entity.transform()),
+					None => Aabb3::new(entity.position, entity.position),
+				}
+			})
+			.collect();
+
+		// Keep the first entity with each name, to match Project::find_entity
+		let mut name_lookup = HashMap::new();
+		for (index, entity) in project.entities.iter().enumerate() {
+			name_lookup.entry(entity.name.as_str()).or_insert(index);
+		}
+
+		EntityIndex {
+			project,
+			bounds_tree: BoundsTree::new(&world_bounds),
+			world_bounds,
+			name_lookup,
+		}
+	}
+
+	pub fn project(&self) -> &'toy Project {
+		self.project
+	}
+
+	/// Starts a new query over all entities in the project.
+	pub fn query(&self) -> EntityQuery<'_, 'toy> {
+		EntityQuery {
+			index: self,
+			scene: None,
+			region: None,
+			filters: Vec::new(),
+		}
+	}
+
+	/// Same as [`Project::find_entity`] but doesn't require a linear scan.
+	pub fn find_entity(&self, name: &str) -> Option<EntityRef<'toy>> {
+		self.name_lookup.get(name)
+			.map(|&index| self.entity(index))
+	}
+
+	/// World space bounds of the entity with the given name. For entities without a mesh, this
+	/// is a zero sized box at the entities position.
+	pub fn world_bounds(&self, name: &str) -> Option<Aabb3> {
+		self.name_lookup.get(name)
+			.map(|&index| self.world_bounds[index])
+	}
+
+	fn entity(&self, index: usize) -> EntityRef<'toy> {
+		EntityRef::from(self.project, &self.project.entities[index])
+	}
+}
+
+
+
+type EntityFilter<'q, 'toy> = Box<dyn Fn(EntityRef<'toy>, &Aabb3) -> bool + 'q>;
+
+/// A set of filters over the entities in an [`EntityIndex`]. Filters are combined such that
+/// only entities matching all filters are returned.
+/// Queries are constructed with [`EntityIndex::query`], and results can be retrieved with [`EntityQuery::iter`],
+/// or by iterating over the query directly.
+///
+/// Results are in the order entities appear in the project, or in the scene for [`EntityQuery::in_scene`] queries
+/// without a bounds or radius filter.
+pub struct EntityQuery<'q, 'toy> {
+	index: &'q EntityIndex<'toy>,
+	scene: Option<SceneRef<'toy>>,
+
+	/// World space box that the bounds of every match must intersect, used to look up candidates in the
+	/// index's [`BoundsTree`]. Spatial filters still check their exact shape.
+	region: Option<Aabb3>,
+
+	filters: Vec<EntityFilter<'q, 'toy>>,
+}
+
+
+impl<'q, 'toy> EntityQuery<'q, 'toy> {
+	/// Only match entities in `scene`.
+	pub fn in_scene(self, scene: SceneRef<'toy>) -> Self {
+		EntityQuery {scene: Some(scene), ..self}
+	}
+
+	/// Only match entities whose name matches the glob `pattern`.
+	/// `*` matches any sequence of characters and `?` matches any single character.
+	pub fn name_glob(self, pattern: &str) -> Self {
+		let pattern: Vec<char> = pattern.chars().collect();
+		self.filter(move |entity| glob_match(&pattern, &entity.name))
+	}
+
+	/// Only match entities whose name matches the regular expression `pattern`.
+	/// Note that the pattern is not implicitly anchored.
+	pub fn name_regex(self, pattern: &str) -> ToyResult<Self> {
+		let regex = regex::Regex::new(pattern)?;
+		Ok(self.filter(move |entity| regex.is_match(&entity.name)))
+	}
+
+	pub fn has_mesh(self) -> Self {
+		self.filter(|entity| entity.mesh().is_some())
+	}
+
+	/// Only match entities with a mesh containing a color layer called `name`.
+	pub fn has_color_layer(self, name: &str) -> Self {
+		let name = name.to_owned();
+		self.filter(move |entity| entity.mesh()
+			.is_some_and(|mesh| mesh.color_layer_by_name(&name).is_some()))
+	}
+
+	/// Only match entities with a mesh containing a uv layer called `name`.
+	pub fn has_uv_layer(self, name: &str) -> Self {
+		let name = name.to_owned();
+		self.filter(move |entity| entity.mesh()
+			.is_some_and(|mesh| mesh.uv_layer_by_name(&name).is_some()))
+	}
+
+	pub fn has_animation_data(self) -> Self {
+		self.filter(|entity| entity.mesh()
+			.is_some_and(|mesh| mesh.animation_data.is_some()))
+	}
+
+	/// Only match entities with a custom property called `name`, regardless of its value.
+	pub fn has_property(self, name: &str) -> Self {
+		let name = name.to_owned();
+		self.filter(move |entity| entity.properties.contains_key(&name))
+	}
+
+	/// Only match entities with a custom property called `name` equal to `value`.
+	pub fn property_eq(self, name: &str, value: impl Into<EntityProperty>) -> Self {
+		let name = name.to_owned();
+		let value = value.into();
+		self.filter(move |entity| entity.property(&name) == Some(&value))
+	}
+
+	/// Only match entities whose world space bounds intersect `bounds`.
+	pub fn intersecting_bounds(self, bounds: Aabb3) -> Self {
+		self.restrict_region(bounds)
+			.filter_with_bounds(move |_, entity_bounds| entity_bounds.intersects(&bounds))
+	}
+
+	/// Only match entities whose world space bounds fall at least partially within `radius` of `center`.
+	pub fn within_radius(self, center: Vec3, radius: f32) -> Self {
+		self.restrict_region(Aabb3::around_point(center, Vec3::splat(radius)))
+			.filter_with_bounds(move |_, entity_bounds| entity_bounds.intersects_sphere(center, radius))
+	}
+
+	fn restrict_region(self, bounds: Aabb3) -> Self {
+		let region = match self.region {
+			Some(region) => Aabb3 {
+				min: Vec3::new(region.min.x.max(bounds.min.x), region.min.y.max(bounds.min.y), region.min.z.max(bounds.min.z)),
+				max: Vec3::new(region.max.x.min(bounds.max.x), region.max.y.min(bounds.max.y), region.max.z.min(bounds.max.z)),
+			},
+
+			None => bounds,
+		};
+
+		EntityQuery {region: Some(region), ..self}
+	}
+
+	/// Only match entities for which `filter` returns true.
+	pub fn filter(self, filter: impl Fn(EntityRef<'toy>) -> bool + 'q) -> Self {
+		self.filter_with_bounds(move |entity, _| filter(entity))
+	}
+
+	/// Same as [`EntityQuery::filter`] but also passes the world space bounds of each entity.
+	pub fn filter_with_bounds(mut self, filter: impl Fn(EntityRef<'toy>, &Aabb3) -> bool + 'q) -> Self {
+		self.filters.push(Box::new(filter));
+		self
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item=EntityRef<'toy>> + '_ {
+		let index = self.index;
+		let num_entities = index.project.entities.len();
+
+		// Scene entity ids are 1-based
+		let scene_entities = self.scene.as_ref().map(|scene| scene.entities.iter()
+			.filter_map(|&id| (id as usize).checked_sub(1))
+			.filter(move |&entity_index| entity_index < num_entities));
+
+		let candidates: Box<dyn Iterator<Item=usize> + '_> = match (scene_entities, self.region) {
+			(Some(scene_entities), None) => Box::new(scene_entities),
+			(None, None) => Box::new(0..num_entities),
+
+			(scene_entities, Some(region)) => {
+				let mut nearby = index.bounds_tree.query(&region);
+				nearby.sort_unstable();
+
+				match scene_entities {
+					Some(scene_entities) => {
+						let scene_entities: HashSet<usize> = scene_entities.collect();
+						Box::new(nearby.into_iter().filter(move |entity_index| scene_entities.contains(entity_index)))
+					}
+
+					None => Box::new(nearby.into_iter()),
+				}
+			}
+		};
+
+		candidates
+			.filter(move |&entity_index| {
+				let entity = index.entity(entity_index);
+				let bounds = &index.world_bounds[entity_index];
+				self.filters.iter().all(|filter| filter(entity, bounds))
+			})
+			.map(move |entity_index| index.entity(entity_index))
+	}
+
+	pub fn first(&self) -> Option<EntityRef<'toy>> {
+		self.iter().next()
+	}
+
+	pub fn count(&self) -> usize {
+		self.iter().count()
+	}
+
+	pub fn to_vec(&self) -> Vec<EntityRef<'toy>> {
+		self.iter().collect()
+	}
+}
+
+impl<'q, 'toy> IntoIterator for &'q EntityQuery<'_, 'toy> {
+	type Item = EntityRef<'toy>;
+	type IntoIter = Box<dyn Iterator<Item=EntityRef<'toy>> + 'q>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		Box::new(self.iter())
+	}
+}
+
+impl<'q, 'toy> IntoIterator for EntityQuery<'q, 'toy> {
+	type Item = EntityRef<'toy>;
+	type IntoIter = std::vec::IntoIter<EntityRef<'toy>>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.to_vec().into_iter()
+	}
+}
+
+
+
+/// A bounding volume hierarchy over entity world bounds, split at the median entity along the longest axis
+/// until each leaf holds at most [`BoundsTree::MAX_LEAF_SIZE`] entities.
+#[derive(Debug, Clone)]
+struct BoundsTree {
+	nodes: Vec<BoundsNode>,
+
+	/// Entity indices, ordered such that each node covers a contiguous range.
+	entities: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct BoundsNode {
+	bounds: Aabb3,
+	start: usize,
+	end: usize,
+
+	/// Node indices of both halves, or `None` for leaves.
+	children: Option<(usize, usize)>,
+}
+
+impl BoundsTree {
+	const MAX_LEAF_SIZE: usize = 8;
+
+	fn new(world_bounds: &[Aabb3]) -> BoundsTree {
+		let mut tree = BoundsTree {
+			nodes: Vec::new(),
+			entities: (0..world_bounds.len()).collect(),
+		};
+
+		if !world_bounds.is_empty() {
+			tree.build_node(world_bounds, 0, world_bounds.len());
+		}
+
+		tree
+	}
+
+	fn build_node(&mut self, world_bounds: &[Aabb3], start: usize, end: usize) -> usize {
+		let bounds = self.entities[start..end].iter()
+			.fold(Aabb3::new_empty(), |bounds, &entity_index| bounds.union(&world_bounds[entity_index]));
+
+		let node_index = self.nodes.len();
+		self.nodes.push(BoundsNode { bounds, start, end, children: None });
+
+		if end - start > Self::MAX_LEAF_SIZE {
+			let size = bounds.size();
+			let axis = match size.x.max(size.y).max(size.z) {
+				max if max == size.x => 0,
+				max if max == size.y => 1,
+				_ => 2,
+			};
+
+			let center = |entity_index: usize| world_bounds[entity_index].center().to_array()[axis];
+
+			let middle = (start + end) / 2;
+			self.entities[start..end].select_nth_unstable_by(middle - start, |&a, &b| center(a).total_cmp(&center(b)));
+
+			let left = self.build_node(world_bounds, start, middle);
+			let right = self.build_node(world_bounds, middle, end);
+			self.nodes[node_index].children = Some((left, right));
+		}
+
+		node_index
+	}
+
+	/// Indices of entities in leaves intersecting `region`, in no particular order.
+	/// Their own bounds may not intersect `region`.
+	fn query(&self, region: &Aabb3) -> Vec<usize> {
+		let mut results = Vec::new();
+		let mut to_visit = Vec::new();
+
+		if !self.nodes.is_empty() {
+			to_visit.push(0);
+		}
+
+		while let Some(node_index) = to_visit.pop() {
+			let node = &self.nodes[node_index];
+			if !node.bounds.intersects(region) {
+				continue
+			}
+
+			match node.children {
+				Some((left, right)) => to_visit.extend([left, right]),
+				None => results.extend_from_slice(&self.entities[node.start..node.end]),
+			}
+		}
+
+		results
+	}
+}
+
+
+
+fn glob_match(pattern: &[char], name: &str) -> bool {
+	let name: Vec<char> = name.chars().collect();
+
+	// Iterative wildcard matching with backtracking to the most recent '*'
+	let (mut p, mut n) = (0, 0);
+	let mut last_star = None;
+
+	while n < name.len() {
+		match pattern.get(p) {
+			Some('*') => {
+				last_star = Some((p, n));
+				p += 1;
+			}
+
+			Some(&c) if c == '?' || c == name[n] => {
+				p += 1;
+				n += 1;
+			}
+
+			_ => match last_star {
+				// Let the last '*' consume one more character and try again
+				Some((star_p, star_n)) => {
+					last_star = Some((star_p, star_n + 1));
+					p = star_p + 1;
+					n = star_n + 1;
+				}
+
+				None => return false,
+			}
+		}
+	}
+
+	pattern[p..].iter().all(|&c| c == '*')
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_util::assert_vec3_eq;
+
+	fn entity(name: &str, position: Vec3) -> Entity {
+		Entity {
+			name: name.into(),
+			mesh_id: 0,
+			position,
+			rotation: Quat::identity(),
+			scale: Vec3::splat(1.0),
+			kind: EntityKind::Object,
+			collision: None,
+			properties: HashMap::new(),
+		}
+	}
+
+	#[test]
+	fn duplicate_names_find_first_entity() {
+		let project = Project {
+			scenes: Vec::new(),
+			entities: vec![
+				entity("door", Vec3::new(1.0, 0.0, 0.0)),
+				entity("door", Vec3::new(2.0, 0.0, 0.0)),
+			],
+			meshes: Vec::new(),
+			nav_meshes: Vec::new(),
+			nav_grids: Vec::new(),
+		};
+
+		let index = project.build_entity_index();
+		let found = index.find_entity("door").unwrap();
+		assert_vec3_eq(found.position, project.find_entity("door").unwrap().position);
+		assert_vec3_eq(found.position, Vec3::new(1.0, 0.0, 0.0));
+		assert_vec3_eq(index.world_bounds("door").unwrap().min, Vec3::new(1.0, 0.0, 0.0));
+		assert_eq!(index.query().name_glob("do?r").count(), 2);
+	}
+
+	fn names<'toy>(entities: impl IntoIterator<Item=EntityRef<'toy>>) -> Vec<String> {
+		entities.into_iter().map(|entity| entity.name.clone()).collect()
+	}
+
+	/// A row of torches along x with alternating `lit` properties, a crate with a unit quad mesh scaled up by 4,
+	/// and a scene containing every other torch.
+	fn level() -> Project {
+		let mut entities: Vec<Entity> = (0..20)
+			.map(|index| {
+				let mut torch = entity(&format!("torch.{:03}", index), Vec3::new(index as f32 * 2.0, 0.0, 0.0));
+				torch.properties.insert("lit".into(), EntityProperty::Bool(index % 2 == 0));
+				torch
+			})
+			.collect();
+
+		let mut crate_entity = entity("crate", Vec3::new(0.0, 10.0, 0.0));
+		crate_entity.mesh_id = 1;
+		crate_entity.scale = Vec3::splat(4.0);
+		entities.push(crate_entity);
+
+		Project {
+			scenes: vec![Scene {
+				name: "odd".into(),
+				entities: (0..20).filter(|index| index % 2 == 1).map(|index| index + 1).collect(),
+			}],
+			entities,
+			meshes: vec![crate::test_util::quad_mesh()],
+			nav_meshes: Vec::new(),
+			nav_grids: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn glob_edge_cases() {
+		let matches = |pattern: &str, name: &str| glob_match(&pattern.chars().collect::<Vec<_>>(), name);
+
+		assert!(matches("", ""));
+		assert!(!matches("", "a"));
+		assert!(matches("*", ""));
+		assert!(matches("**", "anything"));
+		assert!(matches("a*b*c", "aXbYbZc"));
+		assert!(!matches("a*b*c", "aXbYbZ"));
+		assert!(matches("*.001", "torch.001"));
+		assert!(!matches("?", ""));
+		assert!(matches("??", "é!"));
+		assert!(!matches("torch", "torch.001"));
+		assert!(matches("torch*", "torch"));
+	}
+
+	#[test]
+	fn name_filters() {
+		let project = level();
+		let index = project.build_entity_index();
+
+		assert_eq!(index.query().name_glob("torch.01?").count(), 10);
+		assert_eq!(names(index.query().name_glob("*e")), ["crate"]);
+
+		let regex_query = index.query().name_regex(r"^torch\.00[0-2]$").unwrap();
+		assert_eq!(names(&regex_query), ["torch.000", "torch.001", "torch.002"]);
+
+		// Not anchored
+		assert_eq!(index.query().name_regex("rat").unwrap().count(), 1);
+		assert!(index.query().name_regex("(").is_err());
+	}
+
+	#[test]
+	fn property_and_scene_filters() {
+		let project = level();
+		let index = project.build_entity_index();
+
+		assert_eq!(index.query().has_property("lit").count(), 20);
+		assert_eq!(index.query().property_eq("lit", true).count(), 10);
+		assert_eq!(index.query().property_eq("lit", 1).count(), 0);
+		assert!(index.query().property_eq("lit", false).iter().all(|entity| entity.name.ends_with(['1', '3', '5', '7', '9'])));
+
+		let scene = project.find_scene("odd").unwrap();
+		assert_eq!(index.query().in_scene(scene).count(), 10);
+		assert_eq!(index.query().in_scene(scene).property_eq("lit", true).count(), 0);
+		assert_eq!(names(index.query().in_scene(scene).within_radius(Vec3::new(2.0, 0.0, 0.0), 0.5)), ["torch.001"]);
+	}
+
+	#[test]
+	fn bounds_and_radius_filters() {
+		let project = level();
+		let index = project.build_entity_index();
+
+		// The crate's quad spans x 0..4, z -4..0 at y 10
+		let crate_bounds = index.world_bounds("crate").unwrap();
+		assert_vec3_eq(crate_bounds.min, Vec3::new(0.0, 10.0, -4.0));
+		assert_vec3_eq(crate_bounds.max, Vec3::new(4.0, 10.0, 0.0));
+
+		let nearby = index.query().intersecting_bounds(Aabb3::new(Vec3::new(3.0, -1.0, -1.0), Vec3::new(7.0, 11.0, 1.0)));
+		assert_eq!(names(&nearby), ["torch.002", "torch.003", "crate"]);
+
+		let in_radius = index.query().within_radius(Vec3::new(10.0, 0.0, 0.0), 2.0);
+		assert_eq!(names(&in_radius), ["torch.004", "torch.005", "torch.006"]);
+
+		// Corners of the region box are outside the sphere
+		assert_eq!(index.query().within_radius(Vec3::new(1.0, 1.0, 0.0), 1.0).count(), 0);
+
+		// Multiple spatial filters must all match
+		let both = index.query()
+			.within_radius(Vec3::new(10.0, 0.0, 0.0), 2.0)
+			.intersecting_bounds(Aabb3::new(Vec3::new(11.0, -1.0, -1.0), Vec3::new(20.0, 1.0, 1.0)));
+		assert_eq!(names(&both), ["torch.006"]);
+
+		assert_eq!(index.query().within_radius(Vec3::new(0.0, 100.0, 0.0), 1.0).count(), 0);
+	}
+
+	#[test]
+	fn bounds_tree_matches_linear_scan() {
+		let world_bounds: Vec<Aabb3> = (0..200)
+			.map(|index| {
+				let position = Vec3::new((index % 7) as f32 * 3.0, (index % 11) as f32, (index / 13) as f32 * 2.0);
+				Aabb3::around_point(position, Vec3::splat((index % 3) as f32 * 0.5))
+			})
+			.collect();
+
+		let tree = BoundsTree::new(&world_bounds);
+
+		for region in [
+			Aabb3::new(Vec3::zero(), Vec3::splat(5.0)),
+			Aabb3::new(Vec3::new(6.0, 2.0, 10.0), Vec3::new(9.0, 4.0, 20.0)),
+			Aabb3::around_point(Vec3::splat(-10.0), Vec3::splat(1.0)),
+		] {
+			let mut found: Vec<usize> = tree.query(&region).into_iter()
+				.filter(|&index| world_bounds[index].intersects(&region))
+				.collect();
+			found.sort_unstable();
+
+			let expected: Vec<usize> = (0..world_bounds.len())
+				.filter(|&index| world_bounds[index].intersects(&region))
+				.collect();
+
+			assert_eq!(found, expected);
+		}
+
+		assert!(BoundsTree::new(&[]).query(&Aabb3::new(Vec3::zero(), Vec3::splat(1.0))).is_empty());
+	}
+}
@@ -0,0 +1,54 @@
+use crate::types::*;
+use common::*;
+
+
+impl MeshWeightVertex {
+	/// Transforms `position` by each of this vertices bones, and blends the results by weight.
+	/// Bone indices outside of `bone_matrices` are ignored, and vertices without any weights are returned unchanged.
+	pub fn skin_position(&self, position: Vec3, bone_matrices: &[Mat3x4]) -> Vec3 {
+		let mut total_weight = 0.0;
+		let mut skinned_position = Vec3::zero();
+
+		for (&index, &weight) in self.indices.iter().zip(&self.weights) {
+			if let Some(&bone_matrix) = bone_matrices.get(index as usize) {
+				skinned_position += (bone_matrix * position) * weight;
+				total_weight += weight;
+			}
+		}
+
+		if total_weight <= 0.0 {
+			return position
+		}
+
+		// Weights aren't guaranteed to be normalised by the exporter, and can be truncated
+		skinned_position / total_weight
+	}
+}
+
+
+impl MeshAnimationData {
+	/// Deforms `positions` by `bone_matrices` (see [`MeshAnimationData::pose_matrices`]) using this meshes vertex weights.
+	/// `positions` is expected to be the same length as [`MeshAnimationData::weights`].
+	pub fn skin_positions_into(&self, positions: &[Vec3], bone_matrices: &[Mat3x4], skinned_positions: &mut Vec<Vec3>) {
+		skinned_positions.clear();
+		skinned_positions.extend(positions.iter().zip(&self.weights)
+			.map(|(&position, weight)| weight.skin_position(position, bone_matrices)));
+	}
+}
+
+
+impl Mesh {
+	/// Calculates vertex positions deformed by `bone_matrices`.
+	/// If this mesh has no animation data, the rest positions are returned unchanged.
+	pub fn skinned_positions(&self, bone_matrices: &[Mat3x4]) -> Vec<Vec3> {
+		match &self.animation_data {
+			Some(animation_data) => {
+				let mut skinned_positions = Vec::with_capacity(self.positions.len());
+				animation_data.skin_positions_into(&self.positions, bone_matrices, &mut skinned_positions);
+				skinned_positions
+			}
+
+			None => self.positions.clone(),
+		}
+	}
+}
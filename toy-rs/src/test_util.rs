@@ -0,0 +1,143 @@
+//! Fixtures shared by the tests of each module.
+
+use crate::importer::{Tag, SCENE_VERSION};
+use crate::types::*;
+use common::*;
+
+
+/// Builds toy files in the same way as the blender plugin's serializer.
+#[derive(Default)]
+pub(crate) struct Writer(pub(crate) Vec<u8>);
+
+impl Writer {
+	pub(crate) fn file(build: impl FnOnce(&mut Writer)) -> Vec<u8> {
+		let mut writer = Writer::default();
+		writer.0.extend_from_slice(b"TOY");
+		writer.u8(SCENE_VERSION);
+		build(&mut writer);
+		writer.0
+	}
+
+	pub(crate) fn u8(&mut self, v: u8) -> &mut Self { self.0.push(v); self }
+	pub(crate) fn u16(&mut self, v: u16) -> &mut Self { self.0.extend_from_slice(&v.to_le_bytes()); self }
+	pub(crate) fn u32(&mut self, v: u32) -> &mut Self { self.0.extend_from_slice(&v.to_le_bytes()); self }
+	pub(crate) fn f32(&mut self, v: f32) -> &mut Self { self.0.extend_from_slice(&v.to_le_bytes()); self }
+	pub(crate) fn vec3(&mut self, v: Vec3) -> &mut Self { self.f32(v.x).f32(v.y).f32(v.z) }
+	pub(crate) fn quat(&mut self, q: Quat) -> &mut Self { self.f32(q.x).f32(q.y).f32(q.z).f32(q.w) }
+
+	pub(crate) fn string(&mut self, s: &str) -> &mut Self {
+		self.u8(s.len() as u8);
+		self.0.extend_from_slice(s.as_bytes());
+		self
+	}
+
+	pub(crate) fn section(&mut self, tag: &Tag, build: impl FnOnce(&mut Writer)) -> &mut Self {
+		let mut section = Writer::default();
+		build(&mut section);
+
+		self.0.extend_from_slice(tag);
+		self.u32(section.0.len() as u32);
+		self.0.extend_from_slice(&section.0);
+		self
+	}
+
+	pub(crate) fn entity(&mut self, position: Vec3, rotation: Quat, scale: Vec3, build: impl FnOnce(&mut Writer)) -> &mut Self {
+		self.section(b"ENTY", |w| {
+			w.string("Entity").vec3(position).quat(rotation).vec3(scale).u16(0);
+			w.u8(0); // no properties
+			build(w);
+		})
+	}
+}
+
+pub(crate) fn assert_vec3_eq(a: Vec3, b: Vec3) {
+	assert!((a - b).length() < 1.0e-5, "{:?} != {:?}", a, b);
+}
+
+/// A file exercising every section type, including a skinned mesh with every kind of vertex layer.
+/// Also used as a seed for the importer's corruption tests.
+pub(crate) fn sample_file() -> Vec<u8> {
+	Writer::file(|w| {
+		w.section(b"MESH", |w| {
+			w.u32(3).vec3(Vec3::zero()).vec3(Vec3::from_x(1.0)).vec3(Vec3::from_z(1.0));
+			w.u32(1).u8(0).u8(1).u8(2);
+
+			w.u8(1);
+			w.0.extend_from_slice(b"MDTA");
+			w.string("Col").u32(3);
+			for _ in 0..3 { w.f32(1.0).f32(0.5).f32(0.25).f32(1.0); }
+
+			w.u8(1);
+			w.0.extend_from_slice(b"MDUV");
+			w.string("UV").u32(3);
+			for _ in 0..3 { w.u16(0).u16(65535); }
+
+			w.section(b"SKIN", |w| {
+				w.u8(1).string("Bone").vec3(Vec3::zero()).vec3(Vec3::from_y(1.0));
+				w.u32(3).u8(0b01_01_01_00);
+				for _ in 0..3 { w.u8(0).u16(65535); }
+
+				w.section(b"ANMS", |w| {
+					w.section(b"ANIM", |w| {
+						w.string("Wave").f32(30.0).u16(2).u8(1).string("Bone");
+						for _ in 0..2 { w.vec3(Vec3::zero()).quat(Quat::identity()).vec3(Vec3::splat(1.0)); }
+					});
+				});
+			});
+
+			w.section(b"MTLS", |w| {
+				w.u16(1).string("Stone").f32(0.5).f32(0.5).f32(0.5).f32(1.0).u8(0b101).string("stone.png");
+				w.u8(0);
+			});
+
+			w.section(b"MNRM", |w| {
+				w.u32(3);
+				for _ in 0..3 { w.vec3(Vec3::from_y(1.0)); }
+			});
+
+			w.section(b"MTAN", |w| {
+				w.u32(3);
+				for _ in 0..3 { w.f32(1.0).f32(0.0).f32(0.0).f32(1.0); }
+			});
+		});
+
+		w.section(b"ENTY", |w| {
+			w.string("Thing").vec3(Vec3::zero()).quat(Quat::identity()).vec3(Vec3::splat(1.0)).u16(1);
+			w.u8(1).string("health").u8(b'I').u32(10);
+			w.section(b"COLL", |w| { w.u8(b'S').vec3(Vec3::zero()).f32(1.0); });
+		});
+
+		w.entity(Vec3::zero(), Quat::identity(), Vec3::splat(1.0), |w| {
+			w.section(b"CAMR", |w| { w.u8(b'P').f32(1.0).f32(1.5).f32(0.1).f32(100.0); });
+		});
+
+		w.section(b"SCNE", |w| { w.string("Main").u32(2).u32(1).u32(2); });
+
+		w.section(b"NAVM", |w| {
+			w.string("Nav").u32(3).vec3(Vec3::zero()).vec3(Vec3::from_x(1.0)).vec3(Vec3::from_z(1.0));
+			w.u32(1).u8(3).u32(0).u32(1).u32(2);
+		});
+
+		w.section(b"NAVG", |w| {
+			w.string("Grid").vec3(Vec3::zero()).f32(1.0).u32(1).u32(2).f32(0.0).f32(f32::NAN);
+		});
+	})
+}
+
+/// A unit quad on the XZ plane facing +Y, with uvs increasing along +X and -Z like a blender plane.
+pub(crate) fn quad_mesh() -> Mesh {
+	Mesh {
+		positions: vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -1.0)],
+		indices: vec![0, 1, 2, 0, 2, 3],
+		normals: Vec::new(),
+		tangents: Vec::new(),
+		color_layers: Vec::new(),
+		uv_layers: vec![MeshUvLayer {
+			name: "UVMap".into(),
+			data: vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0)],
+		}],
+		materials: Vec::new(),
+		material_indices: Vec::new(),
+		animation_data: None,
+	}
+}
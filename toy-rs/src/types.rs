@@ -1,11 +1,19 @@
+use crate::collision::CollisionShape;
+use crate::navigation::{NavMesh, NavGrid};
+use crate::material::MeshMaterial;
+use crate::ToyResult;
 use common::*;
+use failure::{ensure, bail};
 use std::ops::Deref;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct Project {
 	pub scenes: Vec<Scene>,
 	pub entities: Vec<Entity>,
 	pub meshes: Vec<Mesh>,
+	pub nav_meshes: Vec<NavMesh>,
+	pub nav_grids: Vec<NavGrid>,
 }
 
 #[derive(Debug, Clone)]
@@ -22,14 +30,126 @@ pub struct Entity {
 	pub position: Vec3,
 	pub rotation: Quat,
 	pub scale: Vec3,
+
+	/// The kind of object this entity was exported from, along with any kind specific data.
+	pub kind: EntityKind,
+
+	pub collision: Option<CollisionShape>,
+
+	/// Custom properties set on the object in blender.
+	pub properties: HashMap<String, EntityProperty>,
+}
+
+#[derive(Debug, Clone)]
+pub enum EntityKind {
+	/// A mesh, or any other object without extra data.
+	Object,
+	Camera(Camera),
+	Light(Light),
+
+	/// An empty, typically used to mark points of interest in a scene.
+	Marker(Marker),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+	pub projection: CameraProjection,
+
+	/// Width over height of the render resolution the camera was set up for.
+	pub aspect: f32,
+	pub near: f32,
+
+	/// May be infinite for perspective cameras imported from other formats.
+	pub far: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraProjection {
+	/// `fov_y` is the vertical field of view in radians.
+	Perspective { fov_y: f32 },
+
+	/// `height` is the height of the view volume in world units.
+	Orthographic { height: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+	pub kind: LightKind,
+
+	/// Linear color.
+	pub color: Vec3,
+
+	/// Power in watts, or irradiance in watts per square meter for sun lights.
+	pub energy: f32,
+
+	/// Radius of the light source, used for soft shadows.
+	pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LightKind {
+	Point,
+
+	/// `angle` is the full cone angle in radians, and `blend` is the fraction of the cone that is softened.
+	Spot { angle: f32, blend: f32 },
+	Sun,
+
+	/// `size` is the width and height of the light in its local space.
+	Area { size: Vec2 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Marker {
+	pub display: MarkerDisplay,
+	pub size: f32,
+}
+
+/// The display type of an empty in blender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerDisplay {
+	PlainAxes,
+	Arrows,
+	SingleArrow,
+	Circle,
+	Cube,
+	Sphere,
+	Cone,
+	Image,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityProperty {
+	Bool(bool),
+	Int(i32),
+	Float(f32),
+	String(String),
 }
 
 #[derive(Debug, Clone)]
 pub struct Mesh {
 	pub positions: Vec<Vec3>,
-	pub indices: Vec<u16>,
+
+	/// Triangle list indices into [`Mesh::positions`]. See [`Mesh::indices_u16`] for uploading to 16b index buffers.
+	pub indices: Vec<u32>,
+
+	/// Per-vertex normals, including blender's custom split normals and sharp edges. Empty if the mesh was exported
+	/// without normals - see [`Mesh::compute_normals`].
+	pub normals: Vec<Vec3>,
+
+	/// Per-vertex MikkTSpace tangents, with the sign of the bitangent in `w`.
+	/// Empty if the mesh was exported without tangents or has no uv layers - see [`Mesh::compute_tangents`].
+	pub tangents: Vec<Vec4>,
+
 	pub color_layers: Vec<MeshColorLayer>,
 	pub uv_layers: Vec<MeshUvLayer>,
+
+	/// Material slots, in the order they appear on the object in blender. Empty if the mesh has no materials.
+	pub materials: Vec<MeshMaterial>,
+
+	/// An index into [`Mesh::materials`] for each triangle. Empty if the mesh has no materials.
+	/// See [`Mesh::sub_meshes`] for drawing each material separately.
+	pub material_indices: Vec<u16>,
+
 	pub animation_data: Option<MeshAnimationData>,
 }
 
@@ -76,7 +196,8 @@ pub struct MeshAnimation {
 
 #[derive(Debug, Clone)]
 pub struct MeshAnimationChannel {
-	pub bone: String, // TODO: should be an index
+	/// Index into [`MeshAnimationData::bones`].
+	pub bone: usize,
 	pub frames: Vec<MeshAnimationFrame>,
 }
 
@@ -128,6 +249,21 @@ impl Project {
 		self.entities()
 			.filter(move |entity| entity.name.starts_with(prefix))
 	}
+
+	pub fn cameras(&self) -> impl Iterator<Item=EntityRef<'_>> {
+		self.entities()
+			.filter(|entity| entity.camera().is_some())
+	}
+
+	pub fn lights(&self) -> impl Iterator<Item=EntityRef<'_>> {
+		self.entities()
+			.filter(|entity| entity.light().is_some())
+	}
+
+	pub fn markers(&self) -> impl Iterator<Item=EntityRef<'_>> {
+		self.entities()
+			.filter(|entity| entity.marker().is_some())
+	}
 }
 
 impl Mesh {
@@ -140,6 +276,62 @@ impl Mesh {
 		self.uv_layers.iter()
 			.find(|l| l.name == name)
 	}
+
+	pub fn num_triangles(&self) -> usize {
+		self.indices.len() / 3
+	}
+
+	pub fn triangles(&self) -> impl Iterator<Item=[u32; 3]> + '_ {
+		self.indices.chunks_exact(3)
+			.map(|triangle| [triangle[0], triangle[1], triangle[2]])
+	}
+
+	/// Whether all vertices can be addressed by 16b indices.
+	pub fn fits_u16_indices(&self) -> bool {
+		self.positions.len() <= u16::MAX as usize + 1
+	}
+
+	/// Converts indices to 16b if all vertices can be addressed by them, otherwise returns `None`.
+	pub fn indices_u16(&self) -> Option<Vec<u16>> {
+		if !self.fits_u16_indices() {
+			return None
+		}
+
+		Some(self.indices.iter().map(|&index| index as u16).collect())
+	}
+
+	/// Checks that every per-vertex layer has a vertex for each of [`Mesh::positions`], or is empty, and that every
+	/// index refers to a vertex. [`load`](crate::load) rejects files that fail this, but meshes built or modified
+	/// in code may not.
+	pub fn check_vertex_data(&self) -> ToyResult<()> {
+		let num_vertices = self.positions.len();
+
+		let check_layer = |layer: &str, len: usize| -> ToyResult<()> {
+			ensure!(len == 0 || len == num_vertices, "{} has {} vertices, but the mesh has {}", layer, len, num_vertices);
+			Ok(())
+		};
+
+		check_layer("Normal layer", self.normals.len())?;
+		check_layer("Tangent layer", self.tangents.len())?;
+
+		for layer in self.color_layers.iter() {
+			check_layer(&format!("Color layer '{}'", layer.name), layer.data.len())?;
+		}
+
+		for layer in self.uv_layers.iter() {
+			check_layer(&format!("UV layer '{}'", layer.name), layer.data.len())?;
+		}
+
+		if let Some(animation_data) = &self.animation_data {
+			check_layer("Skin weights", animation_data.weights.len())?;
+		}
+
+		if let Some(&index) = self.indices.iter().find(|&&index| index as usize >= num_vertices) {
+			bail!("Index {} out of range for mesh with {} vertices", index, num_vertices);
+		}
+
+		Ok(())
+	}
 }
 
 impl<'t> SceneRef<'t> {
@@ -147,11 +339,13 @@ impl<'t> SceneRef<'t> {
 		SceneRef { file, scene }
 	}
 
+	/// Entity ids that don't refer to an entity in the project are skipped. [`load`](crate::load) rejects files
+	/// containing these, but they can still occur in projects built or modified in code.
 	pub fn entities(&self) -> impl Iterator<Item=EntityRef<'t>> {
 		let file = self.file;
 
 		self.scene.entities.iter()
-			.map(move |&id| &file.entities[id as usize - 1])
+			.filter_map(move |&id| file.entities.get((id as usize).checked_sub(1)?))
 			.map(move |entity| EntityRef::from(file, entity))
 	}
 
@@ -163,6 +357,21 @@ impl<'t> SceneRef<'t> {
 	pub fn find_entity(&self, name: &str) -> Option<EntityRef<'t>> {
 		self.entities().find(|ent| ent.entity.name == name)
 	}
+
+	pub fn cameras(&self) -> impl Iterator<Item=EntityRef<'t>> {
+		self.entities()
+			.filter(|entity| entity.camera().is_some())
+	}
+
+	pub fn lights(&self) -> impl Iterator<Item=EntityRef<'t>> {
+		self.entities()
+			.filter(|entity| entity.light().is_some())
+	}
+
+	pub fn markers(&self) -> impl Iterator<Item=EntityRef<'t>> {
+		self.entities()
+			.filter(|entity| entity.marker().is_some())
+	}
 }
 
 impl Deref for SceneRef<'_> {
@@ -192,12 +401,142 @@ impl Entity {
 			* self.rotation.to_mat3x4()
 			* Mat3x4::scale(self.scale)
 	}
+
+	pub fn property(&self, name: &str) -> Option<&EntityProperty> {
+		self.properties.get(name)
+	}
+
+	pub fn camera(&self) -> Option<&Camera> {
+		match &self.kind {
+			EntityKind::Camera(camera) => Some(camera),
+			_ => None,
+		}
+	}
+
+	pub fn light(&self) -> Option<&Light> {
+		match &self.kind {
+			EntityKind::Light(light) => Some(light),
+			_ => None,
+		}
+	}
+
+	pub fn marker(&self) -> Option<&Marker> {
+		match &self.kind {
+			EntityKind::Marker(marker) => Some(marker),
+			_ => None,
+		}
+	}
+
+	/// The direction cameras and lights point in.
+	/// Blender points them down their local -Z axis, which becomes -Y after conversion to toy's coordinate system.
+	pub fn forward(&self) -> Vec3 {
+		self.rotation * Vec3::from_y(-1.0)
+	}
+
+	/// The world to view space transform of a camera entity, where the view looks down -Z with +Y up.
+	/// Ignores scale.
+	pub fn view_matrix(&self) -> Mat4 {
+		let orientation = self.rotation * Quat::from_pitch(-PI / 2.0);
+
+		orientation.conjugate().to_mat4()
+			* Mat4::translate(-self.position)
+	}
+
+	/// Combined projection and view transform for a camera entity, or `None` if this isn't a camera.
+	/// `aspect` overrides the aspect ratio the camera was set up with.
+	pub fn projection_view(&self, aspect: impl Into<Option<f32>>) -> Option<Mat4> {
+		let camera = self.camera()?;
+		let aspect = aspect.into().unwrap_or(camera.aspect);
+
+		Some(camera.projection_matrix(aspect) * self.view_matrix())
+	}
+}
+
+impl Camera {
+	/// An OpenGL style projection matrix, mapping view space depth into [-1, 1].
+	pub fn projection_matrix(&self, aspect: f32) -> Mat4 {
+		let Camera { near, far, .. } = *self;
+
+		match self.projection {
+			CameraProjection::Perspective { fov_y } => {
+				let top = (fov_y / 2.0).tan() * near;
+				let right = top * aspect;
+
+				if far.is_finite() {
+					return Mat4::frustum(-right, right, -top, top, near, far)
+				}
+
+				Mat4::new(&[
+					near / right, 0.0, 0.0, 0.0,
+					0.0, near / top, 0.0, 0.0,
+					0.0, 0.0,-1.0,-2.0 * near,
+					0.0, 0.0,-1.0, 0.0,
+				])
+			}
+
+			CameraProjection::Orthographic { height } => {
+				let top = height / 2.0;
+				let right = top * aspect;
+				Mat4::ortho(-right, right, -top, top, near, far)
+			}
+		}
+	}
+}
+
+impl EntityProperty {
+	pub fn as_bool(&self) -> Option<bool> {
+		match *self {
+			EntityProperty::Bool(value) => Some(value),
+			EntityProperty::Int(value) => Some(value != 0),
+			_ => None,
+		}
+	}
+
+	pub fn as_int(&self) -> Option<i32> {
+		match *self {
+			EntityProperty::Int(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	/// Ints are converted to floats since blender doesn't distinguish between them in the ui.
+	pub fn as_float(&self) -> Option<f32> {
+		match *self {
+			EntityProperty::Float(value) => Some(value),
+			EntityProperty::Int(value) => Some(value as f32),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			EntityProperty::String(value) => Some(value),
+			_ => None,
+		}
+	}
+}
+
+impl From<bool> for EntityProperty {
+	fn from(o: bool) -> EntityProperty { EntityProperty::Bool(o) }
+}
+
+impl From<i32> for EntityProperty {
+	fn from(o: i32) -> EntityProperty { EntityProperty::Int(o) }
+}
+
+impl From<f32> for EntityProperty {
+	fn from(o: f32) -> EntityProperty { EntityProperty::Float(o) }
+}
+
+impl From<&str> for EntityProperty {
+	fn from(o: &str) -> EntityProperty { EntityProperty::String(o.into()) }
+}
+
+impl From<String> for EntityProperty {
+	fn from(o: String) -> EntityProperty { EntityProperty::String(o) }
 }
 
 impl Deref for EntityRef<'_> {
 	type Target = Entity;
 	fn deref(&self) -> &Self::Target { self.entity }
 }
-
-// TODO: entity queries
-// TODO: mesh building
\ No newline at end of file
@@ -0,0 +1,250 @@
+//! Checks for data that loads fine but is likely to be a mistake in the source scene.
+
+use crate::types::*;
+use crate::optimize::is_degenerate_triangle;
+use std::fmt;
+
+/// How far the weights of a vertex can sum from 1.0 before being reported by [`validate`].
+pub const WEIGHT_SUM_TOLERANCE: f32 = 0.01;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationWarning {
+	/// Triangles with repeated indices or zero area.
+	DegenerateTriangles { mesh: usize, count: usize },
+
+	/// A mesh that isn't referenced by any entity.
+	UnusedMesh { mesh: usize },
+
+	NonFiniteVertices { mesh: usize, count: usize },
+
+	/// An entity whose position, rotation or scale contains NaN or infinite values.
+	NonFiniteTransform { entity: String },
+
+	/// Skinned vertices whose weights don't sum to 1.
+	UnnormalizedWeights { mesh: usize, count: usize },
+
+	/// An animation channel with a different number of frames to the first channel of the same animation.
+	MismatchedAnimationChannels { mesh: usize, animation: String },
+
+	/// An entity that isn't part of any scene.
+	OrphanedEntity { entity: String },
+}
+
+
+/// Checks `project` for common problems. Meshes are identified by their index in [`Project::meshes`].
+pub fn validate(project: &Project) -> Vec<ValidationWarning> {
+	let mut warnings = Vec::new();
+	let mut mesh_used = vec![false; project.meshes.len()];
+	let mut entity_in_scene = vec![false; project.entities.len()];
+
+	for scene in project.scenes.iter() {
+		for &entity_id in scene.entities.iter() {
+			if let Some(in_scene) = entity_in_scene.get_mut((entity_id as usize).wrapping_sub(1)) {
+				*in_scene = true;
+			}
+		}
+	}
+
+	for (entity, &in_scene) in project.entities.iter().zip(&entity_in_scene) {
+		if let Some(used) = mesh_used.get_mut((entity.mesh_id as usize).wrapping_sub(1)) {
+			*used = true;
+		}
+
+		let Entity { position, rotation, scale, .. } = *entity;
+		let transform_finite = [position.x, position.y, position.z, scale.x, scale.y, scale.z,
+			rotation.x, rotation.y, rotation.z, rotation.w];
+
+		if !transform_finite.iter().all(|value| value.is_finite()) {
+			warnings.push(ValidationWarning::NonFiniteTransform { entity: entity.name.clone() });
+		}
+
+		if !in_scene {
+			warnings.push(ValidationWarning::OrphanedEntity { entity: entity.name.clone() });
+		}
+	}
+
+	for (mesh_index, mesh) in project.meshes.iter().enumerate() {
+		if !mesh_used[mesh_index] {
+			warnings.push(ValidationWarning::UnusedMesh { mesh: mesh_index });
+		}
+
+		validate_mesh(mesh_index, mesh, &mut warnings);
+	}
+
+	warnings
+}
+
+
+fn validate_mesh(mesh_index: usize, mesh: &Mesh, warnings: &mut Vec<ValidationWarning>) {
+	let non_finite_vertices = mesh.positions.iter()
+		.filter(|position| !(position.x.is_finite() && position.y.is_finite() && position.z.is_finite()))
+		.count();
+
+	if non_finite_vertices > 0 {
+		warnings.push(ValidationWarning::NonFiniteVertices { mesh: mesh_index, count: non_finite_vertices });
+	}
+
+	let degenerate_triangles = mesh.triangles()
+		.filter(|&triangle| is_degenerate_triangle(mesh, triangle))
+		.count();
+
+	if degenerate_triangles > 0 {
+		warnings.push(ValidationWarning::DegenerateTriangles { mesh: mesh_index, count: degenerate_triangles });
+	}
+
+	let Some(animation_data) = &mesh.animation_data else {
+		return
+	};
+
+	let unnormalized_weights = animation_data.weights.iter()
+		.filter(|vertex| (vertex.weights.iter().sum::<f32>() - 1.0).abs() > WEIGHT_SUM_TOLERANCE)
+		.count();
+
+	if unnormalized_weights > 0 {
+		warnings.push(ValidationWarning::UnnormalizedWeights { mesh: mesh_index, count: unnormalized_weights });
+	}
+
+	for animation in animation_data.animations.iter() {
+		let num_frames = animation.num_frames();
+		if animation.channels.iter().any(|channel| channel.frames.len() != num_frames) {
+			warnings.push(ValidationWarning::MismatchedAnimationChannels {
+				mesh: mesh_index,
+				animation: animation.name.clone(),
+			});
+		}
+	}
+}
+
+
+impl fmt::Display for ValidationWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ValidationWarning::DegenerateTriangles { mesh, count } => write!(f, "Mesh {} has {} degenerate triangles", mesh, count),
+			ValidationWarning::UnusedMesh { mesh } => write!(f, "Mesh {} isn't used by any entity", mesh),
+			ValidationWarning::NonFiniteVertices { mesh, count } => write!(f, "Mesh {} has {} vertices with NaN or infinite positions", mesh, count),
+			ValidationWarning::NonFiniteTransform { entity } => write!(f, "Entity '{}' has a NaN or infinite transform", entity),
+			ValidationWarning::UnnormalizedWeights { mesh, count } => write!(f, "Mesh {} has {} vertices with weights that don't sum to 1", mesh, count),
+			ValidationWarning::MismatchedAnimationChannels { mesh, animation } => write!(f, "Animation '{}' in mesh {} has channels of different lengths", animation, mesh),
+			ValidationWarning::OrphanedEntity { entity } => write!(f, "Entity '{}' isn't in any scene", entity),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_util::quad_mesh;
+	use common::*;
+	use std::collections::HashMap;
+
+	/// A single entity using a unit quad mesh, in a single scene. Should produce no warnings.
+	fn clean_project() -> Project {
+		Project {
+			scenes: vec![Scene { name: "Main".into(), entities: vec![1] }],
+			entities: vec![Entity {
+				name: "Floor".into(),
+				mesh_id: 1,
+				position: Vec3::zero(),
+				rotation: Quat::identity(),
+				scale: Vec3::splat(1.0),
+				kind: EntityKind::Object,
+				collision: None,
+				properties: HashMap::new(),
+			}],
+			meshes: vec![quad_mesh()],
+			nav_meshes: Vec::new(),
+			nav_grids: Vec::new(),
+		}
+	}
+
+	fn frame() -> MeshAnimationFrame {
+		MeshAnimationFrame { position: Vec3::zero(), rotation: Quat::identity(), scale: Vec3::splat(1.0) }
+	}
+
+	/// Skins every vertex of the quad to a single bone with `weight`, with one animation of two channels.
+	fn skinned_quad(weight: f32, frames_a: usize, frames_b: usize) -> Mesh {
+		let mut mesh = quad_mesh();
+		let bone = |name: &str| MeshBone { name: name.into(), head: Vec3::zero(), tail: Vec3::from_y(1.0) };
+
+		mesh.animation_data = Some(MeshAnimationData {
+			bones: vec![bone("Root"), bone("Tip")],
+			weights: vec![MeshWeightVertex { indices: [0; 3], weights: [weight, 0.0, 0.0] }; mesh.positions.len()],
+			animations: vec![MeshAnimation {
+				name: "Wave".into(),
+				fps: 30.0,
+				channels: vec![
+					MeshAnimationChannel { bone: 0, frames: vec![frame(); frames_a] },
+					MeshAnimationChannel { bone: 1, frames: vec![frame(); frames_b] },
+				],
+			}],
+		});
+
+		mesh
+	}
+
+	#[test]
+	fn clean_project_has_no_warnings() {
+		assert_eq!(validate(&clean_project()), []);
+
+		let mut project = clean_project();
+		project.meshes[0] = skinned_quad(1.0 + WEIGHT_SUM_TOLERANCE / 2.0, 2, 2);
+		assert_eq!(validate(&project), []);
+	}
+
+	#[test]
+	fn unused_mesh_and_orphaned_entity() {
+		let mut project = clean_project();
+		project.meshes.push(quad_mesh());
+		project.scenes[0].entities.clear();
+
+		assert_eq!(validate(&project), [
+			ValidationWarning::OrphanedEntity { entity: "Floor".into() },
+			ValidationWarning::UnusedMesh { mesh: 1 },
+		]);
+	}
+
+	#[test]
+	fn non_finite_transform_and_vertices() {
+		let mut project = clean_project();
+		project.entities[0].rotation.w = f32::NAN;
+		project.meshes[0].positions[1].y = f32::INFINITY;
+		project.meshes[0].positions[2].x = f32::NAN;
+
+		let warnings = validate(&project);
+		assert!(warnings.contains(&ValidationWarning::NonFiniteTransform { entity: "Floor".into() }));
+		assert!(warnings.contains(&ValidationWarning::NonFiniteVertices { mesh: 0, count: 2 }));
+	}
+
+	#[test]
+	fn degenerate_triangles() {
+		let mut project = clean_project();
+		let mesh = &mut project.meshes[0];
+
+		// One triangle with a repeated index, and one with zero area from a vertex in line with 0 and 1
+		mesh.positions.push(Vec3::new(2.0, 0.0, 0.0));
+		mesh.uv_layers[0].data.push(Vec2::new(2.0, 0.0));
+		mesh.indices.extend_from_slice(&[0, 0, 1, 0, 1, 4]);
+
+		assert_eq!(validate(&project), [ValidationWarning::DegenerateTriangles { mesh: 0, count: 2 }]);
+	}
+
+	#[test]
+	fn unnormalized_weights() {
+		let mut project = clean_project();
+		project.meshes[0] = skinned_quad(0.5, 2, 2);
+
+		assert_eq!(validate(&project), [ValidationWarning::UnnormalizedWeights { mesh: 0, count: 4 }]);
+	}
+
+	#[test]
+	fn mismatched_animation_channels() {
+		let mut project = clean_project();
+		project.meshes[0] = skinned_quad(1.0, 2, 3);
+
+		assert_eq!(validate(&project), [
+			ValidationWarning::MismatchedAnimationChannels { mesh: 0, animation: "Wave".into() },
+		]);
+	}
+}
@@ -0,0 +1,463 @@
+//! Borrowing access to toy files, for loading parts of large files without decoding or copying the rest.
+//!
+//! [`ToyView::new`] only reads section headers. Meshes are parsed when requested through [`ToyView::mesh`], and
+//! even then vertex data stays in the backing buffer until it is iterated or decoded. The backing buffer can be
+//! anything that derefs to `&[u8]`, including a memory mapped file.
+
+use crate::types::*;
+use crate::navigation::{NavMesh, NavGrid};
+use crate::material::MeshMaterial;
+use crate::importer::{ToyReader, tag_to_string};
+use crate::ToyResult;
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use common::*;
+use failure::{ensure, bail, format_err};
+
+
+/// A toy file that has only been split into sections.
+#[derive(Debug, Clone)]
+pub struct ToyView<'data> {
+	scenes: Vec<ToyReader<'data>>,
+	entities: Vec<ToyReader<'data>>,
+	meshes: Vec<ToyReader<'data>>,
+	nav_meshes: Vec<ToyReader<'data>>,
+	nav_grids: Vec<ToyReader<'data>>,
+}
+
+/// The layout of a single mesh within a toy file. Vertex and index data are views into the file, which can be
+/// uploaded as is or decoded on demand.
+///
+/// Unlike [`crate::load`], indices aren't checked against the number of vertices until [`MeshView::to_mesh`].
+#[derive(Debug, Clone)]
+pub struct MeshView<'data> {
+	pub positions: ArrayView<'data, F32x3>,
+	pub indices: IndexView<'data>,
+
+	/// Empty if the mesh was exported without normals.
+	pub normals: ArrayView<'data, F32x3>,
+
+	/// Empty if the mesh was exported without tangents.
+	pub tangents: ArrayView<'data, F32x4>,
+
+	pub color_layers: Vec<ColorLayerView<'data>>,
+	pub uv_layers: Vec<UvLayerView<'data>>,
+
+	pub(crate) skin_section: Option<ToyReader<'data>>,
+	pub(crate) materials_section: Option<ToyReader<'data>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ColorLayerView<'data> {
+	pub name: &'data str,
+	pub data: ArrayView<'data, F32x4>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UvLayerView<'data> {
+	pub name: &'data str,
+	pub data: ArrayView<'data, Unorm16x2>,
+}
+
+
+/// How a value is stored in a toy file. Every encoding is little endian and tightly packed.
+pub trait Encoding: 'static {
+	type Value;
+
+	/// Size of a single encoded value in bytes.
+	const SIZE: usize;
+
+	/// Decodes a value from exactly [`Encoding::SIZE`] bytes.
+	fn decode(bytes: &[u8]) -> Self::Value;
+}
+
+/// Three f32s, decoded as a [`Vec3`].
+#[derive(Debug, Clone, Copy)]
+pub struct F32x3;
+
+/// Four f32s, decoded as a [`Vec4`].
+#[derive(Debug, Clone, Copy)]
+pub struct F32x4;
+
+/// Two u16s mapping 0..65535 to 0.0..1.0, decoded as a [`Vec2`]. Used for uvs.
+#[derive(Debug, Clone, Copy)]
+pub struct Unorm16x2;
+
+
+/// A packed array of encoded values, borrowed from a toy file.
+#[derive(Debug, Clone, Copy)]
+pub struct ArrayView<'data, E> {
+	bytes: &'data [u8],
+	encoding: PhantomData<E>,
+}
+
+/// Triangle list indices, stored as u8, u16 or u32 depending on how many vertices the mesh has.
+#[derive(Debug, Clone, Copy)]
+pub struct IndexView<'data> {
+	bytes: &'data [u8],
+	index_size: usize,
+}
+
+
+
+impl<'data> ToyView<'data> {
+	/// Checks the header of `data` and finds its sections. Nothing else is decoded.
+	pub fn new(data: &'data [u8]) -> ToyResult<ToyView<'data>> {
+		ToyView::from_reader(ToyReader::new(data, None))
+	}
+
+	pub(crate) fn from_reader(mut reader: ToyReader<'data>) -> ToyResult<ToyView<'data>> {
+		reader.read_magic()?;
+
+		let mut view = ToyView {
+			scenes: Vec::new(),
+			entities: Vec::new(),
+			meshes: Vec::new(),
+			nav_meshes: Vec::new(),
+			nav_grids: Vec::new(),
+		};
+
+		while !reader.is_empty() {
+			let (tag, section) = reader.read_section()?;
+
+			match &tag {
+				b"SCNE" => view.scenes.push(section),
+				b"MESH" => view.meshes.push(section),
+				b"ENTY" => view.entities.push(section),
+				b"NAVM" => view.nav_meshes.push(section),
+				b"NAVG" => view.nav_grids.push(section),
+				_ => { bail!("Unexpected tag '{}' encountered", tag_to_string(&tag)); }
+			}
+		}
+
+		Ok(view)
+	}
+
+	/// Decodes everything, equivalent to [`crate::load`].
+	pub fn to_project(&self) -> ToyResult<Project> {
+		let scenes = self.scenes()?;
+		let entities = self.entities()?;
+		let meshes = read_sections(&self.meshes, b"MESH", |section| section.read_mesh_view()?.to_mesh())?;
+		let nav_meshes = self.nav_meshes()?;
+		let nav_grids = self.nav_grids()?;
+
+		// References are checked once everything is read, since sections can appear in any order
+		for entity in entities.iter() {
+			ensure!(entity.mesh_id as usize <= meshes.len(), "Entity '{}' refers to missing mesh {}", entity.name, entity.mesh_id);
+		}
+
+		for scene in scenes.iter() {
+			for &entity_id in scene.entities.iter() {
+				ensure!(entity_id != 0 && entity_id as usize <= entities.len(),
+					"Scene '{}' refers to missing entity {}", scene.name, entity_id);
+			}
+		}
+
+		Ok(Project {
+			scenes,
+			entities,
+			meshes,
+			nav_meshes,
+			nav_grids,
+		})
+	}
+
+	pub fn num_meshes(&self) -> usize {
+		self.meshes.len()
+	}
+
+	/// Parses the layout of mesh `index`, i.e. the mesh referred to by an entity with `mesh_id == index + 1`.
+	pub fn mesh(&self, index: usize) -> ToyResult<MeshView<'data>> {
+		let mut section = *self.meshes.get(index)
+			.ok_or_else(|| format_err!("Mesh {} out of range for file with {} meshes", index, self.meshes.len()))?;
+
+		section.read_mesh_view()
+			.map_err(|e| format_err!("While parsing 'MESH' section: {}", e))
+	}
+
+	pub fn meshes(&self) -> impl Iterator<Item=ToyResult<MeshView<'data>>> + '_ {
+		(0..self.meshes.len()).map(move |index| self.mesh(index))
+	}
+
+	/// Decodes every scene. Scenes only store entity ids, so these are cheap.
+	pub fn scenes(&self) -> ToyResult<Vec<Scene>> {
+		read_sections(&self.scenes, b"SCNE", ToyReader::read_scene)
+	}
+
+	/// Decodes every entity, without any of the meshes they refer to.
+	pub fn entities(&self) -> ToyResult<Vec<Entity>> {
+		read_sections(&self.entities, b"ENTY", ToyReader::read_entity)
+	}
+
+	pub fn nav_meshes(&self) -> ToyResult<Vec<NavMesh>> {
+		read_sections(&self.nav_meshes, b"NAVM", ToyReader::read_nav_mesh)
+	}
+
+	pub fn nav_grids(&self) -> ToyResult<Vec<NavGrid>> {
+		read_sections(&self.nav_grids, b"NAVG", ToyReader::read_nav_grid)
+	}
+}
+
+
+fn read_sections<'data, T>(sections: &[ToyReader<'data>], tag: &[u8; 4], mut read: impl FnMut(&mut ToyReader<'data>) -> ToyResult<T>)
+	-> ToyResult<Vec<T>>
+{
+	sections.iter()
+		.map(|&section| {
+			let mut section = section;
+			read(&mut section)
+				.map_err(|e| format_err!("While parsing '{}' section: {}", tag_to_string(tag), e))
+		})
+		.collect()
+}
+
+
+
+impl<'data> MeshView<'data> {
+	pub fn num_vertices(&self) -> usize {
+		self.positions.len()
+	}
+
+	pub fn num_triangles(&self) -> usize {
+		self.indices.len() / 3
+	}
+
+	pub fn color_layer_by_name(&self, name: &str) -> Option<&ColorLayerView<'data>> {
+		self.color_layers.iter()
+			.find(|l| l.name == name)
+	}
+
+	pub fn uv_layer_by_name(&self, name: &str) -> Option<&UvLayerView<'data>> {
+		self.uv_layers.iter()
+			.find(|l| l.name == name)
+	}
+
+	pub fn has_animation_data(&self) -> bool {
+		self.skin_section.is_some()
+	}
+
+	/// Decodes bones, weights and animations, if the mesh has any.
+	pub fn animation_data(&self) -> ToyResult<Option<MeshAnimationData>> {
+		self.skin_section
+			.map(|mut section| section.read_animation_data(self.num_vertices()))
+			.transpose()
+	}
+
+	/// Decodes material slots and the material index of each triangle. See [`Mesh::materials`].
+	pub fn materials(&self) -> ToyResult<(Vec<MeshMaterial>, Vec<u16>)> {
+		match self.materials_section {
+			Some(mut section) => section.read_materials(self.num_triangles()),
+			None => Ok((Vec::new(), Vec::new())),
+		}
+	}
+
+	/// Decodes the whole mesh into an owned [`Mesh`].
+	pub fn to_mesh(&self) -> ToyResult<Mesh> {
+		let num_vertices = self.num_vertices();
+		let indices = self.indices.to_vec();
+
+		if let Some(&index) = indices.iter().find(|&&index| index as usize >= num_vertices) {
+			bail!("Index {} out of range for mesh with {} vertices", index, num_vertices);
+		}
+
+		let color_layers = self.color_layers.iter()
+			.map(|layer| MeshColorLayer { name: layer.name.into(), data: layer.data.to_vec() })
+			.collect();
+
+		let uv_layers = self.uv_layers.iter()
+			.map(|layer| MeshUvLayer { name: layer.name.into(), data: layer.data.to_vec() })
+			.collect();
+
+		let (materials, material_indices) = self.materials()?;
+
+		Ok(Mesh {
+			positions: self.positions.to_vec(),
+			indices,
+			normals: self.normals.to_vec(),
+			tangents: self.tangents.to_vec(),
+			color_layers,
+			uv_layers,
+			materials,
+			material_indices,
+			animation_data: self.animation_data()?,
+		})
+	}
+}
+
+
+
+impl<'data, E: Encoding> ArrayView<'data, E> {
+	/// `bytes` must be a multiple of [`Encoding::SIZE`] long.
+	pub(crate) fn new(bytes: &'data [u8]) -> Self {
+		debug_assert_eq!(bytes.len() % E::SIZE, 0);
+		ArrayView { bytes, encoding: PhantomData }
+	}
+
+	pub(crate) fn empty() -> Self {
+		ArrayView::new(&[])
+	}
+
+	pub fn len(&self) -> usize {
+		self.bytes.len() / E::SIZE
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	/// The encoded data, suitable for uploading directly to a buffer with a matching vertex format.
+	pub fn bytes(&self) -> &'data [u8] {
+		self.bytes
+	}
+
+	pub fn get(&self, index: usize) -> Option<E::Value> {
+		let start = index.checked_mul(E::SIZE)?;
+		self.bytes.get(start..start.checked_add(E::SIZE)?)
+			.map(E::decode)
+	}
+
+	pub fn iter(&self) -> impl ExactSizeIterator<Item=E::Value> + 'data {
+		self.bytes.chunks_exact(E::SIZE)
+			.map(E::decode)
+	}
+
+	pub fn to_vec(&self) -> Vec<E::Value> {
+		self.iter().collect()
+	}
+}
+
+
+impl<'data> IndexView<'data> {
+	pub(crate) fn new(bytes: &'data [u8], index_size: usize) -> Self {
+		debug_assert!(matches!(index_size, 1 | 2 | 4));
+		debug_assert_eq!(bytes.len() % index_size, 0);
+		IndexView { bytes, index_size }
+	}
+
+	pub fn len(&self) -> usize {
+		self.bytes.len() / self.index_size
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	/// Size of each index in bytes - 1, 2 or 4.
+	pub fn index_size(&self) -> usize {
+		self.index_size
+	}
+
+	/// The encoded indices. See [`IndexView::index_size`] for their format.
+	pub fn bytes(&self) -> &'data [u8] {
+		self.bytes
+	}
+
+	pub fn get(&self, index: usize) -> Option<u32> {
+		let start = index.checked_mul(self.index_size)?;
+		self.bytes.get(start..start.checked_add(self.index_size)?)
+			.map(decode_index)
+	}
+
+	pub fn iter(&self) -> impl ExactSizeIterator<Item=u32> + 'data {
+		self.bytes.chunks_exact(self.index_size)
+			.map(decode_index)
+	}
+
+	pub fn to_vec(&self) -> Vec<u32> {
+		self.iter().collect()
+	}
+}
+
+
+fn decode_index(bytes: &[u8]) -> u32 {
+	match *bytes {
+		[a] => a as u32,
+		[a, b] => u16::from_le_bytes([a, b]) as u32,
+		_ => u32::from_le_bytes(bytes.try_into().unwrap()),
+	}
+}
+
+
+
+fn decode_f32(bytes: &[u8], index: usize) -> f32 {
+	let start = index * 4;
+	f32::from_le_bytes(bytes[start..start + 4].try_into().unwrap())
+}
+
+fn decode_unorm16(bytes: &[u8], index: usize) -> f32 {
+	let start = index * 2;
+	u16::from_le_bytes(bytes[start..start + 2].try_into().unwrap()) as f32 / 65535.0
+}
+
+impl Encoding for F32x3 {
+	type Value = Vec3;
+	const SIZE: usize = 12;
+
+	fn decode(bytes: &[u8]) -> Vec3 {
+		Vec3::new(decode_f32(bytes, 0), decode_f32(bytes, 1), decode_f32(bytes, 2))
+	}
+}
+
+impl Encoding for F32x4 {
+	type Value = Vec4;
+	const SIZE: usize = 16;
+
+	fn decode(bytes: &[u8]) -> Vec4 {
+		Vec4::new(decode_f32(bytes, 0), decode_f32(bytes, 1), decode_f32(bytes, 2), decode_f32(bytes, 3))
+	}
+}
+
+impl Encoding for Unorm16x2 {
+	type Value = Vec2;
+	const SIZE: usize = 4;
+
+	fn decode(bytes: &[u8]) -> Vec2 {
+		Vec2::new(decode_unorm16(bytes, 0), decode_unorm16(bytes, 1))
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::load;
+	use crate::test_util::*;
+
+	#[test]
+	fn view_matches_load() {
+		let data = sample_file();
+		let project = load(&data).unwrap();
+		let view = ToyView::new(&data).unwrap();
+
+		assert_eq!(view.num_meshes(), 1);
+		assert_eq!(view.entities().unwrap().len(), project.entities.len());
+		assert_eq!(view.scenes().unwrap()[0].name, project.scenes[0].name);
+		assert!(view.mesh(1).is_err());
+
+		let mesh_view = view.mesh(0).unwrap();
+		let mesh = &project.meshes[0];
+
+		assert_eq!(mesh_view.positions.bytes().len(), mesh.positions.len() * 12);
+		assert_eq!(mesh_view.indices.index_size(), 1);
+		assert_eq!(mesh_view.indices.to_vec(), mesh.indices);
+		assert_eq!(mesh_view.indices.get(mesh.indices.len()), None);
+		assert_eq!(mesh_view.indices.get(usize::MAX), None);
+		assert!(mesh_view.positions.get(usize::MAX / 12).is_none());
+		assert!(mesh_view.has_animation_data());
+
+		for (view_position, &position) in mesh_view.positions.iter().zip(&mesh.positions) {
+			assert_vec3_eq(view_position, position);
+		}
+
+		let uv_layer = mesh_view.uv_layer_by_name(&mesh.uv_layers[0].name).unwrap();
+		for (view_uv, &uv) in uv_layer.data.iter().zip(&mesh.uv_layers[0].data) {
+			assert_eq!((view_uv.x, view_uv.y), (uv.x, uv.y));
+		}
+
+		let decoded = mesh_view.to_mesh().unwrap();
+		assert_eq!(decoded.normals.len(), mesh.normals.len());
+		assert_eq!(decoded.material_indices, mesh.material_indices);
+		assert_eq!(decoded.animation_data.unwrap().bones.len(), mesh.animation_data.as_ref().unwrap().bones.len());
+	}
+}
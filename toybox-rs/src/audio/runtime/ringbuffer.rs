@@ -340,7 +340,7 @@ mod test {
 		let lock = ringbuffer.lock_for_write(5);
 		assert!(lock.len() == 5);
 		assert_eq!(lock.presplit, &[0, 1, 2, 3, 4]);
-		assert_eq!(lock.postsplit, &[]);
+		assert_eq!(lock.postsplit, &[] as &[i32]);
 		drop(lock);
 
 		assert_eq!(ringbuffer.write_ptr.load(Ordering::Relaxed), 0 | LOOP_FLAG);
@@ -375,7 +375,7 @@ mod test {
 
 		let lock = ringbuffer.lock_for_read(5);
 		assert_eq!(lock.presplit, &[0, 1, 2, 3, 4]);
-		assert_eq!(lock.postsplit, &[]);
+		assert_eq!(lock.postsplit, &[] as &[i32]);
 		drop(lock);
 
 		assert_eq!(ringbuffer.write_ptr.load(Ordering::Relaxed), 0 | LOOP_FLAG);
@@ -408,8 +408,8 @@ mod test {
 		ringbuffer.read_ptr.store(0, Ordering::Relaxed);
 
 		let lock = ringbuffer.lock_for_write(5);
-		assert_eq!(lock.presplit, &[]);
-		assert_eq!(lock.postsplit, &[]);
+		assert_eq!(lock.presplit, &[] as &[i32]);
+		assert_eq!(lock.postsplit, &[] as &[i32]);
 		assert_eq!(lock.new_head_encoded, 0 | LOOP_FLAG);
 		drop(lock);
 
@@ -426,8 +426,8 @@ mod test {
 		ringbuffer.read_ptr.store(4, Ordering::Relaxed);
 
 		let lock = ringbuffer.lock_for_write(5);
-		assert_eq!(lock.presplit, &[]);
-		assert_eq!(lock.postsplit, &[]);
+		assert_eq!(lock.presplit, &[] as &[i32]);
+		assert_eq!(lock.postsplit, &[] as &[i32]);
 		assert_eq!(lock.new_head_encoded, 4 | LOOP_FLAG);
 		drop(lock);
 
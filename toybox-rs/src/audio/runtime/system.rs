@@ -77,6 +77,11 @@ impl AudioSystem {
 		self.queue_update(move |graph| graph.remove_node(node))
 	}
 
+	/// The rate at which the audio device consumes samples. Sounds added with [`AudioSystem::add_sound`] should be at this rate.
+	pub fn sample_rate(&self) -> f32 {
+		self.shared.sample_rate
+	}
+
 	pub fn add_sound(&mut self, buffer: Vec<f32>) -> SoundId {
 		// TODO(pat.m): resource scopes
 		let key = self.shared.inner.lock().unwrap().resources.buffers.insert(buffer);
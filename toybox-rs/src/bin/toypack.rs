@@ -0,0 +1,46 @@
+//! Builds and inspects asset packs readable by [`toybox::vfs::Vfs`].
+//!
+//! ```text
+//! toypack build <output.pack> <directory>...
+//! toypack list <input.pack>
+//! ```
+//! Files from later directories replace files with the same relative path from earlier ones.
+
+use toybox::vfs::{Pack, PackBuilder};
+use std::error::Error;
+
+
+fn main() -> Result<(), Box<dyn Error>> {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+
+	match args.as_slice() {
+		[command, output, directories @ ..] if command == "build" && !directories.is_empty() => {
+			let mut builder = PackBuilder::new();
+
+			for directory in directories {
+				builder.add_directory(directory)
+					.map_err(|err| format!("Failed to add '{}': {}", directory, err))?;
+			}
+
+			builder.write(output)?;
+			println!("Wrote {} entries to '{}'", builder.num_entries(), output);
+		}
+
+		[command, input] if command == "list" => {
+			let pack = Pack::open(input)?;
+
+			for name in pack.entry_names() {
+				let size = pack.entry_size(name).unwrap_or(0);
+				println!("{:>10}  {}", size, name);
+			}
+		}
+
+		_ => {
+			eprintln!("usage: toypack build <output.pack> <directory>...");
+			eprintln!("       toypack list <input.pack>");
+			std::process::exit(1);
+		}
+	}
+
+	Ok(())
+}
@@ -2,6 +2,10 @@ use crate::prelude::*;
 use crate::{perf, imgui_backend, window};
 use crate::utility::resource_scope;
 
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
 /// The core of toybox.
 pub struct Engine {
 	pub sdl_ctx: sdl2::Sdl,
@@ -10,12 +14,17 @@ pub struct Engine {
 	pub gfx: gfx::System,
 	pub input: input::InputSystem,
 	pub audio: audio::AudioSystem,
+	pub vfs: vfs::Vfs,
 	pub instrumenter: perf::Instrumenter,
 
 	pub imgui: imgui_backend::ImguiBackend,
 
 	resource_scope_allocator: resource_scope::ResourceScopeAllocator,
 
+	screenshot_requests: Vec<PathBuf>,
+	screenshot_queue: gfx::ReadbackQueue<PathBuf>,
+	screenshot_saver: ScreenshotSaver,
+
 	should_quit: bool,
 }
 
@@ -38,10 +47,13 @@ impl Engine {
 		let event_pump = sdl_ctx.event_pump()?;
 		let mut input = input::InputSystem::new(sdl_ctx.mouse(), global_scope.clone());
 		let audio = audio::AudioSystem::new(sdl_audio, global_scope.clone())?;
+		let vfs = vfs::Vfs::with_default_mounts()?;
 
 		let mut resource_context = gfx.resource_context(None);
 		let mut imgui = imgui_backend::ImguiBackend::new(&mut resource_context)?;
 		let instrumenter = perf::Instrumenter::new(&mut resource_context);
+		let screenshot_queue = gfx::ReadbackQueue::new(&mut resource_context, 2);
+		let screenshot_saver = ScreenshotSaver::new()?;
 
 		// Make sure aspect is set up correctly
 		let (w, h) = window.drawable_size();
@@ -52,7 +64,7 @@ impl Engine {
 
 		gfx.on_resize(drawable_size);
 		imgui.on_resize(drawable_size, window_size);
-		input.on_resize(window_size);
+		input.on_resize(window_size, drawable_size);
 
 		Ok(Engine {
 			sdl_ctx,
@@ -61,12 +73,17 @@ impl Engine {
 			gfx,
 			input,
 			audio,
+			vfs,
 			instrumenter,
 			
 			imgui,
 
 			resource_scope_allocator,
 
+			screenshot_requests: Vec::new(),
+			screenshot_queue,
+			screenshot_saver,
+
 			should_quit: false,
 		})
 	}
@@ -74,6 +91,13 @@ impl Engine {
 	/// Whether or not the OS has requested the application close, e.g., by the user clicking the close button.
 	pub fn should_quit(&self) -> bool { self.should_quit }
 
+	/// Saves the backbuffer to `path` at the end of this frame, not including any debug ui.
+	/// The format is determined by the extension of `path`, though only PNG is enabled. The capture and saving happen in the background,
+	/// so this is cheap enough to bind to a key, e.g., by calling it whenever a trigger action is entered.
+	pub fn screenshot(&mut self, path: impl Into<PathBuf>) {
+		self.screenshot_requests.push(path.into());
+	}
+
 	/// Handles pushing window and input events to the relevant parts of the engine. 
 	/// Should be called at the start of the main loop, or at the start of the frame.
 	#[instrument(skip_all, name="Engine::process_events")]
@@ -98,7 +122,7 @@ impl Engine {
 
 					self.gfx.on_resize(drawable_size);
 					self.imgui.on_resize(drawable_size, window_size);
-					self.input.on_resize(window_size);
+					self.input.on_resize(window_size, drawable_size);
 				}
 
 				_ => {
@@ -114,6 +138,7 @@ impl Engine {
 
 		self.input.process_events();
 
+		self.gfx.start_frame();
 		self.imgui.start_frame();
 	}
 
@@ -122,14 +147,24 @@ impl Engine {
 	#[instrument(skip_all, name="Engine::end_frame")]
 	pub fn end_frame(&mut self) {
 		self.instrumenter.end_frame();
+		self.draw_shader_error_overlay();
 
-		self.imgui.draw(&mut self.gfx.draw_context());
+		{
+			let mut gfx = self.gfx.draw_context();
+
+			for path in self.screenshot_requests.drain(..) {
+				self.screenshot_queue.read_backbuffer(&mut gfx, path);
+			}
+
+			self.imgui.draw(&mut gfx);
+		}
 
 		{
 			let _guard = self.instrumenter.scoped_section("swap");
 			self.window.gl_swap_window();
 		}
 
+		self.save_finished_screenshots();
 		self.cleanup_resources();
 
 		tracing::info!(tracy.frame_mark=true);
@@ -146,6 +181,31 @@ impl Engine {
 
 
 impl Engine {
+	fn draw_shader_error_overlay(&self) {
+		let errors = self.gfx.shader_reload_errors();
+		if errors.is_empty() {
+			return
+		}
+
+		let ui = self.imgui.frame();
+
+		imgui::Window::new("Shader errors")
+			.always_auto_resize(true)
+			.build(ui, || {
+				for error in errors {
+					ui.text_colored([1.0, 0.4, 0.4, 1.0], error.name);
+					ui.text(error.message);
+					ui.separator();
+				}
+			});
+	}
+
+	fn save_finished_screenshots(&mut self) {
+		while let Some((path, image)) = self.screenshot_queue.poll() {
+			self.screenshot_saver.save(path, image);
+		}
+	}
+
 	fn cleanup_resources(&mut self) {
 		let to_remove = self.resource_scope_allocator.reap_dead_scopes();
 
@@ -159,6 +219,55 @@ impl Engine {
 
 
 
+/// Encodes and writes screenshots on a single background thread, in the order they were captured.
+/// Dropping it waits for any screenshots still queued to be saved.
+struct ScreenshotSaver {
+	request_tx: Option<Sender<(PathBuf, image::RgbaImage)>>,
+	thread_handle: Option<JoinHandle<()>>,
+}
+
+impl ScreenshotSaver {
+	fn new() -> std::io::Result<ScreenshotSaver> {
+		let (request_tx, request_rx) = mpsc::channel::<(PathBuf, image::RgbaImage)>();
+
+		let thread_handle = thread::Builder::new().name("screenshot saver".into())
+			.spawn(move || {
+				for (path, image) in request_rx {
+					match image.save(&path) {
+						Ok(()) => tracing::info!("Saved screenshot to '{}'", path.display()),
+						Err(err) => tracing::error!("Failed to save screenshot to '{}': {err}", path.display()),
+					}
+				}
+			})?;
+
+		Ok(ScreenshotSaver {
+			request_tx: Some(request_tx),
+			thread_handle: Some(thread_handle),
+		})
+	}
+
+	fn save(&self, path: PathBuf, image: image::RgbaImage) {
+		let request_tx = self.request_tx.as_ref().unwrap();
+
+		if request_tx.send((path, image)).is_err() {
+			tracing::error!("Screenshot saver thread has stopped, dropping screenshot");
+		}
+	}
+}
+
+impl Drop for ScreenshotSaver {
+	fn drop(&mut self) {
+		// Closing the channel ends the worker once it has drained it.
+		self.request_tx.take();
+
+		if let Some(handle) = self.thread_handle.take() {
+			let _ = handle.join();
+		}
+	}
+}
+
+
+
 #[cfg(feature="tracy")]
 fn init_tracy() {
     use tracing_subscriber::layer::SubscriberExt;
@@ -11,31 +11,44 @@ pub mod raw {
 
 pub mod system;
 pub mod draw_context;
+pub mod pipeline_state;
+pub mod viewport;
+pub mod validation;
+pub mod pixel_target;
+pub mod readback;
 pub mod resources;
 pub mod resource_context;
 pub mod vao;
 pub mod buffer;
 pub mod texture;
+pub mod sampler;
 pub mod framebuffer;
 pub mod vertex;
 pub mod shader;
 pub mod query;
 pub mod capabilities;
 pub mod mesh;
+pub mod skinning;
 
 #[doc(inline)] pub use self::system::*;
 #[doc(inline)] pub use self::draw_context::*;
+#[doc(inline)] pub use self::pipeline_state::*;
+#[doc(inline)] pub use self::viewport::RegionScope;
+#[doc(inline)] pub use self::pixel_target::*;
+#[doc(inline)] pub use self::readback::ReadbackQueue;
 #[doc(inline)] pub use self::resource_context::*;
 #[doc(inline)] pub use self::resources::*;
 #[doc(inline)] pub use self::buffer::*;
 #[doc(inline)] pub use self::mesh::*;
 #[doc(inline)] pub use self::texture::*;
+#[doc(inline)] pub use self::sampler::{SamplerKey, SamplerDesc, Filter, WrapMode, BorderColor};
 #[doc(inline)] pub use self::framebuffer::*;
 #[doc(inline)] pub use self::vao::*;
 #[doc(inline)] pub use self::vertex::*;
 #[doc(inline)] pub use self::shader::*;
 #[doc(inline)] pub use self::query::*;
 #[doc(inline)] pub use self::capabilities::*;
+#[doc(inline)] pub use self::skinning::{SKINNING_SHADER_IMPORT, SKINNING_BONE_BUFFER_BINDING};
 
 /// The kind of primitive to be generated by a draw call.
 /// Used [`DrawContext::draw_arrays`],  [`DrawContext::draw_indexed`], and [`DrawContext::draw_instances_indexed`].
@@ -1,4 +1,5 @@
 use crate::gfx::raw;
+use crate::gfx::sampler::MAX_TEXTURE_MAX_ANISOTROPY;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Capabilities {
@@ -7,6 +8,10 @@ pub struct Capabilities {
 
 	pub ubo_offset_alignment: i32,
 	pub ssbo_offset_alignment: i32,
+
+	/// Upper limit for [`SamplerDesc::anisotropy`](crate::gfx::SamplerDesc::anisotropy).
+	/// 1.0 if anisotropic filtering isn't supported.
+	pub max_anisotropy: f32,
 }
 
 
@@ -17,6 +22,7 @@ impl Capabilities {
 
 		let mut ubo_offset_alignment = 0;
 		let mut ssbo_offset_alignment = 0;
+		let mut max_anisotropy = 1.0;
 
 		unsafe {
 			raw::GetQueryiv(raw::TIME_ELAPSED, raw::QUERY_COUNTER_BITS, &mut max_simultaneous_time_elapsed_queries);
@@ -24,6 +30,11 @@ impl Capabilities {
 
 			raw::GetIntegerv(raw::UNIFORM_BUFFER_OFFSET_ALIGNMENT, &mut ubo_offset_alignment);
 			raw::GetIntegerv(raw::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT, &mut ssbo_offset_alignment);
+
+			// Querying without the extension would raise an error, which the debug callback treats as fatal.
+			if has_extension("GL_ARB_texture_filter_anisotropic") || has_extension("GL_EXT_texture_filter_anisotropic") {
+				raw::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+			}
 		}
 
 		Capabilities {
@@ -31,6 +42,24 @@ impl Capabilities {
 			max_simultaneous_primitive_queries,
 			ubo_offset_alignment,
 			ssbo_offset_alignment,
+			max_anisotropy: max_anisotropy.max(1.0),
 		}
 	}
-}
\ No newline at end of file
+}
+
+
+fn has_extension(name: &str) -> bool {
+	let mut num_extensions = 0;
+
+	unsafe {
+		raw::GetIntegerv(raw::NUM_EXTENSIONS, &mut num_extensions);
+	}
+
+	(0..num_extensions as u32).any(|index| {
+		let extension = unsafe {
+			std::ffi::CStr::from_ptr(raw::GetStringi(raw::EXTENSIONS, index) as *const _)
+		};
+
+		extension.to_bytes() == name.as_bytes()
+	})
+}
@@ -1,5 +1,9 @@
 use crate::prelude::*;
 use crate::gfx::*;
+use crate::gfx::pipeline_state::{apply_blend, apply_blend_constant, apply_depth, apply_stencil,
+	apply_color_write_mask, apply_backface_culling, apply_wireframe};
+use crate::gfx::viewport::{RenderTargetState, RegionScope, apply_viewport, apply_scissor};
+use crate::gfx::validation::DrawValidator;
 
 
 /// Provides access to everything needed to set up and submit draw calls and dispatch compute shaders.
@@ -14,27 +18,76 @@ use crate::gfx::*;
 pub struct DrawContext<'ctx> {
 	pub(super) resources: &'ctx Resources,
 	pub(super) backbuffer_size: Vec2i,
+
+	/// Index type of the [`Vao`] most recently bound through this context, used by indexed draw calls.
+	/// Assumed to be 16b until a [`Vao`] is bound.
+	pub(super) bound_index_type: IndexType,
+
+	/// The last state set through this or any other context. Only valid as long as nothing changes it through [`raw`].
+	pub(super) pipeline_state: &'ctx mut PipelineState,
+
+	/// The bound framebuffer along with its viewport and scissor state.
+	pub(super) render_target: &'ctx mut RenderTargetState,
+
+	/// Checks draw calls against the bound shader in debug builds - see [`System::set_draw_validation_enabled`].
+	pub(super) validator: &'ctx mut DrawValidator,
 }
 
 impl<'ctx> DrawContext<'ctx> {
-	pub fn set_wireframe(&mut self, wireframe_enabled: bool) {
-		let mode = match wireframe_enabled {
-			false => raw::FILL,
-			true => raw::LINE,
-		};
+	/// The pipeline state set by the last call to [`DrawContext::set_pipeline_state`] or any other `set_*` call,
+	/// e.g., so it can be restored after drawing something with different state.
+	pub fn pipeline_state(&self) -> &PipelineState {
+		self.pipeline_state
+	}
 
-		unsafe {
-			raw::PolygonMode(raw::FRONT_AND_BACK, mode);
-		}
+	pub fn set_pipeline_state(&mut self, state: PipelineState) {
+		*self.pipeline_state = state;
+		state.apply();
+	}
+
+	/// Sets [`PipelineState::default`]. Happens automatically at the start of each frame.
+	pub fn reset_pipeline_state(&mut self) {
+		self.set_pipeline_state(PipelineState::default());
+	}
+
+	/// `None` disables blending.
+	pub fn set_blend_mode(&mut self, blend: impl Into<Option<BlendMode>>) {
+		let blend = blend.into();
+		self.pipeline_state.blend = blend;
+		apply_blend(blend);
+	}
+
+	pub fn set_blend_constant(&mut self, color: impl Into<Color>) {
+		let color = color.into();
+		self.pipeline_state.blend_constant = color;
+		apply_blend_constant(color);
+	}
+
+	pub fn set_depth_state(&mut self, depth: DepthState) {
+		self.pipeline_state.depth = depth;
+		apply_depth(depth);
+	}
+
+	/// `None` disables stencil testing and writes.
+	pub fn set_stencil_state(&mut self, stencil: impl Into<Option<StencilState>>) {
+		let stencil = stencil.into();
+		self.pipeline_state.stencil = stencil;
+		apply_stencil(stencil);
+	}
+
+	pub fn set_color_write_mask(&mut self, mask: ColorWriteMask) {
+		self.pipeline_state.color_write_mask = mask;
+		apply_color_write_mask(mask);
+	}
+
+	pub fn set_wireframe(&mut self, wireframe_enabled: bool) {
+		self.pipeline_state.wireframe = wireframe_enabled;
+		apply_wireframe(wireframe_enabled);
 	}
 
 	pub fn set_backface_culling(&mut self, culling_enabled: bool) {
-		unsafe {
-			match culling_enabled {
-				true => raw::Enable(raw::CULL_FACE),
-				false => raw::Disable(raw::CULL_FACE),
-			}
-		}
+		self.pipeline_state.backface_culling = culling_enabled;
+		apply_backface_culling(culling_enabled);
 	}
 
 	pub fn set_clear_color(&mut self, color: impl Into<Color>) {
@@ -44,10 +97,22 @@ impl<'ctx> DrawContext<'ctx> {
 		}
 	}
 
+	/// Clears every plane in `mode` within the current scissor (or the whole target if there isn't one),
+	/// regardless of the color, depth and stencil write masks in the current pipeline state.
 	pub fn clear(&mut self, mode: ClearMode) {
+		let state = *self.pipeline_state;
+
 		unsafe {
+			raw::ColorMask(1, 1, 1, 1);
+			raw::DepthMask(1);
+			raw::StencilMask(0xff);
+
 			raw::Clear(mode.into_gl());
 		}
+
+		apply_color_write_mask(state.color_write_mask);
+		apply_depth(state.depth);
+		apply_stencil(state.stencil);
 	}
 
 	pub fn resources(&self) -> &'ctx Resources {
@@ -80,14 +145,19 @@ impl<'ctx> DrawContext<'ctx> {
 
 	fn bind_image_raw(&mut self, binding: u32, texture_key: impl IntoTextureKey, rw_flags: u32) {
 		// https://www.khronos.org/opengl/wiki/Image_Load_Store#Images_in_the_context
-		let (level, layered, layer) = (0, 0, 0);
 		let texture_key = texture_key.into_texture_key(self.resources);
 		let texture = self.resources.get(texture_key);
 
+		// Arrays, cubemaps and 3D textures are bound whole, as image2DArray, imageCube and image3D respectively.
+		let layered = texture.kind().is_layered() as u8;
+		let (level, layer) = (0, 0);
+
 		unsafe {
 			raw::BindImageTexture(binding, texture.texture_handle, level, layered, layer,
 				rw_flags, texture.format().to_gl());
 		}
+
+		self.validator.track_image(binding, texture.kind(), texture.format());
 	}
 
 	pub fn bind_image_for_rw(&mut self, binding: u32, texture_key: impl IntoTextureKey) {
@@ -102,17 +172,31 @@ impl<'ctx> DrawContext<'ctx> {
 		self.bind_image_raw(binding, texture_key, raw::WRITE_ONLY)
 	}
 
+	/// Binds a texture along with its own sampler - see [`Texture::set_sampler`].
 	pub fn bind_texture(&mut self, binding: u32, texture_key: impl IntoTextureKey) {
+		let texture_key = texture_key.into_texture_key(self.resources);
+		let sampler = texture_key.get(self.resources).sampler();
+		self.bind_texture_with_sampler(binding, texture_key, sampler);
+	}
+
+	/// Binds a texture, overriding its own sampler - e.g., to sample the same texture with and without
+	/// filtering, or a shadow map with and without depth comparison.
+	pub fn bind_texture_with_sampler(&mut self, binding: u32, texture_key: impl IntoTextureKey, sampler: SamplerKey) {
 		let texture_key = texture_key.into_texture_key(self.resources);
 		let texture = texture_key.get(self.resources);
 
 		unsafe {
 			raw::BindTextureUnit(binding, texture.texture_handle);
-			raw::BindSampler(binding, texture.sampler_handle);
+			raw::BindSampler(binding, self.resources.samplers.handle(sampler));
 		}
+
+		let compare = self.resources.samplers.desc(sampler).compare.is_some();
+		self.validator.track_texture(binding, texture.kind(), texture.format(), compare);
 	}
 
 	pub fn bind_vao(&mut self, vao: Vao) {
+		self.bound_index_type = vao.index_type;
+
 		unsafe {
 			raw::BindVertexArray(vao.handle);
 		}
@@ -124,23 +208,128 @@ impl<'ctx> DrawContext<'ctx> {
 		}
 	}
 
+	/// Binds `framebuffer` for drawing, or the backbuffer if `None`.
+	/// Also resets the viewport to cover the whole of the new target, and disables the scissor. Viewports and scissors
+	/// pushed for the previous target are forgotten, and popping them restores these defaults instead.
 	pub fn bind_framebuffer(&mut self, framebuffer: impl Into<Option<FramebufferKey>>) {
-		if let Some(framebuffer) = framebuffer.into() {
-			let framebuffer = framebuffer.get(self.resources);
-			let Vec2i{x,y} = framebuffer.size_mode.resolve(self.backbuffer_size);
+		let framebuffer = framebuffer.into();
 
-			unsafe {
-				raw::Viewport(0, 0, x, y);
-				raw::BindFramebuffer(raw::DRAW_FRAMEBUFFER, framebuffer.handle);
+		let (handle, size) = match framebuffer {
+			Some(framebuffer) => {
+				let framebuffer = framebuffer.get(self.resources);
+				(framebuffer.handle, framebuffer.size())
 			}
-		} else {
-			let Vec2i{x,y} = self.backbuffer_size;
 
-			unsafe {
-				raw::Viewport(0, 0, x, y);
-				raw::BindFramebuffer(raw::DRAW_FRAMEBUFFER, 0);
-			}
+			None => (0, self.backbuffer_size),
+		};
+
+		unsafe {
+			raw::BindFramebuffer(raw::DRAW_FRAMEBUFFER, handle);
+		}
+
+		self.render_target.bind(framebuffer, size);
+	}
+
+	/// The framebuffer currently bound for drawing, or `None` for the backbuffer.
+	pub fn bound_framebuffer(&self) -> Option<FramebufferKey> {
+		self.render_target.framebuffer
+	}
+
+	/// The size in pixels of the framebuffer currently bound for drawing.
+	pub fn target_size(&self) -> Vec2i {
+		self.render_target.size
+	}
+
+	/// The region of the bound target draw calls are mapped to.
+	/// Covers the whole target unless changed by [`DrawContext::set_viewport`] or [`DrawContext::push_viewport`].
+	pub fn viewport(&self) -> Aabb2i {
+		self.render_target.viewport
+	}
+
+	/// Sets the region of the bound target that normalized device coordinates are mapped to, in pixels,
+	/// with the origin in the bottom left corner. Reset whenever a framebuffer is bound.
+	pub fn set_viewport(&mut self, viewport: Aabb2i) {
+		self.render_target.viewport = viewport;
+		apply_viewport(viewport);
+	}
+
+	/// Saves the current viewport before setting `viewport`. Must be matched by a call to [`DrawContext::pop_viewport`].
+	pub fn push_viewport(&mut self, viewport: Aabb2i) {
+		let previous = self.render_target.viewport;
+		self.render_target.viewport_stack.push(previous);
+		self.set_viewport(viewport);
+	}
+
+	/// Restores the viewport saved by the last [`DrawContext::push_viewport`], or the whole target if nothing was pushed since it was bound.
+	pub fn pop_viewport(&mut self) {
+		let previous = self.render_target.pop_viewport();
+		self.set_viewport(previous);
+	}
+
+	/// Like [`DrawContext::push_viewport`], but pops the viewport again when the returned scope is dropped.
+	///
+	/// ```ignore
+	/// for (player, region) in split_screen_regions.iter().enumerate() {
+	/// 	let mut gfx = gfx.scoped_viewport(*region);
+	/// 	draw_world(&mut gfx, player);
+	/// }
+	/// ```
+	pub fn scoped_viewport(&mut self, viewport: Aabb2i) -> RegionScope<'_, 'ctx> {
+		self.push_viewport(viewport);
+		RegionScope::viewport(self)
+	}
+
+	/// The region of the bound target outside of which nothing will be drawn or cleared, if any.
+	pub fn scissor(&self) -> Option<Aabb2i> {
+		self.render_target.scissor
+	}
+
+	/// Restricts draw calls and [`DrawContext::clear`] to `scissor`, in pixels of the bound target
+	/// with the origin in the bottom left corner. `None` disables the scissor test.
+	/// Unlike the viewport, the scissor doesn't transform anything, it just discards fragments outside of it.
+	/// Reset whenever a framebuffer is bound.
+	pub fn set_scissor(&mut self, scissor: impl Into<Option<Aabb2i>>) {
+		let scissor = scissor.into();
+		self.render_target.scissor = scissor;
+		apply_scissor(scissor);
+	}
+
+	/// Saves the current scissor before setting `scissor`. Must be matched by a call to [`DrawContext::pop_scissor`].
+	pub fn push_scissor(&mut self, scissor: impl Into<Option<Aabb2i>>) {
+		let previous = self.render_target.scissor;
+		self.render_target.scissor_stack.push(previous);
+		self.set_scissor(scissor);
+	}
+
+	/// Restores the scissor saved by the last [`DrawContext::push_scissor`], or disables it if nothing was pushed since the target was bound.
+	pub fn pop_scissor(&mut self) {
+		let previous = self.render_target.pop_scissor();
+		self.set_scissor(previous);
+	}
+
+	/// Like [`DrawContext::push_scissor`], but pops the scissor again when the returned scope is dropped.
+	pub fn scoped_scissor(&mut self, scissor: impl Into<Option<Aabb2i>>) -> RegionScope<'_, 'ctx> {
+		self.push_scissor(scissor);
+		RegionScope::scissor(self)
+	}
+
+	/// Reads back the current contents of the backbuffer, flipped so that the first row is the top of the screen.
+	/// Waits for all submitted draw calls to finish, so prefer a [`ReadbackQueue`] for anything done regularly.
+	/// Should be called before the window is swapped.
+	pub fn read_backbuffer(&mut self) -> image::RgbaImage {
+		let Vec2i{x: width, y: height} = self.backbuffer_size;
+		let mut data = vec![0u8; (width * height * 4) as usize];
+
+		unsafe {
+			raw::BindFramebuffer(raw::READ_FRAMEBUFFER, 0);
+			raw::ReadPixels(0, 0, width, height, raw::RGBA, raw::UNSIGNED_BYTE, data.as_mut_ptr() as *mut _);
 		}
+
+		let mut image = image::RgbaImage::from_raw(width as u32, height as u32, data)
+			.expect("Backbuffer size doesn't match image size");
+
+		image::imageops::flip_vertical_in_place(&mut image);
+		image
 	}
 
 	/// Call before drawcalls that read textures/images written by shaders in previous drawcalls.
@@ -169,6 +358,8 @@ impl<'ctx> DrawContext<'ctx> {
 			return
 		}
 
+		self.validator.validate_draw();
+
 		unsafe {
 			raw::DrawArrays(draw_mode.into_gl(), 0, num_vertices as i32);
 		}
@@ -185,10 +376,12 @@ impl<'ctx> DrawContext<'ctx> {
 			return
 		}
 
-		let offset_ptr = (element_offset as usize * std::mem::size_of::<u16>()) as *const _;
+		let offset_ptr = (element_offset as usize * self.bound_index_type.size_bytes()) as *const _;
+
+		self.validator.validate_draw();
 
 		unsafe {
-			raw::DrawElementsBaseVertex(draw_mode.into_gl(), num_elements as i32, raw::UNSIGNED_SHORT, offset_ptr, base_vertex as i32);
+			raw::DrawElementsBaseVertex(draw_mode.into_gl(), num_elements as i32, self.bound_index_type.into_gl(), offset_ptr, base_vertex as i32);
 		}
 	}
 
@@ -197,8 +390,10 @@ impl<'ctx> DrawContext<'ctx> {
 			return
 		}
 
+		self.validator.validate_draw();
+
 		unsafe {
-			raw::DrawElementsInstanced(draw_mode.into_gl(), num_elements as i32, raw::UNSIGNED_SHORT, std::ptr::null(), num_instances as i32);
+			raw::DrawElementsInstanced(draw_mode.into_gl(), num_elements as i32, self.bound_index_type.into_gl(), std::ptr::null(), num_instances as i32);
 		}
 	}
 
@@ -208,6 +403,8 @@ impl<'ctx> DrawContext<'ctx> {
 		assert!(y < 65536, "Work group exceeds guaranteed minimum size along y axis");
 		assert!(z < 65536, "Work group exceeds guaranteed minimum size along z axis");
 
+		self.validator.validate_dispatch();
+
 		unsafe {
 			raw::DispatchCompute(x, y, z);
 		}
@@ -1,7 +1,8 @@
 use crate::prelude::*;
 use crate::gfx::{
 	self, raw,
-	Texture, TextureSize, TextureFormat, TextureKey,
+	Texture, TextureSize, TextureFormat, TextureKey, TextureKind,
+	MipLevels, SamplerDesc,
 };
 
 use crate::utility::resource::ResourceStore;
@@ -10,12 +11,28 @@ use crate::utility::resource::ResourceStore;
 struct Attachment {
 	attachment_point: u32,
 	texture_key: TextureKey,
+
+	/// Set for single layers, faces or slices of textures that aren't plain 2D. See [`FramebufferSettings::add_color_layer`].
+	layer: Option<u32>,
+}
+
+impl Attachment {
+	unsafe fn bind(&self, fbo: u32, textures: &ResourceStore<Texture>) {
+		let handle = textures.get(self.texture_key).texture_handle;
+
+		if let Some(layer) = self.layer {
+			raw::NamedFramebufferTextureLayer(fbo, self.attachment_point, handle, 0, layer as i32);
+		} else {
+			raw::NamedFramebufferTexture(fbo, self.attachment_point, handle, 0);
+		}
+	}
 }
 
 #[derive(Debug)]
 pub struct Framebuffer {
 	pub(super) handle: u32,
 	pub(super) size_mode: TextureSize,
+	current_size: Vec2i,
 
 	depth_stencil_attachment: Option<Attachment>,
 	color_attachments: Vec<Attachment>,
@@ -28,9 +45,25 @@ impl Framebuffer {
 			depth_attachment,
 			stencil_attachment,
 			color_attachments,
+			depth_stencil_layer,
 		} = settings;
 
+		let default_sampler = resources.samplers.get(SamplerDesc::default());
+
 		let depth_stencil_attachment = loop {
+			if let Some((texture_key, layer)) = depth_stencil_layer {
+				check_layer(resources.get(texture_key).kind(), layer);
+
+				let attachment_point = match resources.get(texture_key).format() {
+					TextureFormat::Depth | TextureFormat::Depth16 | TextureFormat::Depth32 => raw::DEPTH_ATTACHMENT,
+					TextureFormat::Stencil => raw::STENCIL_ATTACHMENT,
+					TextureFormat::DepthStencil => raw::DEPTH_STENCIL_ATTACHMENT,
+					format => panic!("Can't use {format:?} texture as a depth or stencil attachment"),
+				};
+
+				break Some(Attachment {attachment_point, texture_key, layer: Some(layer)})
+			}
+
 			let (depth_stencil_format, depth_stencil_attachment_point) = match (depth_attachment, stencil_attachment) {
 				(false, false) => {
 					break None
@@ -40,23 +73,33 @@ impl Framebuffer {
 				(true, true) => (TextureFormat::DepthStencil, raw::DEPTH_STENCIL_ATTACHMENT),
 			};
 
-			let mut depth_stencil_tex = Texture::new(size_mode, backbuffer_size, depth_stencil_format);
-			depth_stencil_tex.set_filter(false, false);
+			let depth_stencil_tex = Texture::new(TextureKind::Texture2D, size_mode, backbuffer_size, depth_stencil_format, MipLevels::One, default_sampler);
 
 			break Some(Attachment {
 				attachment_point: depth_stencil_attachment_point,
-				texture_key: resources.textures.insert(depth_stencil_tex)
+				texture_key: resources.textures.insert(depth_stencil_tex),
+				layer: None,
 			})
 		};
 
 		let color_attachments = color_attachments.iter()
 			.enumerate()
-			.filter_map(|(s, maybe_f)| maybe_f.map(|f| (s, f))) // (attachment_point, format)
-			.map(|(attachment_point, format)| {
-				let color_tex = Texture::new(size_mode, backbuffer_size, format);
-				let texture_key = resources.textures.insert(color_tex);
+			.filter_map(|(s, maybe_source)| maybe_source.map(|source| (s, source))) // (attachment_point, source)
+			.map(|(attachment_point, source)| {
 				let attachment_point = raw::COLOR_ATTACHMENT0 + attachment_point as u32;
-				Attachment {attachment_point, texture_key}
+
+				match source {
+					ColorAttachmentSource::New(format) => {
+						let color_tex = Texture::new(TextureKind::Texture2D, size_mode, backbuffer_size, format, MipLevels::One, default_sampler);
+						let texture_key = resources.textures.insert(color_tex);
+						Attachment {attachment_point, texture_key, layer: None}
+					}
+
+					ColorAttachmentSource::Layer(texture_key, layer) => {
+						check_layer(resources.get(texture_key).kind(), layer);
+						Attachment {attachment_point, texture_key, layer: Some(layer)}
+					}
+				}
 			})
 			.collect(): Vec<_>;
 
@@ -73,22 +116,20 @@ impl Framebuffer {
 		unsafe {
 			raw::CreateFramebuffers(1, &mut fbo);
 
-			if let Some(Attachment{attachment_point, texture_key}) = depth_stencil_attachment {
-				let handle = resources.get(texture_key).texture_handle;
-				raw::NamedFramebufferTexture(fbo, attachment_point, handle, 0);
-			}
-
-			for &Attachment{attachment_point, texture_key} in color_attachments.iter() {
-				let handle = resources.get(texture_key).texture_handle;
-				raw::NamedFramebufferTexture(fbo, attachment_point, handle, 0);
+			for attachment in depth_stencil_attachment.iter().chain(color_attachments.iter()) {
+				attachment.bind(fbo, &resources.textures);
 			}
 
 			raw::NamedFramebufferDrawBuffers(fbo, draw_buffers.len() as _, draw_buffers.as_ptr());
 		}
 
+		let current_size = attachments_size(depth_stencil_attachment.iter().chain(color_attachments.iter()), &resources.textures)
+			.unwrap_or_else(|| size_mode.resolve(backbuffer_size));
+
 		Framebuffer {
 			handle: fbo,
 			size_mode,
+			current_size,
 
 			depth_stencil_attachment,
 			color_attachments,
@@ -96,21 +137,21 @@ impl Framebuffer {
 	}
 
 	pub(super) fn rebind_attachments(&mut self, textures: &ResourceStore<Texture>) {
-		if let Some(Attachment{attachment_point, texture_key}) = self.depth_stencil_attachment {
-			let texture_handle = textures.get(texture_key).texture_handle;
-			unsafe {
-				raw::NamedFramebufferTexture(self.handle, attachment_point, texture_handle, 0);
-			}
+		if let Some(size) = attachments_size(self.depth_stencil_attachment.iter().chain(self.color_attachments.iter()), textures) {
+			self.current_size = size;
 		}
 
-		for &Attachment{attachment_point, texture_key} in self.color_attachments.iter() {
-			let handle = textures.get(texture_key).texture_handle;
+		for attachment in self.depth_stencil_attachment.iter().chain(self.color_attachments.iter()) {
 			unsafe {
-				raw::NamedFramebufferTexture(self.handle, attachment_point, handle, 0);
+				attachment.bind(self.handle, textures);
 			}
 		}
 	}
 
+	/// The size of this framebuffer's attachments. Tracks the backbuffer for [`TextureSize::Backbuffer`]
+	/// and [`TextureSize::BackbufferDivisor`] framebuffers.
+	pub fn size(&self) -> Vec2i { self.current_size }
+
 	pub fn is_complete(&self) -> bool {
 		let status = unsafe {raw::CheckNamedFramebufferStatus(self.handle, raw::DRAW_FRAMEBUFFER)};
 		status == raw::FRAMEBUFFER_COMPLETE
@@ -131,12 +172,47 @@ impl Framebuffer {
 
 
 
+/// Layer attachments bring their own size, so the size of a framebuffer is whatever is actually attached -
+/// which GL requires to be the same for every attachment.
+fn attachments_size<'a>(attachments: impl Iterator<Item=&'a Attachment>, textures: &ResourceStore<Texture>) -> Option<Vec2i> {
+	let mut size = None;
+
+	for attachment in attachments {
+		let attachment_size = textures.get(attachment.texture_key).size();
+
+		match size {
+			None => size = Some(attachment_size),
+			Some(size) => assert_eq!(size, attachment_size,
+				"Framebuffer attachments must all be the same size - attachments created by the framebuffer use its size mode"),
+		}
+	}
+
+	size
+}
+
+fn check_layer(kind: TextureKind, layer: u32) {
+	assert!(kind.is_layered(), "Can't attach a single layer of a {kind:?} texture");
+	assert!(layer < kind.depth_or_layers(), "Can't attach layer {layer} of {kind:?} texture");
+}
+
+
+
+#[derive(Copy, Clone, Debug)]
+enum ColorAttachmentSource {
+	/// A new 2D texture owned by the framebuffer.
+	New(TextureFormat),
+
+	/// A single layer of an existing texture.
+	Layer(TextureKey, u32),
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct FramebufferSettings {
 	size_mode: TextureSize,
 	depth_attachment: bool,
 	stencil_attachment: bool,
-	color_attachments: [Option<TextureFormat>; 8],
+	color_attachments: [Option<ColorAttachmentSource>; 8],
+	depth_stencil_layer: Option<(TextureKey, u32)>,
 }
 
 impl FramebufferSettings {
@@ -146,6 +222,7 @@ impl FramebufferSettings {
 			depth_attachment: false,
 			stencil_attachment: false,
 			color_attachments: [None; 8],
+			depth_stencil_layer: None,
 		}
 	}
 
@@ -163,9 +240,28 @@ impl FramebufferSettings {
 
 	pub fn add_color(mut self, attachment_point: u32, format: TextureFormat) -> Self {
 		assert!(attachment_point < 8);
-		self.color_attachments[attachment_point as usize] = Some(format);
+		self.color_attachments[attachment_point as usize] = Some(ColorAttachmentSource::New(format));
+		self
+	}
+
+	/// Attaches a single layer of an existing array, cubemap or 3D texture rather than creating a new texture -
+	/// e.g., to render into one face of a cubemap, using [`CubeFace::layer`](crate::gfx::CubeFace::layer).
+	/// The texture isn't owned by the framebuffer, and the framebuffer takes its size from it rather than from `size_mode`.
+	///
+	/// Creating the framebuffer panics if `layer` is out of range for `texture`, or if any other attachments differ
+	/// in size.
+	pub fn add_color_layer(mut self, attachment_point: u32, texture: TextureKey, layer: u32) -> Self {
+		assert!(attachment_point < 8);
+		self.color_attachments[attachment_point as usize] = Some(ColorAttachmentSource::Layer(texture, layer));
 		self
 	}
+
+	/// Like [`FramebufferSettings::add_color_layer`] but for the depth and/or stencil attachment, depending on the
+	/// format of `texture`. Replaces [`FramebufferSettings::add_depth`] and [`FramebufferSettings::add_stencil`] -
+	/// e.g., for rendering cascades of a shadow map array.
+	pub fn add_depth_stencil_layer(self, texture: TextureKey, layer: u32) -> Self {
+		FramebufferSettings {depth_stencil_layer: Some((texture, layer)), ..self}
+	}
 }
 
 
@@ -2,6 +2,7 @@
 //!
 //! The core of this module is the [`Mesh`] and [`BasicMesh`] types, and the generic poly builder apis
 //! defined by [`PolyBuilder2D`] and [`PolyBuilder3D`].
+//! Geometry imported from toy files can be converted with [`ToyMeshBuilder`].
 
 use crate::prelude::*;
 
@@ -10,11 +11,13 @@ pub mod util;
 pub mod geom_2d;
 pub mod geom_3d;
 pub mod color_mesh_builder;
+pub mod toy_mesh_builder;
 pub mod plane_mesh_builder_adaptor;
 
 #[doc(inline)] pub use util::*;
 #[doc(inline)] pub use traits::{PolyBuilder2D, PolyBuilder3D};
 #[doc(inline)] pub use color_mesh_builder::ColorMeshBuilder;
+#[doc(inline)] pub use toy_mesh_builder::{ToyMeshBuilder, ToyVertex, ToyVertexSource, ToyMeshError};
 #[doc(inline)] pub use plane_mesh_builder_adaptor::{PlaneMeshBuilderAdaptor, BuilderSurface, OrthogonalOrientation};
 
 /// Reexports [geom_2d] and [geom_3d] modules for convenience.
@@ -26,14 +29,16 @@ pub mod geom {
 
 /// Aggregates a [`gfx::Vao`], vertex [`gfx::Buffer`] and index [`gfx::Buffer`], to simplify managing and
 /// rendering common indexed geometry.
-pub struct Mesh<V: gfx::Vertex> {
+///
+/// Indices are 16b by default, but `Mesh<V, u32>` can be used for geometry with more than 65536 vertices.
+pub struct Mesh<V: gfx::Vertex, I: gfx::IndexElement = u16> {
 	pub vao: gfx::Vao,
 	pub vertex_buffer: gfx::Buffer<V>,
-	pub index_buffer: gfx::Buffer<u16>,
+	pub index_buffer: gfx::Buffer<I>,
 }
 
 
-impl<V: gfx::Vertex> Mesh<V> {
+impl<V: gfx::Vertex, I: gfx::IndexElement> Mesh<V, I> {
 	pub fn with_buffer_usage(gfx: &mut gfx::ResourceContext<'_>, buffer_usage: gfx::BufferUsage) -> Self {
 		let mut vao = gfx.new_vao();
 
@@ -54,7 +59,7 @@ impl<V: gfx::Vertex> Mesh<V> {
 		Mesh::with_buffer_usage(gfx, gfx::BufferUsage::Stream)
 	}
 
-	pub fn from_mesh_data(gfx: &mut gfx::ResourceContext<'_>, mesh_data: &MeshData<V>) -> Self {
+	pub fn from_mesh_data(gfx: &mut gfx::ResourceContext<'_>, mesh_data: &MeshData<V, I>) -> Self {
 		let mut mesh = Mesh::with_buffer_usage(gfx, gfx::BufferUsage::Static);
 		mesh.upload(mesh_data);
 		mesh
@@ -70,12 +75,12 @@ impl<V: gfx::Vertex> Mesh<V> {
 		gfx.draw_instances_indexed(draw_mode, self.index_buffer.len(), num_instances);
 	}
 
-	pub fn upload(&mut self, mesh_data: &MeshData<V>) {
+	pub fn upload(&mut self, mesh_data: &MeshData<V, I>) {
 		self.vertex_buffer.upload(&mesh_data.vertices);
 		self.index_buffer.upload(&mesh_data.indices);
 	}
 
-	pub fn upload_separate(&mut self, vertices: &[V], indices: &[u16]) {
+	pub fn upload_separate(&mut self, vertices: &[V], indices: &[I]) {
 		self.vertex_buffer.upload(vertices);
 		self.index_buffer.upload(indices);
 	}
@@ -128,13 +133,13 @@ impl<V: gfx::Vertex> BasicMesh<V> {
 
 /// Geometry data to be uploaded to a [`Mesh`].
 /// See [`ColorMeshBuilder`] for a common usecase for [`MeshData`].
-pub struct MeshData<V: gfx::Vertex> {
+pub struct MeshData<V: gfx::Vertex, I: gfx::IndexElement = u16> {
 	pub vertices: Vec<V>,
-	pub indices: Vec<u16>,
+	pub indices: Vec<I>,
 }
 
 
-impl<V: gfx::Vertex> MeshData<V> {
+impl<V: gfx::Vertex, I: gfx::IndexElement> MeshData<V, I> {
 	pub fn new() -> Self {
 		MeshData {
 			vertices: Vec::new(),
@@ -147,9 +152,9 @@ impl<V: gfx::Vertex> MeshData<V> {
 		self.indices.clear();
 	}
 
-	pub fn extend(&mut self, vs: impl IntoIterator<Item=V>, is: impl IntoIterator<Item=u16>) {
-		let index_start = self.vertices.len() as u16;
+	pub fn extend(&mut self, vs: impl IntoIterator<Item=V>, is: impl IntoIterator<Item=I>) {
+		let index_start = self.vertices.len();
 		self.vertices.extend(vs);
-		self.indices.extend(is.into_iter().map(|idx| index_start + idx));
+		self.indices.extend(is.into_iter().map(|idx| I::from_usize(index_start + idx.to_usize())));
 	}
 }
@@ -0,0 +1,409 @@
+use crate::prelude::*;
+use crate::gfx::vertex::{ColorVertex, TexturedVertex};
+use crate::gfx::mesh::MeshData;
+use std::ops::DerefMut;
+
+
+/// Implemented by vertex types that can be constructed from the layers of a [`toy::Mesh`].
+/// Implement this for custom vertex types to use them with [`ToyMeshBuilder`].
+pub trait ToyVertex: gfx::Vertex {
+	fn from_toy(source: &ToyVertexSource<'_>) -> Self;
+}
+
+
+/// Provides access to the data associated with a single vertex of a [`toy::Mesh`] while building
+/// [`ToyVertex`]s. Positions and normals have already been transformed into the builder's space.
+pub struct ToyVertexSource<'m> {
+	pub position: Vec3,
+	pub normal: Vec3,
+
+	mesh: &'m toy::Mesh,
+	index: usize,
+
+	color_data: Option<&'m [Vec4]>,
+	uv_data: Option<&'m [Vec2]>,
+	tangent: Vec4,
+}
+
+impl<'m> ToyVertexSource<'m> {
+	/// Index of this vertex within [`ToyVertexSource::mesh`].
+	pub fn index(&self) -> usize { self.index }
+
+	/// The mesh being added. If the builder generated normals this is a copy of the mesh passed to
+	/// [`ToyMeshBuilder::add_mesh`], which may have had its vertices split by [`toy::NormalMode::Flat`].
+	pub fn mesh(&self) -> &'m toy::Mesh { self.mesh }
+
+	/// Color from the builders selected color layer, or white if the mesh doesn't have it.
+	pub fn color(&self) -> Color {
+		self.color_data.and_then(|data| data.get(self.index))
+			.map_or(Color::white(), |&color| color.into())
+	}
+
+	/// UV from the builders selected uv layer, or zero if the mesh doesn't have it.
+	pub fn uv(&self) -> Vec2 {
+		self.uv_data.and_then(|data| data.get(self.index))
+			.copied()
+			.unwrap_or_else(Vec2::zero)
+	}
+
+	/// Tangent from [`toy::Mesh::tangents`] with the bitangent sign in `w`, transformed into the builder's space.
+	/// Zero if the mesh has no tangents.
+	pub fn tangent(&self) -> Vec4 { self.tangent }
+
+	/// Color from the color layer called `name`, or `None` if the mesh doesn't have it or it doesn't cover this vertex.
+	pub fn color_layer(&self, name: &str) -> Option<Color> {
+		self.mesh.color_layer_by_name(name)
+			.and_then(|layer| layer.data.get(self.index))
+			.map(|&color| color.into())
+	}
+
+	/// UV from the uv layer called `name`, or `None` if the mesh doesn't have it or it doesn't cover this vertex.
+	pub fn uv_layer(&self, name: &str) -> Option<Vec2> {
+		self.mesh.uv_layer_by_name(name)
+			.and_then(|layer| layer.data.get(self.index))
+			.copied()
+	}
+}
+
+
+impl ToyVertex for ColorVertex {
+	fn from_toy(source: &ToyVertexSource<'_>) -> Self {
+		ColorVertex::new(source.position, source.color())
+	}
+}
+
+impl ToyVertex for TexturedVertex {
+	fn from_toy(source: &ToyVertexSource<'_>) -> Self {
+		TexturedVertex {
+			pos: source.position,
+			normal: source.normal,
+			color: source.color(),
+			uv: source.uv(),
+		}
+	}
+}
+
+
+
+/// Given a [`MeshData`] for some vertex type implementing [`ToyVertex`], appends geometry from [`toy::Mesh`]es,
+/// entities and whole scenes.
+///
+/// Everything added is transformed by the builders current transform, and entities are additionally transformed
+/// by their own transforms - so whole scenes can be baked into a single static mesh.
+/// Baked scenes can easily exceed 65536 vertices, in which case a `MeshData<V, u32>` should be used.
+pub struct ToyMeshBuilder<MD> {
+	pub data: MD,
+
+	transform: Mat3x4,
+	normal_mode: Option<toy::NormalMode>,
+	color_layer: String,
+	uv_layer: Option<String>,
+}
+
+
+impl<MD> ToyMeshBuilder<MD> {
+	pub fn new(data: MD) -> Self {
+		ToyMeshBuilder {
+			data,
+
+			transform: Mat3x4::identity(),
+			normal_mode: None,
+			color_layer: toy::DEFAULT_COLOR_DATA_NAME.into(),
+			uv_layer: None,
+		}
+	}
+
+	pub fn set_transform(&mut self, transform: Mat3x4) {
+		self.transform = transform;
+	}
+
+	/// Regenerate normals for every mesh added with [`toy::Mesh::compute_normals`], rather than using the normals
+	/// stored in the mesh. Meshes exported without normals always get [`toy::NormalMode::Smooth`] normals.
+	pub fn set_normal_mode(&mut self, normal_mode: impl Into<Option<toy::NormalMode>>) {
+		self.normal_mode = normal_mode.into();
+	}
+
+	/// Select the color layer used by [`ToyVertexSource::color`]. Defaults to [`toy::DEFAULT_COLOR_DATA_NAME`].
+	pub fn set_color_layer(&mut self, name: impl Into<String>) {
+		self.color_layer = name.into();
+	}
+
+	/// Select the uv layer used by [`ToyVertexSource::uv`]. If unset, the first uv layer of each mesh is used.
+	pub fn set_uv_layer(&mut self, name: impl Into<String>) {
+		self.uv_layer = Some(name.into());
+	}
+}
+
+
+impl<MD, V, I> ToyMeshBuilder<MD>
+	where MD: DerefMut<Target=MeshData<V, I>>, V: ToyVertex, I: gfx::IndexElement
+{
+	/// Appends the triangles of `mesh`, transformed by the builder's current transform.
+	///
+	/// Fails without appending anything if the vertices wouldn't be addressable by the index type of the
+	/// [`MeshData`], or if normals need generating and the mesh's layers are inconsistent.
+	pub fn add_mesh(&mut self, mesh: &toy::Mesh) -> Result<(), ToyMeshError> {
+		let normal_mode = match self.normal_mode {
+			None if mesh.normals.len() != mesh.positions.len() => Some(toy::NormalMode::Smooth),
+			normal_mode => normal_mode,
+		};
+
+		// Generate normals on a copy, since flat normals need vertices split
+		let generated_mesh;
+		let mesh = match normal_mode {
+			Some(normal_mode) => {
+				generated_mesh = self.generate_normals(mesh, normal_mode)?;
+				&generated_mesh
+			}
+
+			None => mesh,
+		};
+
+		let data = self.data.deref_mut();
+		let index_start = data.vertices.len();
+		let required_vertices = index_start + mesh.positions.len();
+
+		if required_vertices > I::MAX_VERTICES {
+			return Err(ToyMeshError::TooManyVertices { required: required_vertices, max: I::MAX_VERTICES })
+		}
+
+		let color_data = mesh.color_layer_by_name(&self.color_layer)
+			.map(|layer| layer.data.as_slice());
+
+		let uv_layer = match &self.uv_layer {
+			Some(name) => mesh.uv_layer_by_name(name),
+			None => mesh.uv_layers.first(),
+		};
+
+		let uv_data = uv_layer.map(|layer| layer.data.as_slice());
+
+		// Directions ignore translation, and normals need the inverse transpose to stay perpendicular under
+		// non-uniform scale
+		let [x_axis, y_axis, z_axis, _] = self.transform.columns();
+		let [inv_x, inv_y, inv_z, _] = self.transform.inverse().columns();
+		let handedness = self.transform.determinant().signum();
+
+		data.vertices.extend((0..mesh.positions.len()).map(|index| {
+			let normal = mesh.normals.get(index)
+				.map_or_else(Vec3::zero, |&normal| normalize_or_zero(Vec3::new(inv_x.dot(normal), inv_y.dot(normal), inv_z.dot(normal))));
+
+			let tangent = mesh.tangents.get(index)
+				.map_or_else(Vec4::zero, |&tangent| {
+					let direction = x_axis * tangent.x + y_axis * tangent.y + z_axis * tangent.z;
+					normalize_or_zero(direction).extend(tangent.w * handedness)
+				});
+
+			V::from_toy(&ToyVertexSource {
+				position: self.transform * mesh.positions[index],
+				normal,
+				mesh,
+				index,
+				color_data,
+				uv_data,
+				tangent,
+			})
+		}));
+
+		// Mirroring transforms turn triangles inside out, so flip them back to keep front faces and normals facing out
+		data.indices.extend(mesh.triangles()
+			.flat_map(|[a, b, c]| if handedness < 0.0 { [a, c, b] } else { [a, b, c] })
+			.map(|index| I::from_usize(index_start + index as usize)));
+
+		Ok(())
+	}
+
+	fn generate_normals(&self, mesh: &toy::Mesh, normal_mode: toy::NormalMode) -> Result<toy::Mesh, ToyMeshError> {
+		let mut mesh = mesh.clone();
+		let had_tangents = !mesh.tangents.is_empty();
+
+		mesh.compute_normals(normal_mode)
+			.map_err(|error| ToyMeshError::InvalidMesh(error.to_string()))?;
+
+		// compute_normals drops tangents, since they depend on the normals
+		if had_tangents {
+			mesh.compute_tangents(self.uv_layer.as_deref())
+				.map_err(|error| ToyMeshError::InvalidMesh(error.to_string()))?;
+		}
+
+		Ok(mesh)
+	}
+
+	/// Appends the mesh of `entity` if it has one, transformed by the entities transform.
+	pub fn add_entity(&mut self, entity: toy::EntityRef<'_>) -> Result<(), ToyMeshError> {
+		let Some(mesh) = entity.mesh() else {
+			return Ok(())
+		};
+
+		let prev_transform = self.transform;
+		self.transform = prev_transform * entity.transform();
+		let result = self.add_mesh(mesh);
+		self.transform = prev_transform;
+		result
+	}
+
+	/// Appends the meshes of all entities in `scene`, stopping at the first that fails.
+	pub fn add_scene(&mut self, scene: toy::SceneRef<'_>) -> Result<(), ToyMeshError> {
+		for entity in scene.entities() {
+			self.add_entity(entity)?;
+		}
+
+		Ok(())
+	}
+}
+
+
+
+#[derive(Debug)]
+pub enum ToyMeshError {
+	/// The [`MeshData`] would need more vertices than its index type can address.
+	TooManyVertices {
+		required: usize,
+		max: usize,
+	},
+
+	/// Normals or tangents couldn't be generated because the mesh's layers are inconsistent.
+	/// See [`toy::Mesh::check_vertex_data`].
+	InvalidMesh(String),
+}
+
+impl std::fmt::Display for ToyMeshError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ToyMeshError::TooManyVertices{required, max} =>
+				write!(f, "Mesh needs {required} vertices, but the index type can only address {max}"),
+
+			ToyMeshError::InvalidMesh(reason) => write!(f, "Invalid mesh: {reason}"),
+		}
+	}
+}
+
+impl std::error::Error for ToyMeshError {}
+
+
+fn normalize_or_zero(v: Vec3) -> Vec3 {
+	let length = v.length();
+	if length > 0.0 {
+		v / length
+	} else {
+		Vec3::zero()
+	}
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use common::assert_vec_eq;
+	use super::*;
+
+	fn triangle_mesh() -> toy::Mesh {
+		toy::Mesh {
+			positions: vec![Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+			indices: vec![0, 1, 2],
+			normals: Vec::new(),
+			tangents: Vec::new(),
+			color_layers: Vec::new(),
+			uv_layers: Vec::new(),
+			materials: Vec::new(),
+			material_indices: Vec::new(),
+			animation_data: None,
+		}
+	}
+
+	#[test]
+	fn mirroring_flips_winding() {
+		let mesh = triangle_mesh();
+		let mirror = Mat3x4::scale(Vec3::new(-1.0, 1.0, 1.0));
+
+		let mut data = MeshData::<TexturedVertex>::new();
+		let mut builder = ToyMeshBuilder::new(&mut data);
+		builder.set_transform(mirror);
+		builder.set_normal_mode(toy::NormalMode::Smooth);
+		builder.add_mesh(&mesh).unwrap();
+
+		assert_eq!(data.indices, [0, 2, 1]);
+		for vertex in data.vertices.iter() {
+			assert_vec_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+		}
+
+		let mut data = MeshData::<TexturedVertex>::new();
+		let mut builder = ToyMeshBuilder::new(&mut data);
+		builder.set_transform(mirror);
+		builder.set_normal_mode(toy::NormalMode::Flat);
+		builder.add_mesh(&mesh).unwrap();
+
+		assert_eq!(data.indices, [0, 2, 1]);
+		assert_vec_eq!(data.vertices[1].pos, Vec3::new(-1.0, 0.0, 0.0));
+		for vertex in data.vertices.iter() {
+			assert_vec_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+		}
+	}
+
+	#[test]
+	fn mesh_normals_stay_perpendicular() {
+		let mut mesh = triangle_mesh();
+		mesh.normals = vec![Vec3::new(1.0, 1.0, 0.0).normalize(); 3];
+
+		let mut data = MeshData::<TexturedVertex>::new();
+		let mut builder = ToyMeshBuilder::new(&mut data);
+		builder.set_transform(Mat3x4::scale(Vec3::new(-2.0, 1.0, 1.0)));
+		builder.add_mesh(&mesh).unwrap();
+
+		// The surface x + y = 0 becomes -x/2 + y = 0
+		assert_vec_eq!(data.vertices[0].normal, Vec3::new(-1.0, 2.0, 0.0).normalize());
+	}
+
+	#[test]
+	fn missing_normals_are_generated() {
+		let mut data = MeshData::<TexturedVertex>::new();
+		ToyMeshBuilder::new(&mut data).add_mesh(&triangle_mesh()).unwrap();
+
+		for vertex in data.vertices.iter() {
+			assert_vec_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+		}
+	}
+
+	#[test]
+	fn too_many_vertices() {
+		let mut mesh = triangle_mesh();
+		let num_vertices = u16::MAX as usize + 2;
+		mesh.positions.resize(num_vertices, Vec3::zero());
+		mesh.normals.resize(num_vertices, Vec3::zero());
+
+		let mut data = MeshData::<ColorVertex>::new();
+		let result = ToyMeshBuilder::new(&mut data).add_mesh(&mesh);
+
+		assert!(matches!(result, Err(ToyMeshError::TooManyVertices { required, .. }) if required == num_vertices));
+		assert!(data.vertices.is_empty() && data.indices.is_empty());
+
+		let mut data = MeshData::<ColorVertex, u32>::new();
+		ToyMeshBuilder::new(&mut data).add_mesh(&mesh).unwrap();
+		assert_eq!(data.vertices.len(), num_vertices);
+	}
+
+	#[test]
+	fn short_layers_use_defaults() {
+		let mut mesh = triangle_mesh();
+		mesh.color_layers.push(toy::MeshColorLayer { name: toy::DEFAULT_COLOR_DATA_NAME.into(), data: vec![Vec4::zero()] });
+		mesh.uv_layers.push(toy::MeshUvLayer { name: "UVMap".into(), data: vec![Vec2::splat(0.5)] });
+
+		let source = ToyVertexSource {
+			position: Vec3::zero(),
+			normal: Vec3::zero(),
+			mesh: &mesh,
+			index: 2,
+			color_data: Some(&mesh.color_layers[0].data),
+			uv_data: Some(&mesh.uv_layers[0].data),
+			tangent: Vec4::zero(),
+		};
+
+		assert_vec_eq!(source.color().to_vec4(), Color::white().to_vec4());
+		assert_vec_eq!(source.uv(), Vec2::zero());
+		assert!(source.color_layer(toy::DEFAULT_COLOR_DATA_NAME).is_none());
+		assert!(source.uv_layer("UVMap").is_none());
+
+		let first = ToyVertexSource { index: 0, ..source };
+		assert_vec_eq!(first.color_layer(toy::DEFAULT_COLOR_DATA_NAME).unwrap().to_vec4(), Vec4::zero());
+		assert_vec_eq!(first.uv_layer("UVMap").unwrap(), Vec2::splat(0.5));
+	}
+}
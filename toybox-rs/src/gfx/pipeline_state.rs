@@ -0,0 +1,500 @@
+use crate::prelude::*;
+use crate::gfx::raw;
+
+
+/// Fixed function state used by draw calls - blending, depth and stencil testing, color writes, culling and
+/// polygon mode.
+///
+/// Set through [`DrawContext::set_pipeline_state`](crate::gfx::DrawContext::set_pipeline_state), or piecemeal
+/// through the other `set_*` calls on [`DrawContext`](crate::gfx::DrawContext).
+/// State is reset to [`PipelineState::default`] at the start of every frame, so passes that need something
+/// different should set it up before drawing rather than relying on what the previous pass left behind.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineState {
+	/// `None` disables blending.
+	pub blend: Option<BlendMode>,
+
+	/// The color used by [`BlendFactor::ConstantColor`] and [`BlendFactor::ConstantAlpha`].
+	pub blend_constant: Color,
+
+	pub depth: DepthState,
+
+	/// `None` disables stencil testing and writes.
+	pub stencil: Option<StencilState>,
+
+	pub color_write_mask: ColorWriteMask,
+
+	pub backface_culling: bool,
+	pub wireframe: bool,
+}
+
+impl Default for PipelineState {
+	/// Opaque geometry - no blending, depth tested and written, no stencil and back faces culled.
+	fn default() -> PipelineState {
+		PipelineState {
+			blend: None,
+			blend_constant: Color::black(),
+			depth: DepthState::default(),
+			stencil: None,
+			color_write_mask: ColorWriteMask::ALL,
+			backface_culling: true,
+			wireframe: false,
+		}
+	}
+}
+
+impl PipelineState {
+	/// Alpha blended geometry drawn over opaque geometry - depth tested but not written, and not culled.
+	pub fn transparent(blend: BlendMode) -> PipelineState {
+		PipelineState {
+			blend: Some(blend),
+			depth: DepthState::read_only(),
+			backface_culling: false,
+			..PipelineState::default()
+		}
+	}
+
+	/// Screen space overlays - alpha blended, without depth testing or culling.
+	pub fn overlay() -> PipelineState {
+		PipelineState {
+			blend: Some(BlendMode::ALPHA),
+			depth: DepthState::disabled(),
+			backface_culling: false,
+			..PipelineState::default()
+		}
+	}
+}
+
+
+
+/// How the output of a fragment shader is combined with what is already in the framebuffer.
+/// Each channel is calculated as `equation(source * source_factor, destination * destination_factor)`,
+/// with color and alpha configured separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendMode {
+	pub color_equation: BlendEquation,
+	pub src_color: BlendFactor,
+	pub dst_color: BlendFactor,
+
+	pub alpha_equation: BlendEquation,
+	pub src_alpha: BlendFactor,
+	pub dst_alpha: BlendFactor,
+}
+
+impl BlendMode {
+	/// Standard transparency for non-premultiplied colors.
+	/// Destination alpha accumulates coverage, so render targets composited later stay correct.
+	pub const ALPHA: BlendMode = BlendMode::separate(
+		BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha,
+		BlendFactor::One, BlendFactor::OneMinusSrcAlpha,
+	);
+
+	/// Transparency for colors that have already been multiplied by their alpha.
+	pub const PREMULTIPLIED_ALPHA: BlendMode = BlendMode::new(BlendFactor::One, BlendFactor::OneMinusSrcAlpha);
+
+	/// Adds color scaled by alpha, e.g., for particles and glows. Destination alpha is left unchanged.
+	pub const ADDITIVE: BlendMode = BlendMode::separate(
+		BlendFactor::SrcAlpha, BlendFactor::One,
+		BlendFactor::Zero, BlendFactor::One,
+	);
+
+	/// Multiplies the destination by the source color, e.g., for shadows and tinting.
+	pub const MULTIPLY: BlendMode = BlendMode::new(BlendFactor::DstColor, BlendFactor::Zero);
+
+	/// Uses the same factors for color and alpha, with [`BlendEquation::Add`].
+	pub const fn new(src: BlendFactor, dst: BlendFactor) -> BlendMode {
+		BlendMode::separate(src, dst, src, dst)
+	}
+
+	/// Uses separate factors for color and alpha, with [`BlendEquation::Add`].
+	pub const fn separate(src_color: BlendFactor, dst_color: BlendFactor, src_alpha: BlendFactor, dst_alpha: BlendFactor) -> BlendMode {
+		BlendMode {
+			color_equation: BlendEquation::Add,
+			src_color,
+			dst_color,
+
+			alpha_equation: BlendEquation::Add,
+			src_alpha,
+			dst_alpha,
+		}
+	}
+
+	/// Uses `equation` for both color and alpha.
+	pub const fn with_equation(self, equation: BlendEquation) -> BlendMode {
+		BlendMode {
+			color_equation: equation,
+			alpha_equation: equation,
+			..self
+		}
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+	Zero,
+	One,
+	SrcColor,
+	OneMinusSrcColor,
+	DstColor,
+	OneMinusDstColor,
+	SrcAlpha,
+	OneMinusSrcAlpha,
+	DstAlpha,
+	OneMinusDstAlpha,
+	ConstantColor,
+	OneMinusConstantColor,
+	ConstantAlpha,
+	OneMinusConstantAlpha,
+	SrcAlphaSaturate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendEquation {
+	Add,
+
+	/// Source minus destination.
+	Subtract,
+
+	/// Destination minus source.
+	ReverseSubtract,
+
+	/// Ignores blend factors.
+	Min,
+
+	/// Ignores blend factors.
+	Max,
+}
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthState {
+	/// Whether fragments are discarded when they fail `compare`.
+	pub test: bool,
+
+	/// Whether fragments that pass write their depth.
+	pub write: bool,
+
+	/// Compares incoming depth against the depth buffer. Fragments pass if this returns true.
+	pub compare: CompareFunction,
+}
+
+impl Default for DepthState {
+	fn default() -> DepthState {
+		DepthState {
+			test: true,
+			write: true,
+			compare: CompareFunction::Less,
+		}
+	}
+}
+
+impl DepthState {
+	pub fn disabled() -> DepthState {
+		DepthState { test: false, write: false, ..DepthState::default() }
+	}
+
+	/// Depth tested, but not written, e.g., for transparent geometry.
+	pub fn read_only() -> DepthState {
+		DepthState { write: false, ..DepthState::default() }
+	}
+
+	pub fn with_compare(self, compare: CompareFunction) -> DepthState {
+		DepthState { compare, ..self }
+	}
+}
+
+
+/// Compares an incoming value (the fragment's depth or the stencil reference) against the value stored in the
+/// framebuffer, in that order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareFunction {
+	Never,
+	Less,
+	Equal,
+	LessEqual,
+	Greater,
+	NotEqual,
+	GreaterEqual,
+	Always,
+}
+
+
+
+/// Stencil testing and writes. Requires a framebuffer with a stencil attachment - the backbuffer has one.
+///
+/// The test compares `reference & read_mask` against `stencil & read_mask`, and only bits in `write_mask` are
+/// modified by [`StencilOp`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StencilState {
+	pub front: StencilFaceState,
+	pub back: StencilFaceState,
+
+	pub reference: u8,
+	pub read_mask: u8,
+	pub write_mask: u8,
+}
+
+/// Stencil behaviour for triangles facing a single direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StencilFaceState {
+	pub compare: CompareFunction,
+
+	/// Applied when the stencil test fails.
+	pub fail: StencilOp,
+
+	/// Applied when the stencil test passes but the depth test fails.
+	pub depth_fail: StencilOp,
+
+	/// Applied when both stencil and depth tests pass.
+	pub pass: StencilOp,
+}
+
+impl StencilState {
+	/// Uses `face` for both front and back faces, with full masks.
+	pub fn new(face: StencilFaceState, reference: u8) -> StencilState {
+		StencilState {
+			front: face,
+			back: face,
+			reference,
+			read_mask: 0xff,
+			write_mask: 0xff,
+		}
+	}
+
+	/// Writes `reference` wherever anything is drawn, e.g., for marking out a mask.
+	pub fn write(reference: u8) -> StencilState {
+		StencilState::new(StencilFaceState {
+			compare: CompareFunction::Always,
+			fail: StencilOp::Keep,
+			depth_fail: StencilOp::Keep,
+			pass: StencilOp::Replace,
+		}, reference)
+	}
+
+	/// Only draws where `compare(reference, stencil)` is true, leaving the stencil buffer unchanged.
+	/// e.g., `StencilState::test(CompareFunction::Equal, 1)` only draws within a mask written with [`StencilState::write`].
+	pub fn test(compare: CompareFunction, reference: u8) -> StencilState {
+		StencilState::new(StencilFaceState {
+			compare,
+			fail: StencilOp::Keep,
+			depth_fail: StencilOp::Keep,
+			pass: StencilOp::Keep,
+		}, reference)
+	}
+
+	pub fn with_masks(self, read_mask: u8, write_mask: u8) -> StencilState {
+		StencilState { read_mask, write_mask, ..self }
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StencilOp {
+	Keep,
+	Zero,
+	Replace,
+
+	/// Increments, clamping at 255.
+	Increment,
+	IncrementWrap,
+
+	/// Decrements, clamping at 0.
+	Decrement,
+	DecrementWrap,
+	Invert,
+}
+
+
+
+bitflags::bitflags! {
+	/// Which channels of color attachments are written by draw calls.
+	/// Unlike raw GL, [`DrawContext::clear`](crate::gfx::DrawContext::clear) ignores this.
+	pub struct ColorWriteMask : u32 {
+		const RED = 0b0001;
+		const GREEN = 0b0010;
+		const BLUE = 0b0100;
+		const ALPHA = 0b1000;
+
+		const COLOR = 0b0111;
+		const ALL = 0b1111;
+		const NONE = 0b0000;
+	}
+}
+
+
+
+pub(super) fn apply_blend(blend: Option<BlendMode>) {
+	unsafe {
+		let Some(blend) = blend else {
+			raw::Disable(raw::BLEND);
+			return
+		};
+
+		raw::Enable(raw::BLEND);
+		raw::BlendEquationSeparate(blend.color_equation.into_gl(), blend.alpha_equation.into_gl());
+		raw::BlendFuncSeparate(
+			blend.src_color.into_gl(),
+			blend.dst_color.into_gl(),
+			blend.src_alpha.into_gl(),
+			blend.dst_alpha.into_gl(),
+		);
+	}
+}
+
+pub(super) fn apply_blend_constant(color: Color) {
+	let (r, g, b, a) = color.to_tuple();
+
+	unsafe {
+		raw::BlendColor(r, g, b, a);
+	}
+}
+
+pub(super) fn apply_depth(depth: DepthState) {
+	unsafe {
+		// GL only writes depth while the depth test is enabled, so writes without testing need an always passing test
+		if !depth.test && !depth.write {
+			raw::Disable(raw::DEPTH_TEST);
+		} else {
+			raw::Enable(raw::DEPTH_TEST);
+		}
+
+		let compare = match depth.test {
+			true => depth.compare,
+			false => CompareFunction::Always,
+		};
+
+		raw::DepthFunc(compare.into_gl());
+		raw::DepthMask(depth.write as u8);
+	}
+}
+
+pub(super) fn apply_stencil(stencil: Option<StencilState>) {
+	unsafe {
+		// The write mask still applies to clears while the stencil test is disabled
+		let Some(stencil) = stencil else {
+			raw::Disable(raw::STENCIL_TEST);
+			raw::StencilMask(0xff);
+			return
+		};
+
+		raw::Enable(raw::STENCIL_TEST);
+
+		for (gl_face, face) in [(raw::FRONT, stencil.front), (raw::BACK, stencil.back)] {
+			raw::StencilFuncSeparate(gl_face, face.compare.into_gl(), stencil.reference as i32, stencil.read_mask as u32);
+			raw::StencilOpSeparate(gl_face, face.fail.into_gl(), face.depth_fail.into_gl(), face.pass.into_gl());
+		}
+
+		raw::StencilMask(stencil.write_mask as u32);
+	}
+}
+
+pub(super) fn apply_color_write_mask(mask: ColorWriteMask) {
+	unsafe {
+		raw::ColorMask(
+			mask.contains(ColorWriteMask::RED) as u8,
+			mask.contains(ColorWriteMask::GREEN) as u8,
+			mask.contains(ColorWriteMask::BLUE) as u8,
+			mask.contains(ColorWriteMask::ALPHA) as u8,
+		);
+	}
+}
+
+pub(super) fn apply_backface_culling(culling_enabled: bool) {
+	unsafe {
+		match culling_enabled {
+			true => raw::Enable(raw::CULL_FACE),
+			false => raw::Disable(raw::CULL_FACE),
+		}
+	}
+}
+
+pub(super) fn apply_wireframe(wireframe_enabled: bool) {
+	let mode = match wireframe_enabled {
+		false => raw::FILL,
+		true => raw::LINE,
+	};
+
+	unsafe {
+		raw::PolygonMode(raw::FRONT_AND_BACK, mode);
+	}
+}
+
+impl PipelineState {
+	pub(super) fn apply(&self) {
+		apply_blend(self.blend);
+		apply_blend_constant(self.blend_constant);
+		apply_depth(self.depth);
+		apply_stencil(self.stencil);
+		apply_color_write_mask(self.color_write_mask);
+		apply_backface_culling(self.backface_culling);
+		apply_wireframe(self.wireframe);
+	}
+}
+
+
+
+impl BlendFactor {
+	fn into_gl(self) -> u32 {
+		match self {
+			BlendFactor::Zero => raw::ZERO,
+			BlendFactor::One => raw::ONE,
+			BlendFactor::SrcColor => raw::SRC_COLOR,
+			BlendFactor::OneMinusSrcColor => raw::ONE_MINUS_SRC_COLOR,
+			BlendFactor::DstColor => raw::DST_COLOR,
+			BlendFactor::OneMinusDstColor => raw::ONE_MINUS_DST_COLOR,
+			BlendFactor::SrcAlpha => raw::SRC_ALPHA,
+			BlendFactor::OneMinusSrcAlpha => raw::ONE_MINUS_SRC_ALPHA,
+			BlendFactor::DstAlpha => raw::DST_ALPHA,
+			BlendFactor::OneMinusDstAlpha => raw::ONE_MINUS_DST_ALPHA,
+			BlendFactor::ConstantColor => raw::CONSTANT_COLOR,
+			BlendFactor::OneMinusConstantColor => raw::ONE_MINUS_CONSTANT_COLOR,
+			BlendFactor::ConstantAlpha => raw::CONSTANT_ALPHA,
+			BlendFactor::OneMinusConstantAlpha => raw::ONE_MINUS_CONSTANT_ALPHA,
+			BlendFactor::SrcAlphaSaturate => raw::SRC_ALPHA_SATURATE,
+		}
+	}
+}
+
+impl BlendEquation {
+	fn into_gl(self) -> u32 {
+		match self {
+			BlendEquation::Add => raw::FUNC_ADD,
+			BlendEquation::Subtract => raw::FUNC_SUBTRACT,
+			BlendEquation::ReverseSubtract => raw::FUNC_REVERSE_SUBTRACT,
+			BlendEquation::Min => raw::MIN,
+			BlendEquation::Max => raw::MAX,
+		}
+	}
+}
+
+impl CompareFunction {
+	pub(super) fn into_gl(self) -> u32 {
+		match self {
+			CompareFunction::Never => raw::NEVER,
+			CompareFunction::Less => raw::LESS,
+			CompareFunction::Equal => raw::EQUAL,
+			CompareFunction::LessEqual => raw::LEQUAL,
+			CompareFunction::Greater => raw::GREATER,
+			CompareFunction::NotEqual => raw::NOTEQUAL,
+			CompareFunction::GreaterEqual => raw::GEQUAL,
+			CompareFunction::Always => raw::ALWAYS,
+		}
+	}
+}
+
+impl StencilOp {
+	fn into_gl(self) -> u32 {
+		match self {
+			StencilOp::Keep => raw::KEEP,
+			StencilOp::Zero => raw::ZERO,
+			StencilOp::Replace => raw::REPLACE,
+			StencilOp::Increment => raw::INCR,
+			StencilOp::IncrementWrap => raw::INCR_WRAP,
+			StencilOp::Decrement => raw::DECR,
+			StencilOp::DecrementWrap => raw::DECR_WRAP,
+			StencilOp::Invert => raw::INVERT,
+		}
+	}
+}
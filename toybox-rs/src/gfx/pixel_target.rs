@@ -0,0 +1,202 @@
+use crate::prelude::*;
+use crate::gfx::*;
+
+
+/// How a [`VirtualScreen`] is scaled up to fill its output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UpscaleMode {
+	/// Scale by the largest whole number that fits, and letterbox the rest.
+	/// Every virtual pixel covers exactly the same number of output pixels.
+	/// Falls back to [`UpscaleMode::SharpBilinear`] if the output is smaller than the virtual resolution.
+	Integer,
+
+	/// Scale to fit as much of the output as possible while preserving aspect, and letterbox the rest.
+	/// Pixel edges are blended over at most one output pixel, so pixels stay crisp but may differ in size by one.
+	SharpBilinear,
+}
+
+
+/// A fixed virtual resolution, and how to fit it into some output - usually the backbuffer.
+///
+/// Shared between [`PixelTarget`] which renders at this resolution and presents the result,
+/// and [`MouseSpace::Virtual`](crate::input::MouseSpace::Virtual) which maps mouse input back into virtual pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VirtualScreen {
+	pub size: Vec2i,
+	pub upscale_mode: UpscaleMode,
+}
+
+impl VirtualScreen {
+	pub fn new(size: Vec2i, upscale_mode: UpscaleMode) -> VirtualScreen {
+		assert!(size.x > 0 && size.y > 0, "VirtualScreen must have non-zero size");
+		VirtualScreen { size, upscale_mode }
+	}
+
+	/// How many output pixels each virtual pixel covers along each axis.
+	pub fn scale(&self, output_size: Vec2i) -> f32 {
+		let Vec2{x, y} = output_size.to_vec2() / self.size.to_vec2();
+		let fit = x.min(y);
+
+		match self.upscale_mode {
+			UpscaleMode::Integer if fit >= 1.0 => fit.floor(),
+			_ => fit,
+		}
+	}
+
+	/// The region of the output covered by the scaled up image, centered and in output pixels.
+	/// Origin is in the bottom left, as with [`DrawContext::set_viewport`].
+	pub fn presentation_rect(&self, output_size: Vec2i) -> Aabb2i {
+		let scaled_size = (self.size.to_vec2() * self.scale(output_size)).to_vec2i();
+		let min = (output_size - scaled_size) / 2;
+		Aabb2i::from_min_point(min, scaled_size)
+	}
+
+	/// Maps a position in output pixels into virtual pixels.
+	/// Both have their origin in the top left with y expanding down, as with [`MouseSpace::Window`](crate::input::MouseSpace::Window).
+	/// Positions in the letterbox map to outside of `[0, size)`.
+	pub fn output_to_virtual(&self, output_pos: Vec2, output_size: Vec2i) -> Vec2 {
+		let rect = self.presentation_rect(output_size);
+		let scale = rect.size().to_vec2() / self.size.to_vec2();
+
+		// presentation_rect is bottom left origin, so flip into and back out of that.
+		let output_pos_bl = Vec2::new(output_pos.x, output_size.y as f32 - output_pos.y);
+		let virtual_pos_bl = (output_pos_bl - rect.min.to_vec2()) / scale;
+
+		Vec2::new(virtual_pos_bl.x, self.size.y as f32 - virtual_pos_bl.y)
+	}
+
+	/// Maps a change in position in output pixels into virtual pixels.
+	pub fn output_to_virtual_delta(&self, output_delta: Vec2, output_size: Vec2i) -> Vec2 {
+		let scale = self.presentation_rect(output_size).size().to_vec2() / self.size.to_vec2();
+		output_delta / scale
+	}
+}
+
+
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct PresentUniforms {
+	letterbox_color: Vec4,
+	image_min: Vec2,
+	image_size: Vec2,
+	texture_size: Vec2,
+	scale: f32,
+	_pad: f32,
+}
+
+
+/// A framebuffer with a fixed, usually low, resolution that is presented scaled up to fill the backbuffer,
+/// for pixel art rendering.
+///
+/// ```ignore
+/// pixel_target.bind(&mut gfx);
+/// gfx.clear(gfx::ClearMode::ALL);
+/// draw_world(&mut gfx);
+///
+/// pixel_target.present(&mut gfx);
+/// ```
+///
+/// Mouse input can be mapped into the same pixels with [`MouseSpace::Virtual`](crate::input::MouseSpace::Virtual),
+/// given the same [`VirtualScreen`].
+pub struct PixelTarget {
+	framebuffer: FramebufferKey,
+	screen: VirtualScreen,
+
+	/// Fills the parts of the backbuffer not covered by the image. Black by default.
+	pub letterbox_color: Color,
+
+	shader: Shader,
+	vao: Vao,
+	uniforms: Buffer<PresentUniforms>,
+}
+
+impl PixelTarget {
+	/// Creates a target with an sRGB color attachment and a depth attachment.
+	pub fn new(gfx: &mut ResourceContext<'_>, screen: VirtualScreen) -> Result<PixelTarget, shader::CompilationError> {
+		let settings = FramebufferSettings::new(TextureSize::Fixed(screen.size))
+			.add_depth()
+			.add_color(0, TextureFormat::srgba());
+
+		PixelTarget::with_settings(gfx, screen, settings)
+	}
+
+	/// Creates a target with custom attachments. `settings` must have a [`TextureSize::Fixed`] size matching
+	/// `screen`, and color attachment 0 is what gets presented.
+	pub fn with_settings(gfx: &mut ResourceContext<'_>, screen: VirtualScreen, settings: FramebufferSettings)
+		-> Result<PixelTarget, shader::CompilationError>
+	{
+		let shader = gfx.new_simple_shader(
+			include_str!("shaders/pixel_target.vert.glsl"),
+			include_str!("shaders/pixel_target.frag.glsl"),
+		)?;
+
+		let framebuffer = gfx.new_framebuffer(settings);
+		assert_eq!(gfx.resources.get(framebuffer).size(), screen.size, "PixelTarget framebuffer size doesn't match VirtualScreen");
+
+		let color_attachment = framebuffer.color_attachment(0);
+		let color_attachment = color_attachment.into_texture_key(gfx.resources);
+
+		// Sharp bilinear relies on linear filtering, and is exact at integer scales.
+		let sampler = gfx.get_sampler(SamplerDesc::linear());
+		gfx.resources.get_mut(color_attachment).set_sampler(sampler);
+
+		Ok(PixelTarget {
+			framebuffer,
+			screen,
+			letterbox_color: Color::black(),
+
+			shader,
+			vao: gfx.new_vao(),
+			uniforms: gfx.new_buffer(BufferUsage::Stream),
+		})
+	}
+
+	pub fn framebuffer(&self) -> FramebufferKey { self.framebuffer }
+	pub fn screen(&self) -> VirtualScreen { self.screen }
+	pub fn size(&self) -> Vec2i { self.screen.size }
+
+	pub fn set_upscale_mode(&mut self, upscale_mode: UpscaleMode) {
+		self.screen.upscale_mode = upscale_mode;
+	}
+
+	/// Binds the target for drawing. The viewport will cover the whole virtual resolution.
+	pub fn bind(&self, gfx: &mut DrawContext<'_>) {
+		gfx.bind_framebuffer(self.framebuffer);
+	}
+
+	/// Binds the backbuffer and draws the contents of the target into it, scaled and letterboxed according
+	/// to the [`UpscaleMode`]. Covers the whole backbuffer, so there is no need to clear it first.
+	/// Pipeline state is left as it was.
+	pub fn present(&mut self, gfx: &mut DrawContext<'_>) {
+		gfx.bind_framebuffer(None);
+
+		let output_size = gfx.backbuffer_size();
+		let rect = self.screen.presentation_rect(output_size);
+
+		self.uniforms.upload_single(&PresentUniforms {
+			letterbox_color: self.letterbox_color.to_vec4(),
+			image_min: rect.min.to_vec2(),
+			image_size: rect.size().to_vec2(),
+			texture_size: self.screen.size.to_vec2(),
+			scale: self.screen.scale(output_size),
+			_pad: 0.0,
+		});
+
+		let previous_state = *gfx.pipeline_state();
+
+		gfx.set_pipeline_state(PipelineState {
+			depth: DepthState::disabled(),
+			backface_culling: false,
+			..PipelineState::default()
+		});
+
+		gfx.bind_shader(self.shader);
+		gfx.bind_uniform_buffer(0, self.uniforms);
+		gfx.bind_texture(0, self.framebuffer.color_attachment(0));
+		gfx.bind_vao(self.vao);
+		gfx.draw_arrays(DrawMode::Triangles, 3);
+
+		gfx.set_pipeline_state(previous_state);
+	}
+}
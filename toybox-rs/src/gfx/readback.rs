@@ -0,0 +1,181 @@
+use crate::prelude::*;
+use crate::gfx::*;
+
+use std::collections::VecDeque;
+
+
+/// Reads back the backbuffer or textures without stalling, by copying into pixel buffer objects and only
+/// reading those once the gpu has finished with them - usually a frame or two later.
+/// Suitable for capturing every frame, e.g., for video.
+///
+/// Each readback carries a `T` which is handed back with the image by [`ReadbackQueue::poll`],
+/// e.g., a frame number or a path to save to.
+///
+/// ## Note
+/// Like [`Buffer`], this is not an RAII type. Buffers are cleaned up with the resource scope the queue was
+/// created in, but fences for readbacks that are never polled are leaked.
+pub struct ReadbackQueue<T = ()> {
+	free: Vec<PixelBuffer>,
+	pending: VecDeque<PendingReadback<T>>,
+	ready: VecDeque<(T, image::RgbaImage)>,
+}
+
+struct PixelBuffer {
+	handle: u32,
+	capacity: usize,
+}
+
+struct PendingReadback<T> {
+	buffer: PixelBuffer,
+	fence: raw::types::GLsync,
+	size: Vec2i,
+	tag: T,
+}
+
+impl<T> ReadbackQueue<T> {
+	/// At most `max_in_flight` readbacks can be waiting on the gpu at once. Requesting more than that
+	/// will wait on the oldest one to make space.
+	pub fn new(gfx: &mut ResourceContext<'_>, max_in_flight: usize) -> ReadbackQueue<T> {
+		assert!(max_in_flight > 0, "ReadbackQueue needs at least one buffer");
+
+		let free = (0..max_in_flight)
+			.map(|_| PixelBuffer {
+				handle: gfx.new_buffer::<u8>(BufferUsage::Stream).handle,
+				capacity: 0,
+			})
+			.collect();
+
+		ReadbackQueue {
+			free,
+			pending: VecDeque::new(),
+			ready: VecDeque::new(),
+		}
+	}
+
+	/// Starts reading back the current contents of the backbuffer.
+	/// Should be called before the window is swapped.
+	pub fn read_backbuffer(&mut self, gfx: &mut DrawContext<'_>, tag: T) {
+		let size = gfx.backbuffer_size();
+		let buffer = self.acquire_buffer(size);
+
+		unsafe {
+			raw::BindFramebuffer(raw::READ_FRAMEBUFFER, 0);
+			raw::BindBuffer(raw::PIXEL_PACK_BUFFER, buffer.handle);
+			raw::ReadPixels(0, 0, size.x, size.y, raw::RGBA, raw::UNSIGNED_BYTE, std::ptr::null_mut());
+			raw::BindBuffer(raw::PIXEL_PACK_BUFFER, 0);
+		}
+
+		self.submit(buffer, size, tag);
+	}
+
+	/// Starts reading back the contents of a color texture as RGBA8, as with [`Texture::download_rgba8`].
+	pub fn read_texture(&mut self, gfx: &mut DrawContext<'_>, texture: impl IntoTextureKey, tag: T) {
+		let texture_key = texture.into_texture_key(gfx.resources());
+		let texture = gfx.resources().get(texture_key);
+		texture.assert_color_readable();
+
+		let size = texture.size();
+		let buffer = self.acquire_buffer(size);
+
+		unsafe {
+			raw::BindBuffer(raw::PIXEL_PACK_BUFFER, buffer.handle);
+			raw::GetTextureImage(texture.texture_handle, 0, raw::RGBA, raw::UNSIGNED_BYTE,
+				buffer.capacity as i32, std::ptr::null_mut());
+			raw::BindBuffer(raw::PIXEL_PACK_BUFFER, 0);
+		}
+
+		self.submit(buffer, size, tag);
+	}
+
+	/// Takes the oldest finished readback, if there is one. Never waits on the gpu.
+	/// Images are flipped so that the first row is the top of the image.
+	pub fn poll(&mut self) -> Option<(T, image::RgbaImage)> {
+		if self.ready.is_empty() && self.pending.front().map_or(false, |readback| is_signalled(readback.fence)) {
+			self.finish_oldest();
+		}
+
+		self.ready.pop_front()
+	}
+
+	/// Waits for every readback in flight to finish, and returns all of them, oldest first.
+	pub fn flush(&mut self) -> Vec<(T, image::RgbaImage)> {
+		while !self.pending.is_empty() {
+			self.finish_oldest();
+		}
+
+		self.ready.drain(..).collect()
+	}
+
+	/// How many readbacks have been requested but not yet returned by [`ReadbackQueue::poll`].
+	pub fn len(&self) -> usize {
+		self.pending.len() + self.ready.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+impl<T> ReadbackQueue<T> {
+	fn acquire_buffer(&mut self, size: Vec2i) -> PixelBuffer {
+		if self.free.is_empty() {
+			self.finish_oldest();
+		}
+
+		let mut buffer = self.free.pop()
+			.expect("ReadbackQueue has no free buffers after finishing a readback");
+
+		let size_bytes = (size.x * size.y * 4) as usize;
+		if buffer.capacity < size_bytes {
+			unsafe {
+				raw::NamedBufferData(buffer.handle, size_bytes as _, std::ptr::null(), raw::STREAM_READ);
+			}
+
+			buffer.capacity = size_bytes;
+		}
+
+		buffer
+	}
+
+	fn submit(&mut self, buffer: PixelBuffer, size: Vec2i, tag: T) {
+		let fence = unsafe {
+			raw::FenceSync(raw::SYNC_GPU_COMMANDS_COMPLETE, 0)
+		};
+
+		self.pending.push_back(PendingReadback { buffer, fence, size, tag });
+	}
+
+	/// Waits for the oldest readback if it isn't done yet, and moves its image into `ready`.
+	fn finish_oldest(&mut self) {
+		let Some(PendingReadback { buffer, fence, size, tag }) = self.pending.pop_front() else {
+			return
+		};
+
+		let mut data = vec![0u8; (size.x * size.y * 4) as usize];
+
+		unsafe {
+			// Flushing is required so the fence is guaranteed to be signalled eventually.
+			raw::ClientWaitSync(fence, raw::SYNC_FLUSH_COMMANDS_BIT, u64::MAX);
+			raw::DeleteSync(fence);
+
+			raw::GetNamedBufferSubData(buffer.handle, 0, data.len() as _, data.as_mut_ptr() as *mut _);
+		}
+
+		let mut image = image::RgbaImage::from_raw(size.x as u32, size.y as u32, data)
+			.expect("Readback buffer size doesn't match image size");
+
+		image::imageops::flip_vertical_in_place(&mut image);
+
+		self.free.push(buffer);
+		self.ready.push_back((tag, image));
+	}
+}
+
+
+fn is_signalled(fence: raw::types::GLsync) -> bool {
+	let status = unsafe {
+		raw::ClientWaitSync(fence, 0, 0)
+	};
+
+	matches!(status, raw::ALREADY_SIGNALED | raw::CONDITION_SATISFIED)
+}
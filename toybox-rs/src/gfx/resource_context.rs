@@ -4,6 +4,8 @@ use crate::gfx::*;
 use crate::utility::resource_scope::ResourceScope;
 use crate::gfx::system::ScopedResourceHandle;
 
+use std::path::{Path, PathBuf};
+
 
 /// Allows creation of new resources within a `ResourceScope` (which may be the global `ResourceScope`).
 ///
@@ -41,12 +43,34 @@ impl<'ctx> ResourceContext<'ctx> {
 	}
 
 	pub fn new_texture(&mut self, size: impl Into<TextureSize>, format: TextureFormat) -> TextureKey {
-		let texture = Texture::new(size.into(), self.backbuffer_size, format);
+		self.new_texture_with_mips(size, format, MipLevels::One)
+	}
+
+	/// Like [`ResourceContext::new_texture`], but allocates space for `mip_levels`, to be filled in with
+	/// [`Texture::generate_mipmaps`] or [`Texture::upload_mip_rgba8_raw`].
+	/// Pair with a sampler with a [`SamplerDesc::mip_filter`] set, e.g., [`SamplerDesc::trilinear`].
+	pub fn new_texture_with_mips(&mut self, size: impl Into<TextureSize>, format: TextureFormat, mip_levels: MipLevels) -> TextureKey {
+		self.new_texture_with_kind(TextureKind::Texture2D, size, format, mip_levels)
+	}
+
+	/// Creates an array, 3D texture or cubemap - see [`TextureKind`]. `size` is the size of each layer, face or slice.
+	/// Filled in with [`Texture::upload_layer_rgba8_raw`] or [`Texture::upload_mip_rgba8_raw`].
+	///
+	/// Panics if `kind` is an array or 3D texture with no layers or slices, or a cubemap with non-square faces.
+	pub fn new_texture_with_kind(&mut self, kind: TextureKind, size: impl Into<TextureSize>, format: TextureFormat, mip_levels: MipLevels) -> TextureKey {
+		let default_sampler = self.resources.samplers.get(SamplerDesc::default());
+		let texture = Texture::new(kind, size.into(), self.backbuffer_size, format, mip_levels, default_sampler);
 		let key = self.resources.textures.insert(texture);
 		self.resource_scope.insert(ScopedResourceHandle::Texture{key});
 		key
 	}
 
+	/// Returns the sampler matching `desc`, creating it if this is the first time it has been asked for.
+	/// Samplers are shared and live until shutdown, rather than being associated with a resource scope.
+	pub fn get_sampler(&mut self, desc: SamplerDesc) -> SamplerKey {
+		self.resources.samplers.get(desc)
+	}
+
 	pub fn new_framebuffer(&mut self, settings: FramebufferSettings) -> FramebufferKey {
 		let framebuffer = Framebuffer::new(settings, &mut self.resources, self.backbuffer_size);
 		let key = self.resources.framebuffers.insert(framebuffer);
@@ -78,19 +102,63 @@ impl<'ctx> ResourceContext<'ctx> {
 	}
 
 	pub fn new_shader(&mut self, shaders: &[(u32, &str)]) -> Result<Shader, shader::CompilationError> {
-		self.shader_manager.get_shader(shaders)
+		self.shader_manager.get_shader(shaders, &[])
+	}
+
+	/// Compiles a permutation of a shader, with `defines` as `(name, value)` pairs injected after the `#version`
+	/// directive of each stage. Each distinct set of defines is compiled and cached separately.
+	pub fn new_shader_with_defines(&mut self, shaders: &[(u32, &str)], defines: &[(&str, &str)]) -> Result<Shader, shader::CompilationError> {
+		self.shader_manager.get_shader(shaders, defines)
 	}
 
 	pub fn new_simple_shader(&mut self, vsrc: &str, fsrc: &str) -> Result<Shader, shader::CompilationError> {
 		self.shader_manager.get_shader(&[
 			(raw::VERTEX_SHADER, vsrc),
 			(raw::FRAGMENT_SHADER, fsrc),
-		])
+		], &[])
 	}
 
 	pub fn new_compute_shader(&mut self, csrc: &str) -> Result<Shader, shader::CompilationError> {
 		self.shader_manager.get_shader(&[
 			(raw::COMPUTE_SHADER, csrc)
+		], &[])
+	}
+
+	/// Like [`ResourceContext::add_shader_import`], but reads the import from a file, which is watched for changes
+	/// along with any shaders loaded with [`ResourceContext::load_shader`] that use it.
+	pub fn load_shader_import(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Result<(), shader::CompilationError> {
+		self.shader_manager.load_import(name, path, self.resource_scope.id())
+	}
+
+	/// Compiles a shader from files on disk. The files are watched, and when any of them - or any imports loaded with
+	/// [`ResourceContext::load_shader_import`] - change, the shader is recompiled and swapped in behind the same [`Shader`].
+	/// If recompilation fails, the last good version is kept and the error is shown in an imgui overlay until it's fixed.
+	pub fn load_shader(&mut self, shaders: &[(u32, impl AsRef<Path>)]) -> Result<Shader, shader::CompilationError> {
+		self.load_shader_with_defines(shaders, &[])
+	}
+
+	/// Like [`ResourceContext::load_shader`], with `defines` injected as in [`ResourceContext::new_shader_with_defines`].
+	/// Unlike shaders compiled from strings, each call compiles a new shader.
+	pub fn load_shader_with_defines(&mut self, shaders: &[(u32, impl AsRef<Path>)], defines: &[(&str, &str)])
+		-> Result<Shader, shader::CompilationError>
+	{
+		let stages = shaders.iter()
+			.map(|(ty, path)| (*ty, path.as_ref().to_owned()))
+			.collect();
+
+		self.shader_manager.load_shader(stages, defines, self.resource_scope.id())
+	}
+
+	pub fn load_simple_shader(&mut self, vpath: impl AsRef<Path>, fpath: impl AsRef<Path>) -> Result<Shader, shader::CompilationError> {
+		self.load_shader(&[
+			(raw::VERTEX_SHADER, vpath.as_ref()),
+			(raw::FRAGMENT_SHADER, fpath.as_ref()),
+		])
+	}
+
+	pub fn load_compute_shader(&mut self, cpath: impl AsRef<Path>) -> Result<Shader, shader::CompilationError> {
+		self.load_shader(&[
+			(raw::COMPUTE_SHADER, cpath.as_ref())
 		])
 	}
 }
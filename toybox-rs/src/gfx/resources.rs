@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use crate::gfx::*;
+use crate::gfx::sampler::SamplerCache;
 
 use crate::utility::resource::{Resource, ResourceStore, ResourceLock, ResourceLockMut};
 
@@ -15,6 +16,8 @@ slotmap::new_key_type!{
 pub struct Resources {
 	pub textures: ResourceStore<Texture>,
 	pub framebuffers: ResourceStore<Framebuffer>,
+
+	pub(super) samplers: SamplerCache,
 }
 
 impl Resources {
@@ -29,13 +32,20 @@ impl Resources {
 	{
 		handle.get_mut(self)
 	}
+
+	/// The description a sampler was created from.
+	pub fn sampler_desc(&self, sampler: SamplerKey) -> SamplerDesc {
+		self.samplers.desc(sampler)
+	}
 }
 
 impl Resources {
-	pub(super) fn new() -> Resources {
+	pub(super) fn new(capabilities: &Capabilities) -> Resources {
 		Resources {
 			textures: ResourceStore::new(),
 			framebuffers: ResourceStore::new(),
+
+			samplers: SamplerCache::new(capabilities.max_anisotropy),
 		}
 	}
 
@@ -0,0 +1,248 @@
+use crate::prelude::*;
+use crate::gfx::{raw, CompareFunction};
+
+use std::collections::HashMap;
+
+
+// Core since 4.6, but only available through ARB/EXT_texture_filter_anisotropic in 4.5.
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+pub(super) const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+
+slotmap::new_key_type!{
+	/// A cached sampler object, acquired through [`ResourceContext::get_sampler`](crate::gfx::ResourceContext::get_sampler).
+	/// Bound along with a texture by [`DrawContext::bind_texture_with_sampler`](crate::gfx::DrawContext::bind_texture_with_sampler),
+	/// or used as a texture's default sampler with [`Texture::set_sampler`](crate::gfx::Texture::set_sampler).
+	pub struct SamplerKey;
+}
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Filter {
+	Nearest,
+	Linear,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+	Repeat,
+	MirroredRepeat,
+	ClampToEdge,
+	MirrorClampToEdge,
+
+	/// Samples outside of the texture return [`SamplerDesc::border_color`].
+	ClampToBorder,
+}
+
+/// Only a few fixed colors are supported, so that [`SamplerDesc`] can be hashed and compared exactly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BorderColor {
+	TransparentBlack,
+	OpaqueBlack,
+	OpaqueWhite,
+}
+
+
+/// Describes how textures are sampled. Samplers are cached, so the same [`SamplerDesc`] always results
+/// in the same [`SamplerKey`].
+///
+/// Defaults to nearest filtering without mipmaps, clamped to edge - which is what textures used before
+/// anything else is requested.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+	pub min_filter: Filter,
+	pub mag_filter: Filter,
+
+	/// How to filter between mip levels. `None` only ever samples the base level.
+	pub mip_filter: Option<Filter>,
+
+	pub wrap_u: WrapMode,
+	pub wrap_v: WrapMode,
+	pub wrap_w: WrapMode,
+
+	pub border_color: BorderColor,
+
+	/// Maximum anisotropy, where 1 disables anisotropic filtering.
+	/// Clamped to [`Capabilities::max_anisotropy`](crate::gfx::Capabilities::max_anisotropy).
+	pub anisotropy: u8,
+
+	/// If set, sampling compares against a reference value rather than returning texels - e.g.,
+	/// `sampler2DShadow` lookups for shadow maps. Only meaningful for depth textures.
+	pub compare: Option<CompareFunction>,
+}
+
+impl Default for SamplerDesc {
+	fn default() -> SamplerDesc {
+		SamplerDesc {
+			min_filter: Filter::Nearest,
+			mag_filter: Filter::Nearest,
+			mip_filter: None,
+
+			wrap_u: WrapMode::ClampToEdge,
+			wrap_v: WrapMode::ClampToEdge,
+			wrap_w: WrapMode::ClampToEdge,
+
+			border_color: BorderColor::TransparentBlack,
+			anisotropy: 1,
+			compare: None,
+		}
+	}
+}
+
+impl SamplerDesc {
+	pub fn nearest() -> SamplerDesc {
+		SamplerDesc::default()
+	}
+
+	pub fn linear() -> SamplerDesc {
+		SamplerDesc::default()
+			.with_filter(Filter::Linear)
+	}
+
+	/// Linear filtering within and between mip levels.
+	pub fn trilinear() -> SamplerDesc {
+		SamplerDesc {
+			mip_filter: Some(Filter::Linear),
+			..SamplerDesc::linear()
+		}
+	}
+
+	/// Linear filtering with a depth comparison, for hardware PCF on shadow maps.
+	pub fn shadow(compare: CompareFunction) -> SamplerDesc {
+		SamplerDesc::linear()
+			.with_wrap(WrapMode::ClampToBorder)
+			.with_border_color(BorderColor::OpaqueWhite)
+			.with_compare(compare)
+	}
+
+	pub fn with_filter(self, filter: Filter) -> SamplerDesc {
+		SamplerDesc {min_filter: filter, mag_filter: filter, ..self}
+	}
+
+	pub fn with_mip_filter(self, mip_filter: impl Into<Option<Filter>>) -> SamplerDesc {
+		SamplerDesc {mip_filter: mip_filter.into(), ..self}
+	}
+
+	pub fn with_wrap(self, wrap: WrapMode) -> SamplerDesc {
+		SamplerDesc {wrap_u: wrap, wrap_v: wrap, wrap_w: wrap, ..self}
+	}
+
+	pub fn with_wrap_per_axis(self, wrap_u: WrapMode, wrap_v: WrapMode, wrap_w: WrapMode) -> SamplerDesc {
+		SamplerDesc {wrap_u, wrap_v, wrap_w, ..self}
+	}
+
+	pub fn with_border_color(self, border_color: BorderColor) -> SamplerDesc {
+		SamplerDesc {border_color, ..self}
+	}
+
+	pub fn with_anisotropy(self, anisotropy: u8) -> SamplerDesc {
+		SamplerDesc {anisotropy: anisotropy.max(1), ..self}
+	}
+
+	pub fn with_compare(self, compare: impl Into<Option<CompareFunction>>) -> SamplerDesc {
+		SamplerDesc {compare: compare.into(), ..self}
+	}
+}
+
+
+
+/// Owns every sampler object created, keyed by [`SamplerDesc`].
+/// Samplers are never destroyed - there are only ever expected to be a handful of distinct ones.
+#[derive(Debug)]
+pub(super) struct SamplerCache {
+	samplers: slotmap::SlotMap<SamplerKey, (SamplerDesc, u32)>,
+	lookup: HashMap<SamplerDesc, SamplerKey>,
+	max_anisotropy: f32,
+}
+
+impl SamplerCache {
+	pub(super) fn new(max_anisotropy: f32) -> SamplerCache {
+		SamplerCache {
+			samplers: slotmap::SlotMap::with_key(),
+			lookup: HashMap::new(),
+			max_anisotropy,
+		}
+	}
+
+	pub(super) fn get(&mut self, desc: SamplerDesc) -> SamplerKey {
+		if let Some(&key) = self.lookup.get(&desc) {
+			return key
+		}
+
+		let handle = create_sampler(&desc, self.max_anisotropy);
+		let key = self.samplers.insert((desc, handle));
+		self.lookup.insert(desc, key);
+		key
+	}
+
+	pub(super) fn handle(&self, key: SamplerKey) -> u32 {
+		self.samplers[key].1
+	}
+
+	pub(super) fn desc(&self, key: SamplerKey) -> SamplerDesc {
+		self.samplers[key].0
+	}
+}
+
+
+fn create_sampler(desc: &SamplerDesc, max_anisotropy: f32) -> u32 {
+	let min_filter = match (desc.min_filter, desc.mip_filter) {
+		(Filter::Nearest, None) => raw::NEAREST,
+		(Filter::Linear, None) => raw::LINEAR,
+		(Filter::Nearest, Some(Filter::Nearest)) => raw::NEAREST_MIPMAP_NEAREST,
+		(Filter::Linear, Some(Filter::Nearest)) => raw::LINEAR_MIPMAP_NEAREST,
+		(Filter::Nearest, Some(Filter::Linear)) => raw::NEAREST_MIPMAP_LINEAR,
+		(Filter::Linear, Some(Filter::Linear)) => raw::LINEAR_MIPMAP_LINEAR,
+	};
+
+	let mag_filter = match desc.mag_filter {
+		Filter::Nearest => raw::NEAREST,
+		Filter::Linear => raw::LINEAR,
+	};
+
+	let border_color: [f32; 4] = match desc.border_color {
+		BorderColor::TransparentBlack => [0.0, 0.0, 0.0, 0.0],
+		BorderColor::OpaqueBlack => [0.0, 0.0, 0.0, 1.0],
+		BorderColor::OpaqueWhite => [1.0, 1.0, 1.0, 1.0],
+	};
+
+	let mut handle = 0;
+
+	unsafe {
+		raw::CreateSamplers(1, &mut handle);
+
+		raw::SamplerParameteri(handle, raw::TEXTURE_MIN_FILTER, min_filter as _);
+		raw::SamplerParameteri(handle, raw::TEXTURE_MAG_FILTER, mag_filter as _);
+
+		raw::SamplerParameteri(handle, raw::TEXTURE_WRAP_S, desc.wrap_u.into_gl() as _);
+		raw::SamplerParameteri(handle, raw::TEXTURE_WRAP_T, desc.wrap_v.into_gl() as _);
+		raw::SamplerParameteri(handle, raw::TEXTURE_WRAP_R, desc.wrap_w.into_gl() as _);
+
+		raw::SamplerParameterfv(handle, raw::TEXTURE_BORDER_COLOR, border_color.as_ptr());
+
+		if desc.anisotropy > 1 && max_anisotropy > 1.0 {
+			let anisotropy = (desc.anisotropy as f32).min(max_anisotropy);
+			raw::SamplerParameterf(handle, TEXTURE_MAX_ANISOTROPY, anisotropy);
+		}
+
+		if let Some(compare) = desc.compare {
+			raw::SamplerParameteri(handle, raw::TEXTURE_COMPARE_MODE, raw::COMPARE_REF_TO_TEXTURE as _);
+			raw::SamplerParameteri(handle, raw::TEXTURE_COMPARE_FUNC, compare.into_gl() as _);
+		}
+	}
+
+	handle
+}
+
+
+impl WrapMode {
+	fn into_gl(self) -> u32 {
+		match self {
+			WrapMode::Repeat => raw::REPEAT,
+			WrapMode::MirroredRepeat => raw::MIRRORED_REPEAT,
+			WrapMode::ClampToEdge => raw::CLAMP_TO_EDGE,
+			WrapMode::MirrorClampToEdge => raw::MIRROR_CLAMP_TO_EDGE,
+			WrapMode::ClampToBorder => raw::CLAMP_TO_BORDER,
+		}
+	}
+}
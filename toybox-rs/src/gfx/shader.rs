@@ -3,6 +3,14 @@ use crate::utility::ResourceScopeID;
 use std::error::Error;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+mod preprocessor;
+mod reflection;
+
+use preprocessor::{PreprocessedSource, preprocess, map_error_locations};
+pub use reflection::*;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Shader (pub(super) u32);
@@ -11,16 +19,38 @@ pub struct Shader (pub(super) u32);
 struct ImportData {
 	source: String,
 	resource_scope_id: ResourceScopeID,
+
+	/// Set for imports loaded from disk, which are reloaded when the file changes.
+	path: Option<PathBuf>,
 }
 
 struct ShaderCompileResult {
 	shader: Shader,
 	dependent_resource_scopes: HashSet<ResourceScopeID>,
+	dependent_imports: HashSet<String>,
 }
 
-struct ResolvedShaderSource {
-	resolved_source: String,
+struct CompiledStages {
+	shader_handles: Vec<u32>,
 	dependent_resource_scopes: HashSet<ResourceScopeID>,
+	dependent_imports: HashSet<String>,
+}
+
+/// A single stage to be compiled. `name` identifies it in errors - either the path it was loaded from or its stage type.
+struct StageSource<'a> {
+	ty: u32,
+	name: String,
+	source: &'a str,
+}
+
+impl<'a> StageSource<'a> {
+	fn from_str(ty: u32, source: &'a str) -> StageSource<'a> {
+		StageSource { ty, name: stage_name(ty).into(), source }
+	}
+
+	fn from_file(ty: u32, path: &Path, source: &'a str) -> StageSource<'a> {
+		StageSource { ty, name: path.display().to_string(), source }
+	}
 }
 
 type ShaderSourceHash = u64;
@@ -32,12 +62,54 @@ struct ShaderCache {
 
 	/// Maps resource scope ids to a list of shader hashes that should be invalidated when the scope is cleaned up.
 	scoped_shader_hashes: HashMap<ResourceScopeID, Vec<ShaderSourceHash>>,
+
+	/// Cached shaders that use imports loaded from disk, keyed by shader hash.
+	importing_shaders: HashMap<ShaderSourceHash, ImportingShader>,
+}
+
+
+/// A shader compiled from strings that uses imports loaded with [`ShaderManager::load_import`]. Its sources are kept
+/// so it can be recompiled in place when any of those imports change.
+struct ImportingShader {
+	shader: Shader,
+	stages: Vec<(u32, String)>,
+	defines: Vec<(String, String)>,
+	dependent_imports: HashSet<String>,
+
+	/// Set while the most recent import changes fail to compile.
+	error: Option<String>,
+}
+
+
+/// A shader loaded from files on disk, which is recompiled when any of those files or the files of any of its
+/// imports change.
+struct WatchedShader {
+	shader: Shader,
+	stages: Vec<(u32, PathBuf)>,
+	defines: Vec<(String, String)>,
+
+	/// The scope the shader was loaded in, plus the scopes of any imports it uses.
+	dependent_resource_scopes: HashSet<ResourceScopeID>,
+	dependent_imports: HashSet<String>,
+
+	/// Set while the most recent changes fail to compile. The last good program stays in use until they're fixed.
+	error: Option<String>,
 }
 
 
+/// How often files loaded through [`ShaderManager::load_shader`] and [`ShaderManager::load_import`] are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+
 pub(super) struct ShaderManager {
 	imports: HashMap<String, ImportData>,
 	cache: RefCell<ShaderCache>,
+
+	watched_shaders: Vec<WatchedShader>,
+
+	/// Last modification times of every file loaded by watched shaders and imports.
+	watched_files: HashMap<PathBuf, Option<SystemTime>>,
+	last_poll: Instant,
 }
 
 
@@ -48,25 +120,50 @@ impl ShaderManager {
 			cache: ShaderCache {
 				source_hash_to_shader: HashMap::new(),
 				scoped_shader_hashes: HashMap::new(),
+				importing_shaders: HashMap::new(),
 			}.into(),
+
+			watched_shaders: Vec::new(),
+			watched_files: HashMap::new(),
+			last_poll: Instant::now(),
 		}
 	}
 
 	pub fn add_import(&mut self, name: impl Into<String>, src: impl Into<String>, resource_scope_id: ResourceScopeID) {
+		self.insert_import(name.into(), src.into(), resource_scope_id, None);
+	}
+
+	/// Like [`ShaderManager::add_import`], but reads the import from `path` and reloads it - along with any shaders
+	/// loaded with [`ShaderManager::load_shader`] that use it - whenever the file changes.
+	pub fn load_import(&mut self, name: impl Into<String>, path: impl Into<PathBuf>, resource_scope_id: ResourceScopeID)
+		-> Result<(), CompilationError>
+	{
+		let path = path.into();
+		let src = read_source(&path)?;
+
+		self.watch_file(&path);
+		self.insert_import(name.into(), src, resource_scope_id, Some(path));
+		Ok(())
+	}
+
+	fn insert_import(&mut self, name: String, source: String, resource_scope_id: ResourceScopeID, path: Option<PathBuf>) {
 		use std::collections::hash_map::Entry;
 
-		match self.imports.entry(name.into()) {
+		match self.imports.entry(name) {
 			Entry::Occupied(entry) => panic!("Shader import added more than once: {}", entry.key()),
 			Entry::Vacant(entry) => {
 				entry.insert(ImportData {
-					source: src.into(),
+					source,
 					resource_scope_id,
+					path,
 				});
 			}
 		}
 	}
 
-	pub fn get_shader(&self, shaders: &[(u32, &str)]) -> Result<Shader, CompilationError> {
+	/// Compiles `shaders` with `defines` injected into each stage, or returns the existing shader if this combination of
+	/// sources and defines has been seen before - so each permutation of a shader is only compiled once.
+	pub fn get_shader(&self, shaders: &[(u32, &str)], defines: &[(&str, &str)]) -> Result<Shader, CompilationError> {
 		use std::collections::hash_map::*;
 		use std::hash::Hasher;
 
@@ -76,6 +173,13 @@ impl ShaderManager {
 			hasher.write(contents.as_bytes());
 		}
 
+		for &(name, value) in defines {
+			hasher.write(name.as_bytes());
+			hasher.write_u8(0);
+			hasher.write(value.as_bytes());
+			hasher.write_u8(0);
+		}
+
 		let shader_hash = hasher.finish();
 		let mut cache_ref = self.cache.borrow_mut();
 		let cache = &mut *cache_ref; // reborrow so we can split the borrow
@@ -83,7 +187,8 @@ impl ShaderManager {
 		match cache.source_hash_to_shader.entry(shader_hash) {
 			Entry::Occupied(entry) => Ok(*entry.get()),
 			Entry::Vacant(entry) => {
-				let ShaderCompileResult{shader, dependent_resource_scopes} = compile_shaders(shaders, &self.imports)?;
+				let stages: Vec<_> = shaders.iter().map(|&(ty, src)| StageSource::from_str(ty, src)).collect();
+				let ShaderCompileResult{shader, dependent_resource_scopes, dependent_imports} = compile_shaders(&stages, defines, &self.imports)?;
 
 				for resource_scope in dependent_resource_scopes {
 					cache.scoped_shader_hashes.entry(resource_scope)
@@ -91,11 +196,161 @@ impl ShaderManager {
 						.push(shader_hash);
 				}
 
+				let imports_watched_files = dependent_imports.iter()
+					.any(|name| self.imports.get(name).is_some_and(|import_data| import_data.path.is_some()));
+
+				if imports_watched_files {
+					cache.importing_shaders.insert(shader_hash, ImportingShader {
+						shader,
+						stages: shaders.iter().map(|&(ty, src)| (ty, src.to_owned())).collect(),
+						defines: defines.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())).collect(),
+						dependent_imports,
+						error: None,
+					});
+				}
+
 				Ok(*entry.insert(shader))
 			}
 		}
 	}
 
+	/// Compiles a shader from source files rather than strings. Unlike [`ShaderManager::get_shader`], the result isn't
+	/// shared with other identical shaders, since it is recompiled in place when any of the files change -
+	/// see [`ShaderManager::poll_for_changes`].
+	pub fn load_shader(&mut self, stages: Vec<(u32, PathBuf)>, defines: &[(&str, &str)], resource_scope_id: ResourceScopeID)
+		-> Result<Shader, CompilationError>
+	{
+		let sources = read_stage_sources(&stages)?;
+		let sources: Vec<_> = stages.iter().zip(sources.iter())
+			.map(|((ty, path), src)| StageSource::from_file(*ty, path, src))
+			.collect();
+
+		let ShaderCompileResult{shader, mut dependent_resource_scopes, dependent_imports} = compile_shaders(&sources, defines, &self.imports)?;
+		dependent_resource_scopes.insert(resource_scope_id);
+
+		for (_, path) in stages.iter() {
+			self.watch_file(path);
+		}
+
+		self.watched_shaders.push(WatchedShader {
+			shader,
+			stages,
+			defines: defines.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())).collect(),
+			dependent_resource_scopes,
+			dependent_imports,
+			error: None,
+		});
+
+		Ok(shader)
+	}
+
+	/// Checks whether any files loaded by [`ShaderManager::load_shader`] or [`ShaderManager::load_import`] have changed,
+	/// and recompiles any affected shaders - including shaders from [`ShaderManager::get_shader`] that use changed imports. Shaders that fail to recompile keep their last good program, and report
+	/// the failure through [`ShaderManager::reload_errors`] until they're fixed.
+	/// Returns whether any shaders were recompiled.
+	pub fn poll_for_changes(&mut self) -> bool {
+		if self.watched_files.is_empty() || self.last_poll.elapsed() < POLL_INTERVAL {
+			return false
+		}
+
+		self.last_poll = Instant::now();
+
+		let mut changed_files = HashSet::new();
+
+		for (path, last_modified) in self.watched_files.iter_mut() {
+			let modified = modified_time(path);
+			if modified != *last_modified {
+				*last_modified = modified;
+				changed_files.insert(path.clone());
+			}
+		}
+
+		if changed_files.is_empty() {
+			return false
+		}
+
+		let mut changed_imports = HashSet::new();
+
+		for (name, import_data) in self.imports.iter_mut() {
+			let Some(path) = import_data.path.as_ref() else { continue };
+			if !changed_files.contains(path) {
+				continue
+			}
+
+			// A failed read is most likely an editor midway through saving - the next change will pick it up.
+			match read_source(path) {
+				Ok(source) => {
+					import_data.source = source;
+					changed_imports.insert(name.clone());
+				}
+
+				Err(error) => tracing::warn!("{}", error.summary()),
+			}
+		}
+
+		let mut any_reloaded = false;
+
+		for watched in self.watched_shaders.iter_mut() {
+			let stage_changed = watched.stages.iter().any(|(_, path)| changed_files.contains(path));
+			let import_changed = !watched.dependent_imports.is_disjoint(&changed_imports);
+
+			if stage_changed || import_changed {
+				watched.reload(&self.imports);
+				any_reloaded = true;
+			}
+		}
+
+		if changed_imports.is_empty() {
+			return any_reloaded
+		}
+
+		let mut cache_ref = self.cache.borrow_mut();
+		let cache = &mut *cache_ref; // reborrow so we can split the borrow
+
+		for (&shader_hash, importing) in cache.importing_shaders.iter_mut() {
+			if importing.dependent_imports.is_disjoint(&changed_imports) {
+				continue
+			}
+
+			any_reloaded = true;
+
+			// Imports can pull in other imports, so the shader may now depend on more scopes than before
+			let Some(dependent_resource_scopes) = importing.reload(&self.imports) else { continue };
+
+			for resource_scope in dependent_resource_scopes {
+				let shader_hashes = cache.scoped_shader_hashes.entry(resource_scope).or_default();
+				if !shader_hashes.contains(&shader_hash) {
+					shader_hashes.push(shader_hash);
+				}
+			}
+		}
+
+		any_reloaded
+	}
+
+	/// Shaders whose latest changes, or the latest changes to their imports, failed to compile.
+	pub fn reload_errors(&self) -> Vec<ShaderReloadError> {
+		let watched_errors = self.watched_shaders.iter()
+			.filter_map(|watched| Some(ShaderReloadError {
+				name: watched.name(),
+				message: watched.error.clone()?,
+			}));
+
+		let cache = self.cache.borrow();
+		let importing_errors = cache.importing_shaders.values()
+			.filter_map(|importing| Some(ShaderReloadError {
+				name: importing.name(),
+				message: importing.error.clone()?,
+			}));
+
+		watched_errors.chain(importing_errors).collect()
+	}
+
+	fn watch_file(&mut self, path: &Path) {
+		self.watched_files.entry(path.to_owned())
+			.or_insert_with(|| modified_time(path));
+	}
+
 	// TODO(pat.m): need a way to ensure shaders can't accidentally import imports from shorter lived resource scopes
 	// TODO(pat.m): maybe shaders should work more like other resources, so the ResourceScope itself can handle their deletion?
 	// 	would need to figure out how dependencies worked tho, since shaders could reference imports from different scopes.
@@ -105,6 +360,8 @@ impl ShaderManager {
 
 		if let Some(hashes) = cache.scoped_shader_hashes.remove(&resource_scope_id) {
 			for hash in hashes {
+				cache.importing_shaders.remove(&hash);
+
 				if let Some(shader) = cache.source_hash_to_shader.remove(&hash) {
 					unsafe {
 						gfx::raw::DeleteProgram(shader.0);
@@ -113,30 +370,232 @@ impl ShaderManager {
 			}
 		}
 
+		self.watched_shaders.retain(|watched| {
+			let keep = !watched.dependent_resource_scopes.contains(&resource_scope_id);
+			if !keep {
+				unsafe {
+					gfx::raw::DeleteProgram(watched.shader.0);
+				}
+			}
+
+			keep
+		});
+
 		self.imports.retain(|_, import_data| import_data.resource_scope_id != resource_scope_id);
+
+		// Stop polling files nothing refers to anymore.
+		let watched_shaders = &self.watched_shaders;
+		let imports = &self.imports;
+		self.watched_files.retain(|path, _| {
+			watched_shaders.iter().any(|watched| watched.stages.iter().any(|(_, stage_path)| stage_path == path))
+				|| imports.values().any(|import_data| import_data.path.as_ref() == Some(path))
+		});
+	}
+}
+
+
+impl WatchedShader {
+	fn name(&self) -> String {
+		self.stages.iter()
+			.map(|(_, path)| path.display().to_string())
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+
+	fn reload(&mut self, imports: &HashMap<String, ImportData>) {
+		let name = self.name();
+		let defines: Vec<_> = self.defines.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+
+		let result = read_stage_sources(&self.stages)
+			.and_then(|sources| {
+				let sources: Vec<_> = self.stages.iter().zip(sources.iter())
+					.map(|((ty, path), src)| StageSource::from_file(*ty, path, src))
+					.collect();
+
+				recompile_shader_in_place(self.shader, &sources, &defines, imports)
+			});
+
+		match result {
+			Ok(CompiledStages{dependent_resource_scopes, dependent_imports, ..}) => {
+				tracing::info!("Reloaded shader {name}");
+
+				self.dependent_resource_scopes.extend(dependent_resource_scopes);
+				self.dependent_imports = dependent_imports;
+				self.error = None;
+			}
+
+			Err(error) => {
+				let summary = error.summary();
+				tracing::error!("Failed to reload shader {name}: {summary}");
+				self.error = Some(summary);
+			}
+		}
+	}
+}
+
+
+impl ImportingShader {
+	fn name(&self) -> String {
+		let stages = self.stages.iter()
+			.map(|&(ty, _)| stage_name(ty))
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		let imports = self.dependent_imports.iter()
+			.map(String::as_str)
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		format!("{stages} using {imports}")
+	}
+
+	/// Returns the resource scopes the recompiled shader depends on, or `None` if it failed to compile.
+	fn reload(&mut self, imports: &HashMap<String, ImportData>) -> Option<HashSet<ResourceScopeID>> {
+		let name = self.name();
+		let stages: Vec<_> = self.stages.iter().map(|(ty, src)| StageSource::from_str(*ty, src)).collect();
+		let defines: Vec<_> = self.defines.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+
+		match recompile_shader_in_place(self.shader, &stages, &defines, imports) {
+			Ok(CompiledStages{dependent_resource_scopes, dependent_imports, ..}) => {
+				tracing::info!("Reloaded shader {name}");
+
+				self.dependent_imports = dependent_imports;
+				self.error = None;
+				Some(dependent_resource_scopes)
+			}
+
+			Err(error) => {
+				let summary = error.summary();
+				tracing::error!("Failed to reload shader {name}: {summary}");
+				self.error = Some(summary);
+				None
+			}
+		}
 	}
 }
 
 
+/// A shader whose latest changes failed to compile, as reported by
+/// [`System::shader_reload_errors`](crate::gfx::System::shader_reload_errors).
+/// The last good program stays in use until the errors are fixed.
+#[derive(Debug)]
+pub struct ShaderReloadError {
+	/// The files the shader was loaded from, or its stages and imports if it was compiled from strings.
+	pub name: String,
+	pub message: String,
+}
+
+
+fn read_source(path: &Path) -> Result<String, CompilationError> {
+	std::fs::read_to_string(path)
+		.map_err(|error| CompilationError::new(&format!("reading '{}'", path.display()), &error.to_string()))
+}
+
+fn read_stage_sources(stages: &[(u32, PathBuf)]) -> Result<Vec<String>, CompilationError> {
+	stages.iter()
+		.map(|(_, path)| read_source(path))
+		.collect()
+}
 
-fn compile_shaders(shaders: &[(u32, &str)], imports: &HashMap<String, ImportData>) -> Result<ShaderCompileResult, CompilationError> {
+fn stage_name(ty: u32) -> &'static str {
+	match ty {
+		gfx::raw::VERTEX_SHADER => "vertex shader",
+		gfx::raw::FRAGMENT_SHADER => "fragment shader",
+		gfx::raw::GEOMETRY_SHADER => "geometry shader",
+		gfx::raw::TESS_CONTROL_SHADER => "tessellation control shader",
+		gfx::raw::TESS_EVALUATION_SHADER => "tessellation evaluation shader",
+		gfx::raw::COMPUTE_SHADER => "compute shader",
+		_ => "shader",
+	}
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+	std::fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+}
+
+
+
+fn compile_shaders(stages: &[StageSource<'_>], defines: &[(&str, &str)], imports: &HashMap<String, ImportData>)
+	-> Result<ShaderCompileResult, CompilationError>
+{
+	let CompiledStages{shader_handles, dependent_resource_scopes, dependent_imports} = compile_stages(stages, defines, imports)?;
+
+	let program_handle = unsafe { gfx::raw::CreateProgram() };
+	let link_result = link_program(program_handle, &shader_handles);
+
+	unsafe {
+		delete_shaders(&shader_handles);
+
+		if link_result.is_err() {
+			gfx::raw::DeleteProgram(program_handle);
+		}
+	}
+
+	link_result?;
+
+	Ok(ShaderCompileResult {
+		shader: Shader(program_handle),
+		dependent_resource_scopes,
+		dependent_imports,
+	})
+}
+
+
+/// Relinks `shader` with new sources, so that the same [`Shader`] can be kept around and bound as before.
+/// Uniforms not backed by buffers are reset to their defaults.
+fn recompile_shader_in_place(shader: Shader, stages: &[StageSource<'_>], defines: &[(&str, &str)], imports: &HashMap<String, ImportData>)
+	-> Result<CompiledStages, CompilationError>
+{
+	let stages = compile_stages(stages, defines, imports)?;
+
+	// A failed link throws away the previous program, so make sure it will succeed by linking a scratch program first.
+	let result = unsafe {
+		let scratch_program = gfx::raw::CreateProgram();
+		let scratch_result = link_program(scratch_program, &stages.shader_handles);
+		gfx::raw::DeleteProgram(scratch_program);
+
+		let result = scratch_result.and_then(|_| link_program(shader.0, &stages.shader_handles));
+		delete_shaders(&stages.shader_handles);
+		result
+	};
+
+	result.map(|_| stages)
+}
+
+
+fn compile_stages(stages: &[StageSource<'_>], defines: &[(&str, &str)], imports: &HashMap<String, ImportData>)
+	-> Result<CompiledStages, CompilationError>
+{
 	use std::ffi::CString;
 	use std::str;
 
 	let mut total_dependent_resource_scopes = HashSet::new();
+	let mut total_dependent_imports = HashSet::new();
+	let mut shader_handles = Vec::with_capacity(stages.len());
 
 	unsafe {
-		let program_handle = gfx::raw::CreateProgram();
+		for &StageSource{ty, ref name, source} in stages.iter() {
+			let preprocessed = preprocess(source, name, defines, imports);
+			let PreprocessedSource{source, source_names, dependent_resource_scopes, dependent_imports} = match preprocessed {
+				Ok(preprocessed) => preprocessed,
+				Err(error) => {
+					delete_shaders(&shader_handles);
+					return Err(error)
+				}
+			};
 
-		for &(ty, src) in shaders.iter() {
-			let ResolvedShaderSource{resolved_source, dependent_resource_scopes} = resolve_imports(&src, imports);
-			let src_cstring = CString::new(resolved_source.as_bytes()).unwrap();
+			let Ok(src_cstring) = CString::new(source) else {
+				delete_shaders(&shader_handles);
+				return Err(CompilationError::new("shader compilation", &format!("{name} contains a NUL byte")))
+			};
 
-			for resource_scope in dependent_resource_scopes {
-				total_dependent_resource_scopes.insert(resource_scope);
-			}
+			total_dependent_resource_scopes.extend(dependent_resource_scopes);
+			total_dependent_imports.extend(dependent_imports);
 
 			let shader_handle = gfx::raw::CreateShader(ty);
+			shader_handles.push(shader_handle);
 
 			gfx::raw::ShaderSource(shader_handle, 1, &src_cstring.as_ptr(), std::ptr::null());
 			gfx::raw::CompileShader(shader_handle);
@@ -156,18 +615,40 @@ fn compile_shaders(shaders: &[(u32, &str)], imports: &HashMap<String, ImportData
 					buffer.as_mut_ptr() as *mut _
 				);
 
+				delete_shaders(&shader_handles);
+
 				let error = str::from_utf8(&buffer[..buffer.len()-1])
 					.map_err(|_| CompilationError::new("shader compilation", "error message invalid utf-8"))?;
 
-				return Err(CompilationError::new("shader compilation", error));
+				return Err(CompilationError::new("shader compilation", &map_error_locations(error, &source_names)));
 			}
+		}
+	}
+
+	Ok(CompiledStages {
+		shader_handles,
+		dependent_resource_scopes: total_dependent_resource_scopes,
+		dependent_imports: total_dependent_imports,
+	})
+}
+
+
+/// Links `shader_handles` into `program_handle`. Shaders are detached again afterwards, so they can be deleted
+/// and the program relinked later with different shaders.
+fn link_program(program_handle: u32, shader_handles: &[u32]) -> Result<(), CompilationError> {
+	use std::str;
 
+	unsafe {
+		for &shader_handle in shader_handles {
 			gfx::raw::AttachShader(program_handle, shader_handle);
-			gfx::raw::DeleteShader(shader_handle);
 		}
 
 		gfx::raw::LinkProgram(program_handle);
 
+		for &shader_handle in shader_handles {
+			gfx::raw::DetachShader(program_handle, shader_handle);
+		}
+
 		let mut status = 0;
 		gfx::raw::GetProgramiv(program_handle, gfx::raw::LINK_STATUS, &mut status);
 
@@ -181,55 +662,20 @@ fn compile_shaders(shaders: &[(u32, &str)], imports: &HashMap<String, ImportData
 
 			return Err(CompilationError::new("shader link", error));
 		}
-
-		Ok(ShaderCompileResult {
-			shader: Shader(program_handle),
-			dependent_resource_scopes: total_dependent_resource_scopes,
-		})
 	}
-}
-
-
-
-
-fn resolve_imports(mut src: &str, imports: &HashMap<String, ImportData>) -> ResolvedShaderSource {
-	let search_pattern = "#import";
-
-	let mut resolved_source = String::with_capacity(src.len());
-	let mut dependent_resource_scopes = HashSet::new();
-
-	while !src.is_empty() {
-		let (prefix, suffix) = match src.split_once(search_pattern) {
-			Some(pair) => pair,
-			None => {
-				resolved_source.push_str(src);
-				break
-			}
-		};
-
-		let (import_name, suffix) = suffix.split_once('\n')
-			.expect(&format!("Expected '{search_pattern} <name>'"));
-
-		src = suffix;
 
-		let import_name = import_name.trim();
-		let import_data = imports.get(import_name)
-			.expect("Unknown import");
-
-		resolved_source.push_str(prefix);
-		resolved_source.push_str(&import_data.source);
-
-		dependent_resource_scopes.insert(import_data.resource_scope_id);
-	}
+	Ok(())
+}
 
-	ResolvedShaderSource {
-		resolved_source,
-		dependent_resource_scopes,
+unsafe fn delete_shaders(shader_handles: &[u32]) {
+	for &shader_handle in shader_handles {
+		gfx::raw::DeleteShader(shader_handle);
 	}
 }
 
 
 
+
 #[derive(Debug)]
 pub struct CompilationError {
 	what: String,
@@ -245,6 +691,11 @@ impl CompilationError {
 			backtrace: std::backtrace::Backtrace::capture(),
 		}
 	}
+
+	/// Like the [`Display`](std::fmt::Display) impl but without the backtrace.
+	fn summary(&self) -> String {
+		format!("{} failed\n{}", self.what, self.description)
+	}
 }
 
 impl std::fmt::Display for CompilationError {
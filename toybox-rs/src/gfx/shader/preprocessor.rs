@@ -0,0 +1,347 @@
+//! Expands `#import <name>` directives and injects `#define`s before shader sources are handed to the driver.
+//!
+//! Imports are resolved recursively and each import is only ever included once per stage, so imports can safely
+//! import each other. `#line` directives are emitted around each import, so line numbers in driver errors refer to
+//! the original source - see [`map_error_locations`] for turning source string numbers back into names.
+
+use super::{CompilationError, ImportData};
+use crate::utility::ResourceScopeID;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+
+pub(super) struct PreprocessedSource {
+	pub(super) source: String,
+
+	/// Name of each source string number used in `#line` directives. 0 is always the stage itself.
+	pub(super) source_names: Vec<String>,
+
+	pub(super) dependent_resource_scopes: HashSet<ResourceScopeID>,
+	pub(super) dependent_imports: HashSet<String>,
+}
+
+
+/// `name` is used to identify `src` in errors, and `defines` are injected directly after the `#version` directive.
+pub(super) fn preprocess(src: &str, name: &str, defines: &[(&str, &str)], imports: &HashMap<String, ImportData>)
+	-> Result<PreprocessedSource, CompilationError>
+{
+	let mut preprocessor = Preprocessor {
+		imports,
+		output: String::with_capacity(src.len()),
+		source_names: vec![name.to_owned()],
+		import_stack: Vec::new(),
+		dependent_resource_scopes: HashSet::new(),
+		dependent_imports: HashSet::new(),
+	};
+
+	// #version must come before anything else, so defines have to go after it.
+	let version_line = src.lines()
+		.position(|line| line.trim_start().starts_with("#version"));
+
+	match version_line {
+		Some(version_line) => {
+			let (head, tail) = split_after_line(src, version_line);
+			preprocessor.process(head, 0, 0)?;
+			preprocessor.inject_defines(defines, version_line + 2)?;
+			preprocessor.process(tail, 0, version_line + 1)?;
+		}
+
+		None => {
+			preprocessor.inject_defines(defines, 1)?;
+			preprocessor.process(src, 0, 0)?;
+		}
+	}
+
+	let Preprocessor{output, source_names, dependent_resource_scopes, dependent_imports, ..} = preprocessor;
+
+	Ok(PreprocessedSource {
+		source: output,
+		source_names,
+		dependent_resource_scopes,
+		dependent_imports,
+	})
+}
+
+
+/// Replaces source string numbers at the start of each line of a driver info log with the names they refer to.
+/// Handles the common `0(12) : error ...` and `0:12(3): error ...` formats, optionally prefixed with `ERROR: ` or `WARNING: `.
+pub(super) fn map_error_locations(log: &str, source_names: &[String]) -> String {
+	log.lines()
+		.map(|line| {
+			let prefix_len = ["ERROR: ", "WARNING: "].iter()
+				.find(|prefix| line.starts_with(*prefix))
+				.map_or(0, |prefix| prefix.len());
+
+			let (prefix, location) = line.split_at(prefix_len);
+			let digits_len = location.len() - location.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+			let (source_number, rest) = location.split_at(digits_len);
+
+			let source_name = source_number.parse::<usize>().ok()
+				.filter(|_| rest.starts_with(['(', ':']))
+				.and_then(|source_number| source_names.get(source_number));
+
+			match source_name {
+				Some(source_name) => format!("{prefix}{source_name}{rest}"),
+				None => line.to_owned(),
+			}
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+
+
+struct Preprocessor<'a> {
+	imports: &'a HashMap<String, ImportData>,
+	output: String,
+	source_names: Vec<String>,
+
+	/// Imports currently being expanded, outermost first - for detecting cycles.
+	import_stack: Vec<&'a str>,
+
+	dependent_resource_scopes: HashSet<ResourceScopeID>,
+	dependent_imports: HashSet<String>,
+}
+
+impl<'a> Preprocessor<'a> {
+	/// `first_line` is the zero-based line `src` starts on within the source it came from.
+	fn process(&mut self, src: &str, source_number: usize, first_line: usize) -> Result<(), CompilationError> {
+		let mut in_block_comment = false;
+
+		for (line_index, line) in src.lines().enumerate() {
+			let line_number = first_line + line_index + 1;
+			let starts_in_block_comment = in_block_comment;
+			in_block_comment = ends_in_block_comment(line, in_block_comment);
+
+			let import_name = match starts_in_block_comment {
+				false => parse_import(line).map_err(|message| self.error(source_number, line_number, message))?,
+				true => None,
+			};
+
+			let Some(import_name) = import_name else {
+				self.output.push_str(line);
+				self.output.push('\n');
+				continue
+			};
+
+			// Imports are included once, so keep the directive line as a blank line to keep line numbers intact.
+			if !self.expand_import(import_name, source_number, line_number)? {
+				self.output.push('\n');
+				continue
+			}
+
+			writeln!(self.output, "#line {} {}", line_number + 1, source_number).unwrap();
+		}
+
+		Ok(())
+	}
+
+	/// Returns whether anything was included.
+	fn expand_import(&mut self, name: &str, source_number: usize, line_number: usize) -> Result<bool, CompilationError> {
+		let Some((import_name, import_data)) = self.imports.get_key_value(name) else {
+			return Err(self.error(source_number, line_number, format!("unknown import '{name}'")))
+		};
+
+		let import_name = import_name.as_str();
+
+		if self.import_stack.contains(&import_name) {
+			let cycle = self.import_stack.iter()
+				.copied()
+				.skip_while(|&stacked| stacked != import_name)
+				.chain(std::iter::once(import_name))
+				.collect::<Vec<_>>()
+				.join(" -> ");
+
+			return Err(self.error(source_number, line_number, format!("import cycle: {cycle}")))
+		}
+
+		if !self.dependent_imports.insert(import_name.to_owned()) {
+			return Ok(false)
+		}
+
+		self.dependent_resource_scopes.insert(import_data.resource_scope_id);
+
+		let import_source_number = self.source_names.len();
+		let source_name = match &import_data.path {
+			Some(path) => path.display().to_string(),
+			None => format!("<{import_name}>"),
+		};
+
+		self.source_names.push(source_name);
+
+		writeln!(self.output, "#line 1 {import_source_number}").unwrap();
+
+		self.import_stack.push(import_name);
+		self.process(&import_data.source, import_source_number, 0)?;
+		self.import_stack.pop();
+
+		Ok(true)
+	}
+
+	/// `next_line_number` is the line number in the main source that follows the injected defines.
+	fn inject_defines(&mut self, defines: &[(&str, &str)], next_line_number: usize) -> Result<(), CompilationError> {
+		if defines.is_empty() {
+			return Ok(())
+		}
+
+		for &(name, value) in defines {
+			let is_identifier = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+				&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+			if !is_identifier || value.contains('\n') {
+				return Err(CompilationError::new("shader preprocessing",
+					&format!("invalid define '{name}' = '{value}'")))
+			}
+
+			writeln!(self.output, "#define {name} {value}").unwrap();
+		}
+
+		writeln!(self.output, "#line {next_line_number} 0").unwrap();
+		Ok(())
+	}
+
+	fn error(&self, source_number: usize, line_number: usize, message: impl AsRef<str>) -> CompilationError {
+		let source_name = &self.source_names[source_number];
+		CompilationError::new("shader preprocessing", &format!("{source_name}:{line_number}: {}", message.as_ref()))
+	}
+}
+
+
+/// Returns the name of the import if `line` is an `#import` directive.
+fn parse_import(line: &str) -> Result<Option<&str>, String> {
+	let Some(rest) = line.trim_start().strip_prefix("#import") else {
+		return Ok(None)
+	};
+
+	// Something like #imports isn't ours to deal with.
+	if rest.starts_with(|c: char| !c.is_whitespace()) {
+		return Ok(None)
+	}
+
+	let rest = rest.split("//").next().unwrap_or_default();
+	let name = rest.trim().trim_matches(|c| c == '"' || c == '<' || c == '>');
+
+	match name.is_empty() || name.contains(char::is_whitespace) {
+		true => Err("expected '#import <name>'".into()),
+		false => Ok(Some(name)),
+	}
+}
+
+/// Tracks `/* */` comments through `line`, ignoring anything after a `//`.
+fn ends_in_block_comment(line: &str, mut in_block_comment: bool) -> bool {
+	let mut rest = line;
+
+	loop {
+		if in_block_comment {
+			let Some((_, after)) = rest.split_once("*/") else { return true };
+			rest = after;
+			in_block_comment = false;
+			continue
+		}
+
+		let line_comment = rest.find("//");
+		let block_comment = rest.find("/*");
+
+		match (line_comment, block_comment) {
+			(_, None) => return false,
+			(Some(line_comment), Some(block_comment)) if line_comment < block_comment => return false,
+			(_, Some(block_comment)) => {
+				rest = &rest[block_comment + 2..];
+				in_block_comment = true;
+			}
+		}
+	}
+}
+
+/// Splits `src` into everything up to and including zero-based line `line_index`, and everything after.
+fn split_after_line(src: &str, line_index: usize) -> (&str, &str) {
+	let split_point = src.match_indices('\n')
+		.nth(line_index)
+		.map_or(src.len(), |(index, _)| index + 1);
+
+	src.split_at(split_point)
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn import(source: &str) -> ImportData {
+		ImportData {
+			source: source.into(),
+			resource_scope_id: ResourceScopeID(0),
+			path: None,
+		}
+	}
+
+	fn imports(list: &[(&str, &str)]) -> HashMap<String, ImportData> {
+		list.iter()
+			.map(|&(name, source)| (name.to_owned(), import(source)))
+			.collect()
+	}
+
+	#[test]
+	fn test_nested_imports_included_once() {
+		let imports = imports(&[
+			("a", "#import b\nfloat a;"),
+			("b", "float b;"),
+		]);
+
+		let src = "#version 450\n#import a\n#import b\nvoid main() {}\n";
+		let result = preprocess(src, "main", &[], &imports).unwrap();
+
+		assert_eq!(result.source.matches("float b;").count(), 1);
+		assert_eq!(result.source_names, ["main", "<a>", "<b>"]);
+		assert_eq!(result.dependent_imports.len(), 2);
+
+		// Lines after each import are mapped back to where they came from.
+		assert!(result.source.contains("#line 1 1\n#line 1 2\nfloat b;\n#line 2 1\nfloat a;\n#line 3 0\n\nvoid main() {}"));
+	}
+
+	#[test]
+	fn test_import_errors() {
+		let imports = imports(&[
+			("a", "#import b"),
+			("b", "#import a"),
+		]);
+
+		let cycle = preprocess("#import a\n", "main", &[], &imports).err().unwrap();
+		assert!(cycle.description.contains("a -> b -> a"), "{}", cycle.description);
+
+		let unknown = preprocess("\n#import c\n", "main", &[], &imports).err().unwrap();
+		assert!(unknown.description.contains("main:2: unknown import 'c'"), "{}", unknown.description);
+
+		assert!(preprocess("#import\n", "main", &[], &imports).is_err());
+	}
+
+	#[test]
+	fn test_imports_in_comments_ignored() {
+		let src = "// #import a\n/*\n#import a\n*/ /* */\n#import a\n";
+		let result = preprocess(src, "main", &[], &imports(&[("a", "float a;")])).unwrap();
+
+		assert_eq!(result.source.matches("float a;").count(), 1);
+		assert!(result.source.starts_with("// #import a\n/*\n#import a\n*/ /* */\n#line 1 1\n"));
+	}
+
+	#[test]
+	fn test_defines() {
+		let src = "// comment\n#version 450\nvoid main() {}\n";
+		let result = preprocess(src, "main", &[("FOO", "1"), ("BAR", "")], &HashMap::new()).unwrap();
+
+		assert_eq!(result.source, "// comment\n#version 450\n#define FOO 1\n#define BAR \n#line 3 0\nvoid main() {}\n");
+		assert!(preprocess(src, "main", &[("1FOO", "")], &HashMap::new()).is_err());
+	}
+
+	#[test]
+	fn test_map_error_locations() {
+		let names = ["main.frag".to_owned(), "<common>".to_owned()];
+
+		assert_eq!(map_error_locations("0(12) : error C0000: oops", &names), "main.frag(12) : error C0000: oops");
+		assert_eq!(map_error_locations("1:3(5): error: oops", &names), "<common>:3(5): error: oops");
+		assert_eq!(map_error_locations("ERROR: 1:3: oops", &names), "ERROR: <common>:3: oops");
+		assert_eq!(map_error_locations("7:3: unknown", &names), "7:3: unknown");
+	}
+}
@@ -0,0 +1,499 @@
+use super::Shader;
+use crate::gfx::raw;
+
+
+/// Everything a linked [`Shader`] expects to be bound in order to draw or dispatch, as reported by the driver.
+/// Only resources the driver considers active - i.e., actually used by the shader - are included.
+///
+/// Acquired with [`Shader::reflect`].
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection {
+	/// Vertex shader inputs, excluding builtins like `gl_VertexID`.
+	pub attributes: Vec<AttributeInfo>,
+
+	pub uniform_blocks: Vec<BlockInfo>,
+	pub storage_blocks: Vec<BlockInfo>,
+
+	pub samplers: Vec<OpaqueUniformInfo>,
+	pub images: Vec<OpaqueUniformInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttributeInfo {
+	pub name: String,
+	pub location: u32,
+	pub ty: ShaderType,
+	pub array_size: u32,
+}
+
+/// A uniform block or shader storage block.
+#[derive(Debug, Clone)]
+pub struct BlockInfo {
+	pub name: String,
+	pub binding: u32,
+
+	/// The minimum size a buffer bound to this block must be. For storage blocks ending in an unsized array,
+	/// this includes a single element of that array.
+	pub size_bytes: u32,
+
+	pub members: Vec<BlockMember>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockMember {
+	/// Fully qualified, e.g., `Block.member[0]` for arrays of structs.
+	pub name: String,
+	pub ty: ShaderType,
+	pub offset_bytes: u32,
+
+	/// 0 for unsized arrays at the end of storage blocks.
+	pub array_size: u32,
+	pub array_stride_bytes: u32,
+}
+
+/// A sampler or image uniform.
+#[derive(Debug, Clone)]
+pub struct OpaqueUniformInfo {
+	pub name: String,
+
+	/// The texture or image unit the uniform reads from, as set with `layout(binding = N)`.
+	pub binding: u32,
+	pub ty: ShaderType,
+}
+
+
+/// The GLSL type of a shader variable, wrapping the GL type enum.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderType(pub u32);
+
+/// The type of each component of a [`ShaderType`], or of the texels returned by a sampler or image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ScalarKind {
+	Float,
+	Int,
+	Uint,
+	Bool,
+	Double,
+}
+
+/// The kind of texture a sampler or image type expects.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextureDimension {
+	D1,
+	D2,
+	D3,
+	Cube,
+	D2Array,
+	CubeArray,
+	D2Multisample,
+	Buffer,
+}
+
+
+impl Shader {
+	/// Queries the active attributes, blocks, samplers and images of this shader from the driver.
+	/// This is relatively slow, so avoid calling it every frame.
+	pub fn reflect(&self) -> ShaderReflection {
+		let program = self.0;
+
+		let attributes = (0..active_resources(program, raw::PROGRAM_INPUT))
+			.filter_map(|index| {
+				let [location, ty, array_size] = resource_properties(program, raw::PROGRAM_INPUT, index,
+					[raw::LOCATION, raw::TYPE, raw::ARRAY_SIZE]);
+
+				// Builtins have no location.
+				(location >= 0).then(|| AttributeInfo {
+					name: resource_name(program, raw::PROGRAM_INPUT, index),
+					location: location as u32,
+					ty: ShaderType(ty as u32),
+					array_size: array_size as u32,
+				})
+			})
+			.collect();
+
+		let mut samplers = Vec::new();
+		let mut images = Vec::new();
+
+		for index in 0..active_resources(program, raw::UNIFORM) {
+			let [location, ty, block_index] = resource_properties(program, raw::UNIFORM, index,
+				[raw::LOCATION, raw::TYPE, raw::BLOCK_INDEX]);
+
+			let ty = ShaderType(ty as u32);
+			if block_index >= 0 || location < 0 || !(ty.is_sampler() || ty.is_image()) {
+				continue
+			}
+
+			let mut binding = 0;
+			unsafe {
+				raw::GetUniformiv(program, location, &mut binding);
+			}
+
+			let uniform = OpaqueUniformInfo {
+				name: resource_name(program, raw::UNIFORM, index),
+				binding: binding as u32,
+				ty,
+			};
+
+			match ty.is_sampler() {
+				true => samplers.push(uniform),
+				false => images.push(uniform),
+			}
+		}
+
+		ShaderReflection {
+			attributes,
+			uniform_blocks: reflect_blocks(program, raw::UNIFORM_BLOCK, raw::UNIFORM),
+			storage_blocks: reflect_blocks(program, raw::SHADER_STORAGE_BLOCK, raw::BUFFER_VARIABLE),
+			samplers,
+			images,
+		}
+	}
+}
+
+
+impl ShaderReflection {
+	pub fn attribute(&self, location: u32) -> Option<&AttributeInfo> {
+		self.attributes.iter().find(|attribute| attribute.location == location)
+	}
+
+	pub fn uniform_block(&self, name: &str) -> Option<&BlockInfo> {
+		self.uniform_blocks.iter().find(|block| block.name == name)
+	}
+
+	pub fn storage_block(&self, name: &str) -> Option<&BlockInfo> {
+		self.storage_blocks.iter().find(|block| block.name == name)
+	}
+}
+
+impl BlockInfo {
+	pub fn member(&self, name: &str) -> Option<&BlockMember> {
+		self.members.iter().find(|member| member.name == name)
+	}
+}
+
+
+impl ShaderType {
+	/// The component type and number of components for scalar, vector and matrix types.
+	/// Matrices count every element - e.g., 16 for `mat4`.
+	pub fn components(&self) -> Option<(ScalarKind, u32)> {
+		use ScalarKind::*;
+
+		let info = match self.0 {
+			raw::FLOAT => (Float, 1),
+			raw::FLOAT_VEC2 => (Float, 2),
+			raw::FLOAT_VEC3 => (Float, 3),
+			raw::FLOAT_VEC4 => (Float, 4),
+			raw::FLOAT_MAT2 => (Float, 4),
+			raw::FLOAT_MAT3 => (Float, 9),
+			raw::FLOAT_MAT4 => (Float, 16),
+			raw::FLOAT_MAT2x3 | raw::FLOAT_MAT3x2 => (Float, 6),
+			raw::FLOAT_MAT2x4 | raw::FLOAT_MAT4x2 => (Float, 8),
+			raw::FLOAT_MAT3x4 | raw::FLOAT_MAT4x3 => (Float, 12),
+
+			raw::INT => (Int, 1),
+			raw::INT_VEC2 => (Int, 2),
+			raw::INT_VEC3 => (Int, 3),
+			raw::INT_VEC4 => (Int, 4),
+
+			raw::UNSIGNED_INT => (Uint, 1),
+			raw::UNSIGNED_INT_VEC2 => (Uint, 2),
+			raw::UNSIGNED_INT_VEC3 => (Uint, 3),
+			raw::UNSIGNED_INT_VEC4 => (Uint, 4),
+
+			raw::BOOL => (Bool, 1),
+			raw::BOOL_VEC2 => (Bool, 2),
+			raw::BOOL_VEC3 => (Bool, 3),
+			raw::BOOL_VEC4 => (Bool, 4),
+
+			raw::DOUBLE => (Double, 1),
+			raw::DOUBLE_VEC2 => (Double, 2),
+			raw::DOUBLE_VEC3 => (Double, 3),
+			raw::DOUBLE_VEC4 => (Double, 4),
+
+			_ => return None,
+		};
+
+		Some(info)
+	}
+
+	pub fn is_sampler(&self) -> bool {
+		self.sampler_info().is_some()
+	}
+
+	pub fn is_image(&self) -> bool {
+		self.image_info().is_some()
+	}
+
+	/// Whether this is a shadow sampler, which requires a depth texture sampled with a comparison.
+	pub fn is_shadow_sampler(&self) -> bool {
+		matches!(self.0, raw::SAMPLER_1D_SHADOW | raw::SAMPLER_2D_SHADOW | raw::SAMPLER_CUBE_SHADOW
+			| raw::SAMPLER_2D_ARRAY_SHADOW | raw::SAMPLER_CUBE_MAP_ARRAY_SHADOW)
+	}
+
+	/// The kind of texture this sampler or image type reads from, and the type of value it returns.
+	pub fn texture_info(&self) -> Option<(TextureDimension, ScalarKind)> {
+		self.sampler_info().or_else(|| self.image_info())
+	}
+
+	fn sampler_info(&self) -> Option<(TextureDimension, ScalarKind)> {
+		use TextureDimension::*;
+		use ScalarKind::*;
+
+		let info = match self.0 {
+			raw::SAMPLER_1D | raw::SAMPLER_1D_SHADOW => (D1, Float),
+			raw::SAMPLER_2D | raw::SAMPLER_2D_SHADOW => (D2, Float),
+			raw::SAMPLER_3D => (D3, Float),
+			raw::SAMPLER_CUBE | raw::SAMPLER_CUBE_SHADOW => (Cube, Float),
+			raw::SAMPLER_2D_ARRAY | raw::SAMPLER_2D_ARRAY_SHADOW => (D2Array, Float),
+			raw::SAMPLER_CUBE_MAP_ARRAY | raw::SAMPLER_CUBE_MAP_ARRAY_SHADOW => (CubeArray, Float),
+			raw::SAMPLER_2D_MULTISAMPLE => (D2Multisample, Float),
+			raw::SAMPLER_BUFFER => (Buffer, Float),
+
+			raw::INT_SAMPLER_1D => (D1, Int),
+			raw::INT_SAMPLER_2D => (D2, Int),
+			raw::INT_SAMPLER_3D => (D3, Int),
+			raw::INT_SAMPLER_CUBE => (Cube, Int),
+			raw::INT_SAMPLER_2D_ARRAY => (D2Array, Int),
+			raw::INT_SAMPLER_CUBE_MAP_ARRAY => (CubeArray, Int),
+			raw::INT_SAMPLER_2D_MULTISAMPLE => (D2Multisample, Int),
+			raw::INT_SAMPLER_BUFFER => (Buffer, Int),
+
+			raw::UNSIGNED_INT_SAMPLER_1D => (D1, Uint),
+			raw::UNSIGNED_INT_SAMPLER_2D => (D2, Uint),
+			raw::UNSIGNED_INT_SAMPLER_3D => (D3, Uint),
+			raw::UNSIGNED_INT_SAMPLER_CUBE => (Cube, Uint),
+			raw::UNSIGNED_INT_SAMPLER_2D_ARRAY => (D2Array, Uint),
+			raw::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY => (CubeArray, Uint),
+			raw::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE => (D2Multisample, Uint),
+			raw::UNSIGNED_INT_SAMPLER_BUFFER => (Buffer, Uint),
+
+			_ => return None,
+		};
+
+		Some(info)
+	}
+
+	fn image_info(&self) -> Option<(TextureDimension, ScalarKind)> {
+		use TextureDimension::*;
+		use ScalarKind::*;
+
+		let info = match self.0 {
+			raw::IMAGE_1D => (D1, Float),
+			raw::IMAGE_2D => (D2, Float),
+			raw::IMAGE_3D => (D3, Float),
+			raw::IMAGE_CUBE => (Cube, Float),
+			raw::IMAGE_2D_ARRAY => (D2Array, Float),
+			raw::IMAGE_CUBE_MAP_ARRAY => (CubeArray, Float),
+			raw::IMAGE_2D_MULTISAMPLE => (D2Multisample, Float),
+			raw::IMAGE_BUFFER => (Buffer, Float),
+
+			raw::INT_IMAGE_1D => (D1, Int),
+			raw::INT_IMAGE_2D => (D2, Int),
+			raw::INT_IMAGE_3D => (D3, Int),
+			raw::INT_IMAGE_CUBE => (Cube, Int),
+			raw::INT_IMAGE_2D_ARRAY => (D2Array, Int),
+			raw::INT_IMAGE_CUBE_MAP_ARRAY => (CubeArray, Int),
+			raw::INT_IMAGE_2D_MULTISAMPLE => (D2Multisample, Int),
+			raw::INT_IMAGE_BUFFER => (Buffer, Int),
+
+			raw::UNSIGNED_INT_IMAGE_1D => (D1, Uint),
+			raw::UNSIGNED_INT_IMAGE_2D => (D2, Uint),
+			raw::UNSIGNED_INT_IMAGE_3D => (D3, Uint),
+			raw::UNSIGNED_INT_IMAGE_CUBE => (Cube, Uint),
+			raw::UNSIGNED_INT_IMAGE_2D_ARRAY => (D2Array, Uint),
+			raw::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY => (CubeArray, Uint),
+			raw::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE => (D2Multisample, Uint),
+			raw::UNSIGNED_INT_IMAGE_BUFFER => (Buffer, Uint),
+
+			_ => return None,
+		};
+
+		Some(info)
+	}
+}
+
+impl std::fmt::Display for ShaderType {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		use ScalarKind::*;
+
+		let matrix_name = match self.0 {
+			raw::FLOAT_MAT2 => Some("mat2"),
+			raw::FLOAT_MAT3 => Some("mat3"),
+			raw::FLOAT_MAT4 => Some("mat4"),
+			raw::FLOAT_MAT2x3 => Some("mat2x3"),
+			raw::FLOAT_MAT2x4 => Some("mat2x4"),
+			raw::FLOAT_MAT3x2 => Some("mat3x2"),
+			raw::FLOAT_MAT3x4 => Some("mat3x4"),
+			raw::FLOAT_MAT4x2 => Some("mat4x2"),
+			raw::FLOAT_MAT4x3 => Some("mat4x3"),
+			_ => None,
+		};
+
+		if let Some(matrix_name) = matrix_name {
+			return f.write_str(matrix_name)
+		}
+
+		if let Some((kind, components)) = self.components() {
+			let (scalar, prefix) = match kind {
+				Float => ("float", ""),
+				Int => ("int", "i"),
+				Uint => ("uint", "u"),
+				Bool => ("bool", "b"),
+				Double => ("double", "d"),
+			};
+
+			return match components {
+				1 => f.write_str(scalar),
+				_ => write!(f, "{prefix}vec{components}"),
+			}
+		}
+
+		if let Some((dimension, kind)) = self.texture_info() {
+			let prefix = match kind {
+				Int => "i",
+				Uint => "u",
+				_ => "",
+			};
+
+			let base = if self.is_sampler() { "sampler" } else { "image" };
+			let shadow = if self.is_shadow_sampler() { "Shadow" } else { "" };
+			return write!(f, "{prefix}{base}{dimension}{shadow}")
+		}
+
+		write!(f, "<unknown type 0x{:x}>", self.0)
+	}
+}
+
+impl std::fmt::Display for TextureDimension {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let suffix = match self {
+			TextureDimension::D1 => "1D",
+			TextureDimension::D2 => "2D",
+			TextureDimension::D3 => "3D",
+			TextureDimension::Cube => "Cube",
+			TextureDimension::D2Array => "2DArray",
+			TextureDimension::CubeArray => "CubeArray",
+			TextureDimension::D2Multisample => "2DMS",
+			TextureDimension::Buffer => "Buffer",
+		};
+
+		f.write_str(suffix)
+	}
+}
+
+
+
+fn reflect_blocks(program: u32, block_interface: u32, variable_interface: u32) -> Vec<BlockInfo> {
+	(0..active_resources(program, block_interface))
+		.map(|index| {
+			let [binding, size_bytes, num_variables] = resource_properties(program, block_interface, index,
+				[raw::BUFFER_BINDING, raw::BUFFER_DATA_SIZE, raw::NUM_ACTIVE_VARIABLES]);
+
+			let mut variable_indices = vec![0i32; num_variables as usize];
+
+			if num_variables > 0 {
+				let property = raw::ACTIVE_VARIABLES;
+				unsafe {
+					raw::GetProgramResourceiv(program, block_interface, index, 1, &property,
+						num_variables, std::ptr::null_mut(), variable_indices.as_mut_ptr());
+				}
+			}
+
+			let mut members: Vec<_> = variable_indices.into_iter()
+				.map(|variable_index| {
+					let variable_index = variable_index as u32;
+					let [ty, offset_bytes, array_size, array_stride_bytes] = resource_properties(program, variable_interface,
+						variable_index, [raw::TYPE, raw::OFFSET, raw::ARRAY_SIZE, raw::ARRAY_STRIDE]);
+
+					BlockMember {
+						name: resource_name(program, variable_interface, variable_index),
+						ty: ShaderType(ty as u32),
+						offset_bytes: offset_bytes as u32,
+						array_size: array_size as u32,
+						array_stride_bytes: array_stride_bytes as u32,
+					}
+				})
+				.collect();
+
+			members.sort_by_key(|member| member.offset_bytes);
+
+			BlockInfo {
+				name: resource_name(program, block_interface, index),
+				binding: binding as u32,
+				size_bytes: size_bytes as u32,
+				members,
+			}
+		})
+		.collect()
+}
+
+
+fn active_resources(program: u32, interface: u32) -> u32 {
+	let mut count = 0;
+	unsafe {
+		raw::GetProgramInterfaceiv(program, interface, raw::ACTIVE_RESOURCES, &mut count);
+	}
+
+	count as u32
+}
+
+fn resource_properties<const N: usize>(program: u32, interface: u32, index: u32, properties: [u32; N]) -> [i32; N] {
+	let mut values = [0i32; N];
+	unsafe {
+		raw::GetProgramResourceiv(program, interface, index, N as i32, properties.as_ptr(),
+			N as i32, std::ptr::null_mut(), values.as_mut_ptr());
+	}
+
+	values
+}
+
+fn resource_name(program: u32, interface: u32, index: u32) -> String {
+	let [name_length] = resource_properties(program, interface, index, [raw::NAME_LENGTH]);
+	let mut buffer = vec![0u8; name_length.max(1) as usize];
+	let mut length = 0;
+
+	unsafe {
+		raw::GetProgramResourceName(program, interface, index, buffer.len() as i32, &mut length, buffer.as_mut_ptr() as *mut _);
+	}
+
+	buffer.truncate(length as usize);
+	String::from_utf8_lossy(&buffer).into_owned()
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_shader_type_names() {
+		let names = [
+			(raw::FLOAT, "float"),
+			(raw::UNSIGNED_INT_VEC2, "uvec2"),
+			(raw::FLOAT_VEC4, "vec4"),
+			(raw::FLOAT_MAT3x4, "mat3x4"),
+			(raw::INT_SAMPLER_2D_ARRAY, "isampler2DArray"),
+			(raw::SAMPLER_CUBE_SHADOW, "samplerCubeShadow"),
+			(raw::UNSIGNED_INT_IMAGE_3D, "uimage3D"),
+		];
+
+		for (ty, name) in names {
+			assert_eq!(ShaderType(ty).to_string(), name);
+		}
+	}
+
+	#[test]
+	fn test_shader_type_classification() {
+		assert_eq!(ShaderType(raw::INT_VEC3).components(), Some((ScalarKind::Int, 3)));
+		assert_eq!(ShaderType(raw::FLOAT_MAT4).components(), Some((ScalarKind::Float, 16)));
+		assert_eq!(ShaderType(raw::SAMPLER_2D).components(), None);
+
+		assert!(ShaderType(raw::SAMPLER_2D_SHADOW).is_sampler());
+		assert!(ShaderType(raw::SAMPLER_2D_SHADOW).is_shadow_sampler());
+		assert!(!ShaderType(raw::IMAGE_2D).is_sampler());
+		assert!(ShaderType(raw::IMAGE_2D).is_image());
+
+		assert_eq!(ShaderType(raw::UNSIGNED_INT_SAMPLER_CUBE).texture_info(), Some((TextureDimension::Cube, ScalarKind::Uint)));
+		assert_eq!(ShaderType(raw::FLOAT_VEC2).texture_info(), None);
+	}
+}
@@ -0,0 +1,35 @@
+#version 450
+
+layout(std140, binding = 0) uniform Uniforms {
+    vec4 u_letterbox_color;
+
+    // In output pixels, origin bottom left - same as gl_FragCoord.
+    vec2 u_image_min;
+    vec2 u_image_size;
+
+    vec2 u_texture_size;
+    float u_scale;
+};
+
+layout(binding = 0) uniform sampler2D u_image;
+
+out vec4 out_color;
+
+void main() {
+    vec2 uv = (gl_FragCoord.xy - u_image_min) / u_image_size;
+
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThanEqual(uv, vec2(1.0)))) {
+        out_color = u_letterbox_color;
+        return;
+    }
+
+    // Sharp bilinear: only blend across the one output pixel wide band at the edge of each texel, so
+    // non-integer scales don't produce uneven pixel sizes, and integer scales sample texel centers exactly.
+    vec2 texel = uv * u_texture_size;
+    vec2 texel_floor = floor(texel);
+    vec2 center_dist = fract(texel) - 0.5;
+    vec2 region_range = vec2(max(0.5 - 0.5 / u_scale, 0.0));
+    vec2 f = (center_dist - clamp(center_dist, -region_range, region_range)) * u_scale + 0.5;
+
+    out_color = texture(u_image, (texel_floor + f) / u_texture_size);
+}
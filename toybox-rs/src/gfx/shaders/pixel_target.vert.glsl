@@ -0,0 +1,12 @@
+#version 450
+
+// Single triangle covering the whole viewport - no vertex buffers needed.
+const vec2 c_positions[3] = vec2[](
+    vec2(-1.0, -1.0),
+    vec2( 3.0, -1.0),
+    vec2(-1.0,  3.0)
+);
+
+void main() {
+    gl_Position = vec4(c_positions[gl_VertexID], 0.0, 1.0);
+}
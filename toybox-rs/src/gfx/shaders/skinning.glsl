@@ -0,0 +1,23 @@
+// Bone matrices as produced by toy::MeshAnimationData::pose_matrices.
+// Each is a row-major affine transform, matching the layout of common::Mat3x4.
+layout(std430, row_major, binding = 7) readonly buffer SkinningBoneData {
+	mat4x3 u_bone_matrices[];
+};
+
+mat4x3 skinning_matrix(uvec3 joints, vec3 weights) {
+	// Weights aren't guaranteed to be normalised, so fall back to the identity transform for unweighted vertices
+	float total_weight = weights.x + weights.y + weights.z;
+	if (total_weight <= 0.0) {
+		return mat4x3(1.0);
+	}
+
+	weights /= total_weight;
+
+	return u_bone_matrices[joints.x] * weights.x
+		+ u_bone_matrices[joints.y] * weights.y
+		+ u_bone_matrices[joints.z] * weights.z;
+}
+
+vec3 skin_position(vec3 position, uvec3 joints, vec3 weights) {
+	return skinning_matrix(joints, weights) * vec4(position, 1.0);
+}
@@ -0,0 +1,66 @@
+//! Support for deforming meshes with [`toy::MeshAnimationData`] on the GPU.
+//!
+//! Shaders can `#import skinning` to gain access to `skin_position(position, joints, weights)`, which reads
+//! bone matrices from a shader storage buffer bound at [`SKINNING_BONE_BUFFER_BINDING`].
+//! Bone matrices can be calculated with [`toy::MeshAnimationData::pose_matrices`] and uploaded to a [`gfx::Buffer<Mat3x4>`](gfx::Buffer).
+//!
+//! ```ignore
+//! let matrices = animation_data.pose_matrices(&pose);
+//! bone_buffer.upload(&matrices);
+//!
+//! gfx.bind_shader_storage_buffer(gfx::SKINNING_BONE_BUFFER_BINDING, bone_buffer);
+//! mesh.draw(&mut gfx, gfx::DrawMode::Triangles);
+//! ```
+
+use crate::prelude::*;
+use crate::gfx::{MeshData, SkinnedVertex};
+
+
+/// Name of the standard shader import that provides skinning functions.
+pub const SKINNING_SHADER_IMPORT: &str = "skinning";
+
+/// Shader storage buffer binding that the `skinning` shader import reads bone matrices from.
+pub const SKINNING_BONE_BUFFER_BINDING: u32 = 7;
+
+pub(super) const SKINNING_SHADER_IMPORT_SOURCE: &str = include_str!("shaders/skinning.glsl");
+
+
+/// Builds [`MeshData`] suitable for rendering with the `skinning` shader import from a skinned [`toy::Mesh`].
+/// Vertex colors are taken from `color_layer` if it exists, otherwise vertices will be white.
+/// Meshes without animation data will produce vertices without any weights, which are left undeformed.
+///
+/// ## Panics
+/// Panics if `mesh` has more vertices than can be addressed by `I`.
+pub fn skinned_mesh_data<I: gfx::IndexElement>(mesh: &toy::Mesh, color_layer: Option<&str>) -> MeshData<SkinnedVertex, I> {
+	assert!(mesh.positions.len() <= I::MAX_VERTICES, "Too many vertices for index type");
+
+	let color_data = color_layer.and_then(|name| mesh.color_layer_by_name(name))
+		.map(|layer| layer.data.as_slice());
+
+	let weights = mesh.animation_data.as_ref()
+		.map(|animation_data| animation_data.weights.as_slice());
+
+	let vertices = mesh.positions.iter()
+		.enumerate()
+		.map(|(index, &pos)| {
+			let color = color_data.and_then(|data| data.get(index))
+				.map_or(Color::white(), |&color| Color::from(color));
+
+			let weight = weights.and_then(|weights| weights.get(index))
+				.copied()
+				.unwrap_or_default();
+
+			SkinnedVertex {
+				pos,
+				color,
+				weights: Vec3::from(weight.weights),
+				joints: weight.indices,
+			}
+		})
+		.collect();
+
+	MeshData {
+		vertices,
+		indices: mesh.indices.iter().map(|&index| I::from_usize(index as usize)).collect(),
+	}
+}
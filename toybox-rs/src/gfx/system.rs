@@ -1,5 +1,7 @@
 use crate::prelude::*;
 use crate::gfx::*;
+use crate::gfx::viewport::RenderTargetState;
+use crate::gfx::validation::DrawValidator;
 use crate::utility::resource_scope::*;
 
 
@@ -21,6 +23,9 @@ pub struct System {
 	shader_manager: ShaderManager,
 	capabilities: Capabilities,
 	backbuffer_size: Vec2i,
+	pipeline_state: PipelineState,
+	render_target: RenderTargetState,
+	draw_validator: DrawValidator,
 
 	pub resources: Resources,
 
@@ -38,6 +43,20 @@ impl System {
 
 	pub fn capabilities(&self) -> &Capabilities { &self.capabilities }
 
+	/// Shaders loaded with [`ResourceContext::load_shader`], or using imports loaded with
+	/// [`ResourceContext::load_shader_import`], whose latest changes failed to compile.
+	/// Drawn as an overlay by [`Engine::end_frame`](crate::Engine::end_frame).
+	pub fn shader_reload_errors(&self) -> Vec<ShaderReloadError> {
+		self.shader_manager.reload_errors()
+	}
+
+	/// Whether draw calls and dispatches are checked against the reflection of the bound shader - see [`Shader::reflect`].
+	/// Problems like missing vertex attributes, unbound or undersized buffers and mismatched textures are reported to stderr.
+	/// Enabled by default in debug builds.
+	pub fn set_draw_validation_enabled(&mut self, enabled: bool) {
+		self.draw_validator.enabled = enabled;
+	}
+
 	/// Constructs a temporary [`ResourceContext`] to allow access to resource creation.
 	/// If `resource_scope_id` is None, then resources created with the returned context will be
 	/// associated with the global resource scope, and won't be destroyed until engine shutdown.
@@ -62,6 +81,10 @@ impl System {
 		DrawContext {
 			resources: &self.resources,
 			backbuffer_size: self.backbuffer_size,
+			bound_index_type: IndexType::U16,
+			pipeline_state: &mut self.pipeline_state,
+			render_target: &mut self.render_target,
+			validator: &mut self.draw_validator,
 		}
 	}
 }
@@ -76,12 +99,6 @@ impl System {
 
 			raw::Enable(raw::FRAMEBUFFER_SRGB);
 
-			raw::Enable(raw::DEPTH_TEST);
-			// raw::Enable(raw::BLEND);
-			// raw::BlendFunc(raw::DST_COLOR, raw::ZERO);
-			// raw::BlendEquation(raw::FUNC_ADD);
-
-			raw::Enable(raw::CULL_FACE);
 			raw::FrontFace(raw::CCW);
 			raw::CullFace(raw::BACK);
 
@@ -104,27 +121,59 @@ impl System {
 			);
 		}
 
+		// Standard imports live in the global scope so they're available to all shaders
+		let mut shader_manager = ShaderManager::new();
+		shader_manager.add_import(SKINNING_SHADER_IMPORT, skinning::SKINNING_SHADER_IMPORT_SOURCE, global_scope_token.id());
+
 		let resource_scope_store = ResourceScopeStore::new(global_scope_token);
 
+		let pipeline_state = PipelineState::default();
+		pipeline_state.apply();
+
+		let capabilities = Capabilities::new();
+		let resources = Resources::new(&capabilities);
+
 		System {
 			_sdl_ctx: sdl_ctx,
-			shader_manager: ShaderManager::new(),
-			capabilities: Capabilities::new(),
+			shader_manager,
+			capabilities,
 			backbuffer_size: Vec2i::splat(1),
+			pipeline_state,
+			render_target: RenderTargetState::new(Vec2i::splat(1)),
+			draw_validator: DrawValidator::new(),
 
-			resources: Resources::new(),
+			resources,
 
 			resource_scope_store,
 		}
 	}
 
-	pub(crate) fn on_resize(&mut self, drawable_size: Vec2i) {
-		unsafe {
-			raw::Viewport(0, 0, drawable_size.x, drawable_size.y);
+	/// Puts pipeline state back to known defaults and rebinds the backbuffer, so nothing set during one frame
+	/// leaks into the next. Also picks up changes to shaders loaded from disk.
+	pub(crate) fn start_frame(&mut self) {
+		if self.shader_manager.poll_for_changes() {
+			self.draw_validator.invalidate_reflections();
 		}
 
+		self.draw_validator.clear_bindings();
+
+		self.render_target.reset_stacks();
+
+		let mut gfx = self.draw_context();
+		gfx.reset_pipeline_state();
+		gfx.bind_framebuffer(None);
+	}
+
+	pub(crate) fn on_resize(&mut self, drawable_size: Vec2i) {
 		self.backbuffer_size = drawable_size;
 		self.resources.on_backbuffer_resize(drawable_size);
+
+		let target_size = match self.render_target.framebuffer {
+			Some(framebuffer) => self.resources.get(framebuffer).size(),
+			None => drawable_size,
+		};
+
+		self.render_target.resize(target_size);
 	}
 
 	pub(crate) fn register_resource_scope(&mut self, token: ResourceScopeToken) {
@@ -133,6 +182,8 @@ impl System {
 
 	pub(crate) fn cleanup_resource_scope(&mut self, scope_id: ResourceScopeID) {
 		self.shader_manager.invalidate_shaders_dependent_on_scope(scope_id);
+		self.draw_validator.invalidate_reflections();
+		self.draw_validator.clear_bindings();
 		self.resource_scope_store.cleanup_scope(scope_id, &mut self.resources)
 	}
 }
@@ -211,6 +262,17 @@ impl IndexedDrawParams {
 	pub fn with_base_vertex(self, base_vertex: u32) -> IndexedDrawParams {
 		IndexedDrawParams {base_vertex, ..self}
 	}
+
+	/// Draws the triangles of a single [`toy::SubMesh`], assuming the indices of its mesh start at the beginning
+	/// of the bound index buffer - e.g., for a [`gfx::Mesh`] built from a single toy mesh with
+	/// [`ToyMeshBuilder`](crate::gfx::mesh::ToyMeshBuilder).
+	pub fn from_sub_mesh(sub_mesh: toy::SubMesh) -> IndexedDrawParams {
+		IndexedDrawParams {
+			num_elements: sub_mesh.num_indices,
+			element_offset: sub_mesh.index_offset,
+			base_vertex: 0,
+		}
+	}
 }
 
 impl<T> From<T> for IndexedDrawParams where T : Into<u32> {
@@ -266,7 +328,6 @@ impl ScopedResource for ScopedResourceHandle {
 					.expect("Trying to destroy texture that has already been removed");
 
 				unsafe {
-					raw::DeleteSamplers(1, &texture.sampler_handle);
 					raw::DeleteTextures(1, &texture.texture_handle);
 				}
 			}
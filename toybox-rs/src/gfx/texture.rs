@@ -1,131 +1,328 @@
 use crate::prelude::*;
-use crate::gfx::raw;
+use crate::gfx::{raw, SamplerKey};
 
 
 #[derive(Debug)]
 pub struct Texture {
 	pub(super) texture_handle: u32,
 
-	// TODO(pat.m): sampler could probably be cached and stored separately.
-	pub(super) sampler_handle: u32,
+	/// Used by [`DrawContext::bind_texture`](crate::gfx::DrawContext::bind_texture).
+	sampler: SamplerKey,
+
+	kind: TextureKind,
 	size_mode: TextureSize,
 	format: TextureFormat,
+	mip_levels: MipLevels,
 
 	current_size: Vec2i,
+	num_mip_levels: u32,
 }
 
 
 impl Texture {
+	/// Clears every mip level to zero.
 	pub fn clear(&mut self) {
 		unsafe {
 			let format = self.format.to_gl_unsized();
-			raw::ClearTexImage(self.texture_handle, 0, format, raw::FLOAT, std::ptr::null());
+
+			for level in 0..self.num_mip_levels {
+				raw::ClearTexImage(self.texture_handle, level as i32, format, raw::FLOAT, std::ptr::null());
+			}
 		}
 	}
 
-	pub fn set_filter(&mut self, min_linear: bool, mag_linear: bool) {
-		let min_filter = match min_linear {
-			true => raw::LINEAR,
-			false => raw::NEAREST,
-		};
+	/// Sets the sampler used when this texture is bound with [`DrawContext::bind_texture`](crate::gfx::DrawContext::bind_texture).
+	/// Defaults to [`SamplerDesc::default`](crate::gfx::SamplerDesc::default).
+	pub fn set_sampler(&mut self, sampler: SamplerKey) {
+		self.sampler = sampler;
+	}
 
-		let mag_filter = match mag_linear {
-			true => raw::LINEAR,
-			false => raw::NEAREST,
-		};
+	pub fn sampler(&self) -> SamplerKey { self.sampler }
+
+	pub fn upload_u8(&mut self, data: &[u8]) {
+		self.upload_mip_u8(0, data);
+	}
+
+	pub fn upload_rgba8_raw(&mut self, data: &[u8]) {
+		self.upload_mip_rgba8_raw(0, data);
+	}
+
+	/// Uploads a single channel image into mip `level`, which must be sized according to [`Texture::mip_size`].
+	/// For layered textures, `data` contains every layer, face or slice one after the other.
+	pub fn upload_mip_u8(&mut self, level: u32, data: &[u8]) {
+		let num_layers = self.mip_depth_or_layers(level);
+		self.upload_layers_raw(level, 0, num_layers, raw::RED, 1, data);
+	}
+
+	/// Uploads an RGBA image into mip `level`, which must be sized according to [`Texture::mip_size`].
+	/// For layered textures, `data` contains every layer, face or slice one after the other.
+	pub fn upload_mip_rgba8_raw(&mut self, level: u32, data: &[u8]) {
+		let num_layers = self.mip_depth_or_layers(level);
+		self.upload_layers_raw(level, 0, num_layers, raw::RGBA, 4, data);
+	}
+
+	/// Uploads a single channel image into one layer of an array, one face of a cubemap - see [`CubeFace::layer`] -
+	/// or one slice of a 3D texture.
+	pub fn upload_layer_u8(&mut self, layer: u32, level: u32, data: &[u8]) {
+		self.upload_layers_raw(level, layer, 1, raw::RED, 1, data);
+	}
+
+	/// Uploads an RGBA image into one layer of an array, one face of a cubemap - see [`CubeFace::layer`] -
+	/// or one slice of a 3D texture.
+	pub fn upload_layer_rgba8_raw(&mut self, layer: u32, level: u32, data: &[u8]) {
+		self.upload_layers_raw(level, layer, 1, raw::RGBA, 4, data);
+	}
+
+	/// Uploads `data` into `rect` of the base level, in texels with the origin in the bottom left.
+	/// `data` is interpreted according to `source` and converted to the texture's format, and must be tightly packed.
+	/// See [`Texture::upload_region_with_layout`] for other mip levels, layers and row strides.
+	pub fn upload_region<T: Copy>(&mut self, rect: Aabb2i, data: &[T], source: SourceFormat) -> Result<(), UploadError> {
+		self.upload_region_with_layout(rect, data, source, UploadLayout::default())
+	}
+
+	/// Like [`Texture::upload_region`], but with control over which mip level and layer is written to and
+	/// how rows are laid out in `data` - e.g., for uploading a single cell from a larger atlas image.
+	pub fn upload_region_with_layout<T: Copy>(&mut self, rect: Aabb2i, data: &[T], source: SourceFormat, layout: UploadLayout)
+		-> Result<(), UploadError>
+	{
+		let gl_format = self.validate_upload_source(source)?;
+		self.validate_upload_level(layout)?;
+
+		let mip_size = self.mip_size(layout.level);
+		let row_length = upload_row_length(rect, mip_size, layout, source.bytes_per_pixel(), std::mem::size_of_val(data))?;
+		let Vec2i{x: width, y: height} = rect.size();
 
 		unsafe {
-			raw::SamplerParameteri(self.sampler_handle, raw::TEXTURE_MIN_FILTER, min_filter as _);
-			raw::SamplerParameteri(self.sampler_handle, raw::TEXTURE_MAG_FILTER, mag_filter as _);
+			raw::PixelStorei(raw::UNPACK_ALIGNMENT, layout.alignment as i32);
+			raw::PixelStorei(raw::UNPACK_ROW_LENGTH, row_length as i32);
+
+			if self.kind == TextureKind::Texture2D {
+				raw::TextureSubImage2D(
+					self.texture_handle,
+					layout.level as i32,
+					rect.min.x, rect.min.y,
+					width, height,
+					gl_format,
+					source.component.to_gl(),
+					data.as_ptr() as *const _
+				);
+			} else {
+				raw::TextureSubImage3D(
+					self.texture_handle,
+					layout.level as i32,
+					rect.min.x, rect.min.y, layout.layer as i32,
+					width, height, 1,
+					gl_format,
+					source.component.to_gl(),
+					data.as_ptr() as *const _
+				);
+			}
+
+			// Restore defaults so other uploads aren't affected.
+			raw::PixelStorei(raw::UNPACK_ALIGNMENT, 4);
+			raw::PixelStorei(raw::UNPACK_ROW_LENGTH, 0);
 		}
+
+		Ok(())
 	}
 
-	pub fn set_wrap(&mut self, wrap_repeat: bool) {
-		let wrap_mode = match wrap_repeat {
-			true => raw::REPEAT,
-			false => raw::CLAMP_TO_EDGE,
-		};
+	/// Fills every mip level after the first by repeatedly downsampling the first.
+	pub fn generate_mipmaps(&mut self) {
+		if self.num_mip_levels <= 1 {
+			return
+		}
 
 		unsafe {
-			raw::SamplerParameteri(self.sampler_handle, raw::TEXTURE_WRAP_S, wrap_mode as _);
-			raw::SamplerParameteri(self.sampler_handle, raw::TEXTURE_WRAP_T, wrap_mode as _);
+			raw::GenerateTextureMipmap(self.texture_handle);
 		}
 	}
 
-	pub fn upload_u8(&mut self, data: &[u8]) {
-		assert!(matches!(self.size_mode, TextureSize::Fixed(_)), "Texture uploads not supported for framebuffer sized textures");
+	pub fn kind(&self) -> TextureKind { self.kind }
+	pub fn format(&self) -> TextureFormat { self.format }
+	pub fn size_mode(&self) -> TextureSize { self.size_mode }
 
-		let (level, offset_x, offset_y) = (0, 0, 0);
-		let Vec2i{x: width, y: height} = self.current_size;
+	/// The width and height of the base level. See [`Texture::depth_or_layers`] for the third dimension.
+	pub fn size(&self) -> Vec2i { self.current_size }
 
-		assert!((width*height) as usize == data.len(), "Texture upload input data doesn't match allocated size");
+	/// The depth of a 3D texture, the number of layers in an array, 6 for a cubemap or 1 for a plain 2D texture.
+	pub fn depth_or_layers(&self) -> u32 { self.kind.depth_or_layers() }
 
-		unsafe {
-			raw::TextureSubImage2D(
-				self.texture_handle,
-				level,
-				offset_x, offset_y,
-				width, height,
-				raw::RED,
-				raw::UNSIGNED_BYTE,
-				data.as_ptr() as *const _
-			);
+	/// The number of mip levels allocated, including the base level.
+	pub fn mip_levels(&self) -> u32 { self.num_mip_levels }
+
+	/// The size of mip `level`, where level 0 is the full size texture.
+	pub fn mip_size(&self, level: u32) -> Vec2i {
+		assert!(level < self.num_mip_levels, "Mip level {} out of range - texture has {} levels", level, self.num_mip_levels);
+
+		let Vec2i{x, y} = self.current_size;
+		Vec2i::new((x >> level).max(1), (y >> level).max(1))
+	}
+
+	/// Like [`Texture::depth_or_layers`] but for mip `level` - only 3D textures shrink in depth.
+	pub fn mip_depth_or_layers(&self, level: u32) -> u32 {
+		match self.kind {
+			TextureKind::Texture3D{depth} => (depth >> level).max(1),
+			kind => kind.depth_or_layers(),
 		}
 	}
 
-	pub fn upload_rgba8_raw(&mut self, data: &[u8]) {
+	/// Reads back the contents of a color texture, converting to 8 bits per channel.
+	/// Flipped so that the first row of the image is the top of the texture - the inverse of
+	/// [`Vfs::load_texture`](crate::vfs::Vfs::load_texture). sRGB textures are returned still sRGB encoded.
+	///
+	/// Waits for any draw calls writing to the texture to finish, so prefer a [`ReadbackQueue`](crate::gfx::ReadbackQueue)
+	/// for anything done regularly.
+	pub fn download_rgba8(&self) -> image::RgbaImage {
+		let data = self.download_raw::<u8>(raw::UNSIGNED_BYTE);
+		let mut image = image::RgbaImage::from_raw(self.current_size.x as u32, self.current_size.y as u32, data)
+			.expect("Texture download size doesn't match image size");
+
+		image::imageops::flip_vertical_in_place(&mut image);
+		image
+	}
+
+	/// Like [`Texture::download_rgba8`] but without loss of precision or range, for HDR and non-color data.
+	pub fn download_f32(&self) -> image::Rgba32FImage {
+		let data = self.download_raw::<f32>(raw::FLOAT);
+		let mut image = image::Rgba32FImage::from_raw(self.current_size.x as u32, self.current_size.y as u32, data)
+			.expect("Texture download size doesn't match image size");
+
+		image::imageops::flip_vertical_in_place(&mut image);
+		image
+	}
+}
+
+
+
+impl Texture {
+	pub(super) fn assert_color_readable(&self) {
+		assert!(self.kind == TextureKind::Texture2D, "Downloading is only supported for 2D textures");
+		assert!(!self.format.is_depth_or_stencil(), "Downloading depth and stencil textures is not supported");
+		assert!(!self.format.is_integer(), "Downloading integer textures is not supported");
+	}
+
+	/// Returns the GL pixel format `source` is uploaded as.
+	fn validate_upload_source(&self, source: SourceFormat) -> Result<u32, UploadError> {
+		let unsupported = |reason: &str| UploadError::UnsupportedFormat {
+			source,
+			target: self.format,
+			reason: reason.into(),
+		};
+
+		if self.format.is_depth_or_stencil() {
+			return Err(unsupported("depth and stencil textures can't be uploaded to"))
+		}
+
+		let is_integer = self.format.is_integer();
+		if is_integer && matches!(source.component, SourceComponent::F16 | SourceComponent::F32) {
+			return Err(unsupported("integer textures can only be uploaded from integer data"))
+		}
+
+		let gl_format = match (source.channels, is_integer) {
+			(1, false) => raw::RED,
+			(2, false) => raw::RG,
+			(3, false) => raw::RGB,
+			(4, false) => raw::RGBA,
+			(1, true) => raw::RED_INTEGER,
+			(2, true) => raw::RG_INTEGER,
+			(3, true) => raw::RGB_INTEGER,
+			(4, true) => raw::RGBA_INTEGER,
+			_ => return Err(unsupported("source must have between 1 and 4 channels")),
+		};
+
+		Ok(gl_format)
+	}
+
+	fn validate_upload_level(&self, layout: UploadLayout) -> Result<(), UploadError> {
+		if !matches!(self.size_mode, TextureSize::Fixed(_)) {
+			return Err(UploadError::InvalidRegion("uploads not supported for framebuffer sized textures".into()))
+		}
+
+		if layout.level >= self.num_mip_levels {
+			return Err(UploadError::InvalidRegion(format!("mip level {} out of range - texture has {} levels", layout.level, self.num_mip_levels)))
+		}
+
+		let num_layers = self.mip_depth_or_layers(layout.level);
+		if layout.layer >= num_layers {
+			return Err(UploadError::InvalidRegion(format!("layer {} out of range - texture has {num_layers} layers", layout.layer)))
+		}
+
+		Ok(())
+	}
+
+	fn upload_layers_raw(&mut self, level: u32, first_layer: u32, num_layers: u32, gl_format: u32, bytes_per_pixel: usize, data: &[u8]) {
 		assert!(matches!(self.size_mode, TextureSize::Fixed(_)), "Texture uploads not supported for framebuffer sized textures");
+		assert!(first_layer + num_layers <= self.mip_depth_or_layers(level), "Texture upload layers out of range");
 
-		let (level, offset_x, offset_y) = (0, 0, 0);
-		let Vec2i{x: width, y: height} = self.current_size;
+		let (offset_x, offset_y) = (0, 0);
+		let Vec2i{x: width, y: height} = self.mip_size(level);
 
-		assert!((width*height*4) as usize == data.len(), "Texture upload input data doesn't match allocated size");
+		assert!((width*height) as usize * num_layers as usize * bytes_per_pixel == data.len(), "Texture upload input data doesn't match allocated size");
 
 		unsafe {
-			raw::TextureSubImage2D(
-				self.texture_handle,
-				level,
-				offset_x, offset_y,
-				width, height,
-				raw::RGBA,
-				raw::UNSIGNED_BYTE,
-				data.as_ptr() as *const _
-			);
+			if self.kind == TextureKind::Texture2D {
+				raw::TextureSubImage2D(
+					self.texture_handle,
+					level as i32,
+					offset_x, offset_y,
+					width, height,
+					gl_format,
+					raw::UNSIGNED_BYTE,
+					data.as_ptr() as *const _
+				);
+			} else {
+				// Array layers, cubemap faces and 3D slices are all addressed by z offset with DSA.
+				raw::TextureSubImage3D(
+					self.texture_handle,
+					level as i32,
+					offset_x, offset_y, first_layer as i32,
+					width, height, num_layers as i32,
+					gl_format,
+					raw::UNSIGNED_BYTE,
+					data.as_ptr() as *const _
+				);
+			}
 		}
 	}
 
-	pub fn format(&self) -> TextureFormat { self.format }
-	pub fn size(&self) -> Vec2i { self.current_size }
-	pub fn size_mode(&self) -> TextureSize { self.size_mode }
-}
+	/// Reads level 0 as RGBA. Missing channels are filled in as (0, 0, 1) as usual.
+	fn download_raw<T: Copy + Default>(&self, component_type: u32) -> Vec<T> {
+		self.assert_color_readable();
 
+		let Vec2i{x: width, y: height} = self.current_size;
+		let mut data = vec![T::default(); (width * height * 4) as usize];
+		let size_bytes = data.len() * std::mem::size_of::<T>();
+
+		unsafe {
+			raw::GetTextureImage(self.texture_handle, 0, raw::RGBA, component_type,
+				size_bytes as i32, data.as_mut_ptr() as *mut _);
+		}
 
+		data
+	}
+}
 
-impl Texture {
-	pub(super) fn new(size_mode: TextureSize, backbuffer_size: Vec2i, format: TextureFormat) -> Texture {
-		let Vec2i{x:width, y:height} = size_mode.resolve(backbuffer_size);
-
-		let mut texture = unsafe {
-			let mut texture_handle = 0;
-			let mut sampler_handle = 0;
-			raw::CreateTextures(raw::TEXTURE_2D, 1, &mut texture_handle);
-			raw::CreateSamplers(1, &mut sampler_handle);
-
-			raw::TextureStorage2D(texture_handle, 1, format.to_gl(), width, height);
-			Texture {
-				texture_handle,
-				sampler_handle,
-				size_mode,
-				format,
-
-				current_size: size_mode.resolve(backbuffer_size),
-			}
-		};
 
-		texture.set_wrap(false);
-		texture.set_filter(false, false);
 
-		texture
+impl Texture {
+	pub(super) fn new(kind: TextureKind, size_mode: TextureSize, backbuffer_size: Vec2i, format: TextureFormat,
+		mip_levels: MipLevels, sampler: SamplerKey) -> Texture
+	{
+		let current_size = size_mode.resolve(backbuffer_size);
+		let num_mip_levels = kind.resolve_mip_levels(mip_levels, current_size);
+
+		Texture {
+			texture_handle: create_texture_storage(kind, format, current_size, num_mip_levels),
+			sampler,
+
+			kind,
+			size_mode,
+			format,
+			mip_levels,
+
+			current_size,
+			num_mip_levels,
+		}
 	}
 
 	/// Called when the size of the backbuffer changes.
@@ -137,19 +334,320 @@ impl Texture {
 		}
 
 		self.current_size = self.size_mode.resolve(backbuffer_size);
-		let Vec2i{x: new_width, y: new_height} = self.current_size;
+		self.num_mip_levels = self.kind.resolve_mip_levels(self.mip_levels, self.current_size);
 
 		unsafe {
 			raw::DeleteTextures(1, &mut self.texture_handle);
-			raw::CreateTextures(raw::TEXTURE_2D, 1, &mut self.texture_handle);
-			raw::TextureStorage2D(self.texture_handle, 1, self.format.to_gl(), new_width, new_height);
 		}
 
+		self.texture_handle = create_texture_storage(self.kind, self.format, self.current_size, self.num_mip_levels);
+
 		true
 	}
 }
 
 
+fn create_texture_storage(kind: TextureKind, format: TextureFormat, size: Vec2i, num_mip_levels: u32) -> u32 {
+	let Vec2i{x: width, y: height} = size;
+	let mut texture_handle = 0;
+
+	if kind == TextureKind::Cubemap {
+		assert_eq!(width, height, "Cubemap faces must be square");
+	}
+
+	assert!(kind.depth_or_layers() > 0, "{kind:?} textures need at least one layer or slice");
+
+	unsafe {
+		raw::CreateTextures(kind.to_gl(), 1, &mut texture_handle);
+
+		match kind {
+			TextureKind::Texture2D | TextureKind::Cubemap => {
+				raw::TextureStorage2D(texture_handle, num_mip_levels as i32, format.to_gl(), width, height);
+			}
+
+			TextureKind::Texture2DArray{..} | TextureKind::Texture3D{..} => {
+				let depth = kind.depth_or_layers() as i32;
+				raw::TextureStorage3D(texture_handle, num_mip_levels as i32, format.to_gl(), width, height, depth);
+			}
+		}
+	}
+
+	texture_handle
+}
+
+/// Checks that `rect` lies within a mip level of size `mip_size`, and that `data_size` bytes laid out according to
+/// `layout` cover it. Returns the row length to pass to GL.
+fn upload_row_length(rect: Aabb2i, mip_size: Vec2i, layout: UploadLayout, bytes_per_pixel: usize, data_size: usize)
+	-> Result<u32, UploadError>
+{
+	let Vec2i{x: width, y: height} = rect.size();
+
+	let in_bounds = rect.min.x >= 0 && rect.min.y >= 0
+		&& rect.max.x <= mip_size.x && rect.max.y <= mip_size.y;
+
+	if width <= 0 || height <= 0 || !in_bounds {
+		return Err(UploadError::InvalidRegion(format!("{rect:?} is empty or outside of mip level {} with size {mip_size:?}", layout.level)))
+	}
+
+	if !matches!(layout.alignment, 1 | 2 | 4 | 8) {
+		return Err(UploadError::InvalidLayout(format!("alignment must be 1, 2, 4 or 8, got {}", layout.alignment)))
+	}
+
+	let row_length = layout.row_length.unwrap_or(width as u32);
+	if row_length < width as u32 {
+		return Err(UploadError::InvalidLayout(format!("row length {row_length} is shorter than region width {width}")))
+	}
+
+	let alignment = layout.alignment as usize;
+	let row_pitch = (row_length as usize * bytes_per_pixel).next_multiple_of(alignment);
+	let expected_size = row_pitch * (height as usize - 1) + width as usize * bytes_per_pixel;
+
+	if data_size < expected_size {
+		return Err(UploadError::DataSize { expected: expected_size, actual: data_size })
+	}
+
+	Ok(row_length)
+}
+
+
+
+/// The shape of a texture, and how it is sampled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TextureKind {
+	/// A single image, sampled as `sampler2D`.
+	#[default]
+	Texture2D,
+
+	/// `layers` images of the same size, sampled as `sampler2DArray` - e.g., pages of a sprite sheet, so sprites on
+	/// different pages can be drawn without rebinding.
+	Texture2DArray { layers: u32 },
+
+	/// A volume `depth` images deep, sampled as `sampler3D` - e.g., color grading LUTs or voxel data.
+	Texture3D { depth: u32 },
+
+	/// Six square faces, sampled as `samplerCube` - e.g., skyboxes and environment maps.
+	/// Faces are laid out as layers in the order of [`CubeFace`].
+	Cubemap,
+}
+
+impl TextureKind {
+	pub fn depth_or_layers(&self) -> u32 {
+		match *self {
+			TextureKind::Texture2D => 1,
+			TextureKind::Texture2DArray{layers} => layers,
+			TextureKind::Texture3D{depth} => depth,
+			TextureKind::Cubemap => 6,
+		}
+	}
+
+	/// Whether images of this kind have to be bound as layered to access more than a single 2D image.
+	pub fn is_layered(&self) -> bool {
+		*self != TextureKind::Texture2D
+	}
+
+	/// Only 3D textures take depth into account, since array layers and cubemap faces are never downsampled.
+	fn resolve_mip_levels(&self, mip_levels: MipLevels, size: Vec2i) -> u32 {
+		match *self {
+			TextureKind::Texture3D{depth} => {
+				let depth = depth.min(i32::MAX as u32) as i32;
+				mip_levels.resolve(Vec2i::new(size.x.max(size.y), depth))
+			}
+
+			_ => mip_levels.resolve(size),
+		}
+	}
+
+	fn to_gl(&self) -> u32 {
+		match self {
+			TextureKind::Texture2D => raw::TEXTURE_2D,
+			TextureKind::Texture2DArray{..} => raw::TEXTURE_2D_ARRAY,
+			TextureKind::Texture3D{..} => raw::TEXTURE_3D,
+			TextureKind::Cubemap => raw::TEXTURE_CUBE_MAP,
+		}
+	}
+}
+
+
+/// Faces of a [`TextureKind::Cubemap`], in the order OpenGL expects them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+	PositiveX,
+	NegativeX,
+	PositiveY,
+	NegativeY,
+	PositiveZ,
+	NegativeZ,
+}
+
+impl CubeFace {
+	pub const ALL: [CubeFace; 6] = [
+		CubeFace::PositiveX, CubeFace::NegativeX,
+		CubeFace::PositiveY, CubeFace::NegativeY,
+		CubeFace::PositiveZ, CubeFace::NegativeZ,
+	];
+
+	/// The layer this face is addressed by in uploads, framebuffer attachments and layered image bindings.
+	pub fn layer(self) -> u32 {
+		self as u32
+	}
+}
+
+
+
+/// The type of each channel of data passed to [`Texture::upload_region`].
+/// Half floats are passed as their `u16` bit patterns.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SourceComponent {
+	U8,
+	U16,
+	F16,
+	F32,
+}
+
+impl SourceComponent {
+	pub fn size_bytes(&self) -> usize {
+		match self {
+			SourceComponent::U8 => 1,
+			SourceComponent::U16 | SourceComponent::F16 => 2,
+			SourceComponent::F32 => 4,
+		}
+	}
+
+	fn to_gl(&self) -> u32 {
+		match self {
+			SourceComponent::U8 => raw::UNSIGNED_BYTE,
+			SourceComponent::U16 => raw::UNSIGNED_SHORT,
+			SourceComponent::F16 => raw::HALF_FLOAT,
+			SourceComponent::F32 => raw::FLOAT,
+		}
+	}
+}
+
+
+/// The layout of each pixel of data passed to [`Texture::upload_region`].
+/// Channels are always in RGBA order, with channels missing from the source left as zero - or one for alpha.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SourceFormat {
+	pub component: SourceComponent,
+
+	/// Between 1 and 4.
+	pub channels: u32,
+}
+
+impl SourceFormat {
+	pub fn new(component: SourceComponent, channels: u32) -> SourceFormat {
+		SourceFormat { component, channels }
+	}
+
+	pub fn r8() -> SourceFormat { SourceFormat::new(SourceComponent::U8, 1) }
+	pub fn rgba8() -> SourceFormat { SourceFormat::new(SourceComponent::U8, 4) }
+	pub fn r_f32() -> SourceFormat { SourceFormat::new(SourceComponent::F32, 1) }
+	pub fn rgba_f32() -> SourceFormat { SourceFormat::new(SourceComponent::F32, 4) }
+
+	pub fn bytes_per_pixel(&self) -> usize {
+		self.component.size_bytes() * self.channels as usize
+	}
+}
+
+
+/// Where an upload is written to, and how rows are laid out in the source data.
+/// See [`Texture::upload_region_with_layout`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UploadLayout {
+	pub level: u32,
+
+	/// The array layer, cubemap face or 3D slice to write to. Must be 0 for plain 2D textures.
+	pub layer: u32,
+
+	/// The number of pixels between the start of each row in the source data, if larger than the
+	/// width of the region being uploaded.
+	pub row_length: Option<u32>,
+
+	/// The byte alignment of the start of each row in the source data. One of 1, 2, 4 or 8.
+	/// Defaults to 1 - i.e., tightly packed.
+	pub alignment: u32,
+}
+
+impl Default for UploadLayout {
+	fn default() -> UploadLayout {
+		UploadLayout {
+			level: 0,
+			layer: 0,
+			row_length: None,
+			alignment: 1,
+		}
+	}
+}
+
+
+#[derive(Debug)]
+pub enum UploadError {
+	/// `source` can't be converted to `target`, or `target` doesn't support uploads.
+	UnsupportedFormat {
+		source: SourceFormat,
+		target: TextureFormat,
+		reason: String,
+	},
+
+	/// The destination region, mip level or layer doesn't exist in the texture.
+	InvalidRegion(String),
+
+	InvalidLayout(String),
+
+	/// Not enough data was passed for the region being uploaded.
+	DataSize {
+		expected: usize,
+		actual: usize,
+	},
+}
+
+impl std::fmt::Display for UploadError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			UploadError::UnsupportedFormat{source, target, reason} =>
+				write!(f, "Can't upload {source:?} to {target:?} texture: {reason}"),
+
+			UploadError::InvalidRegion(reason) => write!(f, "Invalid upload region: {reason}"),
+			UploadError::InvalidLayout(reason) => write!(f, "Invalid upload layout: {reason}"),
+
+			UploadError::DataSize{expected, actual} =>
+				write!(f, "Upload needs at least {expected} bytes of data, but only {actual} were provided"),
+		}
+	}
+}
+
+impl std::error::Error for UploadError {}
+
+
+
+/// How many mip levels to allocate for a texture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum MipLevels {
+	/// Just the base level.
+	#[default]
+	One,
+
+	/// Every level down to 1x1.
+	Full,
+
+	/// Up to this many levels, including the base level. Limited to the number in a full chain.
+	Count(u32),
+}
+
+impl MipLevels {
+	/// The number of levels for a texture of `size`.
+	pub fn resolve(&self, size: Vec2i) -> u32 {
+		let largest_axis = size.x.max(size.y).max(1) as u32;
+		let full_chain = 32 - largest_axis.leading_zeros();
+
+		match *self {
+			MipLevels::One => 1,
+			MipLevels::Full => full_chain,
+			MipLevels::Count(count) => count.clamp(1, full_chain),
+		}
+	}
+}
+
 
 
 #[derive(Copy, Clone, Debug)]
@@ -228,6 +726,24 @@ impl TextureFormat {
 
 	pub fn unorm8() -> Self { TextureFormat::Red(BaseFormat::Unorm8) }
 
+	pub fn is_depth_or_stencil(&self) -> bool {
+		matches!(self, TextureFormat::Depth | TextureFormat::DepthStencil | TextureFormat::Stencil
+			| TextureFormat::Depth16 | TextureFormat::Depth32)
+	}
+
+	/// Whether shaders see this format as integers rather than normalized or floating point values.
+	pub fn is_integer(&self) -> bool {
+		use BaseFormat::*;
+
+		match self {
+			TextureFormat::Rgba(base) | TextureFormat::RedGreen(base) | TextureFormat::Red(base)
+				=> matches!(base, I8 | I16 | I32 | U8 | U16 | U32),
+
+			TextureFormat::Rgb10A2Ui => true,
+			_ => false,
+		}
+	}
+
 	pub fn to_gl(&self) -> u32 {
 		match self {
 			TextureFormat::Rgba(BaseFormat::Unorm8) => raw::RGBA8,
@@ -305,3 +821,57 @@ impl TextureFormat {
 	}
 }
 
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn layout(row_length: Option<u32>, alignment: u32) -> UploadLayout {
+		UploadLayout { row_length, alignment, ..UploadLayout::default() }
+	}
+
+	#[test]
+	fn upload_region_bounds() {
+		let mip_size = Vec2i::splat(8);
+		let region = |x, y, w, h| Aabb2i::from_min_point(Vec2i::new(x, y), Vec2i::new(w, h));
+		let row_length = |rect| upload_row_length(rect, mip_size, UploadLayout::default(), 4, 8 * 8 * 4);
+
+		assert_eq!(row_length(region(0, 0, 8, 8)).unwrap(), 8);
+		assert_eq!(row_length(region(4, 4, 4, 4)).unwrap(), 4);
+
+		assert!(matches!(row_length(region(6, 6, 4, 4)), Err(UploadError::InvalidRegion(_))));
+		assert!(matches!(row_length(region(-1, 0, 4, 4)), Err(UploadError::InvalidRegion(_))));
+		assert!(matches!(row_length(region(0, 0, 0, 4)), Err(UploadError::InvalidRegion(_))));
+	}
+
+	#[test]
+	fn upload_region_data_size() {
+		let mip_size = Vec2i::splat(8);
+		let rect = Aabb2i::from_min_point(Vec2i::zero(), Vec2i::new(4, 2));
+
+		assert_eq!(upload_row_length(rect, mip_size, layout(None, 1), 4, 32).unwrap(), 4);
+		assert!(matches!(upload_row_length(rect, mip_size, layout(None, 1), 4, 31),
+			Err(UploadError::DataSize { expected: 32, actual: 31 })));
+
+		// The last row doesn't need to extend to the full row length
+		assert_eq!(upload_row_length(rect, mip_size, layout(Some(8), 1), 4, 48).unwrap(), 8);
+		assert!(matches!(upload_row_length(rect, mip_size, layout(Some(8), 1), 4, 47),
+			Err(UploadError::DataSize { expected: 48, actual: 47 })));
+
+		// 12 byte rows are padded to 16 bytes
+		assert_eq!(upload_row_length(rect, mip_size, layout(None, 8), 3, 28).unwrap(), 4);
+		assert!(matches!(upload_row_length(rect, mip_size, layout(None, 8), 3, 27),
+			Err(UploadError::DataSize { expected: 28, actual: 27 })));
+	}
+
+	#[test]
+	fn upload_region_invalid_layout() {
+		let mip_size = Vec2i::splat(8);
+		let rect = Aabb2i::from_min_point(Vec2i::zero(), Vec2i::new(4, 2));
+
+		assert!(matches!(upload_row_length(rect, mip_size, layout(Some(3), 1), 4, 1024), Err(UploadError::InvalidLayout(_))));
+		assert!(matches!(upload_row_length(rect, mip_size, layout(None, 3), 4, 1024), Err(UploadError::InvalidLayout(_))));
+	}
+}
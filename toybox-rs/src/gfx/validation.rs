@@ -0,0 +1,348 @@
+use crate::gfx::{raw, Shader, ShaderReflection, BlockInfo, ScalarKind, TextureDimension, TextureKind, TextureFormat, BaseFormat};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+
+/// Checks the state a draw call or dispatch is about to use against what the bound shader expects, and reports
+/// anything that doesn't line up - e.g., a [`Vertex`](crate::gfx::Vertex) descriptor missing an attribute, or a
+/// uniform buffer too small for its block - which GL would otherwise silently turn into garbage or zeroes.
+/// Lives in [`System`](crate::gfx::System) so that it survives between [`DrawContext`](crate::gfx::DrawContext)s.
+///
+/// Enabled by default in debug builds only, since it queries a lot of state from the driver for every draw call.
+/// Each distinct problem is only reported once.
+pub(super) struct DrawValidator {
+	pub(super) enabled: bool,
+
+	/// Keyed by program name. Cleared whenever programs may have been relinked or deleted.
+	reflections: RefCell<HashMap<u32, Rc<ShaderReflection>>>,
+	reported: RefCell<HashSet<String>>,
+
+	/// What was last bound to each texture unit and image unit through a [`DrawContext`](crate::gfx::DrawContext).
+	/// Bindings made through [`raw`] aren't tracked, so units missing from here aren't validated.
+	/// Cleared at the start of each frame and whenever textures may have been deleted - see [`DrawValidator::clear_bindings`].
+	textures: HashMap<u32, BoundTexture>,
+	images: HashMap<u32, BoundTexture>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BoundTexture {
+	kind: TextureKind,
+	format: TextureFormat,
+
+	/// Whether the sampler bound alongside the texture does depth comparison.
+	compare: bool,
+}
+
+impl DrawValidator {
+	pub(super) fn new() -> DrawValidator {
+		DrawValidator {
+			enabled: cfg!(debug_assertions),
+			reflections: RefCell::default(),
+			reported: RefCell::default(),
+			textures: HashMap::new(),
+			images: HashMap::new(),
+		}
+	}
+
+	pub(super) fn track_texture(&mut self, unit: u32, kind: TextureKind, format: TextureFormat, compare: bool) {
+		self.textures.insert(unit, BoundTexture{kind, format, compare});
+	}
+
+	pub(super) fn track_image(&mut self, unit: u32, kind: TextureKind, format: TextureFormat) {
+		self.images.insert(unit, BoundTexture{kind, format, compare: false});
+	}
+
+	/// Must be called whenever a program may have been relinked or deleted, since program names can be reused.
+	pub(super) fn invalidate_reflections(&mut self) {
+		self.reflections.get_mut().clear();
+	}
+
+	/// Must be called whenever textures may have been deleted, so stale bindings aren't validated against
+	/// whatever reuses their units.
+	pub(super) fn clear_bindings(&mut self) {
+		self.textures.clear();
+		self.images.clear();
+	}
+
+	pub(super) fn validate_draw(&self) {
+		self.validate(true);
+	}
+
+	pub(super) fn validate_dispatch(&self) {
+		self.validate(false);
+	}
+
+	fn validate(&self, uses_vertex_input: bool) {
+		if !self.enabled {
+			return
+		}
+
+		let program = get_integer(raw::CURRENT_PROGRAM) as u32;
+		if program == 0 {
+			self.report(0, "no shader bound".to_owned());
+			return
+		}
+
+		let reflection = self.reflection(program);
+		let mut errors = Vec::new();
+
+		if uses_vertex_input {
+			validate_attributes(&reflection, &mut errors);
+		}
+
+		for block in reflection.uniform_blocks.iter() {
+			validate_block(block, "uniform", raw::UNIFORM_BUFFER_BINDING, raw::UNIFORM_BUFFER_SIZE, &mut errors);
+		}
+
+		for block in reflection.storage_blocks.iter() {
+			validate_block(block, "storage", raw::SHADER_STORAGE_BUFFER_BINDING, raw::SHADER_STORAGE_BUFFER_SIZE, &mut errors);
+		}
+
+		self.validate_textures(&reflection, &mut errors);
+
+		for error in errors {
+			self.report(program, error);
+		}
+	}
+
+	fn reflection(&self, program: u32) -> Rc<ShaderReflection> {
+		self.reflections.borrow_mut()
+			.entry(program)
+			.or_insert_with(|| Rc::new(Shader(program).reflect()))
+			.clone()
+	}
+
+	fn report(&self, program: u32, error: String) {
+		let message = format!("Draw validation failed for shader {program}: {error}");
+		if self.reported.borrow_mut().insert(message.clone()) {
+			eprintln!("{message}");
+		}
+	}
+
+	fn validate_textures(&self, reflection: &ShaderReflection, errors: &mut Vec<String>) {
+		for sampler in reflection.samplers.iter() {
+			let Some((dimension, texel_kind)) = sampler.ty.texture_info() else { continue };
+			let Some(bound) = self.textures.get(&sampler.binding) else { continue };
+
+			let name = &sampler.name;
+			let unit = sampler.binding;
+			let ty = sampler.ty;
+
+			validate_texture_type(&format!("sampler '{name}' ({ty}) at texture unit {unit}"), dimension, texel_kind, bound, errors);
+
+			let is_depth = bound.format.is_depth_or_stencil();
+
+			if ty.is_shadow_sampler() {
+				if !is_depth {
+					errors.push(format!("sampler '{name}' ({ty}) at texture unit {unit} expects a depth texture, but {:?} is bound", bound.format));
+				} else if !bound.compare {
+					errors.push(format!("sampler '{name}' ({ty}) at texture unit {unit} is a shadow sampler, but the bound sampler doesn't do depth comparison - see SamplerDesc::shadow"));
+				}
+			} else if bound.compare {
+				errors.push(format!("sampler '{name}' ({ty}) at texture unit {unit} isn't a shadow sampler, but the bound sampler does depth comparison"));
+			}
+		}
+
+		for image in reflection.images.iter() {
+			let Some((dimension, texel_kind)) = image.ty.texture_info() else { continue };
+			let Some(bound) = self.images.get(&image.binding) else { continue };
+
+			let context = format!("image '{}' ({}) at image unit {}", image.name, image.ty, image.binding);
+			validate_texture_type(&context, dimension, texel_kind, bound, errors);
+		}
+	}
+}
+
+
+fn validate_attributes(reflection: &ShaderReflection, errors: &mut Vec<String>) {
+	if reflection.attributes.is_empty() {
+		return
+	}
+
+	let vao = get_integer(raw::VERTEX_ARRAY_BINDING) as u32;
+	if vao == 0 {
+		errors.push("shader has vertex inputs but no vao is bound".to_owned());
+		return
+	}
+
+	for attribute in reflection.attributes.iter() {
+		let name = &attribute.name;
+		let location = attribute.location;
+		let ty = attribute.ty;
+
+		let enabled = get_vertex_attrib(vao, location, raw::VERTEX_ATTRIB_ARRAY_ENABLED) != 0;
+		if !enabled {
+			errors.push(format!("vertex input '{name}' ({ty}) at location {location} isn't provided by the bound vao - \
+				check the attributes of the bound Vertex descriptor"));
+			continue
+		}
+
+		// Matrix inputs take up a location per column, which we don't track - so only check they're provided at all.
+		let Some((kind, num_components)) = ty.components() else { continue };
+		if num_components > 4 {
+			continue
+		}
+
+		let num_provided = get_vertex_attrib(vao, location, raw::VERTEX_ATTRIB_ARRAY_SIZE) as u32;
+		let provided_as_integer = get_vertex_attrib(vao, location, raw::VERTEX_ATTRIB_ARRAY_INTEGER) != 0;
+		let expects_integer = matches!(kind, ScalarKind::Int | ScalarKind::Uint);
+
+		if expects_integer != provided_as_integer {
+			let (expected, provided) = if expects_integer {
+				("integer", "float or normalised")
+			} else {
+				("float", "integer")
+			};
+
+			errors.push(format!("vertex input '{name}' ({ty}) at location {location} expects {expected} data, but the bound vao provides {provided} data - \
+				check the AttributeTypeFormat of the corresponding Vertex attribute"));
+		}
+
+		// Missing components are filled in with defaults, so only providing too few is likely to be a mistake.
+		if num_provided < num_components {
+			errors.push(format!("vertex input '{name}' ({ty}) at location {location} expects {num_components} components, but the bound vao only provides {num_provided}"));
+		}
+	}
+}
+
+fn validate_block(block: &BlockInfo, block_kind: &str, binding_query: u32, size_query: u32, errors: &mut Vec<String>) {
+	let name = &block.name;
+	let binding = block.binding;
+
+	let buffer = get_integer_indexed(binding_query, binding) as u32;
+	if buffer == 0 {
+		errors.push(format!("{block_kind} block '{name}' at binding {binding} has no buffer bound"));
+		return
+	}
+
+	// Bindings made with glBindBufferBase report a size of zero, meaning the whole buffer.
+	let mut bound_size = get_integer64_indexed(size_query, binding);
+	if bound_size == 0 {
+		let mut buffer_size = 0;
+		unsafe {
+			raw::GetNamedBufferParameteri64v(buffer, raw::BUFFER_SIZE, &mut buffer_size);
+		}
+
+		bound_size = buffer_size;
+	}
+
+	let required_size = block.size_bytes as i64;
+	if bound_size < required_size {
+		errors.push(format!("{block_kind} block '{name}' at binding {binding} needs at least {required_size} bytes, but only {bound_size} are bound - \
+			check the bound type matches the block layout, e.g., vec3s are padded to 16 bytes"));
+	}
+}
+
+fn validate_texture_type(context: &str, dimension: TextureDimension, texel_kind: ScalarKind, bound: &BoundTexture, errors: &mut Vec<String>) {
+	let bound_dimension = texture_dimension(bound.kind);
+	if bound_dimension != dimension {
+		errors.push(format!("{context} expects a {dimension} texture, but a {bound_dimension} texture is bound"));
+	}
+
+	let bound_texel_kind = texel_kind_of(bound.format);
+	if bound_texel_kind != texel_kind {
+		errors.push(format!("{context} returns {texel_kind:?} values, but the bound texture has format {:?} - \
+			integer formats need isampler/usampler or iimage/uimage types", bound.format));
+	}
+}
+
+fn texture_dimension(kind: TextureKind) -> TextureDimension {
+	match kind {
+		TextureKind::Texture2D => TextureDimension::D2,
+		TextureKind::Texture2DArray{..} => TextureDimension::D2Array,
+		TextureKind::Texture3D{..} => TextureDimension::D3,
+		TextureKind::Cubemap => TextureDimension::Cube,
+	}
+}
+
+/// The type of value shaders get back when sampling or loading from a texture of this format.
+fn texel_kind_of(format: TextureFormat) -> ScalarKind {
+	use BaseFormat::*;
+
+	match format {
+		TextureFormat::Rgba(base) | TextureFormat::RedGreen(base) | TextureFormat::Red(base) => match base {
+			I8 | I16 | I32 => ScalarKind::Int,
+			U8 | U16 | U32 => ScalarKind::Uint,
+			_ => ScalarKind::Float,
+		}
+
+		TextureFormat::Rgb10A2Ui | TextureFormat::Stencil => ScalarKind::Uint,
+		_ => ScalarKind::Float,
+	}
+}
+
+
+fn get_integer(pname: u32) -> i32 {
+	let mut value = 0;
+	unsafe {
+		raw::GetIntegerv(pname, &mut value);
+	}
+	value
+}
+
+fn get_integer_indexed(pname: u32, index: u32) -> i32 {
+	let mut value = 0;
+	unsafe {
+		raw::GetIntegeri_v(pname, index, &mut value);
+	}
+	value
+}
+
+fn get_integer64_indexed(pname: u32, index: u32) -> i64 {
+	let mut value = 0;
+	unsafe {
+		raw::GetInteger64i_v(pname, index, &mut value);
+	}
+	value
+}
+
+fn get_vertex_attrib(vao: u32, index: u32, pname: u32) -> i32 {
+	let mut value = 0;
+	unsafe {
+		raw::GetVertexArrayIndexediv(vao, index, pname, &mut value);
+	}
+	value
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn texture_dimensions() {
+		assert_eq!(texture_dimension(TextureKind::Texture2D), TextureDimension::D2);
+		assert_eq!(texture_dimension(TextureKind::Texture2DArray{layers: 4}), TextureDimension::D2Array);
+		assert_eq!(texture_dimension(TextureKind::Texture3D{depth: 4}), TextureDimension::D3);
+		assert_eq!(texture_dimension(TextureKind::Cubemap), TextureDimension::Cube);
+	}
+
+	#[test]
+	fn texel_kinds() {
+		use BaseFormat::*;
+
+		for base in [Unorm8, Unorm16, F16, F32] {
+			assert_eq!(texel_kind_of(TextureFormat::Rgba(base)), ScalarKind::Float);
+		}
+
+		for base in [I8, I16, I32] {
+			assert_eq!(texel_kind_of(TextureFormat::RedGreen(base)), ScalarKind::Int);
+		}
+
+		for base in [U8, U16, U32] {
+			assert_eq!(texel_kind_of(TextureFormat::Red(base)), ScalarKind::Uint);
+		}
+
+		assert_eq!(texel_kind_of(TextureFormat::Rgb10A2Ui), ScalarKind::Uint);
+		assert_eq!(texel_kind_of(TextureFormat::Stencil), ScalarKind::Uint);
+		assert_eq!(texel_kind_of(TextureFormat::Rgb10A2), ScalarKind::Float);
+		assert_eq!(texel_kind_of(TextureFormat::Srgba8), ScalarKind::Float);
+
+		// Depth textures sample as floats, with or without comparison
+		for format in [TextureFormat::Depth, TextureFormat::Depth16, TextureFormat::Depth32, TextureFormat::DepthStencil] {
+			assert_eq!(texel_kind_of(format), ScalarKind::Float);
+		}
+	}
+}
@@ -1,8 +1,72 @@
 use crate::gfx::{self, raw};
 
+
+/// The type of the indices stored in an index buffer bound to a [`Vao`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexType {
+	U16,
+	U32,
+}
+
+impl IndexType {
+	pub(super) fn into_gl(self) -> u32 {
+		match self {
+			IndexType::U16 => raw::UNSIGNED_SHORT,
+			IndexType::U32 => raw::UNSIGNED_INT,
+		}
+	}
+
+	pub fn size_bytes(self) -> usize {
+		match self {
+			IndexType::U16 => std::mem::size_of::<u16>(),
+			IndexType::U32 => std::mem::size_of::<u32>(),
+		}
+	}
+}
+
+
+/// Marks a type that can be used as the element type of an index buffer.
+/// Implemented for [`u16`] and [`u32`].
+pub trait IndexElement: Copy + 'static {
+	const INDEX_TYPE: IndexType;
+
+	/// The largest number of vertices that can be addressed by this index type.
+	const MAX_VERTICES: usize;
+
+	fn from_usize(index: usize) -> Self;
+	fn to_usize(self) -> usize;
+}
+
+impl IndexElement for u16 {
+	const INDEX_TYPE: IndexType = IndexType::U16;
+	const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+	fn from_usize(index: usize) -> u16 {
+		debug_assert!(index < Self::MAX_VERTICES, "Index out of range for 16b indices");
+		index as u16
+	}
+
+	fn to_usize(self) -> usize { self as usize }
+}
+
+impl IndexElement for u32 {
+	const INDEX_TYPE: IndexType = IndexType::U32;
+	const MAX_VERTICES: usize = u32::MAX as usize + 1;
+
+	fn from_usize(index: usize) -> u32 {
+		debug_assert!(index < Self::MAX_VERTICES, "Index out of range for 32b indices");
+		index as u32
+	}
+
+	fn to_usize(self) -> usize { self as usize }
+}
+
+
+
 #[derive(Copy, Clone, Debug)]
 pub struct Vao {
 	pub(super) handle: u32,
+	pub(super) index_type: IndexType,
 }
 
 
@@ -11,11 +75,18 @@ impl Vao {
 	pub(super) fn new(handle: u32) -> Vao {
 		Vao {
 			handle,
+			index_type: IndexType::U16,
 		}
 	}
 
+	/// The type of the most recently bound index buffer. Used by indexed draw calls made while this [`Vao`] is bound.
+	pub fn index_type(&self) -> IndexType {
+		self.index_type
+	}
+
+	pub fn bind_index_buffer<I: IndexElement>(&mut self, index_buffer: gfx::Buffer<I>) {
+		self.index_type = I::INDEX_TYPE;
 
-	pub fn bind_index_buffer(&mut self, index_buffer: gfx::Buffer<u16>) {
 		unsafe {
 			raw::VertexArrayElementBuffer(self.handle, index_buffer.handle);
 		}
@@ -143,4 +143,59 @@ impl Vertex for ColorVertex2D {
 			size_bytes: std::mem::size_of::<Self>() as u32,
 		}
 	}
-}
\ No newline at end of file
+}
+
+
+/// A lit, textured 3D vertex type.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct TexturedVertex {
+	pub pos: Vec3,
+	pub normal: Vec3,
+	pub color: Color,
+	pub uv: Vec2,
+}
+
+static TEXTURED_VERTEX_ATTRIBUTES: &'static [Attribute] = &[
+	Attribute::new(0, AttributeType::Vec3),
+	Attribute::new(12, AttributeType::Vec3),
+	Attribute::new(24, AttributeType::Vec4),
+	Attribute::new(40, AttributeType::Vec2),
+];
+
+impl Vertex for TexturedVertex {
+	fn descriptor() -> Descriptor {
+		Descriptor {
+			attributes: TEXTURED_VERTEX_ATTRIBUTES,
+			size_bytes: std::mem::size_of::<Self>() as u32,
+		}
+	}
+}
+
+
+/// A color 3D vertex type for meshes deformed by bones.
+/// See [`gfx::skinning`] for how to render these.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SkinnedVertex {
+	pub pos: Vec3,
+	pub color: Color,
+	pub weights: Vec3,
+	pub joints: [u8; 3],
+}
+
+static SKINNED_VERTEX_ATTRIBUTES: &'static [Attribute] = &[
+	Attribute::new(0, AttributeType::Vec3),
+	Attribute::new(12, AttributeType::Vec4),
+	Attribute::new(28, AttributeType::Vec3),
+	Attribute::new(40, AttributeType::Uint8(3)),
+];
+
+impl Vertex for SkinnedVertex {
+	fn descriptor() -> Descriptor {
+		Descriptor {
+			attributes: SKINNED_VERTEX_ATTRIBUTES,
+			size_bytes: std::mem::size_of::<Self>() as u32,
+		}
+	}
+}
@@ -0,0 +1,165 @@
+use crate::prelude::*;
+use crate::gfx::{raw, DrawContext, FramebufferKey};
+
+
+/// Viewport and scissor state for whatever render target is currently bound.
+/// Lives in [`System`](crate::gfx::System) so that it survives between [`DrawContext`]s.
+///
+/// All rects are in pixels of the bound render target, with the origin in the bottom left corner as in OpenGL.
+#[derive(Debug)]
+pub(super) struct RenderTargetState {
+	/// `None` means the backbuffer.
+	pub(super) framebuffer: Option<FramebufferKey>,
+	pub(super) size: Vec2i,
+
+	pub(super) viewport: Aabb2i,
+	pub(super) scissor: Option<Aabb2i>,
+
+	pub(super) viewport_stack: Vec<Aabb2i>,
+	pub(super) scissor_stack: Vec<Option<Aabb2i>>,
+}
+
+impl RenderTargetState {
+	pub(super) fn new(backbuffer_size: Vec2i) -> RenderTargetState {
+		RenderTargetState {
+			framebuffer: None,
+			size: backbuffer_size,
+
+			viewport: full_rect(backbuffer_size),
+			scissor: None,
+
+			viewport_stack: Vec::new(),
+			scissor_stack: Vec::new(),
+		}
+	}
+
+	/// Makes `framebuffer` the current target, resetting the viewport to cover all of it and disabling the scissor.
+	/// Pushed viewports and scissors are forgotten, since they were in pixels of the previous target.
+	pub(super) fn bind(&mut self, framebuffer: Option<FramebufferKey>, size: Vec2i) {
+		self.framebuffer = framebuffer;
+		self.size = size;
+		self.viewport = full_rect(size);
+		self.scissor = None;
+
+		self.viewport_stack.clear();
+		self.scissor_stack.clear();
+
+		apply_viewport(self.viewport);
+		apply_scissor(self.scissor);
+	}
+
+	/// Called when the size of the bound target changes outside of a bind - e.g., when the window is resized
+	/// while the backbuffer or a [`TextureSize::Backbuffer`](crate::gfx::TextureSize::Backbuffer) framebuffer is bound.
+	/// Viewports covering the whole of the old target are grown or shrunk to cover the whole of the new one,
+	/// anything else is left as is.
+	pub(super) fn resize(&mut self, size: Vec2i) {
+		let old_full_rect = full_rect(self.size);
+		let new_full_rect = full_rect(size);
+
+		for viewport in std::iter::once(&mut self.viewport).chain(self.viewport_stack.iter_mut()) {
+			if *viewport == old_full_rect {
+				*viewport = new_full_rect;
+			}
+		}
+
+		self.size = size;
+		apply_viewport(self.viewport);
+	}
+
+	/// Forgets any pushed viewports and scissors. Done at the start of every frame.
+	pub(super) fn reset_stacks(&mut self) {
+		debug_assert!(self.viewport_stack.is_empty() && self.scissor_stack.is_empty(),
+			"Viewport or scissor pushed without matching pop during previous frame");
+
+		self.viewport_stack.clear();
+		self.scissor_stack.clear();
+	}
+
+	/// The viewport to restore when popping. Defaults to the whole target if nothing was pushed since it was bound.
+	pub(super) fn pop_viewport(&mut self) -> Aabb2i {
+		self.viewport_stack.pop()
+			.unwrap_or_else(|| full_rect(self.size))
+	}
+
+	/// The scissor to restore when popping. Defaults to no scissor if nothing was pushed since the target was bound.
+	pub(super) fn pop_scissor(&mut self) -> Option<Aabb2i> {
+		self.scissor_stack.pop()
+			.unwrap_or(None)
+	}
+}
+
+
+fn full_rect(size: Vec2i) -> Aabb2i {
+	Aabb2i::from_min_point(Vec2i::zero(), size)
+}
+
+pub(super) fn apply_viewport(rect: Aabb2i) {
+	let Vec2i{x, y} = rect.min;
+	let Vec2i{x: width, y: height} = rect.size();
+
+	unsafe {
+		raw::Viewport(x, y, width, height);
+	}
+}
+
+pub(super) fn apply_scissor(rect: Option<Aabb2i>) {
+	unsafe {
+		if let Some(rect) = rect {
+			let Vec2i{x, y} = rect.min;
+			let Vec2i{x: width, y: height} = rect.size();
+
+			raw::Enable(raw::SCISSOR_TEST);
+			raw::Scissor(x, y, width, height);
+		} else {
+			raw::Disable(raw::SCISSOR_TEST);
+		}
+	}
+}
+
+
+
+#[derive(Debug, Copy, Clone)]
+enum RegionKind {
+	Viewport,
+	Scissor,
+}
+
+/// Returned by [`DrawContext::scoped_viewport`] and [`DrawContext::scoped_scissor`].
+/// Derefs to the [`DrawContext`] it was created from, and pops the viewport or scissor it pushed when dropped.
+pub struct RegionScope<'scope, 'ctx> {
+	ctx: &'scope mut DrawContext<'ctx>,
+	kind: RegionKind,
+}
+
+impl<'scope, 'ctx> RegionScope<'scope, 'ctx> {
+	pub(super) fn viewport(ctx: &'scope mut DrawContext<'ctx>) -> Self {
+		RegionScope { ctx, kind: RegionKind::Viewport }
+	}
+
+	pub(super) fn scissor(ctx: &'scope mut DrawContext<'ctx>) -> Self {
+		RegionScope { ctx, kind: RegionKind::Scissor }
+	}
+}
+
+impl<'ctx> std::ops::Deref for RegionScope<'_, 'ctx> {
+	type Target = DrawContext<'ctx>;
+
+	fn deref(&self) -> &DrawContext<'ctx> {
+		self.ctx
+	}
+}
+
+impl<'ctx> std::ops::DerefMut for RegionScope<'_, 'ctx> {
+	fn deref_mut(&mut self) -> &mut DrawContext<'ctx> {
+		self.ctx
+	}
+}
+
+impl Drop for RegionScope<'_, '_> {
+	fn drop(&mut self) {
+		match self.kind {
+			RegionKind::Viewport => self.ctx.pop_viewport(),
+			RegionKind::Scissor => self.ctx.pop_scissor(),
+		}
+	}
+}
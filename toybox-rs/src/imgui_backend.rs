@@ -156,22 +156,7 @@ impl ImguiBackend {
 		gfx.bind_shader(self.shader);
 		gfx.bind_uniform_buffer(0, self.uniforms);
 		gfx.bind_vao(self.mesh.vao);
-		gfx.set_wireframe(false);
-
-		unsafe {
-			gfx::raw::Enable(gfx::raw::BLEND);
-			gfx::raw::BlendEquation(gfx::raw::FUNC_ADD);
-			gfx::raw::BlendFuncSeparate(
-				gfx::raw::SRC_ALPHA,
-				gfx::raw::ONE_MINUS_SRC_ALPHA,
-				gfx::raw::ONE,
-				gfx::raw::ONE_MINUS_SRC_ALPHA,
-			);
-			gfx::raw::Disable(gfx::raw::CULL_FACE);
-			gfx::raw::Disable(gfx::raw::DEPTH_TEST);
-			gfx::raw::Disable(gfx::raw::STENCIL_TEST);
-			gfx::raw::Enable(gfx::raw::SCISSOR_TEST);
-		}
+		gfx.set_pipeline_state(gfx::PipelineState::overlay());
 	}
 
 	fn draw_internal(&mut self, gfx: &mut gfx::DrawContext<'_>, draw_data: &imgui::DrawData) {
@@ -184,33 +169,10 @@ impl ImguiBackend {
 			transform: Mat4::ortho(0.0, fb_width, fb_height, 0.0, -10.0, 10.0)
 		});
 
-		let get_parameter = |param| unsafe {
-			let mut value = 0;
-			gfx::raw::GetIntegerv(param, &mut value);
-			value
-		};
-
-		let blend_src_rgb = get_parameter(gfx::raw::BLEND_SRC_RGB);
-		let blend_dst_rgb = get_parameter(gfx::raw::BLEND_DST_RGB);
-		let blend_src_alpha = get_parameter(gfx::raw::BLEND_SRC_ALPHA);
-		let blend_dst_alpha = get_parameter(gfx::raw::BLEND_DST_ALPHA);
-		let blend_equation_rgb = get_parameter(gfx::raw::BLEND_EQUATION_RGB);
-		let blend_equation_alpha = get_parameter(gfx::raw::BLEND_EQUATION_ALPHA);
-		let wireframe_mode = get_parameter(gfx::raw::POLYGON_MODE) == gfx::raw::LINE as i32;
-
-		let depth_test_enabled;
-		let stencil_test_enabled;
-		let cull_face_enabled;
-		let blend_enabled;
-
-		unsafe {
-			depth_test_enabled = gfx::raw::IsEnabled(gfx::raw::DEPTH_TEST) != 0;
-			stencil_test_enabled = gfx::raw::IsEnabled(gfx::raw::STENCIL_TEST) != 0;
-			cull_face_enabled = gfx::raw::IsEnabled(gfx::raw::CULL_FACE) != 0;
-			blend_enabled = gfx::raw::IsEnabled(gfx::raw::BLEND) != 0;
-		}
+		let previous_state = *gfx.pipeline_state();
 
 		self.setup_state(gfx);
+		gfx.push_scissor(None);
 
 		for draw_list in draw_data.draw_lists() {
 			self.mesh.upload_separate(&draw_list.vtx_buffer(), &draw_list.idx_buffer());
@@ -239,14 +201,10 @@ impl ImguiBackend {
 							continue;
 						}
 
-						unsafe {
-							gfx::raw::Scissor(
-								clip_x1 as i32,
-								(fb_height - clip_y2) as i32,
-								(clip_x2 - clip_x1) as i32,
-								(clip_y2 - clip_y1) as i32,
-							);
-						}
+						gfx.set_scissor(Aabb2i::from_min_point(
+							Vec2i::new(clip_x1 as i32, (fb_height - clip_y2) as i32),
+							Vec2i::new((clip_x2 - clip_x1) as i32, (clip_y2 - clip_y1) as i32),
+						));
 
 						let texture_key = gfx::TextureKey::from(slotmap::KeyData::from_ffi(texture_id.id() as u64));
 						gfx.bind_texture(0, texture_key);
@@ -271,35 +229,8 @@ impl ImguiBackend {
 		}
 
 
-		let set_enabled = |param, enabled| unsafe {
-			if enabled {
-				gfx::raw::Enable(param);
-			} else {
-				gfx::raw::Disable(param);
-			}
-		};
-
-		set_enabled(gfx::raw::SCISSOR_TEST, false);
-		set_enabled(gfx::raw::BLEND, blend_enabled);
-		set_enabled(gfx::raw::CULL_FACE, cull_face_enabled);
-		set_enabled(gfx::raw::DEPTH_TEST, depth_test_enabled);
-		set_enabled(gfx::raw::STENCIL_TEST, stencil_test_enabled);
-
-		unsafe {
-			gfx::raw::BlendEquationSeparate(
-				blend_equation_rgb as u32,
-				blend_equation_alpha as u32,
-			);
-
-			gfx::raw::BlendFuncSeparate(
-				blend_src_rgb as u32,
-				blend_dst_rgb as u32,
-				blend_src_alpha as u32,
-				blend_dst_alpha as u32,
-			);
-		}
-
-		gfx.set_wireframe(wireframe_mode);
+		gfx.pop_scissor();
+		gfx.set_pipeline_state(previous_state);
 	}
 }
 
@@ -375,9 +306,11 @@ fn build_font_atlas(gfx: &mut gfx::ResourceContext<'_>, imgui: &mut imgui::Conte
 	);
 
 	let font_atlas_key = gfx.new_texture(font_atlas_size, gfx::TextureFormat::srgba());
+	let sampler = gfx.get_sampler(gfx::SamplerDesc::linear());
+
 	let mut font_atlas = gfx.resources.get_mut(font_atlas_key);
 	font_atlas.upload_rgba8_raw(atlas_texture.data);
-	font_atlas.set_filter(true, true);
+	font_atlas.set_sampler(sampler);
 
 	imgui_fonts.tex_id = texture_key_to_imgui_id(font_atlas_key);
 
@@ -1,5 +1,6 @@
 use crate::input::{raw, context};
 use common::math::*;
+use crate::gfx::VirtualScreen;
 
 #[cfg(doc)]
 use crate::input::frame_state::ActionState::*;
@@ -75,10 +76,27 @@ pub enum MouseSpace {
 	// TODO(pat.m): this is dodgy, but maybe we should keep something like this for resolution independence.
 	// #[deprecated = "Exists only for backwards compatibility."]
 	LegacyPixelRatio,
+
+	/// Pixels of a [`VirtualScreen`] as presented by a [`PixelTarget`](crate::gfx::PixelTarget), so input lines up
+	/// with what was drawn to it. Like `Window`, origin is in the top left and y expands down.
+	/// Positions within the letterbox fall outside of `[0, size)`.
+	Virtual(VirtualScreen),
 }
 
 impl MouseSpace {
+	/// Same as [`MouseSpace::resolve_relative_with_drawable_size`], assuming the drawable size matches the window size.
 	pub fn resolve_relative(&self, window_space_delta: Vec2i, window_size: Vec2i) -> Vec2 {
+		self.resolve_relative_with_drawable_size(window_space_delta, window_size, window_size)
+	}
+
+	/// Same as [`MouseSpace::resolve_absolute_with_drawable_size`], assuming the drawable size matches the window size.
+	pub fn resolve_absolute(&self, window_space_pos: Vec2i, window_size: Vec2i) -> Vec2 {
+		self.resolve_absolute_with_drawable_size(window_space_pos, window_size, window_size)
+	}
+
+	/// `drawable_size` only matters for `Virtual`, since integer scaling happens in drawable pixels,
+	/// which may differ from logical window coordinates on high DPI displays.
+	pub fn resolve_relative_with_drawable_size(&self, window_space_delta: Vec2i, window_size: Vec2i, drawable_size: Vec2i) -> Vec2 {
 		use MouseSpace::*;
 
 		match self {
@@ -102,10 +120,17 @@ impl MouseSpace {
 
 			#[allow(deprecated)]
 			LegacyPixelRatio => window_space_delta.to_vec2() * Vec2::new(1.0 / 100.0, -1.0 / 100.0),
+
+			Virtual(screen) => {
+				let drawable_delta = window_space_delta.to_vec2() * drawable_size.to_vec2() / window_size.to_vec2();
+				screen.output_to_virtual_delta(drawable_delta, drawable_size)
+			}
 		}
 	}
 
-	pub fn resolve_absolute(&self, window_space_pos: Vec2i, window_size: Vec2i) -> Vec2 {
+	/// `drawable_size` only matters for `Virtual`, since integer scaling happens in drawable pixels,
+	/// which may differ from logical window coordinates on high DPI displays.
+	pub fn resolve_absolute_with_drawable_size(&self, window_space_pos: Vec2i, window_size: Vec2i, drawable_size: Vec2i) -> Vec2 {
 		use MouseSpace::*;
 
 		match self {
@@ -117,6 +142,11 @@ impl MouseSpace {
 
 			#[allow(deprecated)]
 			LegacyPixelRatio => unimplemented!("LegacyPixelRatio only provided for Mouse Action kinds"),
+
+			Virtual(screen) => {
+				let drawable_pos = window_space_pos.to_vec2() * drawable_size.to_vec2() / window_size.to_vec2();
+				screen.output_to_virtual(drawable_pos, drawable_size)
+			}
 		}
 	}
 }
@@ -282,4 +312,33 @@ mod test {
 		assert_vec_eq!(preserve_aspect_tr_absolute_portrait, Vec2::new(1.0, 1.0/portrait_aspect));
 		assert_vec_eq!(preserve_aspect_tr_relative_portrait, Vec2::new(1.0, 1.0/portrait_aspect));
 	}
+
+	#[test]
+	fn test_virtual_mouse_space() {
+		use crate::gfx::UpscaleMode;
+
+		// Scale of 3 fits, leaving a 20px letterbox on either side and 30px above and below.
+		let screen = VirtualScreen::new(Vec2i::new(320, 180), UpscaleMode::Integer);
+		let space = MouseSpace::Virtual(screen);
+		let window_size = Vec2i::new(1000, 600);
+
+		assert_vec_eq!(space.resolve_absolute(Vec2i::new(20, 30), window_size), Vec2::new(0.0, 0.0));
+		assert_vec_eq!(space.resolve_absolute(Vec2i::new(500, 300), window_size), Vec2::new(160.0, 90.0));
+		assert_vec_eq!(space.resolve_absolute(Vec2i::new(980, 570), window_size), Vec2::new(320.0, 180.0));
+		assert_vec_eq!(space.resolve_relative(Vec2i::new(3, -6), window_size), Vec2::new(1.0, -2.0));
+
+		// Same drawable size, but with half the logical window size.
+		let hidpi_window_size = window_size / 2;
+		assert_vec_eq!(space.resolve_absolute_with_drawable_size(Vec2i::new(10, 15), hidpi_window_size, window_size), Vec2::new(0.0, 0.0));
+		assert_vec_eq!(space.resolve_relative_with_drawable_size(Vec2i::new(3, -3), hidpi_window_size, window_size), Vec2::new(2.0, -2.0));
+
+		// Letterbox falls outside of the virtual screen.
+		let letterbox_pos = space.resolve_absolute(Vec2i::new(0, 0), window_size);
+		assert!(letterbox_pos.x < 0.0 && letterbox_pos.y < 0.0);
+
+		// Non-integer scales fill as much as possible.
+		let sharp_screen = VirtualScreen::new(Vec2i::new(320, 180), UpscaleMode::SharpBilinear);
+		assert_eq!(sharp_screen.presentation_rect(window_size), Aabb2i::new(Vec2i::new(0, 19), Vec2i::new(1000, 581)));
+		assert_eq!(screen.presentation_rect(window_size), Aabb2i::new(Vec2i::new(20, 30), Vec2i::new(980, 570)));
+	}
 }
\ No newline at end of file
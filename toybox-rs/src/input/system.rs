@@ -40,6 +40,7 @@ pub struct InputSystem {
 
 	/// Used for remapping mouse input.
 	window_size: Vec2i,
+	drawable_size: Vec2i,
 
 	/// Counter for new contexts.
 	context_id_counter: IdCounter,
@@ -195,6 +196,7 @@ impl InputSystem {
 
 			// We're assuming on_resize will be called soon after construction by Engine
 			window_size: Vec2i::zero(),
+			drawable_size: Vec2i::zero(),
 
 			context_id_counter: IdCounter::new(),
 			context_group_id_counter: IdCounter::new(),
@@ -241,8 +243,9 @@ impl InputSystem {
 		}
 	}
 
-	pub(crate) fn on_resize(&mut self, window_size: Vec2i) {
+	pub(crate) fn on_resize(&mut self, window_size: Vec2i, drawable_size: Vec2i) {
 		self.window_size = window_size;
+		self.drawable_size = drawable_size;
 	}
 
 	pub(crate) fn handle_event(&mut self, event: &sdl2::event::Event) {
@@ -290,9 +293,9 @@ impl InputSystem {
 
 			if action.kind.is_relative() {
 				let sensitivity = context.mouse_sensitivity().unwrap_or(1.0);
-				self.frame_state.mouse = self.raw_state.mouse_delta.map(|state| (action_id, mouse_space.resolve_relative(state, self.window_size) * sensitivity));
+				self.frame_state.mouse = self.raw_state.mouse_delta.map(|state| (action_id, mouse_space.resolve_relative_with_drawable_size(state, self.window_size, self.drawable_size) * sensitivity));
 			} else {
-				self.frame_state.mouse = self.raw_state.mouse_absolute.map(|state| (action_id, mouse_space.resolve_absolute(state, self.window_size)));
+				self.frame_state.mouse = self.raw_state.mouse_absolute.map(|state| (action_id, mouse_space.resolve_absolute_with_drawable_size(state, self.window_size, self.drawable_size)));
 			}
 		}
 
@@ -13,6 +13,7 @@ pub mod imgui_backend;
 pub mod input;
 pub mod perf;
 pub mod utility;
+pub mod vfs;
 pub mod window;
 
 pub use crate::prelude::*;
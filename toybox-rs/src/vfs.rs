@@ -0,0 +1,262 @@
+//! A virtual filesystem for loading assets from loose directories and [`Pack`] files.
+//!
+//! Assets are addressed by virtual paths relative to each mount - e.g., `"models/level.toy"` - which are
+//! normalised with [`normalize_path`] before lookup.
+//! By default loose files take priority over packed files in debug builds, so assets can be iterated on during
+//! development without rebuilding packs, while release builds prefer packs.
+//! See [`Vfs::set_prefer_loose_files`].
+//!
+//! Loaders for common asset types are implemented directly on [`Vfs`], e.g., [`Vfs::load_toy`],
+//! [`Vfs::load_texture`] and [`Vfs::load_sound`].
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Cursor};
+use std::path::{Path, PathBuf};
+
+pub mod pack;
+pub mod loaders;
+
+#[doc(inline)] pub use pack::{Pack, PackBuilder};
+
+
+/// The loose directory mounted by [`Vfs::with_default_mounts`].
+pub const DEFAULT_ASSET_DIRECTORY: &str = "assets";
+
+/// The pack mounted by [`Vfs::with_default_mounts`].
+pub const DEFAULT_ASSET_PACK: &str = "assets.pack";
+
+
+pub struct Vfs {
+	loose_roots: Vec<PathBuf>,
+	packs: Vec<Pack>,
+	prefer_loose_files: bool,
+}
+
+impl Vfs {
+	/// Creates a [`Vfs`] with nothing mounted.
+	pub fn new() -> Vfs {
+		Vfs {
+			loose_roots: Vec::new(),
+			packs: Vec::new(),
+			prefer_loose_files: cfg!(debug_assertions),
+		}
+	}
+
+	/// Creates a [`Vfs`] with [`DEFAULT_ASSET_DIRECTORY`] and [`DEFAULT_ASSET_PACK`] mounted, if they exist
+	/// in the working directory.
+	pub fn with_default_mounts() -> io::Result<Vfs> {
+		let mut vfs = Vfs::new();
+
+		if Path::new(DEFAULT_ASSET_DIRECTORY).is_dir() {
+			vfs.add_loose_root(DEFAULT_ASSET_DIRECTORY);
+		}
+
+		if Path::new(DEFAULT_ASSET_PACK).is_file() {
+			vfs.mount_pack(DEFAULT_ASSET_PACK)?;
+		}
+
+		Ok(vfs)
+	}
+
+	/// Mounts a directory of loose files. Roots added later take priority over earlier ones.
+	pub fn add_loose_root(&mut self, root: impl Into<PathBuf>) {
+		self.loose_roots.push(root.into());
+	}
+
+	/// Opens and mounts a [`Pack`]. Packs mounted later take priority over earlier ones.
+	pub fn mount_pack(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+		self.packs.push(Pack::open(path)?);
+		Ok(())
+	}
+
+	/// Controls whether loose files override packed files with the same path, or the other way around.
+	/// Defaults to `true` in debug builds and `false` in release builds.
+	pub fn set_prefer_loose_files(&mut self, prefer_loose_files: bool) {
+		self.prefer_loose_files = prefer_loose_files;
+	}
+
+	pub fn exists(&self, path: &str) -> bool {
+		normalize_path(path).is_ok_and(|path| self.resolve(&path).is_some())
+	}
+
+	pub fn open(&self, path: &str) -> io::Result<VfsFile> {
+		let path = normalize_path(path)?;
+
+		match self.resolve(&path).ok_or_else(|| pack::not_found(&path))? {
+			Source::Loose(full_path) => File::open(full_path).map(VfsFile::Loose),
+			Source::Packed(pack) => pack.read(&path).map(|data| VfsFile::Packed(Cursor::new(data))),
+		}
+	}
+
+	pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+		let path = normalize_path(path)?;
+
+		match self.resolve(&path).ok_or_else(|| pack::not_found(&path))? {
+			Source::Loose(full_path) => std::fs::read(full_path),
+			Source::Packed(pack) => pack.read(&path),
+		}
+	}
+
+	pub fn read_to_string(&self, path: &str) -> io::Result<String> {
+		String::from_utf8(self.read(path)?)
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("'{}' is not valid utf8", path)))
+	}
+
+	/// Lists the virtual paths of all files under `directory` across all mounts, recursively and in sorted order.
+	/// An empty `directory` lists everything.
+	pub fn list(&self, directory: &str) -> io::Result<Vec<String>> {
+		let directory = normalize_path(directory)?;
+		let prefix = match directory.is_empty() {
+			true => String::new(),
+			false => format!("{}/", directory),
+		};
+
+		let mut paths = Vec::new();
+
+		for pack in self.packs.iter() {
+			paths.extend(pack.entry_names()
+				.filter(|name| name.starts_with(&prefix))
+				.map(String::from));
+		}
+
+		for root in self.loose_roots.iter() {
+			let full_directory = root.join(&directory);
+			if !full_directory.is_dir() {
+				continue
+			}
+
+			let virtual_paths = walk_directory(&full_directory)?.into_iter()
+				.filter_map(|path| {
+					let relative_path = path.strip_prefix(root).ok()?;
+					normalize_path(relative_path.to_str()?).ok()
+				});
+
+			paths.extend(virtual_paths);
+		}
+
+		paths.sort();
+		paths.dedup();
+		Ok(paths)
+	}
+}
+
+impl Default for Vfs {
+	fn default() -> Vfs {
+		Vfs::new()
+	}
+}
+
+impl Vfs {
+	fn resolve(&self, path: &str) -> Option<Source<'_>> {
+		if path.is_empty() {
+			return None
+		}
+
+		let loose = || self.loose_roots.iter().rev()
+			.map(|root| root.join(path))
+			.find(|full_path| full_path.is_file())
+			.map(Source::Loose);
+
+		let packed = || self.packs.iter().rev()
+			.find(|pack| pack.contains(path))
+			.map(Source::Packed);
+
+		match self.prefer_loose_files {
+			true => loose().or_else(packed),
+			false => packed().or_else(loose),
+		}
+	}
+}
+
+
+enum Source<'vfs> {
+	Loose(PathBuf),
+	Packed(&'vfs Pack),
+}
+
+
+/// A readable, seekable handle to a file opened through a [`Vfs`].
+/// Packed files are read into memory when opened.
+pub enum VfsFile {
+	Loose(File),
+	Packed(Cursor<Vec<u8>>),
+}
+
+impl Read for VfsFile {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			VfsFile::Loose(file) => file.read(buf),
+			VfsFile::Packed(cursor) => cursor.read(buf),
+		}
+	}
+}
+
+impl Seek for VfsFile {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		match self {
+			VfsFile::Loose(file) => file.seek(pos),
+			VfsFile::Packed(cursor) => cursor.seek(pos),
+		}
+	}
+}
+
+
+
+/// Converts a relative path into the canonical form used for virtual paths: `/` separated, with no empty
+/// or `.` components.
+/// Fails for paths containing `..`, since virtual paths cannot escape their mount.
+pub fn normalize_path(path: &str) -> io::Result<String> {
+	let mut components = Vec::new();
+
+	for component in path.split(['/', '\\']) {
+		match component {
+			"" | "." => {}
+			".." => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Virtual path '{}' contains '..'", path))),
+			_ => components.push(component),
+		}
+	}
+
+	Ok(components.join("/"))
+}
+
+
+/// Recursively collects the paths of all files under `directory`, in no particular order.
+pub(crate) fn walk_directory(directory: &Path) -> io::Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	let mut to_visit = vec![directory.to_owned()];
+
+	while let Some(directory) = to_visit.pop() {
+		for entry in std::fs::read_dir(&directory)? {
+			let entry = entry?;
+			let file_type = entry.file_type()?;
+
+			if file_type.is_dir() {
+				to_visit.push(entry.path());
+			} else if file_type.is_file() {
+				files.push(entry.path());
+			}
+		}
+	}
+
+	Ok(files)
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn normalize_virtual_paths() {
+		assert_eq!(normalize_path("textures/atlas.png").unwrap(), "textures/atlas.png");
+		assert_eq!(normalize_path("./textures/./atlas.png").unwrap(), "textures/atlas.png");
+		assert_eq!(normalize_path("textures\\sprites\\atlas.png").unwrap(), "textures/sprites/atlas.png");
+		assert_eq!(normalize_path("/textures//atlas.png/").unwrap(), "textures/atlas.png");
+		assert_eq!(normalize_path("./").unwrap(), "");
+
+		assert!(normalize_path("../atlas.png").is_err());
+		assert!(normalize_path("textures/../atlas.png").is_err());
+		assert!(normalize_path("textures\\..\\atlas.png").is_err());
+	}
+}
@@ -0,0 +1,176 @@
+//! Loaders for toy scenes, images and sounds that read through a [`Vfs`].
+
+use crate::prelude::*;
+use crate::vfs::Vfs;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use std::io::Cursor;
+
+
+impl Vfs {
+	pub fn load_toy(&self, path: &str) -> Result<toy::Project, Box<dyn Error>> {
+		let data = self.read(path)?;
+		toy::load(&data)
+			.map_err(|err| format!("Failed to load '{}': {}", path, err).into())
+	}
+
+	/// Decodes an image, using the extension of `path` to determine its format.
+	pub fn load_image(&self, path: &str) -> Result<image::DynamicImage, Box<dyn Error>> {
+		let format = image::ImageFormat::from_path(path)?;
+		let data = self.read(path)?;
+		Ok(image::load_from_memory_with_format(&data, format)?)
+	}
+
+	/// Loads an image into a new sRGBA texture, flipped so that the first row of the image is at the top of the texture.
+	pub fn load_texture(&self, gfx: &mut gfx::ResourceContext<'_>, path: &str) -> Result<gfx::TextureKey, Box<dyn Error>> {
+		let image = self.load_image(path)?.flipv().into_rgba8().into_flat_samples();
+		let image_size = Vec2i::new(image.layout.width as i32, image.layout.height as i32);
+		let texture_format = gfx::TextureFormat::srgba();
+
+		let texture = gfx.new_texture(image_size, texture_format);
+
+		{
+			let mut texture = gfx.resources.textures.get_mut(texture);
+			texture.upload_rgba8_raw(&image.samples);
+		}
+
+		Ok(texture)
+	}
+
+	/// Like [`Vfs::load_texture`], but with a full mip chain generated from the image, and sampled with repeating trilinear and
+	/// anisotropic filtering by default. Prefer for textures seen at a distance or at glancing angles, like floors.
+	pub fn load_mipmapped_texture(&self, gfx: &mut gfx::ResourceContext<'_>, path: &str) -> Result<gfx::TextureKey, Box<dyn Error>> {
+		let image = self.load_image(path)?.flipv().into_rgba8().into_flat_samples();
+		let image_size = Vec2i::new(image.layout.width as i32, image.layout.height as i32);
+		let texture_format = gfx::TextureFormat::srgba();
+
+		let texture = gfx.new_texture_with_mips(image_size, texture_format, gfx::MipLevels::Full);
+		let sampler = gfx.get_sampler(gfx::SamplerDesc::trilinear().with_wrap(gfx::WrapMode::Repeat).with_anisotropy(16));
+
+		{
+			let mut texture = gfx.resources.textures.get_mut(texture);
+			texture.upload_rgba8_raw(&image.samples);
+			texture.generate_mipmaps();
+			texture.set_sampler(sampler);
+		}
+
+		Ok(texture)
+	}
+
+	/// Decodes the default track of an audio file into mono samples at `sample_rate`.
+	/// Multichannel audio is downmixed by averaging, and audio at a different rate is linearly resampled.
+	pub fn load_sound_samples(&self, path: &str, sample_rate: f32) -> Result<Vec<f32>, Box<dyn Error>> {
+		let data = self.read(path)?;
+		let source = MediaSourceStream::new(Box::new(Cursor::new(data)), MediaSourceStreamOptions::default());
+
+		let mut hint = Hint::new();
+		if let Some(extension) = std::path::Path::new(path).extension().and_then(|ext| ext.to_str()) {
+			hint.with_extension(extension);
+		}
+
+		let mut format = symphonia::default::get_probe()
+			.format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())?
+			.format;
+
+		let track = format.default_track()
+			.ok_or_else(|| format!("'{}' contains no audio tracks", path))?;
+
+		let track_id = track.id;
+		let source_rate = track.codec_params.sample_rate
+			.ok_or_else(|| format!("'{}' has unknown sample rate", path))?;
+
+		let mut decoder = symphonia::default::get_codecs()
+			.make(&track.codec_params, &DecoderOptions::default())?;
+
+		let mut samples = Vec::new();
+		let mut sample_buffer = None;
+
+		loop {
+			let packet = match format.next_packet() {
+				Ok(packet) => packet,
+				Err(SymphoniaError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+				Err(err) => return Err(err.into()),
+			};
+
+			if packet.track_id() != track_id {
+				continue
+			}
+
+			let decoded = match decoder.decode(&packet) {
+				Ok(decoded) => decoded,
+				Err(SymphoniaError::DecodeError(_)) => continue,
+				Err(err) => return Err(err.into()),
+			};
+
+			let spec = *decoded.spec();
+			let num_channels = spec.channels.count().max(1);
+
+			let sample_buffer = sample_buffer.get_or_insert_with(|| SampleBuffer::<f32>::new(decoded.capacity() as u64, spec));
+			if sample_buffer.capacity() < decoded.capacity() * num_channels {
+				*sample_buffer = SampleBuffer::new(decoded.capacity() as u64, spec);
+			}
+
+			sample_buffer.copy_interleaved_ref(decoded);
+
+			samples.extend(sample_buffer.samples().chunks_exact(num_channels)
+				.map(|frame| frame.iter().sum::<f32>() / num_channels as f32));
+		}
+
+		Ok(resample_linear(&samples, source_rate as f32, sample_rate))
+	}
+
+	/// Decodes an audio file with [`Vfs::load_sound_samples`] and adds it to `audio` at its sample rate.
+	pub fn load_sound(&self, audio: &mut audio::AudioSystem, path: &str) -> Result<audio::SoundId, Box<dyn Error>> {
+		let samples = self.load_sound_samples(path, audio.sample_rate())?;
+		Ok(audio.add_sound(samples))
+	}
+}
+
+
+fn resample_linear(samples: &[f32], source_rate: f32, target_rate: f32) -> Vec<f32> {
+	if source_rate == target_rate || samples.is_empty() {
+		return samples.to_vec()
+	}
+
+	let step = source_rate / target_rate;
+	let num_output_samples = (samples.len() as f32 / step).floor() as usize;
+	let last_sample = samples.len() - 1;
+
+	(0..num_output_samples)
+		.map(|index| {
+			let position = index as f32 * step;
+			let start = (position.floor() as usize).min(last_sample);
+			let end = (start + 1).min(last_sample);
+			let fraction = position.fract();
+
+			samples[start] + (samples[end] - samples[start]) * fraction
+		})
+		.collect()
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn resample() {
+		let samples = [0.0, 1.0, 0.0, -1.0];
+		assert_eq!(resample_linear(&samples, 44100.0, 44100.0), samples);
+		assert_eq!(resample_linear(&[], 22050.0, 44100.0), Vec::<f32>::new());
+
+		// Upsampling interpolates between neighbouring samples
+		assert_eq!(resample_linear(&samples, 22050.0, 44100.0), [0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0]);
+
+		// Downsampling skips samples
+		assert_eq!(resample_linear(&samples, 44100.0, 22050.0), [0.0, 0.0]);
+	}
+}
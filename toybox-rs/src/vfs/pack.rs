@@ -0,0 +1,258 @@
+//! A simple indexed archive format for bundling assets into a single file.
+//!
+//! Packs are laid out as:
+//! ```text
+//! "TPAK" u8:version u32:num_entries
+//! [u16:name_len name:utf8 u64:offset u64:size] * num_entries
+//! [entry data] * num_entries
+//! ```
+//! All integers are little endian, offsets are from the start of the file, and entry data is stored uncompressed.
+//! Entry names are normalised virtual paths, see [`normalize_path`](super::normalize_path).
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const PACK_MAGIC: &[u8; 4] = b"TPAK";
+pub const PACK_VERSION: u8 = 1;
+
+
+#[derive(Copy, Clone, Debug)]
+struct PackEntry {
+	offset: u64,
+	size: u64,
+}
+
+
+/// A read-only handle to a mounted pack file.
+/// Only the index is read up front - entry data is read from disk on request.
+#[derive(Debug)]
+pub struct Pack {
+	path: PathBuf,
+	file: Mutex<File>,
+	entries: BTreeMap<String, PackEntry>,
+}
+
+impl Pack {
+	pub fn open(path: impl AsRef<Path>) -> io::Result<Pack> {
+		let path = path.as_ref();
+		let mut file = BufReader::new(File::open(path)?);
+
+		let mut magic = [0u8; 4];
+		file.read_exact(&mut magic)?;
+		if &magic != PACK_MAGIC {
+			return Err(invalid_data(format!("'{}' is not a pack file", path.display())))
+		}
+
+		let version = read_u8(&mut file)?;
+		if version != PACK_VERSION {
+			return Err(invalid_data(format!("'{}' has unsupported pack version {}", path.display(), version)))
+		}
+
+		let file_size = file.get_ref().metadata()?.len();
+		let num_entries = read_u32(&mut file)?;
+		let mut entries = BTreeMap::new();
+
+		for _ in 0..num_entries {
+			let name_len = read_u16(&mut file)? as usize;
+			let mut name = vec![0u8; name_len];
+			file.read_exact(&mut name)?;
+
+			let name = String::from_utf8(name)
+				.map_err(|_| invalid_data(format!("'{}' contains a non-utf8 entry name", path.display())))?;
+
+			let offset = read_u64(&mut file)?;
+			let size = read_u64(&mut file)?;
+
+			if offset.checked_add(size).filter(|&end| end <= file_size).is_none() {
+				return Err(invalid_data(format!("Entry '{}' in '{}' extends past the end of the file", name, path.display())))
+			}
+
+			entries.insert(name, PackEntry{offset, size});
+		}
+
+		Ok(Pack {
+			path: path.to_owned(),
+			file: Mutex::new(file.into_inner()),
+			entries,
+		})
+	}
+
+	/// The path this pack was opened from.
+	pub fn path(&self) -> &Path { &self.path }
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.entries.contains_key(name)
+	}
+
+	/// The size in bytes of entry `name`, if it exists.
+	pub fn entry_size(&self, name: &str) -> Option<u64> {
+		self.entries.get(name).map(|entry| entry.size)
+	}
+
+	/// Iterates over the names of all entries in the pack in sorted order.
+	pub fn entry_names(&self) -> impl Iterator<Item=&str> + '_ {
+		self.entries.keys().map(String::as_str)
+	}
+
+	pub fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+		let PackEntry{offset, size} = *self.entries.get(name)
+			.ok_or_else(|| not_found(name))?;
+
+		let mut data = vec![0u8; size as usize];
+
+		let mut file = self.file.lock().unwrap();
+		file.seek(SeekFrom::Start(offset))?;
+		file.read_exact(&mut data)?;
+
+		Ok(data)
+	}
+}
+
+
+
+/// Collects files to be written into a new pack with [`PackBuilder::write`].
+#[derive(Default)]
+pub struct PackBuilder {
+	entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl PackBuilder {
+	pub fn new() -> PackBuilder {
+		PackBuilder::default()
+	}
+
+	/// Adds an entry under virtual path `name`, replacing any existing entry with the same name.
+	pub fn add_file(&mut self, name: &str, data: Vec<u8>) -> io::Result<()> {
+		let name = super::normalize_path(name)?;
+		if name.is_empty() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "Pack entries must have a name"))
+		}
+
+		if name.len() > u16::MAX as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Pack entry name too long: '{}'", name)))
+		}
+
+		self.entries.insert(name, data);
+		Ok(())
+	}
+
+	/// Recursively adds every file under `root`, named by their path relative to `root`.
+	pub fn add_directory(&mut self, root: impl AsRef<Path>) -> io::Result<()> {
+		let root = root.as_ref();
+
+		for path in super::walk_directory(root)? {
+			let relative_path = path.strip_prefix(root)
+				.expect("Walked path not within root");
+
+			let name = relative_path.to_str()
+				.ok_or_else(|| invalid_data(format!("Non-utf8 path '{}'", path.display())))?;
+
+			self.add_file(name, std::fs::read(&path)?)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn num_entries(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut file = BufWriter::new(File::create(path)?);
+
+		let num_entries = u32::try_from(self.entries.len())
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many pack entries"))?;
+
+		file.write_all(PACK_MAGIC)?;
+		file.write_all(&[PACK_VERSION])?;
+		file.write_all(&num_entries.to_le_bytes())?;
+
+		let header_size = PACK_MAGIC.len() + 1 + 4;
+		let index_size: usize = self.entries.keys()
+			.map(|name| 2 + name.len() + 8 + 8)
+			.sum();
+
+		let mut offset = (header_size + index_size) as u64;
+
+		for (name, data) in self.entries.iter() {
+			file.write_all(&(name.len() as u16).to_le_bytes())?;
+			file.write_all(name.as_bytes())?;
+			file.write_all(&offset.to_le_bytes())?;
+			file.write_all(&(data.len() as u64).to_le_bytes())?;
+			offset += data.len() as u64;
+		}
+
+		for data in self.entries.values() {
+			file.write_all(data)?;
+		}
+
+		file.flush()
+	}
+}
+
+
+
+fn invalid_data(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub(super) fn not_found(name: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::NotFound, format!("'{}' not found", name))
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+	let mut buf = [0u8; 1];
+	r.read_exact(&mut buf)?;
+	Ok(buf[0])
+}
+
+fn read_u16(r: &mut impl Read) -> io::Result<u16> {
+	let mut buf = [0u8; 2];
+	r.read_exact(&mut buf)?;
+	Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+	let mut buf = [0u8; 4];
+	r.read_exact(&mut buf)?;
+	Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+	let mut buf = [0u8; 8];
+	r.read_exact(&mut buf)?;
+	Ok(u64::from_le_bytes(buf))
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let path = std::env::temp_dir().join(format!("toybox-pack-test-{}.pak", std::process::id()));
+
+		let mut builder = PackBuilder::new();
+		builder.add_file("shaders/basic.glsl", b"void main() {}".to_vec()).unwrap();
+		builder.add_file(".\\textures//atlas.png", vec![1, 2, 3]).unwrap();
+		builder.add_file("empty", Vec::new()).unwrap();
+		assert!(builder.add_file("./", Vec::new()).is_err());
+		assert!(builder.add_file("../escape", Vec::new()).is_err());
+		builder.write(&path).unwrap();
+
+		let pack = Pack::open(&path);
+		std::fs::remove_file(&path).unwrap();
+		let pack = pack.unwrap();
+
+		assert_eq!(pack.entry_names().collect::<Vec<_>>(), ["empty", "shaders/basic.glsl", "textures/atlas.png"]);
+		assert_eq!(pack.read("shaders/basic.glsl").unwrap(), b"void main() {}");
+		assert_eq!(pack.read("textures/atlas.png").unwrap(), [1, 2, 3]);
+		assert_eq!(pack.entry_size("empty"), Some(0));
+		assert_eq!(pack.read("missing").unwrap_err().kind(), io::ErrorKind::NotFound);
+	}
+}